@@ -0,0 +1,838 @@
+//! Import transit routes and schedules from a GTFS feed. OSM route relations are frequently
+//! incomplete and don't say anything about schedules, but GTFS describes every trip. Every
+//! distinct sequence of stops served by a route becomes one `RawBusRoute`, spawning vehicles at
+//! the real departure times. Only trips running on one representative service date are used.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Read;
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use abstutil::{Counter, Timer};
+use geom::{Angle, Distance, Duration, FindClosest, Line, LonLat, Pt2D, Speed, Time};
+use map_model::raw::{OriginalRoad, RawBusRoute, RawBusStop, RawMap};
+use map_model::{osm, Direction, IntersectionType};
+
+use crate::transit::ensure_sidewalk_at_stop;
+
+/// How far a stop can be from the road or track that serves it.
+const MAX_STOP_DIST: Distance = Distance::const_meters(30.0);
+/// How far the point where a route enters or leaves the map can be from a border intersection.
+const MAX_BORDER_DIST: Distance = Distance::const_meters(50.0);
+/// Routes starting off-map have to spawn at the border early enough to reach their first stop on
+/// time. GTFS doesn't say how fast vehicles go in between, so assume a typical urban speed.
+const BORDER_APPROACH_SPEED: Speed = Speed::const_meters_per_second(8.3);
+
+/// Replaces any routes extracted from OSM relations with the routes from a GTFS feed. The path
+/// can be a .zip file or a directory where the feed has already been extracted. Trips run on
+/// `service_date` (formatted like 20210315), or if that's not specified, on the weekday with the
+/// most trips.
+pub fn import(
+    map: &mut RawMap,
+    path: &str,
+    service_date: Option<&str>,
+    timer: &mut Timer,
+) -> Result<()> {
+    timer.start("read GTFS");
+    let mut feed = Feed::open(path)?;
+    let stops: HashMap<String, StopRecord> = feed
+        .read::<StopRecord>("stops.txt")?
+        .into_iter()
+        .map(|rec| (rec.stop_id.clone(), rec))
+        .collect();
+    let routes: HashMap<String, RouteRecord> = feed
+        .read::<RouteRecord>("routes.txt")?
+        .into_iter()
+        .map(|rec| (rec.route_id.clone(), rec))
+        .collect();
+    let trips: Vec<TripRecord> = feed.read("trips.txt")?;
+    // Feeds need at least one of these
+    let calendar: Vec<CalendarRecord> = feed.read("calendar.txt").unwrap_or_default();
+    let calendar_dates: Vec<CalendarDateRecord> =
+        feed.read("calendar_dates.txt").unwrap_or_default();
+    let stop_times: Vec<StopTimeRecord> = feed.read("stop_times.txt")?;
+    // shapes.txt is optional. Without it, assume vehicles go straight between stops.
+    let shapes: Vec<ShapeRecord> = feed.read("shapes.txt").unwrap_or_else(|err| {
+        warn!("GTFS feed has no usable shapes: {}", err);
+        Vec::new()
+    });
+    timer.stop("read GTFS");
+
+    let mut stops_per_trip: HashMap<String, Vec<StopTimeRecord>> = HashMap::new();
+    for rec in stop_times {
+        stops_per_trip
+            .entry(rec.trip_id.clone())
+            .or_insert_with(Vec::new)
+            .push(rec);
+    }
+    for list in stops_per_trip.values_mut() {
+        list.sort_by_key(|rec| rec.stop_sequence);
+    }
+    let mut pts_per_shape: HashMap<String, Vec<ShapeRecord>> = HashMap::new();
+    for rec in shapes {
+        pts_per_shape
+            .entry(rec.shape_id.clone())
+            .or_insert_with(Vec::new)
+            .push(rec);
+    }
+    for list in pts_per_shape.values_mut() {
+        list.sort_by_key(|rec| rec.shape_pt_sequence);
+    }
+
+    // Feeds usually cover a few different service patterns -- weekdays, weekends, holidays --
+    // and often split them further by route or vehicle block. We only simulate one day, so keep
+    // every service running on that date.
+    let mut trips_per_service = Counter::new();
+    for trip in &trips {
+        trips_per_service.inc(trip.service_id.clone());
+    }
+    let calendar = Calendar::new(calendar, calendar_dates)?;
+    let date = match service_date {
+        Some(x) => ServiceDate::parse(x)?,
+        None => calendar
+            .busiest_date(&trips_per_service)
+            .ok_or_else(|| anyhow!("GTFS feed has no trips on any date"))?,
+    };
+    let services = calendar.active_services(date, &trips_per_service);
+    if services.is_empty() {
+        bail!("GTFS feed has no trips on {}", date);
+    }
+    info!(
+        "Using GTFS trips from {} services running on {}",
+        services.len(),
+        date
+    );
+
+    // Group trips that serve the same sequence of stops.
+    let mut patterns: BTreeMap<(String, Vec<String>), Pattern> = BTreeMap::new();
+    for trip in trips {
+        if !services.contains(&trip.service_id) {
+            continue;
+        }
+        let stop_times = if let Some(list) = stops_per_trip.remove(&trip.trip_id) {
+            list
+        } else {
+            continue;
+        };
+        let key = (
+            trip.route_id.clone(),
+            stop_times.iter().map(|rec| rec.stop_id.clone()).collect(),
+        );
+        let pattern = patterns.entry(key).or_insert_with(|| Pattern {
+            shape_id: trip.shape_id.clone(),
+            times_per_trip: Vec::new(),
+        });
+        pattern.times_per_trip.push(
+            stop_times
+                .iter()
+                .map(|rec| {
+                    let time = if rec.departure_time.is_empty() {
+                        &rec.arrival_time
+                    } else {
+                        &rec.departure_time
+                    };
+                    Time::parse(time).ok()
+                })
+                .collect(),
+        );
+    }
+
+    let matcher = RoadMatcher::new(map);
+    let mut results = Vec::new();
+    timer.start_iter("match GTFS routes to the map", patterns.len());
+    for ((route_id, stop_ids), pattern) in patterns {
+        timer.next();
+        let route = if let Some(route) = routes.get(&route_id) {
+            route
+        } else {
+            warn!("GTFS trips refer to unknown route {}", route_id);
+            continue;
+        };
+        let is_bus = match route.route_type {
+            // Bus, trolleybus, and the extended bus types
+            3 | 11 | 700..=799 | 800..=899 => true,
+            // Tram, subway, rail, and the extended rail, metro, and tram types
+            0 | 1 | 2 | 100..=199 | 400..=499 | 900..=999 => false,
+            _ => {
+                continue;
+            }
+        };
+        let full_name = if route.route_long_name.is_empty() {
+            route.route_short_name.clone()
+        } else {
+            route.route_long_name.clone()
+        };
+        let short_name = if route.route_short_name.is_empty() {
+            full_name.clone()
+        } else {
+            route.route_short_name.clone()
+        };
+        let osm_rel_id = osm::RelationID(-(results.len() as i64) - 1);
+        match make_route(
+            map,
+            &matcher,
+            &stops,
+            &pts_per_shape,
+            &stop_ids,
+            &pattern,
+            is_bus,
+        ) {
+            Ok((raw_stops, border_start, border_end, spawn_times)) => {
+                let all_pts = raw_stops.iter().map(|stop| stop.vehicle_pos).collect();
+                results.push(RawBusRoute {
+                    full_name: format!("{} to {}", full_name, raw_stops.last().unwrap().name),
+                    short_name,
+                    osm_rel_id,
+                    gtfs_trip_marker: pattern.shape_id.clone(),
+                    is_bus,
+                    stops: raw_stops,
+                    border_start,
+                    border_end,
+                    all_pts,
+                    spawn_times: Some(spawn_times),
+                });
+            }
+            Err(err) => {
+                warn!("Skipping GTFS route {} ({}): {}", route_id, full_name, err);
+            }
+        }
+    }
+
+    for route in &results {
+        if route.is_bus {
+            for stop in &route.stops {
+                let (road, dir) = stop.matched_road.unwrap();
+                ensure_sidewalk_at_stop(map, road, dir, &stop.name)?;
+            }
+        }
+    }
+    info!("Imported {} transit routes from {}", results.len(), path);
+    map.bus_routes = results;
+    Ok(())
+}
+
+/// Returns the stops, the border where the route starts and ends (if any), and the spawn times.
+#[allow(clippy::type_complexity)]
+fn make_route(
+    map: &RawMap,
+    matcher: &RoadMatcher,
+    stops: &HashMap<String, StopRecord>,
+    pts_per_shape: &HashMap<String, Vec<ShapeRecord>>,
+    stop_ids: &[String],
+    pattern: &Pattern,
+    is_bus: bool,
+) -> Result<(
+    Vec<RawBusStop>,
+    Option<osm::NodeID>,
+    Option<osm::NodeID>,
+    Vec<Time>,
+)> {
+    let mut stop_pts = Vec::new();
+    for id in stop_ids {
+        let stop = stops
+            .get(id)
+            .ok_or_else(|| anyhow!("unknown stop {}", id))?;
+        match (stop.stop_lon, stop.stop_lat) {
+            (Some(lon), Some(lat)) => {
+                stop_pts.push(LonLat::new(lon, lat).to_pt(&map.gps_bounds));
+            }
+            _ => bail!("stop {} has no location", id),
+        }
+    }
+
+    // Like with OSM relations, only keep the first contiguous run of in-bounds stops, so a route
+    // dipping in and out of the boundary doesn't leave gaps.
+    let first_idx = stop_pts
+        .iter()
+        .position(|pt| map.boundary_polygon.contains_pt(*pt))
+        .ok_or_else(|| anyhow!("no stops in bounds"))?;
+    let last_idx = first_idx
+        + stop_pts[first_idx..]
+            .iter()
+            .take_while(|pt| map.boundary_polygon.contains_pt(**pt))
+            .count()
+        - 1;
+    if last_idx == first_idx {
+        // Routes with only 1 stop are pretty much useless.
+        bail!("only one stop in bounds");
+    }
+
+    let mut shape: Vec<Pt2D> = pattern
+        .shape_id
+        .as_ref()
+        .and_then(|id| pts_per_shape.get(id))
+        .map(|pts| {
+            pts.iter()
+                .map(|rec| LonLat::new(rec.shape_pt_lon, rec.shape_pt_lat).to_pt(&map.gps_bounds))
+                .collect()
+        })
+        .unwrap_or_else(|| stop_pts.clone());
+    shape.dedup();
+    if shape.len() < 2 {
+        bail!("shape is too short");
+    }
+    let mut shape_segments: FindClosest<usize> = FindClosest::new(&map.gps_bounds.to_bounds());
+    for (idx, pair) in shape.windows(2).enumerate() {
+        shape_segments.add(idx, pair);
+    }
+
+    // GTFS stops don't correspond to OSM nodes, so use a placeholder. It's only used for
+    // debugging.
+    let placeholder_node = osm::NodeID(-1);
+    let mut raw_stops = Vec::new();
+    let mut first_segment = 0;
+    let mut last_segment = 0;
+    for idx in first_idx..=last_idx {
+        let stop = &stops[&stop_ids[idx]];
+        let pt = stop_pts[idx];
+        // Which way is the vehicle heading here?
+        let (segment, _) = shape_segments
+            .closest_pt(pt, Distance::meters(100.0))
+            .ok_or_else(|| anyhow!("stop {} is far from the route's shape", stop.stop_id))?;
+        let heading = Line::new(shape[segment], shape[segment + 1])
+            .map(|l| l.angle())
+            .ok_or_else(|| anyhow!("shape has a degenerate segment"))?;
+        if idx == first_idx {
+            first_segment = segment;
+        }
+        last_segment = segment;
+
+        let (road, dir, vehicle_pt) = matcher
+            .lookup(map, pt, heading, is_bus)
+            .ok_or_else(|| anyhow!("couldn't match stop {} to a road", stop.stop_id))?;
+        raw_stops.push(RawBusStop {
+            name: stop.stop_name.clone(),
+            vehicle_pos: (placeholder_node, vehicle_pt),
+            matched_road: Some((road, dir)),
+            // Light rail needs explicit platforms. For buses, the sidewalk next to the stop is
+            // fine.
+            ped_pos: if is_bus { None } else { Some(pt) },
+        });
+    }
+
+    // If the route begins or ends off-map, find the border where it enters or leaves.
+    let in_bounds_before = shape[..=first_segment]
+        .iter()
+        .rev()
+        .take_while(|pt| map.boundary_polygon.contains_pt(**pt))
+        .count();
+    let mut border_approach = Duration::ZERO;
+    let border_start = if in_bounds_before <= first_segment {
+        let entry_idx = first_segment + 1 - in_bounds_before;
+        let border = find_border(map, shape[entry_idx]);
+        if let Some(id) = border {
+            // Follow the shape from the border to the first stop
+            let mut dist = map.intersections[&id].point.dist_to(shape[entry_idx]);
+            for pair in shape[entry_idx..=first_segment].windows(2) {
+                dist += pair[0].dist_to(pair[1]);
+            }
+            dist += shape[first_segment].dist_to(raw_stops[0].vehicle_pos.1);
+            border_approach = dist / BORDER_APPROACH_SPEED;
+        }
+        border
+    } else {
+        None
+    };
+    let in_bounds_after = shape[last_segment + 1..]
+        .iter()
+        .take_while(|pt| map.boundary_polygon.contains_pt(**pt))
+        .count();
+    let border_end = if last_segment + 1 + in_bounds_after < shape.len() {
+        find_border(map, shape[last_segment + in_bounds_after])
+    } else {
+        None
+    };
+
+    // Vehicles spawn when the trip departs from the first in-bounds stop, or early enough to
+    // reach it from the border.
+    let mut spawn_times = Vec::new();
+    for times in &pattern.times_per_trip {
+        if let Some(time) = times[first_idx..=last_idx].iter().find_map(|t| *t) {
+            let time = if time - Time::START_OF_DAY > border_approach {
+                time - border_approach
+            } else {
+                Time::START_OF_DAY
+            };
+            spawn_times.push(wrap_past_midnight(time));
+        }
+    }
+    if spawn_times.is_empty() {
+        bail!("no trips have departure times");
+    }
+    spawn_times.sort();
+    spawn_times.dedup();
+
+    Ok((raw_stops, border_start, border_end, spawn_times))
+}
+
+/// GTFS times past midnight belong to the previous service day. We only simulate one day, so
+/// those trips happen early in the morning instead.
+fn wrap_past_midnight(time: Time) -> Time {
+    if time >= Time::START_OF_DAY + Duration::hours(24) {
+        time - Duration::hours(24)
+    } else {
+        time
+    }
+}
+
+fn find_border(map: &RawMap, pt: Pt2D) -> Option<osm::NodeID> {
+    map.intersections
+        .iter()
+        .filter(|(_, i)| {
+            i.intersection_type == IntersectionType::Border
+                && i.point.dist_to(pt) <= MAX_BORDER_DIST
+        })
+        .min_by_key(|(_, i)| i.point.dist_to(pt))
+        .map(|(id, _)| *id)
+}
+
+struct Pattern {
+    shape_id: Option<String>,
+    /// For every trip, when it departs each stop. Intermediate stops may not be timed.
+    times_per_trip: Vec<Vec<Option<Time>>>,
+}
+
+/// Finds the road segment closest to a stop, heading the same way as the route.
+struct RoadMatcher {
+    roads: FindClosest<(OriginalRoad, usize)>,
+    light_rail: FindClosest<(OriginalRoad, usize)>,
+}
+
+impl RoadMatcher {
+    fn new(map: &RawMap) -> RoadMatcher {
+        let bounds = map.gps_bounds.to_bounds();
+        let mut roads = FindClosest::new(&bounds);
+        let mut light_rail = FindClosest::new(&bounds);
+        for (id, r) in &map.roads {
+            let closest = if r.is_light_rail() {
+                &mut light_rail
+            } else if r.is_footway() {
+                continue;
+            } else {
+                &mut roads
+            };
+            for (idx, pair) in r.center_points.windows(2).enumerate() {
+                closest.add((*id, idx), pair);
+            }
+        }
+        RoadMatcher { roads, light_rail }
+    }
+
+    fn lookup(
+        &self,
+        map: &RawMap,
+        pt: Pt2D,
+        heading: Angle,
+        is_bus: bool,
+    ) -> Option<(OriginalRoad, Direction, Pt2D)> {
+        let closest = if is_bus {
+            &self.roads
+        } else {
+            &self.light_rail
+        };
+        let mut best: Option<(OriginalRoad, Direction, Pt2D, Distance)> = None;
+        for ((road, idx), hit, dist) in closest.all_close_pts(pt, MAX_STOP_DIST) {
+            let pts = &map.roads[&road].center_points;
+            let angle = if let Some(line) = Line::new(pts[idx], pts[idx + 1]) {
+                line.angle()
+            } else {
+                continue;
+            };
+            // Ignore cross-streets
+            let dir = if angle.approx_eq(heading, 45.0) {
+                Direction::Fwd
+            } else if angle.opposite().approx_eq(heading, 45.0) {
+                Direction::Back
+            } else {
+                continue;
+            };
+            if best.map(|(_, _, _, d)| dist < d).unwrap_or(true) {
+                best = Some((road, dir, hit, dist));
+            }
+        }
+        best.map(|(road, dir, hit, _)| (road, dir, hit))
+    }
+}
+
+/// A GTFS feed, either zipped or already extracted to a directory.
+enum Feed {
+    Zip(zip::ZipArchive<File>),
+    Directory(String),
+}
+
+impl Feed {
+    fn open(path: &str) -> Result<Feed> {
+        if path.ends_with(".zip") {
+            Ok(Feed::Zip(zip::ZipArchive::new(File::open(path)?)?))
+        } else {
+            Ok(Feed::Directory(path.to_string()))
+        }
+    }
+
+    fn read<T: DeserializeOwned>(&mut self, filename: &str) -> Result<Vec<T>> {
+        let bytes = match self {
+            Feed::Zip(archive) => {
+                let mut bytes = Vec::new();
+                archive.by_name(filename)?.read_to_end(&mut bytes)?;
+                bytes
+            }
+            Feed::Directory(dir) => abstio::slurp_file(format!("{}/{}", dir, filename))?,
+        };
+        parse_csv(&bytes)
+    }
+}
+
+fn parse_csv<T: DeserializeOwned>(bytes: &[u8]) -> Result<Vec<T>> {
+    let mut results = Vec::new();
+    for rec in csv::Reader::from_reader(bytes).deserialize() {
+        results.push(rec?);
+    }
+    Ok(results)
+}
+
+/// A calendar date, stored as the number of days since 1970-01-01.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+struct ServiceDate(i64);
+
+impl ServiceDate {
+    /// Parses GTFS dates, like 20210315.
+    fn parse(x: &str) -> Result<ServiceDate> {
+        if x.len() != 8 || !x.bytes().all(|b| b.is_ascii_digit()) {
+            bail!("bad GTFS date {}", x);
+        }
+        let year = x[0..4].parse::<i64>()?;
+        let month = x[4..6].parse::<i64>()?;
+        let day = x[6..8].parse::<i64>()?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            bail!("bad GTFS date {}", x);
+        }
+
+        // From <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Ok(ServiceDate(era * 146097 + day_of_era - 719468))
+    }
+
+    /// Monday is 0 and Sunday is 6.
+    fn weekday(self) -> usize {
+        // 1970-01-01 was a Thursday
+        (self.0 + 3).rem_euclid(7) as usize
+    }
+
+    fn next(self) -> ServiceDate {
+        ServiceDate(self.0 + 1)
+    }
+}
+
+impl std::fmt::Display for ServiceDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // From <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+        let z = self.0 + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        write!(f, "{:04}{:02}{:02}", year, month, day)
+    }
+}
+
+/// Which services run on which dates, from calendar.txt and calendar_dates.txt
+struct Calendar {
+    /// service_id => (running Monday through Sunday, first date, last date)
+    weekly: BTreeMap<String, ([bool; 7], ServiceDate, ServiceDate)>,
+    /// (service_id, date) => true if service is added that day, false if it's removed
+    exceptions: BTreeMap<(String, ServiceDate), bool>,
+}
+
+impl Calendar {
+    fn new(calendar: Vec<CalendarRecord>, dates: Vec<CalendarDateRecord>) -> Result<Calendar> {
+        let mut weekly = BTreeMap::new();
+        for rec in calendar {
+            weekly.insert(
+                rec.service_id,
+                (
+                    [
+                        rec.monday == 1,
+                        rec.tuesday == 1,
+                        rec.wednesday == 1,
+                        rec.thursday == 1,
+                        rec.friday == 1,
+                        rec.saturday == 1,
+                        rec.sunday == 1,
+                    ],
+                    ServiceDate::parse(&rec.start_date)?,
+                    ServiceDate::parse(&rec.end_date)?,
+                ),
+            );
+        }
+        let mut exceptions = BTreeMap::new();
+        for rec in dates {
+            let added = match rec.exception_type {
+                1 => true,
+                2 => false,
+                x => bail!("unknown exception_type {} for {}", x, rec.service_id),
+            };
+            exceptions.insert((rec.service_id, ServiceDate::parse(&rec.date)?), added);
+        }
+        Ok(Calendar { weekly, exceptions })
+    }
+
+    fn is_active(&self, service: &str, date: ServiceDate) -> bool {
+        if let Some(added) = self.exceptions.get(&(service.to_string(), date)) {
+            return *added;
+        }
+        match self.weekly.get(service) {
+            Some((days, start, end)) => *start <= date && date <= *end && days[date.weekday()],
+            None => false,
+        }
+    }
+
+    /// Of the services with trips, which run on this date?
+    fn active_services(
+        &self,
+        date: ServiceDate,
+        trips_per_service: &Counter<String>,
+    ) -> BTreeSet<String> {
+        trips_per_service
+            .borrow()
+            .keys()
+            .filter(|service| self.is_active(service, date))
+            .cloned()
+            .collect()
+    }
+
+    /// Finds the weekday with the most trips, preferring the earliest date if there's a tie. If
+    /// there's no service on any weekday, considers weekends too.
+    fn busiest_date(&self, trips_per_service: &Counter<String>) -> Option<ServiceDate> {
+        let mut dates = BTreeSet::new();
+        for (_, start, end) in self.weekly.values() {
+            let mut date = *start;
+            while date <= *end {
+                dates.insert(date);
+                date = date.next();
+            }
+        }
+        for (_, date) in self.exceptions.keys() {
+            dates.insert(*date);
+        }
+
+        let mut best_weekday: Option<(usize, ServiceDate)> = None;
+        let mut best_any: Option<(usize, ServiceDate)> = None;
+        for date in dates {
+            let count: usize = self
+                .active_services(date, trips_per_service)
+                .iter()
+                .map(|service| trips_per_service.get(service.clone()))
+                .sum();
+            if count == 0 {
+                continue;
+            }
+            if best_any.map(|(n, _)| count > n).unwrap_or(true) {
+                best_any = Some((count, date));
+            }
+            if date.weekday() < 5 && best_weekday.map(|(n, _)| count > n).unwrap_or(true) {
+                best_weekday = Some((count, date));
+            }
+        }
+        best_weekday.or(best_any).map(|(_, date)| date)
+    }
+}
+
+#[derive(Deserialize)]
+struct StopRecord {
+    stop_id: String,
+    #[serde(default)]
+    stop_name: String,
+    // Some location types, like entrances and generic nodes, may not have a position.
+    stop_lat: Option<f64>,
+    stop_lon: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct RouteRecord {
+    route_id: String,
+    #[serde(default)]
+    route_short_name: String,
+    #[serde(default)]
+    route_long_name: String,
+    route_type: usize,
+}
+
+#[derive(Deserialize)]
+struct TripRecord {
+    route_id: String,
+    service_id: String,
+    trip_id: String,
+    #[serde(default)]
+    shape_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CalendarRecord {
+    service_id: String,
+    monday: u8,
+    tuesday: u8,
+    wednesday: u8,
+    thursday: u8,
+    friday: u8,
+    saturday: u8,
+    sunday: u8,
+    start_date: String,
+    end_date: String,
+}
+
+#[derive(Deserialize)]
+struct CalendarDateRecord {
+    service_id: String,
+    date: String,
+    exception_type: u8,
+}
+
+#[derive(Deserialize)]
+struct StopTimeRecord {
+    trip_id: String,
+    #[serde(default)]
+    arrival_time: String,
+    #[serde(default)]
+    departure_time: String,
+    stop_id: String,
+    stop_sequence: usize,
+}
+
+#[derive(Deserialize)]
+struct ShapeRecord {
+    shape_id: String,
+    shape_pt_lat: f64,
+    shape_pt_lon: f64,
+    shape_pt_sequence: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(x: &str) -> ServiceDate {
+        ServiceDate::parse(x).unwrap()
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let trips: Vec<TripRecord> = parse_csv(
+            b"route_id,service_id,trip_id\n\
+              10,weekday,t1\n\
+              \"10\",weekday,\"t,2\"\n",
+        )
+        .unwrap();
+        assert_eq!(trips.len(), 2);
+        assert_eq!(trips[1].trip_id, "t,2");
+        assert!(trips[0].shape_id.is_none());
+
+        let stops: Vec<StopRecord> = parse_csv(
+            b"stop_id,stop_name,stop_lat,stop_lon\n\
+              s1,Main St,47.6,-122.3\n\
+              entrance,,,\n",
+        )
+        .unwrap();
+        assert_eq!(stops[0].stop_lat, Some(47.6));
+        assert_eq!(stops[1].stop_name, "");
+        assert!(stops[1].stop_lat.is_none());
+
+        // A required column is missing
+        assert!(parse_csv::<StopTimeRecord>(b"trip_id,stop_id\nt1,s1\n").is_err());
+    }
+
+    #[test]
+    fn test_service_dates() {
+        assert_eq!(date("19700101"), ServiceDate(0));
+        // A Monday and a Sunday
+        assert_eq!(date("20210315").weekday(), 0);
+        assert_eq!(date("20210321").weekday(), 6);
+        assert_eq!(date("20210228").next(), date("20210301"));
+        assert_eq!(date("20240228").next().to_string(), "20240229");
+        for bad in vec!["2021-03-15", "20211315", "20210300", "2021031"] {
+            assert!(ServiceDate::parse(bad).is_err(), "{} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn test_service_selection() {
+        let calendar: Vec<CalendarRecord> = parse_csv(
+            b"service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+              weekday,1,1,1,1,1,0,0,20210301,20210331\n\
+              route_2,1,1,1,1,1,0,0,20210301,20210331\n\
+              saturday,0,0,0,0,0,1,0,20210301,20210331\n",
+        )
+        .unwrap();
+        // A holiday on Wednesday the 17th
+        let dates: Vec<CalendarDateRecord> = parse_csv(
+            b"service_id,date,exception_type\n\
+              weekday,20210317,2\n\
+              holiday,20210317,1\n",
+        )
+        .unwrap();
+        let calendar = Calendar::new(calendar, dates).unwrap();
+
+        let mut trips_per_service = Counter::new();
+        for (service, count) in vec![("weekday", 10), ("route_2", 5), ("saturday", 3)] {
+            for _ in 0..count {
+                trips_per_service.inc(service.to_string());
+            }
+        }
+        trips_per_service.inc("holiday".to_string());
+        assert!(calendar.is_active("weekday", date("20210316")));
+        assert!(!calendar.is_active("weekday", date("20210317")));
+        assert!(!calendar.is_active("unknown", date("20210316")));
+
+        let active = |x: &str| -> Vec<String> {
+            calendar
+                .active_services(date(x), &trips_per_service)
+                .into_iter()
+                .collect()
+        };
+        assert_eq!(active("20210316"), vec!["route_2", "weekday"]);
+        assert_eq!(active("20210317"), vec!["holiday", "route_2"]);
+        assert_eq!(active("20210320"), vec!["saturday"]);
+        assert!(active("20210401").is_empty());
+
+        // Every normal weekday ties, so the first one wins
+        assert_eq!(
+            calendar.busiest_date(&trips_per_service),
+            Some(date("20210301"))
+        );
+    }
+
+    #[test]
+    fn test_busiest_date_falls_back_to_weekends() {
+        let calendar: Vec<CalendarRecord> = parse_csv(
+            b"service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+              sunday,0,0,0,0,0,0,1,20210301,20210331\n",
+        )
+        .unwrap();
+        let calendar = Calendar::new(calendar, Vec::new()).unwrap();
+        let mut trips_per_service = Counter::new();
+        trips_per_service.inc("sunday".to_string());
+        assert_eq!(
+            calendar.busiest_date(&trips_per_service),
+            Some(date("20210307"))
+        );
+    }
+
+    #[test]
+    fn test_times_past_midnight() {
+        let wrap = |x: &str| wrap_past_midnight(Time::parse(x).unwrap());
+        assert_eq!(wrap("23:59:00"), Time::parse("23:59:00").unwrap());
+        assert_eq!(wrap("24:00:00"), Time::START_OF_DAY);
+        assert_eq!(wrap("25:10:00"), Time::parse("01:10:00").unwrap());
+    }
+}
@@ -15,6 +15,7 @@ use serde::{Deserialize, Serialize};
 mod clip;
 mod elevation;
 mod extract;
+mod gtfs;
 pub mod osm_geom;
 mod parking;
 pub mod reader;
@@ -39,6 +40,12 @@ pub struct Options {
     /// Only include highways and arterials. This may make sense for some region-wide maps for
     /// particular use cases.
     pub skip_local_roads: bool,
+    /// If provided, import transit routes and schedules from this GTFS feed (a .zip file or an
+    /// extracted directory) instead of from OSM route relations.
+    pub gtfs: Option<String>,
+    /// Only import GTFS trips running on this date, formatted like 20210315. If not specified,
+    /// use the weekday with the most trips.
+    pub gtfs_service_date: Option<String>,
 }
 
 /// What roads will have on-street parking lanes? Data from
@@ -108,6 +115,15 @@ pub fn convert(opts: Options, timer: &mut abstutil::Timer) -> RawMap {
     }
     map.bus_routes = routes;
 
+    if let Some(ref path) = opts.gtfs {
+        timer.start("import GTFS");
+        // If this fails, the routes from OSM are kept
+        if let Err(err) = gtfs::import(&mut map, path, opts.gtfs_service_date.as_deref(), timer) {
+            error!("Couldn't import GTFS from {}: {}", path, err);
+        }
+        timer.stop("import GTFS");
+    }
+
     use_amenities(&mut map, amenities, timer);
 
     parking::apply_parking(&mut map, &opts, timer);
@@ -137,6 +137,7 @@ pub fn extract_route(
         border_start: None,
         border_end: None,
         all_pts,
+        spawn_times: None,
     })
 }
 
@@ -280,38 +281,48 @@ pub fn snap_bus_stops(
             println!("{} matched to {}, {}", stop.vehicle_pos.0, road, dir);
         }
 
-        // If this road is missing a sidewalk (likely because it's a motorway), add one.
-        // https://www.openstreetmap.org/way/325148569 is a motivating example. When we understand
-        // bus platforms properly, won't need this hack.
-        let tags = &mut raw
-            .roads
-            .get_mut(&road)
-            .ok_or_else(|| anyhow!("{} isn't an extracted road", road))?
-            .osm_tags;
-        #[allow(clippy::if_same_then_else)]
-        if tags.is(osm::INFERRED_SIDEWALKS, "true") {
-            let current = tags.get(osm::SIDEWALK).unwrap();
-            if current == "none" {
-                tags.insert(
-                    osm::SIDEWALK,
-                    if dir == Direction::Fwd {
-                        "right"
-                    } else {
-                        "left"
-                    },
-                );
-            } else if current == "right" && dir == Direction::Back {
-                tags.insert(osm::SIDEWALK, "both");
-            } else if current == "left" && dir == Direction::Fwd {
-                tags.insert(osm::SIDEWALK, "both");
-            } else {
-                continue;
-            }
-            info!(
-                "Inferring a sidewalk on {} for bus stop {}",
-                road, stop.vehicle_pos.0
+        ensure_sidewalk_at_stop(raw, road, dir, &stop.name)?;
+    }
+    Ok(route)
+}
+
+/// If this road is missing a sidewalk (likely because it's a motorway), add one.
+/// <https://www.openstreetmap.org/way/325148569> is a motivating example. When we understand bus
+/// platforms properly, won't need this hack.
+pub fn ensure_sidewalk_at_stop(
+    raw: &mut RawMap,
+    road: OriginalRoad,
+    dir: Direction,
+    stop_name: &str,
+) -> Result<()> {
+    let tags = &mut raw
+        .roads
+        .get_mut(&road)
+        .ok_or_else(|| anyhow!("{} isn't an extracted road", road))?
+        .osm_tags;
+    #[allow(clippy::if_same_then_else)]
+    if tags.is(osm::INFERRED_SIDEWALKS, "true") {
+        let current = tags.get(osm::SIDEWALK).unwrap();
+        if current == "none" {
+            tags.insert(
+                osm::SIDEWALK,
+                if dir == Direction::Fwd {
+                    "right"
+                } else {
+                    "left"
+                },
             );
+        } else if current == "right" && dir == Direction::Back {
+            tags.insert(osm::SIDEWALK, "both");
+        } else if current == "left" && dir == Direction::Fwd {
+            tags.insert(osm::SIDEWALK, "both");
+        } else {
+            return Ok(());
         }
+        info!(
+            "Inferring a sidewalk on {} for bus stop {}",
+            road, stop_name
+        );
     }
-    Ok(route)
+    Ok(())
 }
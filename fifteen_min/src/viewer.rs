@@ -580,7 +580,10 @@ pub fn draw_unwalkable_roads(ctx: &mut EventCtx, app: &App, opts: &Options) -> D
             continue;
         }
         for (_, _, lt) in road.lanes_ltr() {
-            if lt == LaneType::Sidewalk || (lt == LaneType::Shoulder && allow_shoulders) {
+            if lt == LaneType::Sidewalk
+                || lt == LaneType::SharedUse
+                || (lt == LaneType::Shoulder && allow_shoulders)
+            {
                 continue 'ROADS;
             }
         }
@@ -109,6 +109,14 @@ impl App {
                     "- parking_lot_changes: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.parking_lot_changes))
                 );
+                println!(
+                    "- sidewalk_los: {} bytes",
+                    prettyprint_usize(serialized_size_bytes(&a.sidewalk_los))
+                );
+                println!(
+                    "- mode_shifts: {} bytes",
+                    prettyprint_usize(serialized_size_bytes(&a.mode_shifts))
+                );
             }
         }
     }
@@ -376,6 +376,8 @@ pub fn color_for_mode(app: &App, m: TripMode) -> Color {
         TripMode::Bike => app.cs.unzoomed_bike,
         TripMode::Transit => app.cs.unzoomed_bus,
         TripMode::Drive => app.cs.unzoomed_car,
+        TripMode::RideHail => app.cs.unzoomed_ride_hail,
+        TripMode::BikeShare => app.cs.unzoomed_bike_share,
     }
 }
 
@@ -386,6 +388,8 @@ pub fn color_for_agent_type(app: &App, a: AgentType) -> Color {
         AgentType::Bus | AgentType::Train => app.cs.unzoomed_bus,
         AgentType::TransitRider => app.cs.bus_trip,
         AgentType::Car => app.cs.unzoomed_car,
+        AgentType::Delivery => app.cs.unzoomed_delivery,
+        AgentType::RideHail => app.cs.unzoomed_ride_hail,
     }
 }
 
@@ -394,11 +398,14 @@ pub fn color_for_trip_phase(app: &App, tpt: TripPhaseType) -> Color {
         TripPhaseType::Driving => app.cs.unzoomed_car,
         TripPhaseType::Walking => app.cs.unzoomed_pedestrian,
         TripPhaseType::Biking => app.cs.bike_trip,
-        TripPhaseType::Parking => app.cs.parking_trip,
+        TripPhaseType::Parking | TripPhaseType::SearchingForParking => app.cs.parking_trip,
         TripPhaseType::WaitingForBus(_, _) => app.cs.bus_layer,
         TripPhaseType::RidingBus(_, _, _) => app.cs.bus_trip,
         TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
         TripPhaseType::DelayedStart => Color::YELLOW,
+        TripPhaseType::Delivering(_) => app.cs.unzoomed_delivery,
+        TripPhaseType::WaitingForRideHail => app.cs.parking_trip,
+        TripPhaseType::RidingRideHail(_) => app.cs.unzoomed_ride_hail,
     }
 }
 
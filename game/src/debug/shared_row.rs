@@ -69,10 +69,13 @@ fn lane(lane: &Lane) -> Option<serde_json::Map<String, serde_json::value::Value>
         match lane.lane_type {
             LaneType::Driving => "drive_lane".into(),
             LaneType::Parking => "parking".into(),
+            LaneType::LoadingZone => "loading_zone".into(),
             LaneType::Sidewalk => "sidewalk".into(),
             // TODO Nope
             LaneType::Shoulder => "sidewalk".into(),
             LaneType::Biking => "bike_lane".into(),
+            // TODO Nope
+            LaneType::SharedUse => "bike_lane".into(),
             LaneType::Bus => "bus_lane".into(),
             LaneType::SharedLeftTurn => "turn_lane".into(),
             LaneType::Construction => "construction_zone".into(),
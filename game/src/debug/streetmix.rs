@@ -48,10 +48,12 @@ fn lane(lane: &Lane, dir: Direction) -> serde_json::Map<String, serde_json::valu
             Direction::Fwd => ("bike-lane", "inbound|green|road"),
             Direction::Back => ("bike-lane", "outbound|green|road"),
         },
+        LaneType::SharedUse => ("TODO", "TODO"),
         LaneType::Bus => match dir {
             Direction::Fwd => ("bus-lane", "inbound|shared"),
             Direction::Back => ("bus-lane", "outbound|shared"),
         },
+        LaneType::LoadingZone => ("TODO", "TODO"),
         LaneType::SharedLeftTurn => ("TODO", "TODO"),
         LaneType::Construction => ("TODO", "TODO"),
         LaneType::LightRail => ("TODO", "TODO"),
@@ -0,0 +1,116 @@
+use map_model::{BikeDock, BuildingID, EditCmd, SharedVehicleType};
+use widgetry::{
+    EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, TextExt,
+    Toggle, VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::app::Transition;
+use crate::edit::apply_map_edits;
+
+pub struct BikeDockEditor {
+    panel: Panel,
+    b: BuildingID,
+}
+
+impl BikeDockEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, b: BuildingID) -> Box<dyn State<App>> {
+        app.primary.current_selection = None;
+
+        let bldg = app.primary.map.get_b(b);
+        let dock = bldg.bike_dock.unwrap_or(BikeDock {
+            vehicle_type: SharedVehicleType::Bike,
+            capacity: 10,
+        });
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Edit bike-share dock")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Line(&bldg.address).into_widget(ctx),
+        ];
+        if bldg.biking_connection(&app.primary.map).is_none() {
+            col.push(
+                Line("Nobody can bike to this building, so the dock won't be used")
+                    .fg(ctx.style().text_destructive_color)
+                    .into_widget(ctx),
+            );
+        }
+        col.push(Toggle::choice(
+            ctx,
+            "vehicle type",
+            "bikes",
+            "scooters",
+            None,
+            dock.vehicle_type == SharedVehicleType::Bike,
+        ));
+        col.push(Widget::row(vec![
+            "Capacity".text_widget(ctx).centered_vert(),
+            Spinner::widget(ctx, "capacity", (1, 100), dock.capacity as isize, 1),
+        ]));
+        col.push(Widget::custom_row(vec![
+            ctx.style()
+                .btn_solid_primary
+                .text("Apply")
+                .hotkey(Key::Enter)
+                .build_def(ctx),
+            ctx.style()
+                .btn_solid_destructive
+                .text("Remove dock")
+                .disabled(bldg.bike_dock.is_none())
+                .build_def(ctx),
+        ]));
+
+        Box::new(BikeDockEditor {
+            panel: Panel::new_builder(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            b,
+        })
+    }
+}
+
+impl State<App> for BikeDockEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            let new = match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Apply" => Some(BikeDock {
+                    vehicle_type: if self.panel.is_checked("vehicle type") {
+                        SharedVehicleType::Bike
+                    } else {
+                        SharedVehicleType::Scooter
+                    },
+                    capacity: self.panel.spinner::<isize>("capacity") as usize,
+                }),
+                "Remove dock" => None,
+                _ => unreachable!(),
+            };
+
+            let map = &app.primary.map;
+            let old = map.get_b(self.b).bike_dock;
+            if old != new {
+                let mut edits = map.get_edits().clone();
+                edits.commands.push(EditCmd::ChangeBikeDock {
+                    b: self.b,
+                    old,
+                    new,
+                });
+                apply_map_edits(ctx, app, edits);
+            }
+            return Transition::Pop;
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
@@ -17,7 +17,12 @@ pub fn add_new_lane(road: &mut EditRoad, lt: LaneType, osm_tags: &Tags) -> usize
                 .map(|x| x + 1)
                 .unwrap_or(road.lanes_ltr.len());
         }
-        LaneType::Biking | LaneType::Bus | LaneType::Parking | LaneType::Construction => {
+        LaneType::Biking
+        | LaneType::SharedUse
+        | LaneType::Bus
+        | LaneType::Parking
+        | LaneType::LoadingZone
+        | LaneType::Construction => {
             let relevant_lanes: Vec<&LaneSpec> =
                 road.lanes_ltr.iter().filter(|x| x.lt == lt).collect();
             dir = if !relevant_lanes.is_empty() {
@@ -96,6 +101,56 @@ pub fn add_new_lane(road: &mut EditRoad, lt: LaneType, osm_tags: &Tags) -> usize
     idx
 }
 
+/// If every driving lane on the road points the same way, returns that direction.
+pub fn one_way_driving_dir(road: &EditRoad) -> Option<Direction> {
+    let mut dirs = road
+        .lanes_ltr
+        .iter()
+        .filter(|spec| spec.lt == LaneType::Driving)
+        .map(|spec| spec.dir);
+    let dir = dirs.next()?;
+    if dirs.all(|x| x == dir) {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+/// Is a one-way road two-way for bikes, by having a bike lane going against traffic?
+pub fn has_contraflow_bike_lane(road: &EditRoad) -> bool {
+    if let Some(dir) = one_way_driving_dir(road) {
+        road.lanes_ltr
+            .iter()
+            .any(|spec| spec.lt == LaneType::Biking && spec.dir != dir)
+    } else {
+        false
+    }
+}
+
+/// Make a one-way road two-way for bikes by adding a contraflow bike lane, or make it one-way
+/// again by removing any. Does nothing to two-way roads.
+pub fn toggle_contraflow_bike_lane(road: &mut EditRoad, osm_tags: &Tags) {
+    let dir = if let Some(dir) = one_way_driving_dir(road) {
+        dir.opposite()
+    } else {
+        return;
+    };
+    if has_contraflow_bike_lane(road) {
+        road.lanes_ltr
+            .retain(|spec| spec.lt != LaneType::Biking || spec.dir != dir);
+        return;
+    }
+    let idx = default_outside_lane_placement(road, dir);
+    road.lanes_ltr.insert(
+        idx,
+        LaneSpec {
+            lt: LaneType::Biking,
+            dir,
+            width: LaneSpec::typical_lane_widths(LaneType::Biking, osm_tags)[0].0,
+        },
+    );
+}
+
 /// Place the new lane according to its direction on the outside unless the outside is walkable in
 /// which case place inside the walkable lane
 fn default_outside_lane_placement(road: &mut EditRoad, dir: Direction) -> usize {
@@ -219,4 +274,45 @@ mod tests {
         }
         assert!(ok);
     }
+
+    #[test]
+    fn test_toggle_contraflow_bike_lane() {
+        let mut ok = true;
+        for (description, input_lt, input_dir, expected_lt, expected_dir) in vec![
+            (
+                "One-way, add a contraflow lane",
+                "sdds",
+                "v^^^",
+                "sbdds",
+                "vv^^^",
+            ),
+            (
+                "One-way with a contraflow lane, remove it",
+                "sbdds",
+                "vv^^^",
+                "sdds",
+                "v^^^",
+            ),
+            (
+                "Two-way roads aren't changed",
+                "sdds",
+                "vv^^",
+                "sdds",
+                "vv^^",
+            ),
+        ] {
+            let input = EditRoad::create_for_test(input_lt, input_dir);
+            let mut actual_output = input.clone();
+            toggle_contraflow_bike_lane(&mut actual_output, &Tags::empty());
+            actual_output.check_lanes_ltr(
+                description.to_string(),
+                input_lt,
+                input_dir,
+                expected_lt,
+                expected_dir,
+                &mut ok,
+            );
+        }
+        assert!(ok);
+    }
 }
@@ -6,28 +6,34 @@ use map_gui::options::OptionsPanel;
 use map_gui::render::DrawMap;
 use map_gui::tools::{grey_out_map, ChooseSomething, ColorLegend, PopupMsg};
 use map_gui::ID;
-use map_model::{EditCmd, IntersectionID, LaneID, MapEdits};
+use map_model::{EditCmd, IntersectionID, LaneID, MapEdits, ParkingArea};
 use widgetry::{
     lctrl, Choice, Color, ControlState, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Image,
     Key, Line, Menu, Outcome, Panel, State, Text, TextBox, TextExt, VerticalAlignment, Widget,
 };
 
+pub use self::bike_dock::BikeDockEditor;
+pub use self::parking::ParkingEditor;
 pub use self::roads::RoadEditor;
 pub use self::routes::RouteEditor;
 pub use self::stop_signs::StopSignEditor;
 pub use self::traffic_signals::TrafficSignalEditor;
+pub use self::turn_restrictions::TurnRestrictionEditor;
 pub use self::validate::{check_blackholes, check_sidewalk_connectivity};
 use crate::app::{App, Transition};
 use crate::common::{tool_panel, CommonState, Warping};
 use crate::debug::DebugMode;
 use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
 
+mod bike_dock;
 mod heuristics;
 mod multiple_roads;
+mod parking;
 mod roads;
 mod routes;
 mod stop_signs;
 mod traffic_signals;
+mod turn_restrictions;
 mod validate;
 mod zones;
 
@@ -881,6 +887,14 @@ fn cmd_to_id(cmd: &EditCmd) -> Option<ID> {
         EditCmd::ChangeRoad { r, .. } => Some(ID::Road(*r)),
         EditCmd::ChangeIntersection { i, .. } => Some(ID::Intersection(*i)),
         EditCmd::ChangeRouteSchedule { .. } => None,
+        EditCmd::ChangeParkingPricing { area, .. } | EditCmd::ChangeChargingSpots { area, .. } => {
+            match area {
+                ParkingArea::Lane(r, _) => Some(ID::Road(*r)),
+                ParkingArea::Lot(pl) => Some(ID::ParkingLot(*pl)),
+            }
+        }
+        EditCmd::ChangeBikeDock { b, .. } => Some(ID::Building(*b)),
+        EditCmd::ChangeMovementBan { mvmnt, .. } => Some(ID::Intersection(mvmnt.parent)),
     }
 }
 
@@ -0,0 +1,147 @@
+use geom::Duration;
+use map_model::{EditCmd, ParkingArea, ParkingPricing};
+use widgetry::{
+    EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, TextExt,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::app::Transition;
+use crate::edit::apply_map_edits;
+
+pub struct ParkingEditor {
+    panel: Panel,
+    area: ParkingArea,
+}
+
+impl ParkingEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, area: ParkingArea) -> Box<dyn State<App>> {
+        app.primary.current_selection = None;
+
+        let map = &app.primary.map;
+        let pricing = map.get_parking_pricing(area);
+        let (name, capacity) = match area {
+            ParkingArea::Lane(r, idx) => (
+                format!(
+                    "Parking along {}",
+                    map.get_r(r).get_name(app.opts.language.as_ref())
+                ),
+                map.get_l(map.get_r(r).lanes_ltr()[idx].0)
+                    .number_parking_spots(map.get_config()),
+            ),
+            ParkingArea::Lot(pl) => (pl.to_string(), map.get_pl(pl).capacity()),
+        };
+        Box::new(ParkingEditor {
+            panel: Panel::new_builder(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Edit parking").small_heading().into_widget(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                Line(name).into_widget(ctx),
+                Widget::row(vec![
+                    "Hourly rate".text_widget(ctx).centered_vert(),
+                    // Signed, so the spinner can't underflow before clamping
+                    Spinner::widget_with_custom_rendering(
+                        ctx,
+                        "cents_per_hour",
+                        (0, 2000),
+                        pricing.cents_per_hour as isize,
+                        25,
+                        Box::new(|x| ParkingPricing::describe_cents(x as usize)),
+                    ),
+                ]),
+                Widget::row(vec![
+                    "Time limit".text_widget(ctx).centered_vert(),
+                    Spinner::widget_with_custom_rendering(
+                        ctx,
+                        "time_limit",
+                        (Duration::ZERO, Duration::hours(12)),
+                        pricing.time_limit.unwrap_or(Duration::ZERO),
+                        Duration::minutes(15),
+                        Box::new(|x| {
+                            if x == Duration::ZERO {
+                                "none".to_string()
+                            } else {
+                                x.to_string()
+                            }
+                        }),
+                    ),
+                ]),
+                Widget::row(vec![
+                    "Spots with EV chargers".text_widget(ctx).centered_vert(),
+                    Spinner::widget(
+                        ctx,
+                        "charging_spots",
+                        (0, capacity as isize),
+                        map.get_charging_spots(area) as isize,
+                        1,
+                    ),
+                ]),
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Apply")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            area,
+        })
+    }
+}
+
+impl State<App> for ParkingEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Apply" => {
+                    let time_limit: Duration = self.panel.spinner("time_limit");
+                    let new = ParkingPricing {
+                        cents_per_hour: self.panel.spinner::<isize>("cents_per_hour") as usize,
+                        time_limit: if time_limit == Duration::ZERO {
+                            None
+                        } else {
+                            Some(time_limit)
+                        },
+                    };
+
+                    let charging_spots = self.panel.spinner::<isize>("charging_spots") as usize;
+
+                    let map = &app.primary.map;
+                    let mut edits = map.get_edits().clone();
+                    let old = map.get_parking_pricing(self.area);
+                    if old != new {
+                        edits.commands.push(EditCmd::ChangeParkingPricing {
+                            area: self.area,
+                            old,
+                            new,
+                        });
+                    }
+                    let old = map.get_charging_spots(self.area);
+                    if old != charging_spots {
+                        edits.commands.push(EditCmd::ChangeChargingSpots {
+                            area: self.area,
+                            old,
+                            new: charging_spots,
+                        });
+                    }
+                    apply_map_edits(ctx, app, edits);
+
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
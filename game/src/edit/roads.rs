@@ -10,12 +10,14 @@ use map_model::{
 use widgetry::{
     lctrl, Choice, Color, ControlState, DragDrop, Drawable, EdgeInsets, EventCtx, GeomBatch,
     GeomBatchStack, GfxCtx, HorizontalAlignment, Image, Key, Line, Outcome, Panel, PersistentSplit,
-    Spinner, State, Text, TextExt, VerticalAlignment, Widget, DEFAULT_CORNER_RADIUS,
+    Spinner, State, Text, TextExt, Toggle, VerticalAlignment, Widget, DEFAULT_CORNER_RADIUS,
 };
 
 use crate::app::{App, Transition};
 use crate::common::Warping;
-use crate::edit::heuristics::add_new_lane;
+use crate::edit::heuristics::{
+    add_new_lane, has_contraflow_bike_lane, one_way_driving_dir, toggle_contraflow_bike_lane,
+};
 use crate::edit::zones::ZoneEditor;
 use crate::edit::{apply_map_edits, can_edit_lane, speed_limit_choices};
 
@@ -99,7 +101,7 @@ impl RoadEditor {
             let mut parking = 0;
             let mut driving = 0;
             for spec in &new.lanes_ltr {
-                if spec.lt == LaneType::Parking {
+                if spec.lt == LaneType::Parking || spec.lt == LaneType::LoadingZone {
                     parking += 1;
                 } else if spec.lt == LaneType::Driving {
                     driving += 1;
@@ -308,7 +310,7 @@ impl State<App> for RoadEditor {
                     };
 
                     // Special check here
-                    if lt == LaneType::Parking
+                    if (lt == LaneType::Parking || lt == LaneType::LoadingZone)
                         && app
                             .primary
                             .map
@@ -386,6 +388,22 @@ impl State<App> for RoadEditor {
                     app.session.buffer_lane_type =
                         self.main_panel.persistent_split_value("add buffer");
                 }
+                "two-way for bikes" => {
+                    let mut edits = app.primary.map.get_edits().clone();
+                    let osm_tags = &app.primary.map.get_r(self.r).osm_tags;
+                    edits
+                        .commands
+                        .push(app.primary.map.edit_road_cmd(self.r, |new| {
+                            toggle_contraflow_bike_lane(new, osm_tags);
+                        }));
+                    apply_map_edits(ctx, app, edits);
+                    self.redo_stack.clear();
+
+                    // The lanes shifted around
+                    self.selected_lane = None;
+                    self.hovering_on_lane = None;
+                    panels_need_recalc = true;
+                }
                 _ => unreachable!(),
             },
             Outcome::DragDropReleased(_, old_idx, new_idx) => {
@@ -545,11 +563,13 @@ fn make_main_panel(
         (LaneType::Biking, Some(Key::B)),
         (LaneType::Bus, Some(Key::T)),
         (LaneType::Sidewalk, Some(Key::S)),
+        (LaneType::SharedUse, Some(Key::U)),
         (LaneType::Parking, Some(Key::P)),
+        (LaneType::LoadingZone, Some(Key::L)),
         (LaneType::Construction, Some(Key::C)),
     ];
     // All the buffer lanes are grouped into a PersistentSplit
-    let moving_lane_idx = 4;
+    let moving_lane_idx = 5;
 
     let mut lane_type_buttons = HashMap::new();
     for (lane_type, _key) in lane_types {
@@ -740,7 +760,8 @@ fn make_main_panel(
                                 btn = btn
                                     .disabled(true)
                                     .disabled_tooltip("This road already has two parking lanes");
-                            } else if lt == LaneType::Sidewalk
+                            } else if lt.is_walkable()
+                                && !current_lt.map(|x| x.is_walkable()).unwrap_or(false)
                                 && current_lts.iter().filter(|x| x.is_walkable()).count() == 2
                             {
                                 // Max 2 sidewalks or shoulders per road.
@@ -864,6 +885,26 @@ fn make_main_panel(
             .text("Access restrictions")
             .build_def(ctx)
             .centered_vert(),
+        {
+            // Only one-way roads can be made two-way for bikes
+            let edit_road = map.get_r_edit(road.id);
+            if one_way_driving_dir(&edit_road).is_some() {
+                let has_lane = has_contraflow_bike_lane(&edit_road);
+                let checkbox =
+                    Toggle::checkbox(ctx, "two-way for bikes", None, has_lane).centered_vert();
+                if road.contraflow_bikes_allowed() && !has_lane {
+                    Widget::col(vec![
+                        checkbox,
+                        "Bikes may already ride both ways here, but need a lane to do so"
+                            .text_widget(ctx),
+                    ])
+                } else {
+                    checkbox
+                }
+            } else {
+                Widget::nothing()
+            }
+        },
     ]);
 
     Panel::new_builder(
@@ -930,8 +971,12 @@ fn lane_type_to_icon(lt: LaneType) -> Option<&'static str> {
     match lt {
         LaneType::Driving => Some("system/assets/edit/driving.svg"),
         LaneType::Parking => Some("system/assets/edit/parking.svg"),
+        // TODO Needs its own icon
+        LaneType::LoadingZone => Some("system/assets/edit/parking.svg"),
         LaneType::Sidewalk | LaneType::Shoulder => Some("system/assets/edit/sidewalk.svg"),
         LaneType::Biking => Some("system/assets/edit/bike.svg"),
+        // TODO Needs its own icon
+        LaneType::SharedUse => Some("system/assets/edit/bike.svg"),
         LaneType::Bus => Some("system/assets/edit/bus.svg"),
         LaneType::SharedLeftTurn => Some("system/assets/map/shared_left_turn.svg"),
         LaneType::Construction => Some("system/assets/edit/construction.svg"),
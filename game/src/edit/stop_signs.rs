@@ -15,11 +15,11 @@ use widgetry::{
 use crate::app::App;
 use crate::app::Transition;
 use crate::common::CommonState;
-use crate::edit::{apply_map_edits, check_sidewalk_connectivity, TrafficSignalEditor};
+use crate::edit::{
+    apply_map_edits, check_sidewalk_connectivity, TrafficSignalEditor, TurnRestrictionEditor,
+};
 use crate::sandbox::GameplayMode;
 
-// TODO For now, individual turns can't be manipulated. Banning turns could be useful, but I'm not
-// sure what to do about the player orphaning a section of the map.
 pub struct StopSignEditor {
     id: IntersectionID,
     mode: GameplayMode,
@@ -36,20 +36,28 @@ impl StopSignEditor {
         mode: GameplayMode,
     ) -> Box<dyn State<App>> {
         app.primary.current_selection = None;
-        let geom = app
-            .primary
-            .map
-            .get_stop_sign(id)
-            .roads
-            .iter()
-            .filter_map(|(r, ss)| {
-                DrawIntersection::stop_sign_geom(ss, &app.primary.map)
-                    .map(|(octagon, pole, _)| (*r, (octagon, pole)))
-            })
-            .collect();
+        let sign = app.primary.map.get_stop_sign(id);
+        // Nobody stops at a roundabout, so don't offer to flip signs there
+        let geom = if sign.roundabout {
+            HashMap::new()
+        } else {
+            sign.roads
+                .iter()
+                .filter_map(|(r, ss)| {
+                    DrawIntersection::stop_sign_geom(ss, &app.primary.map)
+                        .map(|(octagon, pole, _)| (*r, (octagon, pole)))
+                })
+                .collect()
+        };
 
         let panel = Panel::new_builder(Widget::col(vec![
-            Line("Stop sign editor").small_heading().into_widget(ctx),
+            Line(if sign.roundabout {
+                "Roundabout editor"
+            } else {
+                "Stop sign editor"
+            })
+            .small_heading()
+            .into_widget(ctx),
             ctx.style()
                 .btn_outline
                 .text("reset to default")
@@ -68,6 +76,23 @@ impl StopSignEditor {
                 .btn_outline
                 .text("convert to traffic signal")
                 .build_def(ctx),
+            if sign.roundabout {
+                ctx.style()
+                    .btn_outline
+                    .text("convert to stop signs")
+                    .build_def(ctx)
+            } else {
+                ctx.style()
+                    .btn_outline
+                    .text("convert to mini-roundabout")
+                    .disabled(sign.roads.len() < 3)
+                    .build_def(ctx)
+            },
+            ctx.style()
+                .btn_outline
+                .text("edit turn restrictions")
+                .hotkey(Key::T)
+                .build_def(ctx),
             ctx.style()
                 .btn_solid_primary
                 .text("Finish")
@@ -148,6 +173,45 @@ impl SimpleState<App> for StopSignEditor {
                     self.mode.clone(),
                 ))
             }
+            "convert to mini-roundabout" => {
+                let mut sign = app.primary.map.get_stop_sign(self.id).clone();
+                sign.convert_to_mini_roundabout();
+
+                let mut edits = app.primary.map.get_edits().clone();
+                edits.commands.push(EditCmd::ChangeIntersection {
+                    i: self.id,
+                    old: app.primary.map.get_i_edit(self.id),
+                    new: EditIntersection::StopSign(sign),
+                });
+                apply_map_edits(ctx, app, edits);
+                Transition::Replace(StopSignEditor::new_state(
+                    ctx,
+                    app,
+                    self.id,
+                    self.mode.clone(),
+                ))
+            }
+            "convert to stop signs" => {
+                let mut sign = app.primary.map.get_stop_sign(self.id).clone();
+                sign.convert_to_stop_signs(&app.primary.map);
+
+                let mut edits = app.primary.map.get_edits().clone();
+                edits.commands.push(EditCmd::ChangeIntersection {
+                    i: self.id,
+                    old: app.primary.map.get_i_edit(self.id),
+                    new: EditIntersection::StopSign(sign),
+                });
+                apply_map_edits(ctx, app, edits);
+                Transition::Replace(StopSignEditor::new_state(
+                    ctx,
+                    app,
+                    self.id,
+                    self.mode.clone(),
+                ))
+            }
+            "edit turn restrictions" => {
+                Transition::Push(TurnRestrictionEditor::new_state(ctx, app, self.id))
+            }
             _ => unreachable!(),
         }
     }
@@ -1,4 +1,4 @@
-use geom::Duration;
+use geom::{Duration, Time};
 use map_gui::tools::{ChooseSomething, FilePicker, PopupMsg};
 use map_model::{
     ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, StageType,
@@ -10,10 +10,13 @@ use widgetry::{
 
 use crate::app::{App, Transition};
 use crate::edit::traffic_signals::{BundleEdits, TrafficSignalEditor};
-use crate::edit::{apply_map_edits, check_sidewalk_connectivity, StopSignEditor};
+use crate::edit::{
+    apply_map_edits, check_sidewalk_connectivity, StopSignEditor, TurnRestrictionEditor,
+};
 use crate::sandbox::GameplayMode;
 
 pub struct ChangeDuration {
+    plan: usize,
     idx: usize,
 }
 
@@ -22,8 +25,10 @@ impl ChangeDuration {
         ctx: &mut EventCtx,
         app: &App,
         signal: &ControlTrafficSignal,
+        plan: usize,
         idx: usize,
     ) -> Box<dyn State<App>> {
+        let stage = &signal.get_plan_stages(plan)[idx];
         let panel = Panel::new_builder(Widget::col(vec![
             Widget::row(vec![
                 Line("How long should this stage last?")
@@ -36,8 +41,11 @@ impl ChangeDuration {
                 Spinner::widget(
                     ctx,
                     "duration",
-                    (signal.get_min_crossing_time(idx), Duration::minutes(5)),
-                    signal.stages[idx].stage_type.simple_duration(),
+                    (
+                        signal.get_min_crossing_time(plan, idx),
+                        Duration::minutes(5),
+                    ),
+                    stage.stage_type.simple_duration(),
                     Duration::seconds(1.0),
                 ),
             ]),
@@ -45,7 +53,7 @@ impl ChangeDuration {
                 .secondary()
                 .into_widget(ctx),
             Widget::col(vec![
-                Text::from_all(match signal.stages[idx].stage_type {
+                Text::from_all(match stage.stage_type {
                     StageType::Fixed(_) => vec![
                         Line("Fixed timing").small_heading(),
                         Line(" (Adjust both values below to enable variable timing)"),
@@ -65,7 +73,7 @@ impl ChangeDuration {
                         ctx,
                         "additional",
                         (Duration::ZERO, Duration::minutes(5)),
-                        match signal.stages[idx].stage_type {
+                        match stage.stage_type {
                             StageType::Fixed(_) => Duration::ZERO,
                             StageType::Variable(_, _, additional) => additional,
                         },
@@ -80,7 +88,7 @@ impl ChangeDuration {
                         ctx,
                         "delay",
                         (Duration::ZERO, Duration::seconds(300.0)),
-                        match signal.stages[idx].stage_type {
+                        match stage.stage_type {
                             StageType::Fixed(_) => Duration::ZERO,
                             StageType::Variable(_, delay, _) => delay,
                         },
@@ -98,7 +106,7 @@ impl ChangeDuration {
                 .build_def(ctx),
         ]))
         .build(ctx);
-        <dyn SimpleState<_>>::new_state(panel, Box::new(ChangeDuration { idx }))
+        <dyn SimpleState<_>>::new_state(panel, Box::new(ChangeDuration { plan, idx }))
     }
 }
 
@@ -115,13 +123,14 @@ impl SimpleState<App> for ChangeDuration {
                 } else {
                     StageType::Variable(dt, delay, additional)
                 };
+                let plan = self.plan;
                 let idx = self.idx;
                 Transition::Multi(vec![
                     Transition::Pop,
                     Transition::ModifyState(Box::new(move |state, ctx, app| {
                         let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
                         editor.add_new_edit(ctx, app, idx, |ts| {
-                            ts.stages[idx].stage_type = new_type.clone();
+                            ts.get_plan_stages_mut(plan)[idx].stage_type = new_type.clone();
                         });
                     })),
                 ])
@@ -168,6 +177,92 @@ impl SimpleState<App> for ChangeDuration {
     }
 }
 
+pub struct ChangePlanStart {
+    plan: usize,
+}
+
+impl ChangePlanStart {
+    pub fn new_state(
+        ctx: &mut EventCtx,
+        app: &App,
+        signal: &ControlTrafficSignal,
+        plan: usize,
+    ) -> Box<dyn State<App>> {
+        // Keep the plans in order
+        let earliest = signal.get_plan_start_time(plan - 1) + Duration::minutes(1);
+        let next_start = if plan + 1 < signal.num_plans() {
+            signal.get_plan_start_time(plan + 1)
+        } else {
+            Duration::hours(24)
+        };
+        let latest = next_start - Duration::minutes(1);
+        let panel = Panel::new_builder(Widget::col(vec![
+            Widget::row(vec![
+                Line("When should this plan start?")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Widget::row(vec![
+                "Time after midnight:".text_widget(ctx).centered_vert(),
+                Spinner::widget(
+                    ctx,
+                    "start time",
+                    (earliest, latest),
+                    signal.get_plan_start_time(plan),
+                    Duration::minutes(1),
+                ),
+            ]),
+            format!(
+                "The previous plan starts at {}",
+                (Time::START_OF_DAY + signal.get_plan_start_time(plan - 1)).ampm_tostring()
+            )
+            .text_widget(ctx),
+            ctx.style()
+                .btn_solid_primary
+                .text("Apply")
+                .hotkey(Key::Enter)
+                .build_def(ctx),
+        ]))
+        .build(ctx);
+        <dyn SimpleState<_>>::new_state(panel, Box::new(ChangePlanStart { plan }))
+    }
+}
+
+impl SimpleState<App> for ChangePlanStart {
+    fn on_click(&mut self, _: &mut EventCtx, _: &mut App, x: &str, panel: &Panel) -> Transition {
+        match x {
+            "close" => Transition::Pop,
+            "Apply" => {
+                let start_time = panel.spinner("start time");
+                let plan = self.plan;
+                Transition::Multi(vec![
+                    Transition::Pop,
+                    Transition::ModifyState(Box::new(move |state, ctx, app| {
+                        let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                        let idx = editor.current_stage;
+                        editor.add_new_edit(ctx, app, idx, |ts| {
+                            ts.other_plans[plan - 1].start_time = start_time;
+                        });
+                    })),
+                ])
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn other_event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        if ctx.normal_left_click() && ctx.canvas.get_cursor_in_screen_space().is_none() {
+            return Transition::Pop;
+        }
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+}
+
 pub fn edit_entire_signal(
     ctx: &mut EventCtx,
     app: &App,
@@ -186,8 +281,10 @@ pub fn edit_entire_signal(
     let all_walk = "add an all-walk stage at the end";
     let major_minor_timing = "use timing pattern for a major/minor intersection";
     let stop_sign = "convert to stop signs";
+    let mini_roundabout = "convert to a mini-roundabout";
     let close = "close intersection for construction";
     let reset = "reset to default";
+    let turn_restrictions = "edit turn restrictions";
     let gmns_picker = "import from a new GMNS timing.csv";
     let gmns_existing = app
         .session
@@ -204,9 +301,11 @@ pub fn edit_entire_signal(
     // TODO Conflating stop signs and construction here
     if mode.can_edit_stop_signs() {
         choices.push(stop_sign.to_string());
+        choices.push(mini_roundabout.to_string());
         choices.push(close.to_string());
     }
     choices.push(reset.to_string());
+    choices.push(turn_restrictions.to_string());
     choices.push(gmns_picker.to_string());
     if let Some(x) = gmns_existing.clone() {
         choices.push(x);
@@ -298,6 +397,23 @@ pub fn edit_entire_signal(
                     Transition::Replace(StopSignEditor::new_state(ctx, app, i, mode)),
                 ])
             }
+            x if x == mini_roundabout => {
+                original.apply(app);
+
+                let mut sign = ControlStopSign::new(&app.primary.map, i);
+                sign.convert_to_mini_roundabout();
+                let mut edits = app.primary.map.get_edits().clone();
+                edits.commands.push(EditCmd::ChangeIntersection {
+                    i,
+                    old: app.primary.map.get_i_edit(i),
+                    new: EditIntersection::StopSign(sign),
+                });
+                apply_map_edits(ctx, app, edits);
+                Transition::Multi(vec![
+                    Transition::Pop,
+                    Transition::Replace(StopSignEditor::new_state(ctx, app, i, mode)),
+                ])
+            }
             x if x == close => {
                 original.apply(app);
 
@@ -329,6 +445,9 @@ pub fn edit_entire_signal(
                     });
                 })),
             ]),
+            x if x == turn_restrictions => {
+                Transition::Replace(TurnRestrictionEditor::new_state(ctx, app, i))
+            }
             x if x == gmns_picker => Transition::Replace(FilePicker::new_state(
                 ctx,
                 None,
@@ -45,6 +45,7 @@ pub fn import(map: &Map, i: IntersectionID, path: &str) -> Result<ControlTraffic
 
     let mut signal = ControlTrafficSignal::new(map, i.id);
     signal.stages.clear();
+    signal.other_plans.clear();
     for rec in records {
         let stage_idx = rec.stage - 1;
         match signal.stages.len().cmp(&stage_idx) {
@@ -3,13 +3,13 @@ use std::collections::{BTreeSet, VecDeque};
 use anyhow::Result;
 
 use abstutil::Timer;
-use geom::{Distance, Line, Polygon, Pt2D};
+use geom::{Distance, Duration, Line, Polygon, Pt2D, Time};
 use map_gui::options::TrafficSignalStyle;
 use map_gui::render::{traffic_signal, DrawMovement, DrawOptions};
 use map_gui::tools::PopupMsg;
 use map_model::{
     ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, MovementID, Stage, StageType,
-    TurnPriority,
+    TimingPlan, TurnPriority,
 };
 use widgetry::{
     include_labeled_bytes, lctrl, Color, ControlState, DrawBaselayer, Drawable, EventCtx,
@@ -36,6 +36,8 @@ pub struct TrafficSignalEditor {
 
     mode: GameplayMode,
     members: BTreeSet<IntersectionID>,
+    // All members have the same time-of-day plans; this one is being edited
+    current_plan: usize,
     current_stage: usize,
 
     movements: Vec<DrawMovement>,
@@ -74,9 +76,10 @@ impl TrafficSignalEditor {
         synced.apply(app);
 
         let mut editor = TrafficSignalEditor {
-            side_panel: make_side_panel(ctx, app, &members, 0),
+            side_panel: make_side_panel(ctx, app, &members, 0, 0),
             top_panel: make_top_panel(ctx, app, false, false),
             mode,
+            current_plan: 0,
             current_stage: 0,
             movements: Vec::new(),
             movement_selected: None,
@@ -94,18 +97,47 @@ impl TrafficSignalEditor {
     }
 
     fn change_stage(&mut self, ctx: &mut EventCtx, app: &App, idx: usize) {
+        // Undo or replacing the entire signal may remove the plan being edited
+        let num_plans = app
+            .primary
+            .map
+            .get_traffic_signal(*self.members.iter().next().unwrap())
+            .num_plans();
+        if self.current_plan >= num_plans {
+            self.current_plan = 0;
+        }
+
         if self.current_stage == idx {
-            let mut new = make_side_panel(ctx, app, &self.members, self.current_stage);
+            let mut new = make_side_panel(
+                ctx,
+                app,
+                &self.members,
+                self.current_plan,
+                self.current_stage,
+            );
             new.restore(ctx, &self.side_panel);
             self.side_panel = new;
         } else {
             self.current_stage = idx;
-            self.side_panel = make_side_panel(ctx, app, &self.members, self.current_stage);
+            self.side_panel = make_side_panel(
+                ctx,
+                app,
+                &self.members,
+                self.current_plan,
+                self.current_stage,
+            );
         }
 
         self.recalc_draw_current(ctx, app);
     }
 
+    fn change_plan(&mut self, ctx: &mut EventCtx, app: &App, plan: usize) {
+        self.current_plan = plan;
+        self.current_stage = 0;
+        self.side_panel = make_side_panel(ctx, app, &self.members, plan, 0);
+        self.recalc_draw_current(ctx, app);
+    }
+
     fn add_new_edit<F: Fn(&mut ControlTrafficSignal)>(
         &mut self,
         ctx: &mut EventCtx,
@@ -129,12 +161,17 @@ impl TrafficSignalEditor {
         let mut batch = GeomBatch::new();
         let mut movements = Vec::new();
         for i in &self.members {
-            let stage = &app.primary.map.get_traffic_signal(*i).stages[self.current_stage];
+            let stage = &app
+                .primary
+                .map
+                .get_traffic_signal(*i)
+                .get_plan_stages(self.current_plan)[self.current_stage];
             for (m, draw) in DrawMovement::for_i(
                 ctx.prerender,
                 &app.primary.map,
                 &app.cs,
                 *i,
+                self.current_plan,
                 self.current_stage,
             ) {
                 if self
@@ -206,7 +243,7 @@ impl State<App> for TrafficSignalEditor {
             .primary
             .map
             .get_traffic_signal(*self.members.iter().next().unwrap());
-        let num_stages = canonical_signal.stages.len();
+        let num_stages = canonical_signal.get_plan_stages(self.current_plan).len();
 
         if let Outcome::Clicked(x) = self.side_panel.event(ctx) {
             match x.as_ref() {
@@ -227,8 +264,9 @@ impl State<App> for TrafficSignalEditor {
                     ));
                 }
                 "Add a new stage" => {
+                    let plan = self.current_plan;
                     self.add_new_edit(ctx, app, num_stages, |ts| {
-                        ts.stages.push(Stage::new());
+                        ts.get_plan_stages_mut(plan).push(Stage::new());
                     });
                     return Transition::Keep;
                 }
@@ -237,31 +275,80 @@ impl State<App> for TrafficSignalEditor {
                         ctx,
                         app,
                         canonical_signal,
+                        self.current_plan,
                         self.current_stage,
                     ));
                 }
                 "delete stage" => {
+                    let plan = self.current_plan;
                     let idx = self.current_stage;
                     self.add_new_edit(ctx, app, 0, |ts| {
-                        ts.stages.remove(idx);
+                        ts.get_plan_stages_mut(plan).remove(idx);
                     });
                     return Transition::Keep;
                 }
                 "move stage left" => {
+                    let plan = self.current_plan;
                     let idx = self.current_stage;
                     self.add_new_edit(ctx, app, idx - 1, |ts| {
-                        ts.stages.swap(idx, idx - 1);
+                        ts.get_plan_stages_mut(plan).swap(idx, idx - 1);
                     });
                     return Transition::Keep;
                 }
                 "move stage right" => {
+                    let plan = self.current_plan;
                     let idx = self.current_stage;
                     self.add_new_edit(ctx, app, idx + 1, |ts| {
-                        ts.stages.swap(idx, idx + 1);
+                        ts.get_plan_stages_mut(plan).swap(idx, idx + 1);
+                    });
+                    return Transition::Keep;
+                }
+                "Add a time-of-day plan" => {
+                    // Start an hour after the last plan, copying the current one
+                    let start_time = canonical_signal
+                        .get_plan_start_time(canonical_signal.num_plans() - 1)
+                        + Duration::hours(1);
+                    if start_time >= Duration::hours(24) {
+                        return Transition::Push(PopupMsg::new_state(
+                            ctx,
+                            "Error",
+                            vec!["The last plan already starts too late in the day"],
+                        ));
+                    }
+                    let plan = self.current_plan;
+                    self.current_plan = canonical_signal.num_plans();
+                    self.add_new_edit(ctx, app, 0, |ts| {
+                        let new_plan = TimingPlan {
+                            start_time,
+                            stages: ts.get_plan_stages(plan).clone(),
+                            offset: ts.get_plan_offset(plan),
+                        };
+                        ts.other_plans.push(new_plan);
+                    });
+                    return Transition::Keep;
+                }
+                "change plan start time" => {
+                    return Transition::Push(edits::ChangePlanStart::new_state(
+                        ctx,
+                        app,
+                        canonical_signal,
+                        self.current_plan,
+                    ));
+                }
+                "delete plan" => {
+                    let plan = self.current_plan;
+                    self.current_plan = 0;
+                    self.add_new_edit(ctx, app, 0, |ts| {
+                        ts.other_plans.remove(plan - 1);
                     });
                     return Transition::Keep;
                 }
                 x => {
+                    if let Some(x) = x.strip_prefix("plan ") {
+                        let plan = x.parse::<usize>().unwrap() - 1;
+                        self.change_plan(ctx, app, plan);
+                        return Transition::Keep;
+                    }
                     if let Some(x) = x.strip_prefix("stage ") {
                         // 123, Intersection #456
                         let parts = x.split(", Intersection #").collect::<Vec<_>>();
@@ -379,6 +466,7 @@ impl State<App> for TrafficSignalEditor {
                         ctx,
                         app,
                         self.members.clone(),
+                        self.current_plan,
                         self.current_stage,
                     ));
                 }
@@ -420,7 +508,7 @@ impl State<App> for TrafficSignalEditor {
                 for m in &self.movements {
                     let signal = app.primary.map.get_traffic_signal(m.id.parent);
                     if m.hitbox.contains_pt(pt) {
-                        let stage = &signal.stages[self.current_stage];
+                        let stage = &signal.get_plan_stages(self.current_plan)[self.current_stage];
                         let next_priority = match stage.get_priority_of_movement(m.id) {
                             TurnPriority::Banned => {
                                 if stage.could_be_protected(m.id, &signal.movements) {
@@ -453,10 +541,11 @@ impl State<App> for TrafficSignalEditor {
 
         if let Some((id, Some(pri))) = self.movement_selected {
             let signal = app.primary.map.get_traffic_signal(id.parent);
+            let stage = &signal.get_plan_stages(self.current_plan)[self.current_stage];
             let mut txt = Text::new();
             txt.add_line(Line(format!(
                 "{} {}",
-                match signal.stages[self.current_stage].get_priority_of_movement(id) {
+                match stage.get_priority_of_movement(id) {
                     TurnPriority::Protected => "Protected",
                     TurnPriority::Yield => "Yielding",
                     TurnPriority::Banned => "Forbidden",
@@ -479,15 +568,17 @@ impl State<App> for TrafficSignalEditor {
                 ctx,
                 format!(
                     "toggle from {:?} to {:?}",
-                    signal.stages[self.current_stage].get_priority_of_movement(id),
+                    stage.get_priority_of_movement(id),
                     pri
                 ),
             ) {
+                let plan = self.current_plan;
                 let idx = self.current_stage;
                 let signal = signal.clone();
                 self.add_new_edit(ctx, app, idx, |ts| {
                     if ts.id == id.parent {
-                        ts.stages[idx].edit_movement(&signal.movements[&id], pri);
+                        ts.get_plan_stages_mut(plan)[idx]
+                            .edit_movement(&signal.movements[&id], pri);
                     }
                 });
                 return Transition::KeepWithMouseover;
@@ -617,11 +708,13 @@ fn make_side_panel(
     ctx: &mut EventCtx,
     app: &App,
     members: &BTreeSet<IntersectionID>,
+    plan: usize,
     selected: usize,
 ) -> Panel {
     let map = &app.primary.map;
     // Use any member for stage duration
     let canonical_signal = map.get_traffic_signal(*members.iter().next().unwrap());
+    let stages = canonical_signal.get_plan_stages(plan);
 
     let mut txt = Text::new();
     if members.len() == 1 {
@@ -655,6 +748,47 @@ fn make_side_panel(
     }
     let mut col = vec![txt.into_widget(ctx)];
 
+    // Time-of-day plan controls
+    let mut plans_row = Vec::new();
+    for idx in 0..canonical_signal.num_plans() {
+        let label = format!(
+            "From {}",
+            (Time::START_OF_DAY + canonical_signal.get_plan_start_time(idx)).ampm_tostring()
+        );
+        plans_row.push(if idx == plan {
+            ctx.style()
+                .btn_solid_primary
+                .text(label)
+                .build_widget(ctx, format!("plan {}", idx + 1))
+        } else {
+            ctx.style()
+                .btn_outline
+                .text(label)
+                .build_widget(ctx, format!("plan {}", idx + 1))
+        });
+    }
+    if plan != 0 {
+        plans_row.push(
+            ctx.style()
+                .btn_plain
+                .icon("system/assets/tools/pencil.svg")
+                .build_widget(ctx, "change plan start time"),
+        );
+        plans_row.push(
+            ctx.style()
+                .btn_solid_destructive
+                .icon("system/assets/tools/trash.svg")
+                .build_widget(ctx, "delete plan"),
+        );
+    }
+    plans_row.push(
+        ctx.style()
+            .btn_plain
+            .icon("system/assets/speed/plus.svg")
+            .build_widget(ctx, "Add a time-of-day plan"),
+    );
+    col.push(Widget::row(plans_row).padding(10).bg(app.cs.inner_panel_bg));
+
     // Stage controls
     col.push(
         Widget::row(vec![
@@ -670,9 +804,9 @@ fn make_side_panel(
                 .icon_bytes(include_labeled_bytes!(
                     "../../../../widgetry/icons/arrow_right.svg"
                 ))
-                .disabled(selected == canonical_signal.stages.len() - 1)
+                .disabled(selected == stages.len() - 1)
                 .build_widget(ctx, "move stage right"),
-            match canonical_signal.stages[selected].stage_type {
+            match stages[selected].stage_type {
                 StageType::Fixed(d) => format!("Stage duration: {}", d),
                 StageType::Variable(min, delay, additional) => format!(
                     "Stage duration: {}, {}, {} (variable)",
@@ -686,7 +820,7 @@ fn make_side_panel(
                 .icon("system/assets/tools/pencil.svg")
                 .hotkey(Key::X)
                 .build_widget(ctx, "change duration"),
-            if canonical_signal.stages.len() > 1 {
+            if stages.len() > 1 {
                 ctx.style()
                     .btn_solid_destructive
                     .icon("system/assets/tools/trash.svg")
@@ -711,18 +845,18 @@ fn make_side_panel(
     );
 
     let mut stages_row = Vec::new();
-    for idx in 0..canonical_signal.stages.len() {
+    for idx in 0..stages.len() {
         let stage_btn = Widget::col(vec![
             format!(
                 "Stage {}: {}",
                 idx + 1,
-                match canonical_signal.stages[idx].stage_type {
+                match stages[idx].stage_type {
                     StageType::Fixed(d) => format!("{}", d),
                     StageType::Variable(min, _, _) => format!("{} (v)", min),
                 },
             )
             .text_widget(ctx),
-            draw_multiple_signals(ctx, app, members, idx, &translations),
+            draw_multiple_signals(ctx, app, members, plan, idx, &translations),
         ])
         .padding(10);
         // TODO Add a proper hover state to these buttons. Complication is that they're
@@ -743,7 +877,7 @@ fn make_side_panel(
         // TODO Say "normally" to account for variable stages?
         format!(
             "One full cycle lasts {}",
-            canonical_signal.simple_cycle_duration()
+            canonical_signal.simple_plan_cycle_duration(plan)
         )
         .text_widget(ctx)
         .centered_vert(),
@@ -800,28 +934,59 @@ impl BundleEdits {
         BundleEdits { signals }
     }
 
-    // If the intersections haven't been edited together before, the number of stages and the
-    // durations might not match up. Just initially force them to align somehow.
+    // If the intersections haven't been edited together before, the time-of-day plans, number of
+    // stages, and the durations might not match up. Just initially force them to align somehow.
     fn synchronize(app: &App, members: &BTreeSet<IntersectionID>) -> BundleEdits {
         let map = &app.primary.map;
-        // Pick one of the members with the most stages as canonical.
-        let canonical = map.get_traffic_signal(
-            *members
+        let mut signals: Vec<ControlTrafficSignal> = members
+            .iter()
+            .map(|i| map.get_traffic_signal(*i).clone())
+            .collect();
+
+        // Pick one of the members with the most plans as canonical, and make everybody else switch
+        // plans at the same times, using whatever plan they had in effect then.
+        let start_times: Vec<Duration> = signals
+            .iter()
+            .max_by_key(|signal| signal.num_plans())
+            .unwrap()
+            .other_plans
+            .iter()
+            .map(|plan| plan.start_time)
+            .collect();
+        for signal in &mut signals {
+            let other_plans = start_times
                 .iter()
-                .max_by_key(|i| map.get_traffic_signal(**i).stages.len())
-                .unwrap(),
-        );
+                .map(|start_time| {
+                    let plan = signal.plan_at(Time::START_OF_DAY + *start_time);
+                    TimingPlan {
+                        start_time: *start_time,
+                        stages: signal.get_plan_stages(plan).clone(),
+                        offset: signal.get_plan_offset(plan),
+                    }
+                })
+                .collect();
+            signal.other_plans = other_plans;
+        }
 
-        let mut signals = Vec::new();
-        for i in members {
-            let mut signal = map.get_traffic_signal(*i).clone();
-            for (idx, canonical_stage) in canonical.stages.iter().enumerate() {
-                if signal.stages.len() == idx {
-                    signal.stages.push(Stage::new());
+        for plan in 0..signals[0].num_plans() {
+            // Pick one of the members with the most stages as canonical.
+            let canonical: Vec<StageType> = signals
+                .iter()
+                .max_by_key(|signal| signal.get_plan_stages(plan).len())
+                .unwrap()
+                .get_plan_stages(plan)
+                .iter()
+                .map(|stage| stage.stage_type.clone())
+                .collect();
+            for signal in &mut signals {
+                let stages = signal.get_plan_stages_mut(plan);
+                for (idx, stage_type) in canonical.iter().enumerate() {
+                    if stages.len() == idx {
+                        stages.push(Stage::new());
+                    }
+                    stages[idx].stage_type = stage_type.clone();
                 }
-                signal.stages[idx].stage_type = canonical_stage.stage_type.clone();
             }
-            signals.push(signal);
         }
 
         BundleEdits { signals }
@@ -830,38 +995,47 @@ impl BundleEdits {
 
 // If None, nothing missing.
 fn check_for_missing_turns(app: &App, members: &BTreeSet<IntersectionID>) -> Option<BundleEdits> {
-    let mut all_missing = BTreeSet::new();
-    for i in members {
-        all_missing.extend(app.primary.map.get_traffic_signal(*i).missing_turns());
-    }
-    if all_missing.is_empty() {
-        return None;
-    }
-
     let mut bundle = BundleEdits::get_current(app, members);
-    // Stick all the missing turns in a new stage at the beginning.
-    for signal in &mut bundle.signals {
-        let mut stage = Stage::new();
-        // TODO Could do this more efficiently
-        for m in &all_missing {
-            if m.parent != signal.id {
-                continue;
-            }
-            if m.crosswalk {
-                stage.protected_movements.insert(*m);
-            } else {
-                stage.yield_movements.insert(*m);
+    let mut any_missing = false;
+    for plan in 0..bundle.signals[0].num_plans() {
+        let mut all_missing = BTreeSet::new();
+        for signal in &bundle.signals {
+            all_missing.extend(signal.missing_turns(plan));
+        }
+        if all_missing.is_empty() {
+            continue;
+        }
+        any_missing = true;
+
+        // Stick all the missing turns in a new stage at the beginning.
+        for signal in &mut bundle.signals {
+            let mut stage = Stage::new();
+            // TODO Could do this more efficiently
+            for m in &all_missing {
+                if m.parent != signal.id {
+                    continue;
+                }
+                if m.crosswalk {
+                    stage.protected_movements.insert(*m);
+                } else {
+                    stage.yield_movements.insert(*m);
+                }
             }
+            signal.get_plan_stages_mut(plan).insert(0, stage);
         }
-        signal.stages.insert(0, stage);
     }
-    Some(bundle)
+    if any_missing {
+        Some(bundle)
+    } else {
+        None
+    }
 }
 
 fn draw_multiple_signals(
     ctx: &mut EventCtx,
     app: &App,
     members: &BTreeSet<IntersectionID>,
+    plan: usize,
     idx: usize,
     translations: &[(f64, f64)],
 ) -> Widget {
@@ -874,7 +1048,7 @@ fn draw_multiple_signals(
         );
         traffic_signal::draw_signal_stage(
             ctx.prerender,
-            &app.primary.map.get_traffic_signal(*i).stages[idx],
+            &app.primary.map.get_traffic_signal(*i).get_plan_stages(plan)[idx],
             idx,
             *i,
             None,
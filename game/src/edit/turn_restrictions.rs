@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+use geom::{ArrowCap, Distance, Duration, Time};
+use map_gui::tools::PopupMsg;
+use map_model::{EditCmd, IntersectionID, Map, MovementBan, MovementID, TurnID, TurnType};
+use widgetry::{
+    Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    Panel, Spinner, State, TextExt, Toggle, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::edit::apply_map_edits;
+
+/// Ban or allow individual vehicle movements through one intersection.
+pub struct TurnRestrictionEditor {
+    panel: Panel,
+    i: IntersectionID,
+    mvmnt: MovementID,
+    draw: Drawable,
+}
+
+impl TurnRestrictionEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, i: IntersectionID) -> Box<dyn State<App>> {
+        match vehicle_movements(&app.primary.map, i).keys().next() {
+            Some(mvmnt) => TurnRestrictionEditor::for_movement(ctx, app, *mvmnt),
+            None => PopupMsg::new_state(
+                ctx,
+                "No turns",
+                vec![format!("No vehicles can turn at {}", i)],
+            ),
+        }
+    }
+
+    fn for_movement(ctx: &mut EventCtx, app: &mut App, mvmnt: MovementID) -> Box<dyn State<App>> {
+        app.primary.current_selection = None;
+
+        let map = &app.primary.map;
+        let i = mvmnt.parent;
+        let movements = vehicle_movements(map, i);
+        let current = map.get_i(i).movement_bans.get(&mvmnt).cloned();
+        let ban = current.unwrap_or(MovementBan {
+            except_buses: false,
+            except_bikes: false,
+            time_window: None,
+        });
+        let (start_hour, end_hour) = ban
+            .time_window
+            .map(|(start, end)| (start.get_hours(), end.get_hours()))
+            .unwrap_or((7, 9));
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Edit turn restrictions")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Widget::dropdown(
+                ctx,
+                "movement",
+                mvmnt,
+                movements
+                    .iter()
+                    .map(|(id, (turn_type, _))| Choice::new(describe(app, *id, *turn_type), *id))
+                    .collect(),
+            ),
+            match current {
+                Some(ban) => format!("Currently {}", ban.describe()),
+                None => "Currently allowed".to_string(),
+            }
+            .text_widget(ctx),
+            Toggle::checkbox(ctx, "except buses", None, ban.except_buses),
+            Toggle::checkbox(ctx, "except bikes", None, ban.except_bikes),
+            Toggle::checkbox(ctx, "only part of the day", None, ban.time_window.is_some()),
+            Widget::row(vec![
+                "From hour".text_widget(ctx).centered_vert(),
+                Spinner::widget(ctx, "start hour", (0, 23), start_hour as isize, 1),
+                "to hour".text_widget(ctx).centered_vert(),
+                Spinner::widget(ctx, "end hour", (1, 24), end_hour as isize, 1),
+            ]),
+            Line("Routes avoid part-time bans all day; drivers detour around them while active")
+                .secondary()
+                .into_widget(ctx),
+        ];
+        col.push(Widget::custom_row(vec![
+            ctx.style()
+                .btn_solid_destructive
+                .text("Ban")
+                .hotkey(Key::Enter)
+                .build_def(ctx),
+            ctx.style()
+                .btn_solid_primary
+                .text("Allow")
+                .disabled(current.is_none())
+                .build_def(ctx),
+        ]));
+
+        let mut batch = GeomBatch::new();
+        for (id, (_, turns)) in &movements {
+            let color = if *id == mvmnt {
+                app.cs.perma_selected_object
+            } else if map.get_i(i).movement_bans.contains_key(id) {
+                Color::RED.alpha(0.8)
+            } else {
+                continue;
+            };
+            for t in turns {
+                batch.push(
+                    color,
+                    map.get_t(*t)
+                        .geom
+                        .make_arrow(Distance::meters(0.5), ArrowCap::Triangle),
+                );
+            }
+        }
+
+        Box::new(TurnRestrictionEditor {
+            panel: Panel::new_builder(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            i,
+            mvmnt,
+            draw: ctx.upload(batch),
+        })
+    }
+}
+
+impl State<App> for TurnRestrictionEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                let new = match x.as_ref() {
+                    "close" => {
+                        return Transition::Pop;
+                    }
+                    "Ban" => {
+                        let time_window = if self.panel.is_checked("only part of the day") {
+                            let start = self.panel.spinner::<isize>("start hour") as usize;
+                            let end = self.panel.spinner::<isize>("end hour") as usize;
+                            if start >= end {
+                                return Transition::Push(PopupMsg::new_state(
+                                    ctx,
+                                    "Error",
+                                    vec!["The ban has to end after it starts"],
+                                ));
+                            }
+                            Some((
+                                Time::START_OF_DAY + Duration::hours(start),
+                                Time::START_OF_DAY + Duration::hours(end),
+                            ))
+                        } else {
+                            None
+                        };
+                        Some(MovementBan {
+                            except_buses: self.panel.is_checked("except buses"),
+                            except_bikes: self.panel.is_checked("except bikes"),
+                            time_window,
+                        })
+                    }
+                    "Allow" => None,
+                    _ => unreachable!(),
+                };
+
+                let map = &app.primary.map;
+                let old = map.get_i(self.i).movement_bans.get(&self.mvmnt).cloned();
+                if old != new {
+                    let mut edits = map.get_edits().clone();
+                    edits.commands.push(EditCmd::ChangeMovementBan {
+                        mvmnt: self.mvmnt,
+                        old,
+                        new,
+                    });
+                    apply_map_edits(ctx, app, edits);
+                }
+                return Transition::Replace(TurnRestrictionEditor::for_movement(
+                    ctx, app, self.mvmnt,
+                ));
+            }
+            Outcome::Changed(_) => {
+                let mvmnt = self.panel.dropdown_value("movement");
+                if mvmnt != self.mvmnt {
+                    return Transition::Replace(TurnRestrictionEditor::for_movement(
+                        ctx, app, mvmnt,
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+    }
+}
+
+/// Groups all of the vehicle turns through an intersection by movement.
+fn vehicle_movements(
+    map: &Map,
+    i: IntersectionID,
+) -> BTreeMap<MovementID, (TurnType, Vec<TurnID>)> {
+    let mut movements: BTreeMap<MovementID, (TurnType, Vec<TurnID>)> = BTreeMap::new();
+    for t in &map.get_i(i).turns {
+        if t.between_sidewalks() {
+            continue;
+        }
+        movements
+            .entry(t.id.to_movement(map))
+            .or_insert_with(|| (t.turn_type, Vec::new()))
+            .1
+            .push(t.id);
+    }
+    movements
+}
+
+fn describe(app: &App, mvmnt: MovementID, turn_type: TurnType) -> String {
+    let map = &app.primary.map;
+    let lang = app.opts.language.as_ref();
+    let turn = match turn_type {
+        TurnType::Left => "left turn",
+        TurnType::Right => "right turn",
+        TurnType::UTurn => "U-turn",
+        _ => "straight",
+    };
+    format!(
+        "{} onto {} ({})",
+        map.get_r(mvmnt.from.id).get_name(lang),
+        map.get_r(mvmnt.to.id).get_name(lang),
+        turn
+    )
+}
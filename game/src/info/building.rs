@@ -55,6 +55,19 @@ fn info_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingI
         kv.push(("Parking", "None".to_string()));
     }
 
+    if let Some(dock) = b.bike_dock {
+        let docked = app
+            .primary
+            .sim
+            .bike_share_dock_status(id)
+            .map(|(docked, _)| docked)
+            .unwrap_or(0);
+        kv.push((
+            "Bike-share",
+            format!("{}, {} there now", dock.describe(), docked),
+        ));
+    }
+
     rows.extend(make_table(ctx, kv));
 
     let mut txt = Text::new();
@@ -111,6 +124,17 @@ fn info_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingI
         rows.push(txt.into_widget(ctx))
     }
 
+    rows.push(
+        ctx.style()
+            .btn_outline
+            .text(if b.bike_dock.is_some() {
+                "Edit bike-share dock"
+            } else {
+                "Add bike-share dock"
+            })
+            .build_widget(ctx, format!("edit bike dock {}", id)),
+    );
+
     if app.opts.dev {
         rows.push(
             ctx.style()
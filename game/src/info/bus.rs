@@ -129,10 +129,13 @@ fn bus_status_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: Car
         Tab::BusRoute(route.id),
     );
 
+    let (passengers, capacity) = app.primary.sim.get_transit_load(id);
     rows.push(
         Line(format!(
-            "Currently has {} passengers",
-            app.primary.sim.num_transit_passengers(id),
+            "Currently has {} passengers ({} seats, room for {} total)",
+            passengers,
+            capacity.seated,
+            capacity.total(),
         ))
         .into_widget(ctx),
     );
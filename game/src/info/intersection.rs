@@ -314,13 +314,23 @@ fn traffic_signal_body(ctx: &mut EventCtx, app: &App, id: IntersectionID) -> Wid
     let bbox = Polygon::rectangle(zoom * bounds.width(), zoom * bounds.height());
 
     let signal = app.primary.map.get_traffic_signal(id);
+    let plan = app.primary.sim.current_signal_plan(id);
+    let stages = signal.get_plan_stages(plan);
     {
         let mut txt = Text::new();
-        txt.add_line(Line(format!("{} stages", signal.stages.len())).small_heading());
-        txt.add_line(format!("Signal offset: {}", signal.offset));
+        txt.add_line(Line(format!("{} stages", stages.len())).small_heading());
+        if signal.num_plans() > 1 {
+            txt.add_line(format!(
+                "Using time-of-day plan {} of {}, starting at {}",
+                plan + 1,
+                signal.num_plans(),
+                (Time::START_OF_DAY + signal.get_plan_start_time(plan)).ampm_tostring()
+            ));
+        }
+        txt.add_line(format!("Signal offset: {}", signal.get_plan_offset(plan)));
         {
             let mut total = Duration::ZERO;
-            for s in &signal.stages {
+            for s in stages {
                 total += s.stage_type.simple_duration();
             }
             // TODO Say "normally" or something?
@@ -329,7 +339,7 @@ fn traffic_signal_body(ctx: &mut EventCtx, app: &App, id: IntersectionID) -> Wid
         rows.push(txt.into_widget(ctx));
     }
 
-    for (idx, stage) in signal.stages.iter().enumerate() {
+    for (idx, stage) in stages.iter().enumerate() {
         rows.push(
             match stage.stage_type {
                 StageType::Fixed(d) => Line(format!("Stage {}: {}", idx + 1, d)),
@@ -442,7 +452,13 @@ fn header(
     let i = app.primary.map.get_i(id);
 
     let label = match i.intersection_type {
-        IntersectionType::StopSign => format!("{} (Stop signs)", id),
+        IntersectionType::StopSign => {
+            if app.primary.map.get_stop_sign(id).roundabout {
+                format!("{} (Roundabout)", id)
+            } else {
+                format!("{} (Stop signs)", id)
+            }
+        }
         IntersectionType::TrafficSignal => format!("{} (Traffic signals)", id),
         IntersectionType::Border => format!("Border #{}", id.0),
         IntersectionType::Construction => format!("{} (under construction)", id),
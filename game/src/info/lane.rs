@@ -1,7 +1,8 @@
 use std::collections::HashSet;
 
 use abstutil::prettyprint_usize;
-use map_model::{LaneID, PathConstraints};
+use map_model::{LaneID, ParkingPricing, PathConstraints};
+use sim::Analytics;
 use widgetry::{EventCtx, Line, LinePlot, PlotOptions, Series, Text, TextExt, Widget};
 
 use crate::app::App;
@@ -23,7 +24,7 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
 
     let mut kv = Vec::new();
 
-    if !l.is_walkable() {
+    if !l.is_walkable() || l.is_shared_use() {
         kv.push(("Type", l.lane_type.describe().to_string()));
     }
     if r.is_private() {
@@ -47,12 +48,54 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
                 l.number_parking_spots(app.primary.map.get_config())
             ),
         ));
+        kv.push(("Pricing", l.parking_pricing.describe()));
+        if l.charging_spots > 0 {
+            kv.push((
+                "EV chargers",
+                format!("{} spots", prettyprint_usize(l.charging_spots)),
+            ));
+        }
+        if l.parking_pricing != ParkingPricing::free() {
+            let (revenue, overstays) = Analytics::total_parking_revenue(
+                app.primary
+                    .sim
+                    .get_analytics()
+                    .parking_lane_revenue
+                    .get(&l.id),
+            );
+            kv.push((
+                "Revenue so far",
+                format!(
+                    "{}, {} cars overstayed",
+                    ParkingPricing::describe_cents(revenue),
+                    prettyprint_usize(overstays)
+                ),
+            ));
+        }
     } else {
         kv.push(("Speed limit", r.speed_limit.to_string(&app.opts.units)));
     }
 
     kv.push(("Length", l.length().to_string(&app.opts.units)));
 
+    if l.is_walkable() {
+        if let Some(los) = app.primary.sim.sidewalk_level_of_service(l.id) {
+            let worst = app
+                .primary
+                .sim
+                .get_analytics()
+                .sidewalk_los
+                .get(&l.id)
+                .and_then(|changes| changes.iter().map(|(_, x)| *x).max())
+                .unwrap_or(los)
+                .max(los);
+            kv.push((
+                "Level of service",
+                format!("{} now, {} at worst", los, worst),
+            ));
+        }
+    }
+
     rows.extend(make_table(ctx, kv));
 
     if l.is_parking() {
@@ -96,6 +139,13 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
         .bg(app.cs.inner_panel_bg)
         .outline(ctx.style().section_outline);
         rows.push(section);
+
+        rows.push(
+            ctx.style()
+                .btn_outline
+                .text("Edit parking")
+                .build_widget(ctx, format!("edit parking {}", l.id)),
+        );
     }
 
     Widget::col(rows)
@@ -263,6 +313,8 @@ fn header(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID, tab: Tab
         "Shoulder"
     } else if l.is_sidewalk() {
         "Sidewalk"
+    } else if l.is_shared_use() {
+        "Shared-use path"
     } else {
         "Lane"
     };
@@ -5,7 +5,9 @@ pub use trip::OpenTrip;
 use geom::{Circle, Distance, Polygon, Time};
 use map_gui::tools::open_browser;
 use map_gui::ID;
-use map_model::{AreaID, BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, ParkingLotID};
+use map_model::{
+    AreaID, BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, ParkingArea, ParkingLotID,
+};
 use sim::{
     AgentID, AgentType, Analytics, CarID, ParkingSpot, PedestrianID, PersonID, PersonState, TripID,
     VehicleType,
@@ -18,7 +20,7 @@ use widgetry::{
 use crate::app::{App, Transition};
 use crate::common::{color_for_agent_type, Warping};
 use crate::debug::path_counter::PathCounter;
-use crate::edit::{EditMode, RouteEditor};
+use crate::edit::{BikeDockEditor, EditMode, ParkingEditor, RouteEditor};
 use crate::layer::PANEL_PLACEMENT;
 use crate::sandbox::{dashboards, GameplayMode, SandboxMode, TimeWarpScreen};
 
@@ -154,6 +156,8 @@ impl Tab {
                         "status" => Tab::BusStatus(c),
                         _ => unreachable!(),
                     }
+                } else if let Some(p) = app.primary.sim.get_ride_hail_passenger(c) {
+                    Tab::PersonTrips(p, BTreeMap::new())
                 } else {
                     Tab::ParkedCar(c)
                 }
@@ -584,6 +588,47 @@ impl InfoPanel {
                             )),
                         ])),
                     )
+                } else if let Some(x) = action.strip_prefix("edit parking ") {
+                    let area = if let Some(l) = x.strip_prefix("Lane #") {
+                        ParkingArea::from_lane(
+                            LaneID(l.parse::<usize>().unwrap()),
+                            &app.primary.map,
+                        )
+                    } else {
+                        ParkingArea::Lot(ParkingLotID(
+                            x.strip_prefix("Parking lot #")
+                                .unwrap()
+                                .parse::<usize>()
+                                .unwrap(),
+                        ))
+                    };
+                    (
+                        false,
+                        Some(Transition::Multi(vec![
+                            Transition::Push(EditMode::new_state(
+                                ctx,
+                                app,
+                                ctx_actions.gameplay_mode(),
+                            )),
+                            Transition::Push(ParkingEditor::new_state(ctx, app, area)),
+                        ])),
+                    )
+                } else if let Some(x) = action.strip_prefix("edit bike dock Building #") {
+                    (
+                        false,
+                        Some(Transition::Multi(vec![
+                            Transition::Push(EditMode::new_state(
+                                ctx,
+                                app,
+                                ctx_actions.gameplay_mode(),
+                            )),
+                            Transition::Push(BikeDockEditor::new_state(
+                                ctx,
+                                app,
+                                BuildingID(x.parse::<usize>().unwrap()),
+                            )),
+                        ])),
+                    )
                 } else if action == "Explore demand across all traffic signals" {
                     (
                         false,
@@ -1,7 +1,8 @@
 use std::collections::HashSet;
 
 use abstutil::prettyprint_usize;
-use map_model::ParkingLotID;
+use map_model::{ParkingLotID, ParkingPricing};
+use sim::Analytics;
 use widgetry::{EventCtx, Line, LinePlot, PlotOptions, Series, TextExt, Widget};
 
 use crate::app::App;
@@ -27,6 +28,33 @@ fn info_body(ctx: &mut EventCtx, app: &App, id: ParkingLotID) -> Widget {
         )
         .text_widget(ctx),
     );
+    rows.push(format!("Pricing: {}", pl.pricing.describe()).text_widget(ctx));
+    if pl.charging_spots > 0 {
+        rows.push(
+            format!(
+                "EV chargers: {} spots",
+                prettyprint_usize(pl.charging_spots)
+            )
+            .text_widget(ctx),
+        );
+    }
+    if pl.pricing != ParkingPricing::free() {
+        let (revenue, overstays) = Analytics::total_parking_revenue(
+            app.primary
+                .sim
+                .get_analytics()
+                .parking_lot_revenue
+                .get(&pl.id),
+        );
+        rows.push(
+            format!(
+                "Revenue so far: {}, {} cars overstayed",
+                ParkingPricing::describe_cents(revenue),
+                prettyprint_usize(overstays)
+            )
+            .text_widget(ctx),
+        );
+    }
 
     let mut series = vec![Series {
         label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
@@ -69,6 +97,13 @@ fn info_body(ctx: &mut EventCtx, app: &App, id: ParkingLotID) -> Widget {
     .outline(ctx.style().section_outline);
     rows.push(section);
 
+    rows.push(
+        ctx.style()
+            .btn_outline
+            .text("Edit parking")
+            .build_widget(ctx, format!("edit parking {}", pl.id)),
+    );
+
     if app.opts.dev {
         rows.push(
             ctx.style()
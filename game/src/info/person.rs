@@ -146,8 +146,8 @@ fn trips_body(
                     ctx.prerender,
                     match trip.mode {
                         TripMode::Walk => "system/assets/meters/pedestrian.svg",
-                        TripMode::Bike => "system/assets/meters/bike.svg",
-                        TripMode::Drive => "system/assets/meters/car.svg",
+                        TripMode::Bike | TripMode::BikeShare => "system/assets/meters/bike.svg",
+                        TripMode::Drive | TripMode::RideHail => "system/assets/meters/car.svg",
                         TripMode::Transit => "system/assets/meters/bus.svg",
                     },
                 )
@@ -356,7 +356,15 @@ fn bio_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: PersonID)
         } else if let PersonState::Trip(t) = person.state {
             match app.primary.sim.trip_to_agent(t) {
                 TripResult::Ok(AgentID::Car(x)) if x == v.id => {
-                    rows.push(format!("Owner of {} (currently driving)", v.id).text_widget(ctx));
+                    let mut status = format!("Owner of {} (currently driving", v.id);
+                    if let Some(battery) = app.primary.sim.get_battery(v.id) {
+                        status.push_str(&format!(
+                            ", battery {}% charged",
+                            (battery.fraction_charged() * 100.0).round()
+                        ));
+                    }
+                    status.push(')');
+                    rows.push(status.text_widget(ctx));
                 }
                 _ => {
                     rows.push(format!("Owner of {} (off-map)", v.id).text_widget(ctx));
@@ -553,17 +561,21 @@ fn parked_car_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: Car
     // TODO prev trips, next trips, etc
     let mut rows = vec![];
 
-    let p = app.primary.sim.get_owner_of_car(id).unwrap();
-    rows.push(
-        ctx.style()
-            .btn_outline
-            .text(format!("Owned by {}", p))
-            .build_def(ctx),
-    );
-    details.hyperlinks.insert(
-        format!("Owned by {}", p),
-        Tab::PersonTrips(p, BTreeMap::new()),
-    );
+    if let Some(p) = app.primary.sim.get_owner_of_car(id) {
+        rows.push(
+            ctx.style()
+                .btn_outline
+                .text(format!("Owned by {}", p))
+                .build_def(ctx),
+        );
+        details.hyperlinks.insert(
+            format!("Owned by {}", p),
+            Tab::PersonTrips(p, BTreeMap::new()),
+        );
+    } else {
+        // Ride-hail vehicles aren't owned by anybody
+        rows.push(format!("{} isn't carrying anybody right now", id).text_widget(ctx));
+    }
 
     if let Some(p) = app.primary.sim.lookup_parked_car(id) {
         match p.spot {
@@ -591,6 +603,22 @@ fn parked_car_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: Car
             )
             .text_widget(ctx),
         );
+        if let Some(battery) = p.vehicle.battery {
+            rows.push(
+                if p.spot.has_charger(&app.primary.map) {
+                    format!(
+                        "Battery {}% charged when parked here, plugged into a charger",
+                        (battery.fraction_charged() * 100.0).round()
+                    )
+                } else {
+                    format!(
+                        "Battery {}% charged",
+                        (battery.fraction_charged() * 100.0).round()
+                    )
+                }
+                .text_widget(ctx),
+            );
+        }
     } else {
         rows.push("No longer parked".text_widget(ctx));
     }
@@ -628,8 +656,17 @@ fn header(
                     AgentID::Car(c) => match c.vehicle_type {
                         VehicleType::Car => ("driving", Some("system/assets/meters/car.svg")),
                         VehicleType::Bike => ("biking", Some("system/assets/meters/bike.svg")),
-                        VehicleType::Bus | VehicleType::Train => unreachable!(),
+                        VehicleType::Delivery => {
+                            ("delivering", Some("system/assets/meters/car.svg"))
+                        }
+                        VehicleType::Bus | VehicleType::Train | VehicleType::RideHail => {
+                            unreachable!()
+                        }
                     },
+                    AgentID::BusPassenger(_, c) if c.vehicle_type == VehicleType::RideHail => (
+                        "riding in a ride-hail",
+                        Some("system/assets/meters/car.svg"),
+                    ),
                     AgentID::BusPassenger(_, _) => {
                         ("riding a bus", Some("system/assets/meters/bus.svg"))
                     }
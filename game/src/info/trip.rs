@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashSet};
 
 use maplit::btreemap;
 
-use geom::{Distance, Duration, Percent, Polygon, Pt2D};
+use geom::{Circle, Distance, Duration, Percent, Polygon, Pt2D};
 use map_gui::ID;
 use map_model::{Map, Path, PathStep, Traversable};
 use sim::{
@@ -329,6 +329,19 @@ pub fn finished(
             waiting.to_string(&app.opts.units).text_widget(ctx),
         ]));
 
+        let emitted = app.primary.sim.get_trip_emissions(id);
+        if emitted.energy_kwh > 0.0 {
+            col.push(Widget::custom_row(vec![
+                Widget::custom_row(vec![Line("Emissions").secondary().into_widget(ctx)])
+                    .force_width_window_pct(ctx, col_width),
+                format!(
+                    "{:.0} g CO2, {:.1} g NOx, {:.1} kWh",
+                    emitted.co2_grams, emitted.nox_grams, emitted.energy_kwh
+                )
+                .text_widget(ctx),
+            ]));
+        }
+
         col.push(Widget::custom_row(vec![
             Widget::custom_row(vec![Line("Purpose").secondary().into_widget(ctx)])
                 .force_width_window_pct(ctx, col_width),
@@ -442,6 +455,10 @@ fn describe_problems(
                     }
                     Problem::ArterialIntersectionCrossing(_) => {}
                     Problem::IntersectionDelay(_, _) => {}
+                    Problem::TransitBoardingDenied(_, _) => {}
+                    Problem::NoChargerAvailable(_) | Problem::BatteryDepleted(_) => {}
+                    Problem::DockEmpty(_) | Problem::DockFull(_) => {}
+                    Problem::RideHailUnreachable(_) => {}
                 }
             }
             let mut txt = Text::new();
@@ -587,6 +604,106 @@ fn draw_problems(
                     ]),
                 ));
             }
+            Problem::TransitBoardingDenied(bs, route) => {
+                let pt = map.get_bs(*bs).sidewalk_pos.pt(map);
+                details.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    Circle::new(pt, Distance::meters(10.0)).to_polygon(),
+                    Text::from(format!(
+                        "A vehicle on route {} was too full to board here.",
+                        map.get_br(*route).short_name
+                    )),
+                ));
+            }
+            Problem::NoChargerAvailable(l) => {
+                let pt = map.get_l(*l).lane_center_pts.middle();
+                details.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    map.get_parent(*l).get_thick_polygon(map),
+                    Text::from("The battery was low, but no charger was free near here."),
+                ));
+            }
+            Problem::BatteryDepleted(on) => {
+                let pt = on.get_polyline(map).middle();
+                details.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    match on {
+                        Traversable::Lane(l) => map.get_parent(*l).get_thick_polygon(map),
+                        Traversable::Turn(t) => map.get_i(t.parent).polygon.clone(),
+                    },
+                    Text::from("The battery ran out of energy near here."),
+                ));
+            }
+            Problem::DockEmpty(b) | Problem::DockFull(b) => {
+                let pt = map.get_b(*b).polygon.center();
+                details.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    map.get_b(*b).polygon.clone(),
+                    Text::from(if matches!(problem, Problem::DockEmpty(_)) {
+                        "The bike-share dock here was empty, so they walked instead."
+                    } else {
+                        "The bike-share dock here was full, so they rode on to another one."
+                    }),
+                ));
+            }
+            Problem::RideHailUnreachable(b) => {
+                let pt = map.get_b(*b).polygon.center();
+                details.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    map.get_b(*b).polygon.clone(),
+                    Text::from("No ride-hail vehicle could reach this pickup, so they gave up."),
+                ));
+            }
         }
     }
 }
@@ -684,11 +801,17 @@ fn make_timeline(
                     TripPhaseType::Driving => "system/assets/timeline/driving.svg",
                     TripPhaseType::Walking => "system/assets/timeline/walking.svg",
                     TripPhaseType::Biking => "system/assets/timeline/biking.svg",
-                    TripPhaseType::Parking => "system/assets/timeline/parking.svg",
+                    TripPhaseType::Parking
+                    | TripPhaseType::SearchingForParking
+                    | TripPhaseType::Delivering(_) => "system/assets/timeline/parking.svg",
                     TripPhaseType::WaitingForBus(_, _) => {
                         "system/assets/timeline/waiting_for_bus.svg"
                     }
                     TripPhaseType::RidingBus(_, _, _) => "system/assets/timeline/riding_bus.svg",
+                    TripPhaseType::WaitingForRideHail => {
+                        "system/assets/timeline/waiting_for_bus.svg"
+                    }
+                    TripPhaseType::RidingRideHail(_) => "system/assets/timeline/driving.svg",
                     TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
                     TripPhaseType::DelayedStart => "system/assets/timeline/delayed_start.svg",
                 },
@@ -769,7 +892,8 @@ fn make_trip_details(
             // Don't show the elevation plot for somebody walking to their car
             if ((trip.mode == TripMode::Walk || trip.mode == TripMode::Transit)
                 && p.phase_type == TripPhaseType::Walking)
-                || (trip.mode == TripMode::Bike && p.phase_type == TripPhaseType::Biking)
+                || ((trip.mode == TripMode::Bike || trip.mode == TripMode::BikeShare)
+                    && p.phase_type == TripPhaseType::Biking)
             {
                 elevation.push(make_elevation(
                     ctx,
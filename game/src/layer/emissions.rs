@@ -0,0 +1,194 @@
+use abstutil::{prettyprint_usize, Counter};
+use geom::{Pt2D, Time};
+use map_gui::tools::{make_heatmap, ColorLegend, ColorNetwork, HeatmapOptions};
+use sim::Emissions;
+use widgetry::{Drawable, EventCtx, GeomBatch, GfxCtx, Line, Outcome, Panel, Text, Toggle, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+/// The heatmap is built from points spread along roads and at intersections, one per some amount
+/// of pollution. Cap the number of points, so big maps stay responsive.
+const MAX_HEATMAP_PTS: f64 = 20_000.0;
+
+pub struct EmissionsMap {
+    time: Time,
+    opts: Options,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for EmissionsMap {
+    fn name(&self) -> Option<&'static str> {
+        Some("emissions")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            let mut new = EmissionsMap::new(ctx, app, self.opts.clone());
+            new.panel.restore(ctx, &self.panel);
+            *self = new;
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            _ => {
+                let new_opts = self.options();
+                if self.opts != new_opts {
+                    *self = EmissionsMap::new(ctx, app, new_opts);
+                }
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl EmissionsMap {
+    pub fn new(ctx: &mut EventCtx, app: &App, opts: Options) -> EmissionsMap {
+        let map = &app.primary.map;
+        let analytics = app.primary.sim.get_analytics();
+        let amount = |x: &Emissions| {
+            if opts.nox {
+                x.nox_grams
+            } else {
+                x.co2_grams
+            }
+        };
+
+        let (unzoomed, zoomed, legend) = if let Some(ref o) = opts.heatmap {
+            let total: f64 = analytics
+                .emissions_per_road
+                .values()
+                .chain(analytics.emissions_per_intersection.values())
+                .map(amount)
+                .sum();
+            let per_pt = total / MAX_HEATMAP_PTS;
+
+            let mut pts: Vec<Pt2D> = Vec::new();
+            if per_pt > 0.0 {
+                for (r, x) in &analytics.emissions_per_road {
+                    let n = (amount(x) / per_pt).round() as usize;
+                    let pl = &map.get_r(*r).center_pts;
+                    for i in 0..n {
+                        let dist = pl.length() * ((i as f64 + 0.5) / n as f64);
+                        pts.push(pl.must_dist_along(dist).0);
+                    }
+                }
+                for (i, x) in &analytics.emissions_per_intersection {
+                    let n = (amount(x) / per_pt).round() as usize;
+                    let pt = map.get_i(*i).polygon.center();
+                    pts.extend(std::iter::repeat(pt).take(n));
+                }
+            }
+
+            let mut batch = GeomBatch::new();
+            let legend = make_heatmap(ctx, &mut batch, map.get_bounds(), pts, o);
+            (ctx.upload(batch), Drawable::empty(ctx), legend)
+        } else {
+            // Counters need whole numbers, so count milligrams
+            let mut per_road = Counter::new();
+            for (r, x) in &analytics.emissions_per_road {
+                per_road.add(*r, (amount(x) * 1000.0) as usize);
+            }
+            let mut per_intersection = Counter::new();
+            for (i, x) in &analytics.emissions_per_intersection {
+                per_intersection.add(*i, (amount(x) * 1000.0) as usize);
+            }
+
+            let mut colorer = ColorNetwork::new(app);
+            colorer.ranked_roads(per_road, &app.cs.good_to_bad_red);
+            colorer.ranked_intersections(per_intersection, &app.cs.good_to_bad_red);
+            let (unzoomed, zoomed) = colorer.build(ctx);
+            let legend = ColorLegend::gradient(
+                ctx,
+                &app.cs.good_to_bad_red,
+                vec!["lowest", "highest emissions"],
+            );
+            (unzoomed, zoomed, legend)
+        };
+
+        let panel = make_controls(ctx, app, &opts, legend);
+        EmissionsMap {
+            time: app.primary.sim.time(),
+            opts,
+            unzoomed,
+            zoomed,
+            panel,
+        }
+    }
+
+    fn options(&self) -> Options {
+        let heatmap = if self.panel.is_checked("Show heatmap") {
+            Some(HeatmapOptions::from_controls(&self.panel))
+        } else {
+            None
+        };
+        Options {
+            heatmap,
+            nox: !self.panel.is_checked("pollutant"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Options {
+    // If None, color roads and intersections by rank
+    pub heatmap: Option<HeatmapOptions>,
+    // If false, show CO2
+    pub nox: bool,
+}
+
+fn make_controls(ctx: &mut EventCtx, app: &App, opts: &Options, legend: Widget) -> Panel {
+    let total = app.primary.sim.get_total_emissions();
+
+    let mut col = vec![
+        header(ctx, "Emissions"),
+        Text::from_multiline(vec![
+            Line(format!(
+                "{} kg CO2, {} g NOx",
+                prettyprint_usize(total.co2_grams as usize / 1000),
+                prettyprint_usize(total.nox_grams as usize)
+            )),
+            Line(format!(
+                "{} kWh of fuel and electricity used",
+                prettyprint_usize(total.energy_kwh as usize)
+            ))
+            .secondary(),
+        ])
+        .into_widget(ctx),
+        Toggle::choice(ctx, "pollutant", "CO2", "NOx", None, !opts.nox),
+        Toggle::choice(
+            ctx,
+            "Show heatmap",
+            "Heatmap",
+            "Roads",
+            None,
+            opts.heatmap.is_some(),
+        ),
+    ];
+    if let Some(ref o) = opts.heatmap {
+        col.extend(o.to_controls(ctx, legend));
+    } else {
+        col.push(legend);
+    }
+
+    Panel::new_builder(Widget::col(col))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx)
+}
@@ -96,7 +96,7 @@ impl BikeActivity {
                     .get_r(*r)
                     .lanes_ltr()
                     .into_iter()
-                    .any(|(_, _, lt)| lt == LaneType::Biking)
+                    .any(|(_, _, lt)| lt == LaneType::Biking || lt == LaneType::SharedUse)
                 {
                     on_bike_lanes.add(*r, *count);
                 } else {
@@ -233,6 +233,9 @@ impl Static {
         for i in edits.original_intersections.keys() {
             colorer.add_i(*i, "modified road/intersection");
         }
+        for mvmnt in &edits.changed_movement_bans {
+            colorer.add_i(mvmnt.parent, "modified road/intersection");
+        }
 
         Static::new(
             ctx,
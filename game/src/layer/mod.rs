@@ -9,6 +9,7 @@ use crate::app::{App, Transition};
 use crate::sandbox::dashboards;
 
 pub mod elevation;
+mod emissions;
 pub mod favorites;
 pub mod map;
 mod pandemic;
@@ -104,6 +105,7 @@ impl PickLayer {
                     btn("throughput", Key::T),
                     btn("traffic jams", Key::J),
                     btn("cycling activity", Key::B),
+                    btn("emissions", Key::C),
                 ]),
                 Widget::col(vec![
                     "Map".text_widget(ctx),
@@ -176,6 +178,16 @@ impl State<App> for PickLayer {
                 "steep streets" => {
                     app.primary.layer = Some(Box::new(elevation::SteepStreets::new(ctx, app)));
                 }
+                "emissions" => {
+                    app.primary.layer = Some(Box::new(emissions::EmissionsMap::new(
+                        ctx,
+                        app,
+                        emissions::Options {
+                            heatmap: Some(HeatmapOptions::new()),
+                            nox: false,
+                        },
+                    )));
+                }
                 "elevation" => {
                     app.primary.layer = Some(Box::new(elevation::ElevationContours::new(ctx, app)));
                 }
@@ -73,6 +73,21 @@ impl ProblemMap {
                         Problem::ArterialIntersectionCrossing(t) => {
                             app.primary.map.get_t(*t).geom.middle()
                         }
+                        Problem::TransitBoardingDenied(bs, _) => app
+                            .primary
+                            .map
+                            .get_bs(*bs)
+                            .sidewalk_pos
+                            .pt(&app.primary.map),
+                        Problem::NoChargerAvailable(l) => {
+                            app.primary.map.get_l(*l).lane_center_pts.middle()
+                        }
+                        Problem::BatteryDepleted(on) => on.get_polyline(&app.primary.map).middle(),
+                        Problem::DockEmpty(b)
+                        | Problem::DockFull(b)
+                        | Problem::RideHailUnreachable(b) => {
+                            app.primary.map.get_b(*b).polygon.center()
+                        }
                     });
                 }
             }
@@ -133,6 +148,18 @@ impl ProblemMap {
             show_arterial_crossings: self
                 .panel
                 .is_checked("show where pedestrians cross arterial intersections"),
+            show_denied_boardings: self
+                .panel
+                .is_checked("show where buses and trains were too full to board"),
+            show_charging: self
+                .panel
+                .is_checked("show where electric cars couldn't charge or ran out of energy"),
+            show_docks: self
+                .panel
+                .is_checked("show where bike-share docks were empty or full"),
+            show_ride_hail: self
+                .panel
+                .is_checked("show where ride-hail vehicles couldn't reach somebody"),
         }
     }
 }
@@ -148,6 +175,10 @@ pub struct Options {
     show_complex_crossings: bool,
     show_overtakes: bool,
     show_arterial_crossings: bool,
+    show_denied_boardings: bool,
+    show_charging: bool,
+    show_docks: bool,
+    show_ride_hail: bool,
     // TODO Time range
 }
 
@@ -162,6 +193,10 @@ impl Options {
             show_complex_crossings: true,
             show_overtakes: true,
             show_arterial_crossings: true,
+            show_denied_boardings: true,
+            show_charging: true,
+            show_docks: true,
+            show_ride_hail: true,
         }
     }
 
@@ -174,6 +209,10 @@ impl Options {
             Problem::ComplexIntersectionCrossing(_) => self.show_complex_crossings,
             Problem::OvertakeDesired(_) => self.show_overtakes,
             Problem::ArterialIntersectionCrossing(_) => self.show_arterial_crossings,
+            Problem::TransitBoardingDenied(_, _) => self.show_denied_boardings,
+            Problem::NoChargerAvailable(_) | Problem::BatteryDepleted(_) => self.show_charging,
+            Problem::DockEmpty(_) | Problem::DockFull(_) => self.show_docks,
+            Problem::RideHailUnreachable(_) => self.show_ride_hail,
         }
     }
 }
@@ -237,6 +276,30 @@ fn make_controls(
         None,
         opts.show_arterial_crossings,
     ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where buses and trains were too full to board",
+        None,
+        opts.show_denied_boardings,
+    ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where electric cars couldn't charge or ran out of energy",
+        None,
+        opts.show_charging,
+    ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where bike-share docks were empty or full",
+        None,
+        opts.show_docks,
+    ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where ride-hail vehicles couldn't reach somebody",
+        None,
+        opts.show_ride_hail,
+    ));
 
     col.push(Toggle::choice(
         ctx,
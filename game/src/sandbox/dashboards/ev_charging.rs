@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use abstutil::{prettyprint_usize, Counter};
+use map_model::{LaneID, ParkingLotID, RoadID};
+use sim::{ParkingSpot, Problem};
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, TextExt, Widget};
+
+use crate::app::{App, Transition};
+use crate::info::Tab;
+use crate::sandbox::dashboards::DashTab;
+use crate::sandbox::SandboxMode;
+
+pub struct EvCharging {
+    panel: Panel,
+    /// The info panel to open for each row
+    tabs: Vec<Tab>,
+}
+
+struct Entry {
+    name: String,
+    tab: Tab,
+    chargers: usize,
+    in_use: usize,
+    sessions: usize,
+    energy_kwh: f64,
+}
+
+impl EvCharging {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let sim = &app.primary.sim;
+        let analytics = sim.get_analytics();
+
+        let mut per_lane: BTreeMap<LaneID, (usize, f64)> = BTreeMap::new();
+        let mut per_lot: BTreeMap<ParkingLotID, (usize, f64)> = BTreeMap::new();
+        for (_, spot, energy, _) in &analytics.ev_charging {
+            let entry = match spot {
+                ParkingSpot::Onstreet(l, _) => per_lane.entry(*l).or_insert((0, 0.0)),
+                ParkingSpot::Lot(pl, _) => per_lot.entry(*pl).or_insert((0, 0.0)),
+                ParkingSpot::Offstreet(_, _) => continue,
+            };
+            entry.0 += 1;
+            entry.1 += energy;
+        }
+
+        let mut entries = Vec::new();
+        for l in map.all_lanes().values() {
+            if !l.is_parking() || l.charging_spots == 0 {
+                continue;
+            }
+            let (sessions, energy_kwh) = per_lane.get(&l.id).cloned().unwrap_or((0, 0.0));
+            let free = sim
+                .get_free_onstreet_spots(l.id)
+                .into_iter()
+                .filter(|spot| spot.has_charger(map))
+                .count();
+            entries.push(Entry {
+                name: map.get_parent(l.id).get_name(app.opts.language.as_ref()),
+                tab: Tab::LaneInfo(l.id),
+                chargers: l.charging_spots,
+                in_use: l.charging_spots - free,
+                sessions,
+                energy_kwh,
+            });
+        }
+        for pl in map.all_parking_lots() {
+            if pl.charging_spots == 0 {
+                continue;
+            }
+            let (sessions, energy_kwh) = per_lot.get(&pl.id).cloned().unwrap_or((0, 0.0));
+            let free = sim
+                .get_free_lot_spots(pl.id)
+                .into_iter()
+                .filter(|spot| spot.has_charger(map))
+                .count();
+            entries.push(Entry {
+                name: pl.id.to_string(),
+                tab: Tab::ParkingLot(pl.id),
+                chargers: pl.charging_spots,
+                in_use: pl.charging_spots - free,
+                sessions,
+                energy_kwh,
+            });
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.sessions));
+
+        // Where did drivers want to charge, but couldn't?
+        let mut no_charger: Counter<RoadID> = Counter::new();
+        let mut example_lane: BTreeMap<RoadID, LaneID> = BTreeMap::new();
+        let mut depleted = 0;
+        for problems in analytics.problems_per_trip.values() {
+            for (_, problem) in problems {
+                match problem {
+                    Problem::NoChargerAvailable(l) => {
+                        let r = map.get_l(*l).parent;
+                        no_charger.inc(r);
+                        example_lane.insert(r, *l);
+                    }
+                    Problem::BatteryDepleted(_) => {
+                        depleted += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let total_energy: f64 = entries.iter().map(|entry| entry.energy_kwh).sum();
+        let mut col = vec![
+            DashTab::EvCharging.picker(ctx, app),
+            Line(format!(
+                "{:.1} kWh delivered by {} charging areas",
+                total_energy,
+                prettyprint_usize(entries.len())
+            ))
+            .small_heading()
+            .into_widget(ctx),
+            format!(
+                "{} times, electric cars with a low battery couldn't find a free charger nearby. {} \
+                 cars ran out of energy.",
+                prettyprint_usize(no_charger.sum()),
+                prettyprint_usize(depleted)
+            )
+            .text_widget(ctx),
+        ];
+        if entries.is_empty() {
+            col.push(
+                "There are no chargers yet. Add some to a parking lane or lot from its info panel."
+                    .text_widget(ctx),
+            );
+        }
+
+        let mut tabs = Vec::new();
+        for entry in entries {
+            col.push(Widget::row(vec![
+                ctx.style()
+                    .btn_outline
+                    .text(&entry.name)
+                    .build_widget(ctx, tabs.len().to_string()),
+                format!(
+                    "{} / {} chargers in use, {} charging sessions, {:.1} kWh delivered",
+                    prettyprint_usize(entry.in_use),
+                    prettyprint_usize(entry.chargers),
+                    prettyprint_usize(entry.sessions),
+                    entry.energy_kwh
+                )
+                .text_widget(ctx)
+                .centered_vert(),
+            ]));
+            tabs.push(entry.tab);
+        }
+
+        if no_charger.sum() > 0 {
+            col.push(
+                Line("Where drivers couldn't find a charger")
+                    .small_heading()
+                    .into_widget(ctx),
+            );
+            for (r, count) in no_charger.highest_n(10) {
+                col.push(Widget::row(vec![
+                    ctx.style()
+                        .btn_outline
+                        .text(map.get_r(r).get_name(app.opts.language.as_ref()))
+                        .build_widget(ctx, tabs.len().to_string()),
+                    format!("{} times", prettyprint_usize(count))
+                        .text_widget(ctx)
+                        .centered_vert(),
+                ]));
+                tabs.push(Tab::LaneInfo(example_lane[&r]));
+            }
+        }
+
+        Box::new(EvCharging {
+            panel: Panel::new_builder(Widget::col(col))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+            tabs,
+        })
+    }
+}
+
+impl State<App> for EvCharging {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let tab = match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                self.tabs[x.parse::<usize>().unwrap()].clone()
+            }
+            Outcome::Changed(_) => {
+                return DashTab::EvCharging
+                    .transition(ctx, app, &self.panel)
+                    .unwrap_or(Transition::Keep);
+            }
+            _ => {
+                return Transition::Keep;
+            }
+        };
+
+        Transition::Multi(vec![
+            Transition::Pop,
+            Transition::ModifyState(Box::new(move |state, ctx, app| {
+                let sandbox = state.downcast_mut::<SandboxMode>().unwrap();
+                let mut actions = sandbox.contextual_actions();
+                sandbox.controls.common.as_mut().unwrap().launch_info_panel(
+                    ctx,
+                    app,
+                    tab,
+                    &mut actions,
+                )
+            })),
+        ])
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
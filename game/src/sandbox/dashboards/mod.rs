@@ -7,10 +7,13 @@ use crate::app::App;
 use crate::app::Transition;
 
 mod commuter;
+mod ev_charging;
 mod generic_trip_table;
 mod misc;
 mod mode_shift;
 mod parking_overhead;
+mod parking_revenue;
+mod ride_hail;
 mod risks;
 mod selector;
 mod traffic_signals;
@@ -25,6 +28,9 @@ pub enum DashTab {
     TravelTimes,
     RiskSummaries,
     ParkingOverhead,
+    ParkingRevenue,
+    EvCharging,
+    RideHail,
     ActiveTraffic,
     TransitRoutes,
     CommuterPatterns,
@@ -39,6 +45,9 @@ impl DashTab {
             Choice::new("Travel Times", DashTab::TravelTimes),
             Choice::new("Risk Exposure", DashTab::RiskSummaries),
             Choice::new("Parking Overhead", DashTab::ParkingOverhead),
+            Choice::new("Parking Revenue", DashTab::ParkingRevenue),
+            Choice::new("EV Charging", DashTab::EvCharging),
+            Choice::new("Ride-hail", DashTab::RideHail),
             Choice::new("Active Traffic", DashTab::ActiveTraffic),
             Choice::new("Transit Routes", DashTab::TransitRoutes),
             Choice::new("Commuter Patterns", DashTab::CommuterPatterns),
@@ -68,6 +77,9 @@ impl DashTab {
             }
             DashTab::RiskSummaries => risks::RiskSummaries::new_state(ctx, app, false),
             DashTab::ParkingOverhead => parking_overhead::ParkingOverhead::new_state(ctx, app),
+            DashTab::ParkingRevenue => parking_revenue::ParkingRevenue::new_state(ctx, app),
+            DashTab::EvCharging => ev_charging::EvCharging::new_state(ctx, app),
+            DashTab::RideHail => ride_hail::RideHail::new_state(ctx, app),
             DashTab::ActiveTraffic => misc::ActiveTraffic::new_state(ctx, app),
             DashTab::TransitRoutes => misc::TransitRoutes::new_state(ctx, app),
             DashTab::CommuterPatterns => CommuterPatterns::new_state(ctx, app),
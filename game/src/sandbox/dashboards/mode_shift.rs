@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use abstutil::Counter;
+use abstutil::{prettyprint_usize, Counter};
 use geom::{Distance, Duration};
 use map_gui::tools::ColorNetwork;
 use map_model::{PathStepV2, RoadID};
@@ -33,6 +33,7 @@ impl ModeShift {
                     Line("Off-map starts/ends are excluded."),
                 ])
                 .into_widget(ctx),
+                mode_choice_summary(ctx, app),
                 ctx.style()
                     .btn_outline
                     .text("Show most important gaps in cycling infrastructure")
@@ -171,6 +172,31 @@ fn produce_raw_data(ctx: &mut EventCtx, app: &App) -> Vec<Entry> {
     .collect()
 }
 
+/// If people are choosing their mode as trips start, summarize how many switched so far.
+fn mode_choice_summary(ctx: &mut EventCtx, app: &App) -> Widget {
+    let shifts = &app.primary.sim.get_analytics().mode_shifts;
+    if shifts.is_empty() {
+        return Widget::nothing();
+    }
+    let mut cnt = Counter::new();
+    for (_, _, from, to) in shifts {
+        cnt.inc((*from, *to));
+    }
+    let mut txt = Text::from(Line(format!(
+        "Mode choice has switched {} trips so far",
+        prettyprint_usize(shifts.len())
+    )));
+    for ((from, to), n) in cnt.consume() {
+        txt.add_line(format!(
+            "- {} from {} to {}",
+            prettyprint_usize(n),
+            from.ongoing_verb(),
+            to.ongoing_verb()
+        ));
+    }
+    txt.into_widget(ctx)
+}
+
 fn make_table(ctx: &mut EventCtx, app: &App) -> Table<App, Entry, Filters> {
     let filter: Filter<App, Entry, Filters> = Filter {
         state: Filters {
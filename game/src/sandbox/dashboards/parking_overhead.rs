@@ -1,4 +1,4 @@
-use geom::Duration;
+use geom::{Distance, Duration};
 use sim::{TripEndpoint, TripID, TripPhaseType};
 use widgetry::table::{Col, Filter, Table};
 use widgetry::{
@@ -29,6 +29,10 @@ impl ParkingOverhead {
                             "Trips taken by car also include time to walk between the building \
                              and parking spot, as well as the time to find parking.",
                         ),
+                        Line(
+                            "Time and distance spent cruising for parking is tracked separately \
+                             from driving.",
+                        ),
                         Line("Overhead is 1 - driving time / total time"),
                         Line("Ideally, overhead is 0% -- the entire trip is just spent driving."),
                         Line(""),
@@ -109,6 +113,8 @@ struct Entry {
     total_duration: Duration,
     driving_duration: Duration,
     parking_duration: Duration,
+    searching_duration: Duration,
+    searching_distance: Distance,
     walking_duration: Duration,
     percent_overhead: usize,
     starts_off_map: bool,
@@ -123,7 +129,8 @@ struct Filters {
 fn produce_raw_data(app: &App) -> Vec<Entry> {
     // Gather raw data
     let mut data = Vec::new();
-    for (id, phases) in app.primary.sim.get_analytics().get_all_trip_phases() {
+    let analytics = app.primary.sim.get_analytics();
+    for (id, phases) in analytics.get_all_trip_phases() {
         let trip = app.primary.sim.trip_info(id);
         let starts_off_map = matches!(trip.start, TripEndpoint::Border(_));
         let ends_off_map = matches!(trip.end, TripEndpoint::Border(_));
@@ -131,6 +138,7 @@ fn produce_raw_data(app: &App) -> Vec<Entry> {
         let mut total_duration = Duration::ZERO;
         let mut driving_duration = Duration::ZERO;
         let mut parking_duration = Duration::ZERO;
+        let mut searching_duration = Duration::ZERO;
         let mut walking_duration = Duration::ZERO;
         let mut ok = true;
         for p in phases {
@@ -147,6 +155,9 @@ fn produce_raw_data(app: &App) -> Vec<Entry> {
                     TripPhaseType::Parking => {
                         parking_duration += dt;
                     }
+                    TripPhaseType::SearchingForParking => {
+                        searching_duration += dt;
+                    }
                     _ => {}
                 }
             } else {
@@ -163,6 +174,12 @@ fn produce_raw_data(app: &App) -> Vec<Entry> {
             total_duration,
             driving_duration,
             parking_duration,
+            searching_duration,
+            searching_distance: analytics
+                .parking_search_per_trip
+                .get(&id)
+                .cloned()
+                .unwrap_or(Distance::ZERO),
             walking_duration,
             percent_overhead: (100.0 * (1.0 - (driving_duration / total_duration))) as usize,
             starts_off_map,
@@ -226,6 +243,18 @@ fn make_table(app: &App) -> Table<App, Entry, Filters> {
         }),
         Col::Sortable(Box::new(|rows| rows.sort_by_key(|x| x.parking_duration))),
     );
+    table.column(
+        "Searching for parking",
+        Box::new(|ctx, app, x| {
+            Text::from(format!(
+                "{} ({})",
+                x.searching_duration.to_string(&app.opts.units),
+                x.searching_distance.to_string(&app.opts.units)
+            ))
+            .render(ctx)
+        }),
+        Col::Sortable(Box::new(|rows| rows.sort_by_key(|x| x.searching_duration))),
+    );
     table.column(
         "Walking duration",
         Box::new(|ctx, app, x| {
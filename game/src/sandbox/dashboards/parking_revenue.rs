@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use abstutil::prettyprint_usize;
+use map_model::{ParkingPricing, RoadID};
+use sim::Analytics;
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, TextExt, Widget};
+
+use crate::app::{App, Transition};
+use crate::info::Tab;
+use crate::sandbox::dashboards::DashTab;
+use crate::sandbox::SandboxMode;
+
+pub struct ParkingRevenue {
+    panel: Panel,
+    /// The info panel to open for each row
+    tabs: Vec<Tab>,
+}
+
+struct Entry {
+    name: String,
+    tab: Tab,
+    priced: bool,
+    revenue: usize,
+    overstays: usize,
+    occupied: usize,
+    capacity: usize,
+}
+
+impl ParkingRevenue {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let sim = &app.primary.sim;
+        let analytics = sim.get_analytics();
+
+        // Group on-street parking by block
+        let mut per_block: BTreeMap<RoadID, Entry> = BTreeMap::new();
+        for l in map.all_lanes().values() {
+            if !l.is_parking() {
+                continue;
+            }
+            let (revenue, overstays) =
+                Analytics::total_parking_revenue(analytics.parking_lane_revenue.get(&l.id));
+            let capacity = l.number_parking_spots(map.get_config());
+            let entry = per_block.entry(l.parent).or_insert_with(|| Entry {
+                name: map.get_r(l.parent).get_name(app.opts.language.as_ref()),
+                tab: Tab::LaneInfo(l.id),
+                priced: false,
+                revenue: 0,
+                overstays: 0,
+                occupied: 0,
+                capacity: 0,
+            });
+            entry.revenue += revenue;
+            entry.overstays += overstays;
+            entry.occupied += capacity - sim.get_free_onstreet_spots(l.id).len();
+            entry.capacity += capacity;
+            // Prefer opening the priced side of the road
+            if l.parking_pricing != ParkingPricing::free() {
+                entry.tab = Tab::LaneInfo(l.id);
+                entry.priced = true;
+            }
+        }
+        let mut entries: Vec<Entry> = per_block.into_values().collect();
+        for pl in map.all_parking_lots() {
+            let (revenue, overstays) =
+                Analytics::total_parking_revenue(analytics.parking_lot_revenue.get(&pl.id));
+            entries.push(Entry {
+                name: pl.id.to_string(),
+                tab: Tab::ParkingLot(pl.id),
+                priced: pl.pricing != ParkingPricing::free(),
+                revenue,
+                overstays,
+                occupied: pl.capacity() - sim.get_free_lot_spots(pl.id).len(),
+                capacity: pl.capacity(),
+            });
+        }
+        // Skip places that've never been priced
+        entries.retain(|entry| entry.priced || entry.revenue > 0);
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.revenue));
+
+        let total: usize = entries.iter().map(|entry| entry.revenue).sum();
+        let mut col = vec![
+            DashTab::ParkingRevenue.picker(ctx, app),
+            Line(format!(
+                "{} collected from {} priced blocks and lots",
+                ParkingPricing::describe_cents(total),
+                prettyprint_usize(entries.len())
+            ))
+            .small_heading()
+            .into_widget(ctx),
+        ];
+        if entries.is_empty() {
+            col.push(
+                "Nothing is priced yet. Edit the pricing of a parking lane or lot from its info \
+                 panel."
+                    .text_widget(ctx),
+            );
+        }
+        for (idx, entry) in entries.iter().enumerate() {
+            col.push(Widget::row(vec![
+                ctx.style()
+                    .btn_outline
+                    .text(&entry.name)
+                    .build_widget(ctx, idx.to_string()),
+                format!(
+                    "{} collected, {} cars overstayed, {} / {} spots currently occupied",
+                    ParkingPricing::describe_cents(entry.revenue),
+                    prettyprint_usize(entry.overstays),
+                    prettyprint_usize(entry.occupied),
+                    prettyprint_usize(entry.capacity)
+                )
+                .text_widget(ctx)
+                .centered_vert(),
+            ]));
+        }
+
+        Box::new(ParkingRevenue {
+            panel: Panel::new_builder(Widget::col(col))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+            tabs: entries.into_iter().map(|entry| entry.tab).collect(),
+        })
+    }
+}
+
+impl State<App> for ParkingRevenue {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let tab = match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                self.tabs[x.parse::<usize>().unwrap()].clone()
+            }
+            Outcome::Changed(_) => {
+                return DashTab::ParkingRevenue
+                    .transition(ctx, app, &self.panel)
+                    .unwrap_or(Transition::Keep);
+            }
+            _ => {
+                return Transition::Keep;
+            }
+        };
+
+        Transition::Multi(vec![
+            Transition::Pop,
+            Transition::ModifyState(Box::new(move |state, ctx, app| {
+                let sandbox = state.downcast_mut::<SandboxMode>().unwrap();
+                let mut actions = sandbox.contextual_actions();
+                sandbox.controls.common.as_mut().unwrap().launch_info_panel(
+                    ctx,
+                    app,
+                    tab,
+                    &mut actions,
+                )
+            })),
+        ])
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
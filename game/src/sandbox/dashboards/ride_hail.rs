@@ -0,0 +1,110 @@
+use abstutil::prettyprint_usize;
+use geom::{Distance, Duration, Time};
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, TextExt, Widget};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+pub struct RideHail {
+    panel: Panel,
+}
+
+impl RideHail {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let sim = &app.primary.sim;
+        let rides = &sim.get_analytics().ride_hail_rides;
+        let fleet = sim.ride_hail_fleet_size();
+
+        let mut col = vec![
+            DashTab::RideHail.picker(ctx, app),
+            Line(format!(
+                "{} rides finished by a fleet of {} vehicles",
+                prettyprint_usize(rides.len()),
+                prettyprint_usize(fleet)
+            ))
+            .small_heading()
+            .into_widget(ctx),
+            format!(
+                "Right now, {} vehicles are busy and {} people are waiting for a pickup",
+                prettyprint_usize(sim.num_busy_ride_hail()),
+                prettyprint_usize(sim.num_waiting_for_ride_hail())
+            )
+            .text_widget(ctx),
+        ];
+        if fleet == 0 {
+            col.push(
+                "There's no fleet in service. Start the simulation with --ride_hail_fleet."
+                    .text_widget(ctx),
+            );
+        }
+
+        if !rides.is_empty() {
+            let total_wait: Duration = rides.iter().map(|(_, r)| r.wait).sum();
+            let max_wait = rides
+                .iter()
+                .map(|(_, r)| r.wait)
+                .fold(Duration::ZERO, |a, b| if b > a { b } else { a });
+            col.push(
+                format!(
+                    "Riders waited {} on average, and at most {}",
+                    total_wait / (rides.len() as f64),
+                    max_wait
+                )
+                .text_widget(ctx),
+            );
+
+            let empty: Distance = rides.iter().map(|(_, r)| r.empty_dist).sum();
+            let occupied: Distance = rides.iter().map(|(_, r)| r.occupied_dist).sum();
+            let total = empty + occupied;
+            if total > Distance::ZERO {
+                col.push(
+                    format!(
+                        "Vehicles drove {} without a passenger and {} with one ({}% empty)",
+                        empty.to_string(&app.opts.units),
+                        occupied.to_string(&app.opts.units),
+                        (100.0 * (empty / total)).round()
+                    )
+                    .text_widget(ctx),
+                );
+            }
+        }
+
+        // How much of the fleet's time so far was spent on a job?
+        let elapsed = sim.time() - Time::START_OF_DAY;
+        if fleet > 0 && elapsed > Duration::ZERO {
+            let busy: Duration = rides.iter().map(|(_, r)| r.busy).sum();
+            col.push(
+                format!(
+                    "Fleet utilization: {}%",
+                    (100.0 * (busy / (elapsed * (fleet as f64)))).round()
+                )
+                .text_widget(ctx),
+            );
+        }
+
+        Box::new(RideHail {
+            panel: Panel::new_builder(Widget::col(col))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State<App> for RideHail {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::RideHail
+                .transition(ctx, app, &self.panel)
+                .unwrap_or(Transition::Keep),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
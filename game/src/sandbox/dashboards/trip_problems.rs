@@ -17,6 +17,12 @@ pub enum ProblemType {
     ComplexIntersectionCrossing,
     OvertakeDesired,
     ArterialIntersectionCrossing,
+    TransitBoardingDenied,
+    NoChargerAvailable,
+    BatteryDepleted,
+    DockEmpty,
+    DockFull,
+    RideHailUnreachable,
 }
 
 impl From<&Problem> for ProblemType {
@@ -26,6 +32,12 @@ impl From<&Problem> for ProblemType {
             Problem::ComplexIntersectionCrossing(_) => Self::ComplexIntersectionCrossing,
             Problem::OvertakeDesired(_) => Self::OvertakeDesired,
             Problem::ArterialIntersectionCrossing(_) => Self::ArterialIntersectionCrossing,
+            Problem::TransitBoardingDenied(_, _) => Self::TransitBoardingDenied,
+            Problem::NoChargerAvailable(_) => Self::NoChargerAvailable,
+            Problem::BatteryDepleted(_) => Self::BatteryDepleted,
+            Problem::DockEmpty(_) => Self::DockEmpty,
+            Problem::DockFull(_) => Self::DockFull,
+            Problem::RideHailUnreachable(_) => Self::RideHailUnreachable,
         }
     }
 }
@@ -47,6 +59,12 @@ impl ProblemType {
             ProblemType::ComplexIntersectionCrossing,
             ProblemType::OvertakeDesired,
             ProblemType::ArterialIntersectionCrossing,
+            ProblemType::TransitBoardingDenied,
+            ProblemType::NoChargerAvailable,
+            ProblemType::BatteryDepleted,
+            ProblemType::DockEmpty,
+            ProblemType::DockFull,
+            ProblemType::RideHailUnreachable,
         ]
     }
 }
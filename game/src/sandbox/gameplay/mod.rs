@@ -190,7 +190,11 @@ impl GameplayMode {
                     }
                     _ => {}
                 },
-                EditCmd::ChangeRouteSchedule { .. } => {}
+                EditCmd::ChangeRouteSchedule { .. }
+                | EditCmd::ChangeParkingPricing { .. }
+                | EditCmd::ChangeChargingSpots { .. }
+                | EditCmd::ChangeBikeDock { .. }
+                | EditCmd::ChangeMovementBan { .. } => {}
             }
         }
         true
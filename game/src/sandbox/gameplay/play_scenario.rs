@@ -275,6 +275,16 @@ impl EditScenarioModifiers {
                 .text("Repeat schedule multiple days")
                 .build_def(ctx),
         ]));
+        rows.push(Widget::row(vec![
+            Spinner::widget(ctx, "delivery_tours", (1, 1000), 50_usize, 10),
+            "vans, each stopping".text_widget(ctx).centered_vert(),
+            Spinner::widget(ctx, "delivery_stops", (1, 30), 8_usize, 1),
+            "times".text_widget(ctx).centered_vert(),
+            ctx.style()
+                .btn_outline
+                .text("Add delivery tours")
+                .build_def(ctx),
+        ]));
         rows.push(Widget::horiz_separator(ctx, 1.0));
         rows.push(
             Widget::row(vec![
@@ -371,6 +381,17 @@ impl State<App> for EditScenarioModifiers {
                         self.modifiers.clone(),
                     ));
                 }
+                "Add delivery tours" => {
+                    self.modifiers.push(ScenarioModifier::AddDeliveryTours {
+                        num_tours: self.panel.spinner("delivery_tours"),
+                        stops_per_tour: self.panel.spinner("delivery_stops"),
+                    });
+                    return Transition::Replace(EditScenarioModifiers::new_state(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
                 x => {
                     if let Some(x) = x.strip_prefix("delete modifier ") {
                         self.modifiers.remove(x.parse::<usize>().unwrap() - 1);
@@ -253,6 +253,11 @@ fn make_agent_toggles(ctx: &mut EventCtx, app: &App, is_enabled: [bool; 4]) -> V
                 prettyprint_usize(counts.sov_drivers)
             ))
             .secondary(),
+            Line(format!(
+                "Riding in a ride-hail: {}",
+                prettyprint_usize(counts.ride_hail_riders)
+            ))
+            .secondary(),
         ]);
         colored_checkbox(
             ctx,
@@ -66,7 +66,7 @@ impl DrawNetworkLayer {
             let mut bike_lane = false;
             let mut buffer = false;
             for (_, _, lt) in r.lanes_ltr() {
-                if lt == LaneType::Biking {
+                if lt == LaneType::Biking || lt == LaneType::SharedUse {
                     bike_lane = true;
                 } else if matches!(lt, LaneType::Buffer(_)) {
                     buffer = true;
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use geom::Distance;
+use geom::{Circle, Distance};
 use map_gui::tools::{Navigator, PopupMsg};
 use map_model::osm::RoadRank;
 use map_model::LaneType;
@@ -21,6 +21,7 @@ pub struct Layers {
     labels: Option<DrawRoadLabels>,
     elevation: bool,
     steep_streets: Option<Drawable>,
+    bike_share: Option<Drawable>,
     // TODO Once widgetry buttons can take custom enums, that'd be perfect here
     road_types: HashMap<String, Drawable>,
 
@@ -36,6 +37,7 @@ impl Layers {
             labels: Some(DrawRoadLabels::new()),
             elevation: false,
             steep_streets: None,
+            bike_share: None,
             road_types: HashMap::new(),
             zoom_enabled_cache_key: zoom_enabled_cache_key(ctx),
             map_edit_key: usize::MAX,
@@ -52,6 +54,9 @@ impl Layers {
                 self.bike_network = Some(DrawNetworkLayer::new());
             }
             self.road_types.clear();
+            if self.bike_share.is_some() {
+                self.bike_share = Some(draw_bike_share_coverage(ctx, app));
+            }
         }
 
         if ctx.redo_mouseover() && self.elevation {
@@ -150,6 +155,13 @@ impl Layers {
                     }
                     self.update_panel(ctx, app);
                 }
+                "bike-share stations" => {
+                    if self.panel.is_checked("bike-share stations") {
+                        self.bike_share = Some(draw_bike_share_coverage(ctx, app));
+                    } else {
+                        self.bike_share = None;
+                    }
+                }
                 _ => unreachable!(),
             },
             _ => {}
@@ -185,6 +197,9 @@ impl Layers {
             if let Some(ref draw) = self.steep_streets {
                 g.redraw(draw);
             }
+            if let Some(ref draw) = self.bike_share {
+                g.redraw(draw);
+            }
 
             if let Some(name) = self.panel.currently_hovering() {
                 if let Some(draw) = self.road_types.get(name) {
@@ -286,6 +301,7 @@ impl Layers {
                 }
                 row
             }),
+            Toggle::checkbox(ctx, "bike-share stations", None, self.bike_share.is_some()),
             // TODO Probably a collisions layer
         ])
     }
@@ -296,6 +312,7 @@ impl Layers {
             || name == "road labels"
             || name == "elevation"
             || name == "steep streets"
+            || name == "bike-share stations"
             || name.starts_with("about ")
         {
             return;
@@ -310,7 +327,7 @@ impl Layers {
             let mut bike_lane = false;
             let mut buffer = false;
             for (_, _, lt) in r.lanes_ltr() {
-                if lt == LaneType::Biking {
+                if lt == LaneType::Biking || lt == LaneType::SharedUse {
                     bike_lane = true;
                 } else if matches!(lt, LaneType::Buffer(_)) {
                     buffer = true;
@@ -335,6 +352,24 @@ impl Layers {
     }
 }
 
+/// Shade the area within walking distance of every bike-share dock.
+fn draw_bike_share_coverage(ctx: &mut EventCtx, app: &App) -> Drawable {
+    let map = &app.primary.map;
+    let mut batch = GeomBatch::new();
+    for b in map.all_bike_docks() {
+        let center = map.get_b(b).polygon.center();
+        batch.push(
+            Color::PURPLE.alpha(0.2),
+            Circle::new(center, sim::MAX_WALK_TO_DOCK).to_polygon(),
+        );
+        batch.push(
+            Color::PURPLE,
+            Circle::new(center, Distance::meters(15.0)).to_polygon(),
+        );
+    }
+    ctx.upload(batch)
+}
+
 fn make_zoom_controls(ctx: &mut EventCtx) -> Widget {
     let builder = ctx
         .style()
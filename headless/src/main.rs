@@ -8,15 +8,33 @@
 // it's now 01:01:00.0
 // > curl http://localhost:1234/data/get-road-thruput
 // ... huge JSON blob
+// > curl "http://localhost:1234/sim/stream-events?t=02:00:00&kinds=TripFinished,Alert"
+// ... one JSON event per line, sent as the simulation advances
+//
+// Independent simulations can run side-by-side in named sessions. Every route above also works
+// prefixed by /session/{id}; without a prefix, routes use the "default" session.
+// > curl "http://localhost:1234/session/create?id=experiment&rng_seed=7"
+// session experiment created
+// > curl http://localhost:1234/session/experiment/sim/goto-time?t=01:00:00
+// it's now 01:00:00.0
+// > curl http://localhost:1234/session/experiment/delete
+// session experiment deleted
+//
+// To branch a simulation from some point in time and later roll back:
+// > curl http://localhost:1234/sim/save
+// > curl http://localhost:1234/sim/list-savestates
+// ["07h00m00.0s"]
+// > curl http://localhost:1234/sim/load-savestate?name=07h00m00.0s
 
 #[macro_use]
 extern crate anyhow;
 #[macro_use]
 extern crate log;
 
+use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 use anyhow::Result;
 use hyper::{Body, Request, Response, Server, StatusCode};
@@ -26,30 +44,26 @@ use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
 use abstutil::{serialize_btreemap, CmdArgs, Timer};
-use geom::{Distance, Duration, LonLat, Time};
+use geom::{Distance, Duration, LonLat, Pt2D, Time};
 use map_model::{
-    CompressedMovementID, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map,
-    MovementID, PermanentMapEdits, RoadID, TurnID,
+    CompressedMovementID, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, LaneID,
+    Map, MapEdits, MovementID, ParkingLotID, PermanentEditCmd, PermanentMapEdits, RoadID,
+    Traversable, TurnID,
 };
 use sim::{
-    AgentID, AgentType, DelayCause, ExternalPerson, PersonID, Scenario, ScenarioModifier, Sim,
-    SimFlags, SimOptions, TripID, TripMode, VehicleType,
+    AgentID, AgentType, AlertLocation, DelayCause, Emissions, Event, ExternalPerson, PersonID,
+    Scenario, ScenarioModifier, Sim, SimFlags, SimOptions, TripID, TripMode, VehicleType,
 };
 
 lazy_static::lazy_static! {
-    static ref MAP: RwLock<Map> = RwLock::new(Map::blank());
-    static ref SIM: RwLock<Sim> = RwLock::new(Sim::new(&Map::blank(), SimOptions::new("tmp")));
-    static ref LOAD: RwLock<LoadSim> = RwLock::new({
-        LoadSim {
-            scenario: abstio::path_scenario(&MapName::seattle("montlake"), "weekday"),
-            modifiers: Vec::new(),
-            edits: None,
-            rng_seed: SimFlags::RNG_SEED,
-            opts: SimOptions::default(),
-        }
-    });
+    /// Every session is an independent simulation, keyed by a name chosen by the client.
+    static ref SESSIONS: RwLock<BTreeMap<String, Arc<RwLock<Session>>>> =
+        RwLock::new(BTreeMap::new());
 }
 
+/// Routes that don't start with `/session/{id}` operate on this session, which always exists.
+const DEFAULT_SESSION: &str = "default";
+
 #[tokio::main]
 async fn main() {
     let mut args = CmdArgs::new();
@@ -61,15 +75,17 @@ async fn main() {
     let port = args.required("--port").parse::<u16>().unwrap();
     args.done();
 
-    {
-        let mut load = LOAD.write().unwrap();
-        load.rng_seed = rng_seed;
-        load.opts = opts;
-
-        let (map, sim) = load.setup(&mut timer);
-        *MAP.write().unwrap() = map;
-        *SIM.write().unwrap() = sim;
-    }
+    let load = LoadSim {
+        scenario: abstio::path_scenario(&MapName::seattle("montlake"), "weekday"),
+        modifiers: Vec::new(),
+        edits: None,
+        rng_seed,
+        opts,
+    };
+    SESSIONS.write().unwrap().insert(
+        DEFAULT_SESSION.to_string(),
+        Arc::new(RwLock::new(Session::new(load, &mut timer).unwrap())),
+    );
 
     let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
     info!("Listening on http://{}", addr);
@@ -92,25 +108,196 @@ async fn serve_req(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
             .collect();
     let body = hyper::body::to_bytes(req).await?.to_vec();
     info!("Handling {}", path);
-    Ok(
-        match handle_command(
-            &path,
-            &params,
-            &body,
-            &mut SIM.write().unwrap(),
-            &mut MAP.write().unwrap(),
-            &mut LOAD.write().unwrap(),
-        ) {
-            Ok(resp) => Response::new(Body::from(resp)),
-            Err(err) => {
-                error!("{}: {}", path, err);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from(format!("Bad command {}: {}", path, err)))
-                    .unwrap()
+
+    Ok(match route_command(&path, &params, &body) {
+        Ok(resp) => resp,
+        Err(err) => {
+            error!("{}: {}", path, err);
+            Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Bad command {}: {}", path, err)))
+                .unwrap()
+        }
+    })
+}
+
+/// Figures out which session a command is for, then runs it.
+fn route_command(
+    path: &str,
+    params: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<Response<Body>> {
+    // Managing sessions
+    match path {
+        "/session/create" => {
+            let id = params
+                .get("id")
+                .ok_or_else(|| anyhow!("missing GET parameter id"))?
+                .clone();
+            if id.is_empty() || id.contains('/') {
+                bail!("bad session ID {:?}", id);
+            }
+            if SESSIONS.read().unwrap().contains_key(&id) {
+                bail!("session {} already exists", id);
+            }
+
+            // Start from the default session's settings, optionally overriding the scenario,
+            // modifiers, edits, and RNG seed.
+            let mut load = get_session(DEFAULT_SESSION)?.read().unwrap().load.clone();
+            if !body.is_empty() {
+                let args: LoadSim = abstutil::from_json(body)?;
+                load.scenario = args.scenario;
+                load.modifiers = args.modifiers;
+                load.edits = args.edits;
+            }
+            if let Some(seed) = params.get("rng_seed") {
+                load.rng_seed = seed.parse::<u64>()?;
+            }
+            // Keep savestates from different sessions apart
+            load.opts.run_name = id.clone();
+
+            // Don't hold the lock while loading. Another request may have created the same session
+            // meanwhile, so check again when inserting.
+            let session = Session::new(load, &mut Timer::new(format!("create session {}", id)))?;
+            match SESSIONS.write().unwrap().entry(id.clone()) {
+                Entry::Occupied(_) => bail!("session {} already exists", id),
+                Entry::Vacant(entry) => {
+                    entry.insert(Arc::new(RwLock::new(session)));
+                }
+            }
+            return Ok(Response::new(Body::from(format!("session {} created", id))));
+        }
+        "/session/list" => {
+            let ids: Vec<String> = SESSIONS.read().unwrap().keys().cloned().collect();
+            return Ok(Response::new(Body::from(abstutil::to_json(&ids))));
+        }
+        _ => {}
+    }
+
+    let (id, cmd) = if let Some(rest) = path.strip_prefix("/session/") {
+        match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => bail!("session routes look like /session/ID/sim/get-time"),
+        }
+    } else {
+        (DEFAULT_SESSION, path)
+    };
+
+    if cmd == "/delete" {
+        if id == DEFAULT_SESSION {
+            bail!("the default session can't be deleted");
+        }
+        if SESSIONS.write().unwrap().remove(id).is_none() {
+            bail!("no session {}", id);
+        }
+        return Ok(Response::new(Body::from(format!("session {} deleted", id))));
+    }
+
+    let session = get_session(id)?;
+    // Streaming responses can't be produced all at once, so they're handled separately.
+    if cmd == "/sim/stream-events" {
+        return stream_events(session, params);
+    }
+
+    let mut session = session.write().unwrap();
+    let Session { map, sim, load } = &mut *session;
+    let resp = handle_command(cmd, params, body, sim, map, load)?;
+    Ok(Response::new(Body::from(resp)))
+}
+
+fn get_session(id: &str) -> Result<Arc<RwLock<Session>>> {
+    SESSIONS
+        .read()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| anyhow!("no session {}", id))
+}
+
+/// Advances the simulation to the time `t`, streaming every event matching the filters as
+/// newline-delimited JSON while the simulation runs. The simulation is only locked while advancing
+/// by `step` seconds at a time, so other requests can be interleaved.
+fn stream_events(
+    session: Arc<RwLock<Session>>,
+    params: &HashMap<String, String>,
+) -> Result<Response<Body>> {
+    let end_time = Time::parse(
+        params
+            .get("t")
+            .ok_or_else(|| anyhow!("missing GET parameter t"))?,
+    )?;
+    let step = match params.get("step") {
+        Some(x) => Duration::seconds(x.parse::<f64>()?),
+        None => Duration::seconds(1.0),
+    };
+    if step <= Duration::ZERO {
+        bail!("step must be positive");
+    }
+    let filter = EventFilter::from_params(params)?;
+    {
+        let session = session.read().unwrap();
+        if end_time <= session.sim.time() {
+            bail!("{} is in the past. call /sim/reset first?", end_time);
+        }
+    }
+
+    let (mut sender, body) = Body::channel();
+    tokio::task::spawn_blocking(move || {
+        let handle = tokio::runtime::Handle::current();
+        let subscriber = session.write().unwrap().sim.subscribe_to_events();
+        loop {
+            let (events, done) = {
+                let mut session = session.write().unwrap();
+                let Session { map, sim, .. } = &mut *session;
+                if sim.time() < end_time {
+                    let dt = step.min(end_time - sim.time());
+                    sim.timed_step(map, dt, &mut None, &mut Timer::throwaway());
+                }
+                (
+                    sim.drain_buffered_events(subscriber),
+                    sim.time() >= end_time,
+                )
+            };
+
+            let mut chunk = String::new();
+            let mut failed = false;
+            for (time, ev) in events {
+                match filter.to_line(time, ev) {
+                    Ok(Some(line)) => {
+                        chunk.push_str(&line);
+                        chunk.push('\n');
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        // The status code has already been sent, so end the stream with the error
+                        // instead.
+                        error!("Couldn't stream an event: {}", err);
+                        let line = serde_json::json!({ "error": err.to_string() });
+                        chunk.push_str(&line.to_string());
+                        chunk.push('\n');
+                        failed = true;
+                        break;
+                    }
+                }
+            }
+            if !chunk.is_empty() && handle.block_on(sender.send_data(chunk.into())).is_err() {
+                info!("Client stopped listening to events");
+                break;
             }
-        },
-    )
+            if done || failed {
+                break;
+            }
+        }
+        session
+            .write()
+            .unwrap()
+            .sim
+            .unsubscribe_from_events(subscriber);
+    });
+
+    Ok(Response::builder()
+        .header("Content-Type", "application/x-ndjson")
+        .body(body)?)
 }
 
 fn handle_command(
@@ -130,7 +317,7 @@ fn handle_command(
     match path {
         // Controlling the simulation
         "/sim/reset" => {
-            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"));
+            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"))?;
             *map = new_map;
             *sim = new_sim;
             Ok("sim reloaded".to_string())
@@ -138,14 +325,16 @@ fn handle_command(
         "/sim/load" => {
             let args: LoadSim = abstutil::from_json(body)?;
 
-            load.scenario = args.scenario;
-            load.modifiers = args.modifiers;
-            load.edits = args.edits;
+            let mut new_load = load.clone();
+            new_load.scenario = args.scenario;
+            new_load.modifiers = args.modifiers;
+            new_load.edits = args.edits;
 
-            // Also reset
-            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"));
+            // Also reset. If the new flags are bad, keep the current ones.
+            let (new_map, new_sim) = new_load.setup(&mut Timer::new("reset sim"))?;
             *map = new_map;
             *sim = new_sim;
+            *load = new_load;
 
             Ok("flags changed and sim reloaded".to_string())
         }
@@ -160,6 +349,24 @@ fn handle_command(
                 Ok(format!("it's now {}", t))
             }
         }
+        "/sim/save" => Ok(sim.save()),
+        "/sim/list-savestates" => {
+            let names = abstio::list_all_objects(sim.save_dir());
+            Ok(abstutil::to_json(&names))
+        }
+        "/sim/load-savestate" => {
+            // Savestates are named by the time they were saved, as returned by
+            // /sim/list-savestates
+            let name = get("name")?;
+            if name.contains('/') {
+                bail!("bad savestate name {}", name);
+            }
+            let path = format!("{}/{}.bin", sim.save_dir(), name);
+            let new_sim = Sim::load_savestate(path, &mut Timer::new("load savestate"))?;
+            new_sim.check_savestate_matches(map)?;
+            *sim = new_sim;
+            Ok(format!("loaded savestate, it's now {}", sim.time()))
+        }
         "/sim/new-person" => {
             let input: ExternalPerson = abstutil::from_json(body)?;
             for trip in &input.trips {
@@ -272,6 +479,7 @@ fn handle_command(
                 all_state.insert(
                     i.id,
                     TrafficSignalState {
+                        current_plan_idx: sim.current_signal_plan(i.id),
                         current_stage_idx,
                         remaining_time,
                         accepted: sim
@@ -328,6 +536,68 @@ fn handle_command(
                 .map(|((r, a, hr), cnt)| (*r, *a, *hr, *cnt))
                 .collect(),
         })),
+        "/data/get-parking-search" => {
+            let area_size = match params.get("area_size") {
+                Some(x) => Distance::meters(x.parse::<f64>()?),
+                None => Distance::meters(500.0),
+            };
+            if area_size <= Distance::ZERO {
+                bail!("area_size must be positive");
+            }
+            let size = area_size.inner_meters();
+            Ok(abstutil::to_json(&ParkingSearch {
+                per_trip: sim
+                    .get_analytics()
+                    .parking_search_per_trip
+                    .iter()
+                    .map(|(t, dist)| (*t, *dist))
+                    .collect(),
+                per_road: sim
+                    .get_analytics()
+                    .parking_search_per_road
+                    .iter()
+                    .map(|(r, dist)| (*r, *dist))
+                    .collect(),
+                per_area: sim
+                    .get_analytics()
+                    .parking_search_per_area(area_size, map)
+                    .into_iter()
+                    .map(|((x, y), roads, total)| {
+                        let (x1, y1) = (x as f64 * size, y as f64 * size);
+                        let (x2, y2) = (x1 + size, y1 + size);
+                        ParkingSearchArea {
+                            bounds: vec![(x1, y1), (x2, y1), (x2, y2), (x1, y2)]
+                                .into_iter()
+                                .map(|(x, y)| Pt2D::new(x, y).to_gps(map.get_gps_bounds()))
+                                .collect(),
+                            roads,
+                            total,
+                        }
+                    })
+                    .collect(),
+            }))
+        }
+        "/data/get-emissions" => Ok(abstutil::to_json(&EmissionsTotals {
+            total: sim.get_total_emissions(),
+            per_trip: sim
+                .get_analytics()
+                .emissions_per_trip
+                .iter()
+                .map(|(t, x)| (*t, *x))
+                .collect(),
+            per_road: sim
+                .get_analytics()
+                .emissions_per_road
+                .iter()
+                .map(|(r, x)| (*r, *x))
+                .collect(),
+            per_intersection: sim
+                .get_analytics()
+                .emissions_per_intersection
+                .iter()
+                .map(|(i, x)| (*i, *x))
+                .collect(),
+        })),
         "/data/get-blocked-by-graph" => Ok(abstutil::to_json(&BlockedByGraph {
             blocked_by: sim
                 .get_blocked_by_graph(map)
@@ -368,6 +638,30 @@ fn handle_command(
             edits.compress(map);
             Ok(abstutil::to_json(&edits.to_permanent(map)))
         }
+        "/map/apply-edits" => {
+            // Replace all current edits
+            let perma: PermanentMapEdits = abstutil::from_json(body)?;
+            if &perma.map_name != map.get_name() {
+                bail!(
+                    "edits are for {}, but the map is {}",
+                    perma.map_name.describe(),
+                    map.get_name().describe()
+                );
+            }
+            let edits = perma.into_edits(map)?;
+            let results = apply_live_edits(edits, map, sim, load)?;
+            Ok(abstutil::to_json(&results))
+        }
+        "/map/append-edit-commands" => {
+            // Add more commands on top of the current edits
+            let cmds: Vec<PermanentEditCmd> = abstutil::from_json(body)?;
+            let mut edits = map.get_edits().clone();
+            for cmd in cmds {
+                edits.commands.push(cmd.into_cmd(map)?);
+            }
+            let results = apply_live_edits(edits, map, sim, load)?;
+            Ok(abstutil::to_json(&results))
+        }
         "/map/get-edit-road-command" => {
             let r = RoadID(get("id")?.parse::<usize>()?);
             Ok(abstutil::to_json(
@@ -383,8 +677,54 @@ fn handle_command(
     }
 }
 
+/// Applies edits to the map and updates the running simulation without resetting it. Later
+/// resets of the session will keep these edits. Invalid edits are rejected, leaving the map and
+/// simulation untouched.
+fn apply_live_edits(
+    edits: MapEdits,
+    map: &mut Map,
+    sim: &mut Sim,
+    load: &mut LoadSim,
+) -> Result<EditResults> {
+    let mut timer = Timer::new("apply edits");
+    let effects = map.apply_untrusted_edits(edits)?;
+    map.recalculate_pathfinding_after_edits(&mut timer);
+    sim.handle_live_edited_traffic_signals(map);
+    let (trips_cancelled, parked_cars_displaced) = sim.handle_live_edits(map, &mut timer);
+
+    let mut edits = map.get_edits().clone();
+    edits.commands.clear();
+    edits.compress(map);
+    load.edits = Some(edits.to_permanent(map));
+
+    Ok(EditResults {
+        changed_roads: effects.changed_roads,
+        deleted_lanes: effects.deleted_lanes,
+        changed_intersections: effects.changed_intersections,
+        added_turns: effects.added_turns,
+        deleted_turns: effects.deleted_turns,
+        changed_parking_lots: effects.changed_parking_lots,
+        trips_cancelled,
+        parked_cars_displaced,
+    })
+}
+
 // TODO I think specifying the API with protobufs or similar will be a better idea.
 
+#[derive(Serialize)]
+struct EditResults {
+    changed_roads: BTreeSet<RoadID>,
+    deleted_lanes: BTreeSet<LaneID>,
+    changed_intersections: BTreeSet<IntersectionID>,
+    added_turns: BTreeSet<TurnID>,
+    deleted_turns: BTreeSet<TurnID>,
+    changed_parking_lots: BTreeSet<ParkingLotID>,
+    /// Trips crossing something that changed are cancelled
+    trips_cancelled: usize,
+    /// Cars parked somewhere that changed are removed
+    parked_cars_displaced: usize,
+}
+
 #[derive(Serialize)]
 struct FinishedTrip {
     id: TripID,
@@ -443,8 +783,39 @@ struct RoadThroughput {
     counts: Vec<(RoadID, AgentType, usize, usize)>,
 }
 
+#[derive(Serialize)]
+struct ParkingSearch {
+    // How far each trip drove while cruising for parking
+    per_trip: Vec<(TripID, Distance)>,
+    // How far vehicles drove along each road while cruising for parking
+    per_road: Vec<(RoadID, Distance)>,
+    // The same, summed over square areas of the map, to find neighborhoods where parking is scarce
+    per_area: Vec<ParkingSearchArea>,
+}
+
+#[derive(Serialize)]
+struct ParkingSearchArea {
+    // The corners of the area
+    bounds: Vec<LonLat>,
+    // Roads whose middle is in this area, with some cruising
+    roads: Vec<RoadID>,
+    total: Distance,
+}
+
+#[derive(Serialize)]
+struct EmissionsTotals {
+    // Everything emitted so far, including by buses and trains
+    total: Emissions,
+    // What the vehicle used for each trip emitted. Transit isn't included.
+    per_trip: Vec<(TripID, Emissions)>,
+    per_road: Vec<(RoadID, Emissions)>,
+    // Emitted while making turns
+    per_intersection: Vec<(IntersectionID, Emissions)>,
+}
+
 #[derive(Serialize)]
 struct TrafficSignalState {
+    current_plan_idx: usize,
     current_stage_idx: usize,
     remaining_time: Duration,
     accepted: BTreeSet<AgentID>,
@@ -461,7 +832,131 @@ struct BlockedByGraph {
     blocked_by: BTreeMap<AgentID, (Duration, DelayCause, Option<TripID>, Option<PersonID>)>,
 }
 
-#[derive(Deserialize)]
+/// Restricts which events are streamed. Each filter is optional; when several are specified, an
+/// event must pass all of them.
+struct EventFilter {
+    /// The name of the Event variant, like "TripFinished" or "Alert"
+    kinds: Option<BTreeSet<String>>,
+    /// Only events that happen at one of these intersections
+    intersections: Option<BTreeSet<IntersectionID>>,
+    /// Only events belonging to one of these trips
+    trips: Option<BTreeSet<TripID>>,
+}
+
+impl EventFilter {
+    /// Each filter is a comma-separated list, like `?kinds=TripFinished,Alert&intersections=3,4`
+    fn from_params(params: &HashMap<String, String>) -> Result<EventFilter> {
+        let kinds = params
+            .get("kinds")
+            .map(|x| x.split(',').map(|k| k.to_string()).collect());
+        let mut intersections = None;
+        if let Some(x) = params.get("intersections") {
+            let mut set = BTreeSet::new();
+            for i in x.split(',') {
+                set.insert(IntersectionID(i.parse::<usize>()?));
+            }
+            intersections = Some(set);
+        }
+        let mut trips = None;
+        if let Some(x) = params.get("trips") {
+            let mut set = BTreeSet::new();
+            for t in x.split(',') {
+                set.insert(TripID(t.parse::<usize>()?));
+            }
+            trips = Some(set);
+        }
+        Ok(EventFilter {
+            kinds,
+            intersections,
+            trips,
+        })
+    }
+
+    /// If the event passes the filter, returns it as one line of JSON.
+    fn to_line(&self, time: Time, ev: Event) -> Result<Option<String>> {
+        if let Some(ref intersections) = self.intersections {
+            match event_intersection(&ev) {
+                Some(i) if intersections.contains(&i) => {}
+                _ => return Ok(None),
+            }
+        }
+        if let Some(ref trips) = self.trips {
+            match event_trip(&ev) {
+                Some(t) if trips.contains(&t) => {}
+                _ => return Ok(None),
+            }
+        }
+
+        // Variants carrying data are serialized as an object with exactly one key: the name of
+        // the variant. Variants without data are just the name.
+        let value = serde_json::to_value(&ev)?;
+        let kind = match value {
+            serde_json::Value::Object(ref obj) if obj.len() == 1 => {
+                obj.keys().next().unwrap().clone()
+            }
+            serde_json::Value::String(ref name) => name.clone(),
+            _ => bail!("unexpected serialization of {:?}: {}", ev, value),
+        };
+        if let Some(ref kinds) = self.kinds {
+            if !kinds.contains(&kind) {
+                return Ok(None);
+            }
+        }
+        Ok(Some(serde_json::to_string(&StreamedEvent {
+            time,
+            kind,
+            event: value,
+        })?))
+    }
+}
+
+fn event_intersection(ev: &Event) -> Option<IntersectionID> {
+    match ev {
+        Event::AgentEntersTraversable(_, _, Traversable::Turn(t), _)
+        | Event::VehicleEmitted(_, _, Traversable::Turn(t), _) => Some(t.parent),
+        Event::IntersectionDelayMeasured(_, t, _, _) => Some(t.parent),
+        Event::PersonLeavesMap(_, _, i) | Event::PersonEntersMap(_, _, i) => Some(*i),
+        Event::Alert(AlertLocation::Intersection(i), _) => Some(*i),
+        _ => None,
+    }
+}
+
+fn event_trip(ev: &Event) -> Option<TripID> {
+    match ev {
+        Event::AgentEntersTraversable(_, trip, _, _) | Event::VehicleEmitted(_, trip, _, _) => {
+            *trip
+        }
+        Event::IntersectionDelayMeasured(trip, _, _, _)
+        | Event::ProblemEncountered(trip, _)
+        | Event::TripCancelled(trip, _)
+        | Event::TripPhaseStarting(trip, _, _, _)
+        | Event::TripFinished { trip, .. } => Some(*trip),
+        _ => None,
+    }
+}
+
+#[derive(Serialize)]
+struct StreamedEvent {
+    time: Time,
+    kind: String,
+    event: serde_json::Value,
+}
+
+/// An independent simulation. Changing one session never affects another.
+struct Session {
+    map: Map,
+    sim: Sim,
+    load: LoadSim,
+}
+
+impl Session {
+    fn new(load: LoadSim, timer: &mut Timer) -> Result<Session> {
+        let (map, sim) = load.setup(timer)?;
+        Ok(Session { map, sim, load })
+    }
+}
+
+#[derive(Clone, Deserialize)]
 struct LoadSim {
     scenario: String,
     modifiers: Vec<ScenarioModifier>,
@@ -474,12 +969,20 @@ struct LoadSim {
 }
 
 impl LoadSim {
-    fn setup(&self, timer: &mut Timer) -> (Map, Sim) {
-        let mut scenario: Scenario = abstio::must_read_object(self.scenario.clone(), timer);
+    /// Fails if the scenario or edits come from an HTTP client and are bad.
+    fn setup(&self, timer: &mut Timer) -> Result<(Map, Sim)> {
+        let mut scenario: Scenario = abstio::read_object(self.scenario.clone(), timer)?;
 
+        if !abstio::file_exists(scenario.map_name.path()) {
+            bail!(
+                "scenario {} needs missing map {}",
+                self.scenario,
+                scenario.map_name.path()
+            );
+        }
         let mut map = Map::load_synchronously(scenario.map_name.path(), timer);
         if let Some(perma) = self.edits.clone() {
-            let edits = perma.into_edits(&map).unwrap();
+            let edits = perma.into_edits(&map)?;
             map.must_apply_edits(edits);
             map.recalculate_pathfinding_after_edits(timer);
         }
@@ -492,7 +995,7 @@ impl LoadSim {
         let mut sim = Sim::new(&map, self.opts.clone());
         scenario.instantiate(&mut sim, &map, &mut rng, timer);
 
-        (map, sim)
+        Ok((map, sim))
     }
 }
 
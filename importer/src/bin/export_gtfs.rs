@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use abstutil::{CmdArgs, Timer};
+use geom::{Duration, Pt2D, Time};
+use map_model::{BusRoute, BusRouteID, BusStopID, Map, MapEdits, PathConstraints};
+use sim::{transit_dwell_time, Analytics};
+
+const SERVICE_ID: &str = "everyday";
+
+/// Export a map's transit routes, stops, and (possibly edited) schedules as a GTFS feed. Arrival
+/// times at each stop are estimated from speed limits, without any congestion. Dwell times use the
+/// simulation's model, with the average number of passengers boarding and alighting from a
+/// previous run if `--prebaked` is given. Otherwise, vehicles dwell for the minimum time.
+fn main() -> Result<()> {
+    let mut args = CmdArgs::new();
+    let map_path = args.required("--map");
+    let edits_path = args.optional("--edits");
+    // Analytics from a previous run, like the prebaked results for a scenario
+    let prebaked_path = args.optional("--prebaked");
+    // A directory or a .zip file
+    let output = args.required("--output");
+    let timezone = args
+        .optional("--timezone")
+        .unwrap_or_else(|| "Etc/UTC".to_string());
+    args.done();
+
+    let mut timer = Timer::new("export GTFS");
+    let mut map = Map::load_synchronously(map_path, &mut timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load_from_file(&map, path, &mut timer)?;
+        map.must_apply_edits(edits);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+
+    // BusStopIDs aren't meaningful outside of this map, so just number them
+    let stop_ids: BTreeMap<BusStopID, String> = map
+        .all_bus_stops()
+        .keys()
+        .enumerate()
+        .map(|(idx, id)| (*id, idx.to_string()))
+        .collect();
+    let dwell_times = match prebaked_path {
+        Some(path) => dwell_times(&abstio::read_binary::<Analytics>(path, &mut timer)),
+        None => BTreeMap::new(),
+    };
+    let (trips, stop_times) = trips_and_stop_times(&map, &stop_ids, &dwell_times, &mut timer);
+
+    let files = vec![
+        (
+            "agency.txt",
+            to_csv(vec![AgencyRecord {
+                agency_id: "abstreet".to_string(),
+                agency_name: "A/B Street".to_string(),
+                agency_url: "https://abstreet.org".to_string(),
+                agency_timezone: timezone,
+            }])?,
+        ),
+        (
+            "calendar.txt",
+            to_csv(vec![CalendarRecord {
+                service_id: SERVICE_ID.to_string(),
+                monday: 1,
+                tuesday: 1,
+                wednesday: 1,
+                thursday: 1,
+                friday: 1,
+                saturday: 1,
+                sunday: 1,
+                start_date: "20200101".to_string(),
+                end_date: "20991231".to_string(),
+            }])?,
+        ),
+        ("routes.txt", to_csv(routes(&map))?),
+        ("stops.txt", to_csv(stops(&map, &stop_ids))?),
+        ("trips.txt", to_csv(trips)?),
+        ("stop_times.txt", to_csv(stop_times)?),
+        ("shapes.txt", to_csv(shapes(&map))?),
+    ];
+
+    if output.ends_with(".zip") {
+        let mut zip = zip::ZipWriter::new(File::create(&output)?);
+        for (name, bytes) in files {
+            zip.start_file(name, zip::write::FileOptions::default())?;
+            zip.write_all(&bytes)?;
+        }
+        zip.finish()?;
+    } else {
+        std::fs::create_dir_all(&output)?;
+        for (name, bytes) in files {
+            File::create(format!("{}/{}", output, name))?.write_all(&bytes)?;
+        }
+    }
+    println!("Wrote {} routes to {}", map.all_bus_routes().len(), output);
+    Ok(())
+}
+
+fn to_csv<T: Serialize>(records: Vec<T>) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for rec in records {
+        writer.serialize(rec)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+fn routes(map: &Map) -> Vec<RouteRecord> {
+    map.all_bus_routes()
+        .iter()
+        .map(|r| RouteRecord {
+            route_id: r.id.0.to_string(),
+            agency_id: "abstreet".to_string(),
+            route_short_name: r.short_name.clone(),
+            route_long_name: r.full_name.clone(),
+            route_type: if r.route_type == PathConstraints::Train {
+                // Tram, streetcar, or light rail
+                0
+            } else {
+                3
+            },
+        })
+        .collect()
+}
+
+fn stops(map: &Map, stop_ids: &BTreeMap<BusStopID, String>) -> Vec<StopRecord> {
+    map.all_bus_stops()
+        .values()
+        .map(|bs| {
+            let gps = bs.sidewalk_pos.pt(map).to_gps(map.get_gps_bounds());
+            StopRecord {
+                stop_id: stop_ids[&bs.id].clone(),
+                stop_name: bs.name.clone(),
+                stop_lat: gps.y(),
+                stop_lon: gps.x(),
+            }
+        })
+        .collect()
+}
+
+fn shapes(map: &Map) -> Vec<ShapeRecord> {
+    let mut results = Vec::new();
+    for r in map.all_bus_routes() {
+        let mut pts: Vec<Pt2D> = Vec::new();
+        for req in r.all_steps(map) {
+            if let Some(pl) = map.pathfind(req).ok().and_then(|path| path.trace(map)) {
+                pts.extend(pl.into_points());
+            }
+        }
+        pts.dedup();
+        for (idx, gps) in map
+            .get_gps_bounds()
+            .convert_back(&pts)
+            .into_iter()
+            .enumerate()
+        {
+            results.push(ShapeRecord {
+                shape_id: r.id.0.to_string(),
+                shape_pt_lat: gps.y(),
+                shape_pt_lon: gps.x(),
+                shape_pt_sequence: idx,
+            });
+        }
+    }
+    results
+}
+
+/// Using the average number of passengers boarding and alighting per arrival, how long do vehicles
+/// dwell at each stop? Crowding isn't known, so boardings are assumed to find a seat.
+fn dwell_times(prebaked: &Analytics) -> BTreeMap<(BusRouteID, BusStopID), Duration> {
+    let mut arrivals: BTreeMap<(BusRouteID, BusStopID), usize> = BTreeMap::new();
+    for (_, _, route, stop) in &prebaked.bus_arrivals {
+        *arrivals.entry((*route, *stop)).or_insert(0) += 1;
+    }
+    let mut boardings: BTreeMap<(BusRouteID, BusStopID), usize> = BTreeMap::new();
+    for (stop, list) in &prebaked.passengers_boarding {
+        for (_, route, _) in list {
+            *boardings.entry((*route, *stop)).or_insert(0) += 1;
+        }
+    }
+    let mut alightings: BTreeMap<(BusRouteID, BusStopID), usize> = BTreeMap::new();
+    for (stop, list) in &prebaked.passengers_alighting {
+        for (_, route) in list {
+            *alightings.entry((*route, *stop)).or_insert(0) += 1;
+        }
+    }
+
+    arrivals
+        .into_iter()
+        .map(|(key, count)| {
+            let avg = |per_stop: &BTreeMap<(BusRouteID, BusStopID), usize>| {
+                let total = per_stop.get(&key).cloned().unwrap_or(0);
+                ((total as f64) / (count as f64)).round() as usize
+            };
+            (
+                key,
+                transit_dwell_time(avg(&alightings), avg(&boardings), 0),
+            )
+        })
+        .collect()
+}
+
+fn trips_and_stop_times(
+    map: &Map,
+    stop_ids: &BTreeMap<BusStopID, String>,
+    dwell_times: &BTreeMap<(BusRouteID, BusStopID), Duration>,
+    timer: &mut Timer,
+) -> (Vec<TripRecord>, Vec<StopTimeRecord>) {
+    let mut trips = Vec::new();
+    let mut stop_times = Vec::new();
+    timer.start_iter("estimate stop times", map.all_bus_routes().len());
+    for r in map.all_bus_routes() {
+        timer.next();
+        let mut travel_times = Vec::new();
+        // all_steps starts with the path to the first stop, and may end with a path to a border
+        for req in r.all_steps(map).into_iter().take(r.stops.len()) {
+            match map.pathfind(req) {
+                Ok(path) => {
+                    travel_times.push(path.estimate_duration(map, None));
+                }
+                Err(_) => {
+                    break;
+                }
+            }
+        }
+        if travel_times.len() != r.stops.len() {
+            println!("Skipping {}: no path between stops", r.full_name);
+            continue;
+        }
+        let stops: Vec<(String, Duration)> = r
+            .stops
+            .iter()
+            .map(|bs| {
+                let dwell = dwell_times
+                    .get(&(r.id, *bs))
+                    .cloned()
+                    .unwrap_or_else(|| transit_dwell_time(0, 0, 0));
+                (stop_ids[bs].clone(), dwell)
+            })
+            .collect();
+
+        for (trip_idx, start) in r.spawn_times.iter().enumerate() {
+            let trip_id = format!("{}_{}", r.id.0, trip_idx);
+            trips.push(TripRecord {
+                route_id: r.id.0.to_string(),
+                service_id: SERVICE_ID.to_string(),
+                trip_id: trip_id.clone(),
+                shape_id: r.id.0.to_string(),
+            });
+            stop_times.extend(stop_time_records(&trip_id, *start, &stops, &travel_times));
+        }
+    }
+    (trips, stop_times)
+}
+
+/// Produces one trip's stop times. `stops` has the GTFS ID of each stop and how long to dwell
+/// there. `travel_times` has how long it takes to reach each stop from the previous one, or from
+/// the start for the first stop.
+fn stop_time_records(
+    trip_id: &str,
+    start: Time,
+    stops: &[(String, Duration)],
+    travel_times: &[Duration],
+) -> Vec<StopTimeRecord> {
+    let mut results = Vec::new();
+    let mut time = start;
+    for (seq, ((stop_id, dwell), travel_time)) in stops.iter().zip(travel_times).enumerate() {
+        let arrival = time + *travel_time;
+        time = arrival + *dwell;
+        results.push(StopTimeRecord {
+            trip_id: trip_id.to_string(),
+            arrival_time: format_time(arrival),
+            departure_time: format_time(time),
+            stop_id: stop_id.clone(),
+            stop_sequence: seq,
+        });
+    }
+    results
+}
+
+/// GTFS uses HH:MM:SS, and times past midnight exceed 24 hours.
+fn format_time(time: Time) -> String {
+    let secs = (time - Time::START_OF_DAY).inner_seconds().round() as usize;
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+#[derive(Serialize)]
+struct AgencyRecord {
+    agency_id: String,
+    agency_name: String,
+    agency_url: String,
+    agency_timezone: String,
+}
+
+#[derive(Serialize)]
+struct CalendarRecord {
+    service_id: String,
+    monday: usize,
+    tuesday: usize,
+    wednesday: usize,
+    thursday: usize,
+    friday: usize,
+    saturday: usize,
+    sunday: usize,
+    start_date: String,
+    end_date: String,
+}
+
+#[derive(Serialize)]
+struct RouteRecord {
+    route_id: String,
+    agency_id: String,
+    route_short_name: String,
+    route_long_name: String,
+    route_type: usize,
+}
+
+#[derive(Serialize)]
+struct StopRecord {
+    stop_id: String,
+    stop_name: String,
+    stop_lat: f64,
+    stop_lon: f64,
+}
+
+#[derive(Serialize)]
+struct TripRecord {
+    route_id: String,
+    service_id: String,
+    trip_id: String,
+    shape_id: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct StopTimeRecord {
+    trip_id: String,
+    arrival_time: String,
+    departure_time: String,
+    stop_id: String,
+    stop_sequence: usize,
+}
+
+#[derive(Serialize)]
+struct ShapeRecord {
+    shape_id: String,
+    shape_pt_lat: f64,
+    shape_pt_lon: f64,
+    shape_pt_sequence: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_times_for_route() {
+        let stops = vec![
+            ("0".to_string(), transit_dwell_time(0, 0, 0)),
+            ("1".to_string(), transit_dwell_time(1, 2, 0)),
+            ("2".to_string(), transit_dwell_time(3, 0, 0)),
+        ];
+        let travel_times = vec![
+            Duration::minutes(1),
+            Duration::minutes(2),
+            Duration::seconds(90.0),
+        ];
+        let start = Time::START_OF_DAY + Duration::hours(23) + Duration::minutes(59);
+        let records = stop_time_records("5_0", start, &stops, &travel_times);
+
+        let expected = vec![
+            ("0", "24:00:00", "24:00:10"),
+            ("1", "24:02:10", "24:02:28"),
+            ("2", "24:03:58", "24:04:14"),
+        ];
+        assert_eq!(records.len(), expected.len());
+        for (seq, (record, (stop_id, arrival, departure))) in
+            records.into_iter().zip(expected).enumerate()
+        {
+            assert_eq!(
+                record,
+                StopTimeRecord {
+                    trip_id: "5_0".to_string(),
+                    arrival_time: arrival.to_string(),
+                    departure_time: departure.to_string(),
+                    stop_id: stop_id.to_string(),
+                    stop_sequence: seq,
+                }
+            );
+        }
+    }
+}
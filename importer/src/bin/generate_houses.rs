@@ -202,6 +202,7 @@ fn import_map(
             include_railroads: true,
             extra_buildings: None,
             skip_local_roads: false,
+            gtfs: None,
         },
         timer,
     );
@@ -29,6 +29,14 @@ pub struct GenericCityImporter {
     pub include_railroads: bool,
     /// If provided, read polygons from this GeoJSON file and add them to the RawMap as buildings.
     pub extra_buildings: Option<String>,
+    /// The URL to a GTFS .zip file. If provided, transit routes and schedules are imported from
+    /// this instead of OSM route relations. Like `osm_url`, this can also be a local path.
+    #[serde(default)]
+    pub gtfs_url: Option<String>,
+    /// Only import GTFS trips running on this date, formatted like 20210315. If not specified,
+    /// use the weekday with the most trips.
+    #[serde(default)]
+    pub gtfs_service_date: Option<String>,
 }
 
 impl GenericCityImporter {
@@ -54,6 +62,18 @@ impl GenericCityImporter {
             self.osm_url.clone()
         };
 
+        let local_gtfs_file = if let Some(ref url) = self.gtfs_url {
+            if url.starts_with("http") {
+                let file = name.city.input_path("gtfs.zip");
+                download(config, file.clone(), url).await;
+                Some(file)
+            } else {
+                Some(url.clone())
+            }
+        } else {
+            None
+        };
+
         osmconvert(
             local_osm_file,
             format!(
@@ -81,6 +101,8 @@ impl GenericCityImporter {
                 extra_buildings: self.extra_buildings.clone(),
                 // TODO Total hack! Need to figure out how to express per-map config overrides
                 skip_local_roads: name == MapName::new("us", "phoenix", "loop101"),
+                gtfs: local_gtfs_file,
+                gtfs_service_date: self.gtfs_service_date.clone(),
             },
             timer,
         );
@@ -40,9 +40,11 @@ async fn main() {
     if let Some(path) = args.optional("--oneshot") {
         let clip = args.optional("--oneshot_clip");
         let drive_on_left = args.enabled("--oneshot_drive_on_left");
+        let gtfs = args.optional("--oneshot_gtfs");
+        let gtfs_service_date = args.optional("--oneshot_gtfs_service_date");
         args.done();
 
-        oneshot(path, clip, !drive_on_left, opts);
+        oneshot(path, clip, gtfs, gtfs_service_date, !drive_on_left, opts);
         return;
     }
 
@@ -352,7 +354,14 @@ impl Job {
     }
 }
 
-fn oneshot(osm_path: String, clip: Option<String>, drive_on_right: bool, opts: RawToMapOptions) {
+fn oneshot(
+    osm_path: String,
+    clip: Option<String>,
+    gtfs: Option<String>,
+    gtfs_service_date: Option<String>,
+    drive_on_right: bool,
+    opts: RawToMapOptions,
+) {
     let mut timer = abstutil::Timer::new("oneshot");
     println!("- Running convert_osm on {}", osm_path);
     let name = abstutil::basename(&osm_path);
@@ -379,6 +388,8 @@ fn oneshot(osm_path: String, clip: Option<String>, drive_on_right: bool, opts: R
             include_railroads: true,
             extra_buildings: None,
             skip_local_roads: false,
+            gtfs,
+            gtfs_service_date,
         },
         &mut timer,
     );
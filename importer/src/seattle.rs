@@ -159,6 +159,8 @@ pub async fn osm_to_raw(name: &str, timer: &mut Timer<'_>, config: &ImporterConf
             include_railroads: false,
             extra_buildings: None,
             skip_local_roads: false,
+            gtfs: None,
+            gtfs_service_date: None,
         },
         timer,
     );
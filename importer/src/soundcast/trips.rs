@@ -204,8 +204,8 @@ fn clip_trips(
                 borders.for_mode(orig.mode),
                 match orig.mode {
                     TripMode::Walk | TripMode::Transit => PathConstraints::Pedestrian,
-                    TripMode::Drive => PathConstraints::Car,
-                    TripMode::Bike => PathConstraints::Bike,
+                    TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
+                    TripMode::Bike | TripMode::BikeShare => PathConstraints::Bike,
                 },
                 maybe_huge_map.as_ref(),
                 only_passthrough_trips,
@@ -70,6 +70,8 @@ impl Model {
                     include_railroads: true,
                     extra_buildings: None,
                     skip_local_roads: false,
+                    gtfs: None,
+                    gtfs_service_date: None,
                 },
                 &mut timer,
             )
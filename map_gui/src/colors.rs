@@ -71,6 +71,7 @@ pub struct ColorScheme {
     driving_lane: Color,
     bus_lane: Color,
     parking_lane: Color,
+    loading_zone: Color,
     bike_lane: Color,
     sidewalk: Color,
     pub sidewalk_lines: Color,
@@ -117,6 +118,9 @@ pub struct ColorScheme {
     pub unzoomed_car: Color,
     pub unzoomed_bike: Color,
     pub unzoomed_bus: Color,
+    pub unzoomed_delivery: Color,
+    pub unzoomed_ride_hail: Color,
+    pub unzoomed_bike_share: Color,
     pub unzoomed_pedestrian: Color,
 
     // Agents
@@ -200,6 +204,7 @@ impl ColorScheme {
             driving_lane: Color::BLACK,
             bus_lane: Color::rgb(190, 74, 76),
             parking_lane: Color::grey(0.2),
+            loading_zone: hex("#5E4B26"),
             bike_lane: Color::rgb(15, 125, 75),
             sidewalk: Color::grey(0.8),
             sidewalk_lines: Color::grey(0.7),
@@ -246,6 +251,9 @@ impl ColorScheme {
             unzoomed_car: hex("#FE5f55"),
             unzoomed_bike: hex("#90BE6D"),
             unzoomed_bus: hex("#FFD166"),
+            unzoomed_delivery: hex("#8E6C8A"),
+            unzoomed_ride_hail: hex("#F78C6B"),
+            unzoomed_bike_share: hex("#43AA8B"),
             unzoomed_pedestrian: hex("#457B9D"),
 
             // Agents
@@ -398,7 +406,8 @@ impl ColorScheme {
             LaneType::Driving => main_asphalt,
             LaneType::Bus => self.bus_lane,
             LaneType::Parking => parking_asphalt,
-            LaneType::Sidewalk | LaneType::Shoulder => self.sidewalk,
+            LaneType::LoadingZone => self.loading_zone,
+            LaneType::Sidewalk | LaneType::Shoulder | LaneType::SharedUse => self.sidewalk,
             LaneType::Biking => self.bike_lane,
             LaneType::SharedLeftTurn => main_asphalt,
             LaneType::Construction => parking_asphalt,
@@ -53,7 +53,7 @@ pub trait AppLike {
     where
         Self: Sized;
 
-    // These two are needed to render traffic signals. Splitting them from sim() allows
+    // These are needed to render traffic signals. Splitting them from sim() allows
     // applications that don't run a traffic sim to work.
     fn sim_time(&self) -> Time {
         self.sim().time()
@@ -61,6 +61,9 @@ pub trait AppLike {
     fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
         self.sim().current_stage_and_remaining_time(id)
     }
+    fn current_signal_plan(&self, id: IntersectionID) -> usize {
+        self.sim().current_signal_plan(id)
+    }
 
     /// Change the color scheme. Idempotent. Return true if there was a change.
     fn change_color_scheme(&mut self, ctx: &mut EventCtx, cs: ColorSchemeChoice) -> bool {
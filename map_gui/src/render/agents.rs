@@ -201,6 +201,20 @@ impl UnzoomedAgents {
                     None
                 }
             }
+            Some(VehicleType::Delivery) => {
+                if self.cars {
+                    Some(color_scheme.unzoomed_delivery)
+                } else {
+                    None
+                }
+            }
+            Some(VehicleType::RideHail) => {
+                if self.cars {
+                    Some(color_scheme.unzoomed_ride_hail)
+                } else {
+                    None
+                }
+            }
             Some(VehicleType::Bike) => {
                 if self.bikes {
                     Some(color_scheme.unzoomed_bike)
@@ -1,6 +1,8 @@
 use std::cell::RefCell;
 
-use geom::{Angle, ArrowCap, Distance, Line, PolyLine, Polygon, Pt2D, Ring, Time, EPSILON_DIST};
+use geom::{
+    Angle, ArrowCap, Circle, Distance, Line, PolyLine, Polygon, Pt2D, Ring, Time, EPSILON_DIST,
+};
 use map_model::{
     Direction, DrivingSide, Intersection, IntersectionID, IntersectionType, LaneType, Map, Road,
     RoadWithStopSign, Turn, TurnType, SIDEWALK_THICKNESS,
@@ -78,7 +80,16 @@ impl DrawIntersection {
                 );
             }
             IntersectionType::StopSign => {
-                for ss in map.get_stop_sign(i.id).roads.values() {
+                let sign = map.get_stop_sign(i.id);
+                // Roundabouts along a ring of roads are obvious; mark the mini ones.
+                if sign.is_mini_roundabout(map) {
+                    if let Ok(outline) = Circle::new(i.polygon.center(), Distance::meters(2.0))
+                        .to_outline(Distance::meters(0.5))
+                    {
+                        default_geom.push(app.cs().general_road_marking, outline);
+                    }
+                }
+                for ss in sign.roads.values() {
                     if ss.must_stop {
                         if let Some((octagon, pole, angle)) =
                             DrawIntersection::stop_sign_geom(ss, map)
@@ -223,10 +234,11 @@ impl Renderable for DrawIntersection {
                     .unwrap_or(true);
                 if recalc {
                     let (idx, remaining) = app.current_stage_and_remaining_time(self.id);
+                    let plan = app.current_signal_plan(self.id);
                     let mut batch = GeomBatch::new();
                     traffic_signal::draw_signal_stage(
                         g.prerender,
-                        &signal.stages[idx],
+                        &signal.get_plan_stages(plan)[idx],
                         idx,
                         self.id,
                         Some(remaining),
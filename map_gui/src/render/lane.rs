@@ -65,7 +65,7 @@ impl DrawLane {
                     );
                 }
             }
-            LaneType::Parking => {
+            LaneType::Parking | LaneType::LoadingZone => {
                 batch.extend(general_road_marking, calculate_parking_lines(lane, map));
             }
             LaneType::Driving => {
@@ -102,6 +102,28 @@ impl DrawLane {
                     );
                 }
             }
+            LaneType::SharedUse => {
+                batch.extend(general_road_marking, calculate_shared_use_lines(lane, road));
+                // Alternate icons, so it's clear both bikes and pedestrians belong
+                for (idx, (pt, angle)) in lane
+                    .lane_center_pts
+                    .step_along(Distance::meters(30.0), Distance::meters(5.0))
+                    .into_iter()
+                    .enumerate()
+                {
+                    let icon = if idx % 2 == 0 {
+                        "system/assets/meters/bike.svg"
+                    } else {
+                        "system/assets/meters/pedestrian.svg"
+                    };
+                    batch.append(
+                        GeomBatch::load_svg(prerender, icon)
+                            .scale(0.06)
+                            .centered_on(pt)
+                            .rotate(angle.shortest_rotation_towards(Angle::degrees(-90.0))),
+                    );
+                }
+            }
             LaneType::SharedLeftTurn => {
                 let thickness = Distance::meters(0.25);
                 batch.push(
@@ -303,6 +325,27 @@ fn calculate_driving_lines(lane: &Lane, parent: &Road) -> Vec<Polygon> {
     )
 }
 
+// Like calculate_driving_lines, but shared-use paths are split down the middle no matter the
+// direction.
+fn calculate_shared_use_lines(lane: &Lane, parent: &Road) -> Vec<Polygon> {
+    let lanes = parent.lanes_ltr();
+    let idx = parent.offset(lane.id);
+    if idx == 0 || lanes[idx - 1].2 != LaneType::SharedUse {
+        return Vec::new();
+    }
+
+    let lane_edge_pts = if lanes[idx].1 == Direction::Fwd {
+        lane.lane_center_pts.must_shift_left(lane.width / 2.0)
+    } else {
+        lane.lane_center_pts.must_shift_right(lane.width / 2.0)
+    };
+    lane_edge_pts.dashed_lines(
+        Distance::meters(0.25),
+        Distance::meters(1.0),
+        Distance::meters(1.5),
+    )
+}
+
 fn calculate_turn_markings(map: &Map, lane: &Lane) -> Vec<Polygon> {
     if lane.length() < Distance::meters(7.0) {
         return Vec::new();
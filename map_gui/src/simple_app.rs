@@ -290,8 +290,10 @@ impl<T: 'static> AppLike for SimpleApp<T> {
 
     fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
         let signal = self.map.get_traffic_signal(id);
-        let mut time_left = (self.time - Time::START_OF_DAY) % signal.simple_cycle_duration();
-        for (idx, stage) in signal.stages.iter().enumerate() {
+        let plan = self.current_signal_plan(id);
+        let mut time_left =
+            (self.time - Time::START_OF_DAY) % signal.simple_plan_cycle_duration(plan);
+        for (idx, stage) in signal.get_plan_stages(plan).iter().enumerate() {
             if time_left < stage.stage_type.simple_duration() {
                 return (idx, time_left);
             }
@@ -299,6 +301,10 @@ impl<T: 'static> AppLike for SimpleApp<T> {
         }
         unreachable!()
     }
+
+    fn current_signal_plan(&self, id: IntersectionID) -> usize {
+        self.map.get_traffic_signal(id).plan_at(self.time)
+    }
 }
 
 impl<T: 'static> SharedAppState for SimpleApp<T> {
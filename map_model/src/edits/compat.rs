@@ -111,6 +111,20 @@ pub fn upgrade(mut value: Value, map: &Map) -> Result<PermanentMapEdits> {
             .unwrap()
             .insert("version".to_string(), Value::Number(11.into()));
     }
+    if value["version"] == Value::Number(11.into()) {
+        fix_stop_sign_roundabouts(&mut value);
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("version".to_string(), Value::Number(12.into()));
+    }
+    if value["version"] == Value::Number(12.into()) {
+        fix_shared_use_paths(&mut value, map)?;
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("version".to_string(), Value::Number(13.into()));
+    }
 
     abstutil::from_json(&value.to_string().into_bytes())
 }
@@ -442,6 +456,80 @@ fn remove_vehicle_caps(value: &mut Value) {
     });
 }
 
+// Stop signs can now be mini-roundabouts
+fn fix_stop_sign_roundabouts(value: &mut Value) {
+    walk(value, &|map| {
+        if map.contains_key("must_stop") {
+            map.insert("roundabout".to_string(), Value::Bool(false));
+            true
+        } else {
+            false
+        }
+    });
+}
+
+// Paths shared by people walking and cycling used to be imported as a bike lane and a shoulder in
+// each direction. Now each direction is one SharedUse lane.
+fn fix_shared_use_paths(value: &mut Value, map: &Map) -> Result<()> {
+    for orig in value.as_object_mut().unwrap()["commands"]
+        .as_array_mut()
+        .unwrap()
+    {
+        let cmd = orig.as_object_mut().unwrap();
+        if let Some(cmd) = cmd.get_mut("ChangeRoad") {
+            let road_id: OriginalRoad = serde_json::from_value(cmd["r"].clone()).unwrap();
+            let current = map.get_r(map.find_r_by_osm_id(road_id)?).lanes_ltr();
+            if !current.iter().any(|(_, _, lt)| *lt == LaneType::SharedUse) {
+                continue;
+            }
+            let cmd = cmd.as_object_mut().unwrap();
+
+            for key in ["old", "new"] {
+                let lanes_ltr: Vec<LaneSpec> =
+                    serde_json::from_value(cmd[key]["lanes_ltr"].clone())?;
+                let mut lanes_ltr = merge_shared_use(lanes_ltr);
+                // Use the width of the path in the current basemap, if the lanes still line up
+                if lanes_ltr.len() == current.len() {
+                    for (spec, (l, _, lt)) in lanes_ltr.iter_mut().zip(current.iter()) {
+                        if spec.lt == LaneType::SharedUse && *lt == LaneType::SharedUse {
+                            spec.width = map.get_l(*l).width;
+                        }
+                    }
+                }
+                cmd[key]["lanes_ltr"] = serde_json::to_value(lanes_ltr).unwrap();
+            }
+        }
+    }
+    Ok(())
+}
+
+// Merge each bike lane and shoulder next to each other going the same direction into one
+// SharedUse lane.
+fn merge_shared_use(lanes_ltr: Vec<LaneSpec>) -> Vec<LaneSpec> {
+    let mut result = Vec::new();
+    let mut iter = lanes_ltr.into_iter().peekable();
+    while let Some(lane) = iter.next() {
+        let pairs_up = iter.peek().map_or(false, |next| {
+            next.dir == lane.dir
+                && matches!(
+                    (lane.lt, next.lt),
+                    (LaneType::Biking, LaneType::Shoulder) | (LaneType::Shoulder, LaneType::Biking)
+                )
+        });
+        if pairs_up {
+            let next = iter.next().unwrap();
+            result.push(LaneSpec {
+                lt: LaneType::SharedUse,
+                dir: lane.dir,
+                width: lane.width + next.width,
+            });
+        } else {
+            result.push(lane);
+        }
+    }
+    result
+}
+
 // These're old structs used in fix_old_lane_cmds.
 #[derive(Debug, Deserialize)]
 struct OriginalLane {
@@ -499,3 +587,40 @@ impl OriginalLane {
         Ok((r.id, r.offset(l)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use geom::Distance;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_shared_use() {
+        let lane = |lt, dir, width| LaneSpec {
+            lt,
+            dir,
+            width: Distance::meters(width),
+        };
+        // A two-way path, as it was imported before
+        assert_eq!(
+            merge_shared_use(vec![
+                lane(LaneType::Shoulder, Direction::Back, 1.0),
+                lane(LaneType::Biking, Direction::Back, 1.5),
+                lane(LaneType::Biking, Direction::Fwd, 1.5),
+                lane(LaneType::Shoulder, Direction::Fwd, 1.0),
+            ]),
+            vec![
+                lane(LaneType::SharedUse, Direction::Back, 2.5),
+                lane(LaneType::SharedUse, Direction::Fwd, 2.5),
+            ]
+        );
+        // Edited lanes that no longer pair up are left alone
+        let edited = vec![
+            lane(LaneType::Sidewalk, Direction::Back, 1.0),
+            lane(LaneType::Biking, Direction::Back, 1.5),
+            lane(LaneType::Biking, Direction::Fwd, 1.5),
+            lane(LaneType::Shoulder, Direction::Back, 1.0),
+        ];
+        assert_eq!(merge_shared_use(edited.clone()), edited);
+    }
+}
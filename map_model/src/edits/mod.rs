@@ -4,19 +4,20 @@
 
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
 use abstutil::Timer;
 use geom::{Distance, HashablePt2D, Line, Speed, Time};
 
-pub use self::perma::PermanentMapEdits;
+pub use self::perma::{PermanentEditCmd, PermanentMapEdits};
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
 use crate::make::{match_points_to_lanes, snap_driveway, trim_path};
 use crate::{
-    connectivity, AccessRestrictions, BuildingID, BusRouteID, ControlStopSign,
+    connectivity, AccessRestrictions, BikeDock, BuildingID, BusRouteID, ControlStopSign,
     ControlTrafficSignal, Direction, IntersectionID, IntersectionType, LaneID, LaneSpec, LaneType,
-    Map, MapConfig, ParkingLotID, PathConstraints, Pathfinder, Road, RoadID, TurnID, Zone,
+    Map, MapConfig, MovementBan, MovementID, ParkingArea, ParkingLotID, ParkingPricing,
+    PathConstraints, Pathfinder, Road, RoadID, TurnID, Zone,
 };
 
 mod compat;
@@ -39,6 +40,10 @@ pub struct MapEdits {
     pub changed_roads: BTreeSet<RoadID>,
     pub original_intersections: BTreeMap<IntersectionID, EditIntersection>,
     pub changed_routes: BTreeSet<BusRouteID>,
+    pub changed_parking_pricing: BTreeSet<ParkingArea>,
+    pub changed_charging_spots: BTreeSet<ParkingArea>,
+    pub changed_bike_docks: BTreeSet<BuildingID>,
+    pub changed_movement_bans: BTreeSet<MovementID>,
 
     /// Some edits are included in the game by default, in data/system/proposals, as "community
     /// proposals." They require a description and may have a link to a write-up.
@@ -189,6 +194,26 @@ pub enum EditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    ChangeParkingPricing {
+        area: ParkingArea,
+        old: ParkingPricing,
+        new: ParkingPricing,
+    },
+    ChangeChargingSpots {
+        area: ParkingArea,
+        old: usize,
+        new: usize,
+    },
+    ChangeBikeDock {
+        b: BuildingID,
+        old: Option<BikeDock>,
+        new: Option<BikeDock>,
+    },
+    ChangeMovementBan {
+        mvmnt: MovementID,
+        old: Option<MovementBan>,
+        new: Option<MovementBan>,
+    },
 }
 
 pub struct EditEffects {
@@ -212,6 +237,10 @@ impl MapEdits {
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            changed_parking_pricing: BTreeSet::new(),
+            changed_charging_spots: BTreeSet::new(),
+            changed_bike_docks: BTreeSet::new(),
+            changed_movement_bans: BTreeSet::new(),
         }
     }
 
@@ -258,6 +287,10 @@ impl MapEdits {
         self.changed_roads.clear();
         self.original_intersections.clear();
         self.changed_routes.clear();
+        self.changed_parking_pricing.clear();
+        self.changed_charging_spots.clear();
+        self.changed_bike_docks.clear();
+        self.changed_movement_bans.clear();
 
         for cmd in &self.commands {
             match cmd {
@@ -272,6 +305,18 @@ impl MapEdits {
                 EditCmd::ChangeRouteSchedule { id, .. } => {
                     self.changed_routes.insert(*id);
                 }
+                EditCmd::ChangeParkingPricing { area, .. } => {
+                    self.changed_parking_pricing.insert(*area);
+                }
+                EditCmd::ChangeChargingSpots { area, .. } => {
+                    self.changed_charging_spots.insert(*area);
+                }
+                EditCmd::ChangeBikeDock { b, .. } => {
+                    self.changed_bike_docks.insert(*b);
+                }
+                EditCmd::ChangeMovementBan { mvmnt, .. } => {
+                    self.changed_movement_bans.insert(*mvmnt);
+                }
             }
         }
 
@@ -284,6 +329,33 @@ impl MapEdits {
             let r = map.get_br(*br);
             r.spawn_times != r.orig_spawn_times
         });
+        // Nothing is priced in the basemap. If a road's lanes are later edited, the parking lane
+        // may not exist anymore.
+        self.changed_parking_pricing.retain(|area| match area {
+            ParkingArea::Lane(r, idx) => map
+                .get_r(*r)
+                .lanes_ltr()
+                .get(*idx)
+                .map(|(l, _, _)| !map.get_l(*l).parking_pricing.is_free())
+                .unwrap_or(false),
+            ParkingArea::Lot(pl) => !map.get_pl(*pl).pricing.is_free(),
+        });
+        // Same for chargers
+        self.changed_charging_spots.retain(|area| match area {
+            ParkingArea::Lane(r, idx) => map
+                .get_r(*r)
+                .lanes_ltr()
+                .get(*idx)
+                .map(|(l, _, _)| map.get_l(*l).charging_spots > 0)
+                .unwrap_or(false),
+            ParkingArea::Lot(pl) => map.get_pl(*pl).charging_spots > 0,
+        });
+        // And no building has a dock in the basemap
+        self.changed_bike_docks
+            .retain(|b| map.get_b(*b).bike_dock.is_some());
+        // Or any movement bans
+        self.changed_movement_bans
+            .retain(|mvmnt| map.get_i(mvmnt.parent).movement_bans.contains_key(mvmnt));
     }
 
     /// Assumes update_derived has been called.
@@ -310,6 +382,34 @@ impl MapEdits {
                 old: r.orig_spawn_times.clone(),
             });
         }
+        for area in &self.changed_parking_pricing {
+            self.commands.push(EditCmd::ChangeParkingPricing {
+                area: *area,
+                old: ParkingPricing::free(),
+                new: map.get_parking_pricing(*area),
+            });
+        }
+        for area in &self.changed_charging_spots {
+            self.commands.push(EditCmd::ChangeChargingSpots {
+                area: *area,
+                old: 0,
+                new: map.get_charging_spots(*area),
+            });
+        }
+        for b in &self.changed_bike_docks {
+            self.commands.push(EditCmd::ChangeBikeDock {
+                b: *b,
+                old: None,
+                new: map.get_b(*b).bike_dock,
+            });
+        }
+        for mvmnt in &self.changed_movement_bans {
+            self.commands.push(EditCmd::ChangeMovementBan {
+                mvmnt: *mvmnt,
+                old: None,
+                new: map.get_i(mvmnt.parent).movement_bans.get(mvmnt).cloned(),
+            });
+        }
     }
 
     /// Pick apart changed_roads and figure out if an entire road was edited, or just a few lanes.
@@ -376,6 +476,39 @@ impl EditCmd {
             EditCmd::ChangeRouteSchedule { id, .. } => {
                 format!("reschedule route {}", map.get_br(*id).short_name)
             }
+            EditCmd::ChangeParkingPricing { area, old, new } => {
+                details.push(format!("{} -> {}", old.describe(), new.describe()));
+                match area {
+                    ParkingArea::Lane(r, _) => format!("parking pricing on road #{}", r.0),
+                    ParkingArea::Lot(pl) => format!("parking pricing for {}", pl),
+                }
+            }
+            EditCmd::ChangeChargingSpots { area, old, new } => {
+                details.push(format!("{} -> {} spots", old, new));
+                match area {
+                    ParkingArea::Lane(r, _) => format!("chargers on road #{}", r.0),
+                    ParkingArea::Lot(pl) => format!("chargers for {}", pl),
+                }
+            }
+            EditCmd::ChangeBikeDock { b, old, new } => {
+                let describe = |dock: &Option<BikeDock>| {
+                    dock.map(|d| d.describe())
+                        .unwrap_or_else(|| "no dock".to_string())
+                };
+                details.push(format!("{} -> {}", describe(old), describe(new)));
+                format!("dock at {}", map.get_b(*b).address)
+            }
+            EditCmd::ChangeMovementBan { mvmnt, old, new } => {
+                let describe = |ban: &Option<MovementBan>| {
+                    ban.map(|b| b.describe())
+                        .unwrap_or_else(|| "allowed".to_string())
+                };
+                details.push(format!("{} -> {}", describe(old), describe(new)));
+                format!(
+                    "turn from road #{} to road #{} at {}",
+                    mvmnt.from.id.0, mvmnt.to.id.0, mvmnt.parent
+                )
+            }
         };
         (summary, details)
     }
@@ -452,6 +585,36 @@ impl EditCmd {
             EditCmd::ChangeRouteSchedule { id, new, .. } => {
                 map.bus_routes[id.0].spawn_times = new.clone();
             }
+            EditCmd::ChangeParkingPricing { area, new, .. } => match area {
+                ParkingArea::Lane(r, idx) => {
+                    let l = map.get_r(*r).lanes_ltr()[*idx].0;
+                    map.lanes.get_mut(&l).unwrap().parking_pricing = *new;
+                }
+                ParkingArea::Lot(pl) => {
+                    map.parking_lots[pl.0].pricing = *new;
+                }
+            },
+            EditCmd::ChangeChargingSpots { area, new, .. } => match area {
+                ParkingArea::Lane(r, idx) => {
+                    let l = map.get_r(*r).lanes_ltr()[*idx].0;
+                    map.lanes.get_mut(&l).unwrap().charging_spots = *new;
+                }
+                ParkingArea::Lot(pl) => {
+                    map.parking_lots[pl.0].charging_spots = *new;
+                }
+            },
+            EditCmd::ChangeBikeDock { b, new, .. } => {
+                map.buildings[b.0].bike_dock = *new;
+            }
+            EditCmd::ChangeMovementBan { mvmnt, new, .. } => {
+                let i = &mut map.intersections[mvmnt.parent.0];
+                if let Some(ban) = new {
+                    i.movement_bans.insert(*mvmnt, *ban);
+                } else {
+                    i.movement_bans.remove(mvmnt);
+                }
+                effects.changed_intersections.insert(mvmnt.parent);
+            }
         }
     }
 
@@ -472,6 +635,26 @@ impl EditCmd {
                 old: new,
                 new: old,
             },
+            EditCmd::ChangeParkingPricing { area, old, new } => EditCmd::ChangeParkingPricing {
+                area,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeChargingSpots { area, old, new } => EditCmd::ChangeChargingSpots {
+                area,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeBikeDock { b, old, new } => EditCmd::ChangeBikeDock {
+                b,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeMovementBan { mvmnt, old, new } => EditCmd::ChangeMovementBan {
+                mvmnt,
+                old: new,
+                new: old,
+            },
         }
     }
 }
@@ -552,8 +735,11 @@ fn modify_lanes(map: &mut Map, r: RoadID, lanes_ltr: Vec<LaneSpec>, effects: &mu
     // existing lanes may change. We could try to preserve existing LaneIDs and modify them, but
     // it's simpler to just delete all of the lanes and create them again.
 
+    // Parking lanes that stay in the same position keep their pricing and chargers.
+    let mut old_pricing = Vec::new();
     for (l, _, _) in road.lanes_ltr.drain(..) {
-        map.lanes.remove(&l).unwrap();
+        let lane = map.lanes.remove(&l).unwrap();
+        old_pricing.push((lane.lane_type, lane.parking_pricing, lane.charging_spots));
         effects.deleted_lanes.insert(l);
     }
 
@@ -565,7 +751,13 @@ fn modify_lanes(map: &mut Map, r: RoadID, lanes_ltr: Vec<LaneSpec>, effects: &mu
     for lane in &new_lanes {
         road.lanes_ltr.push((lane.id, lane.dir, lane.lane_type));
     }
-    for lane in new_lanes {
+    for (idx, mut lane) in new_lanes.into_iter().enumerate() {
+        if let Some((LaneType::Parking, pricing, charging_spots)) = old_pricing.get(idx) {
+            if lane.lane_type == LaneType::Parking {
+                lane.parking_pricing = *pricing;
+                lane.charging_spots = *charging_spots;
+            }
+        }
         map.lanes.insert(lane.id, lane);
     }
 
@@ -843,16 +1035,34 @@ impl Map {
 
     /// Returns (changed_roads, deleted_lanes, deleted_turns, added_turns, changed_intersections)
     pub fn must_apply_edits(&mut self, new_edits: MapEdits) -> EditEffects {
-        self.apply_edits(new_edits, true)
+        self.apply_edits(new_edits, true).unwrap()
     }
 
     pub fn try_apply_edits(&mut self, new_edits: MapEdits) {
-        self.apply_edits(new_edits, false);
+        // Nothing is validated, so this can't fail
+        self.apply_edits(new_edits, false).unwrap();
+    }
+
+    /// Like must_apply_edits, but for edits that come from somewhere untrusted. If they're
+    /// invalid, the map is left as it was and an error is returned.
+    pub fn apply_untrusted_edits(&mut self, new_edits: MapEdits) -> Result<EditEffects> {
+        let old_edits = self.edits.clone();
+        let was_dirty = self.pathfinder_dirty;
+        match self.apply_edits(new_edits, true) {
+            Ok(effects) => Ok(effects),
+            Err(err) => {
+                // The old edits were valid
+                self.apply_edits(old_edits, true).unwrap();
+                self.pathfinder_dirty = was_dirty;
+                Err(err)
+            }
+        }
     }
 
     // new_edits don't necessarily have to be valid; this could be used for speculatively testing
-    // edits. Doesn't update pathfinding yet.
-    fn apply_edits(&mut self, mut new_edits: MapEdits, enforce_valid: bool) -> EditEffects {
+    // edits. Doesn't update pathfinding yet. If enforce_valid is set and the edits are invalid,
+    // they're still applied, but an error is returned.
+    fn apply_edits(&mut self, mut new_edits: MapEdits, enforce_valid: bool) -> Result<EditEffects> {
         self.edits_generation += 1;
 
         let mut effects = EditEffects {
@@ -866,7 +1076,7 @@ impl Map {
 
         // Short-circuit to avoid marking pathfinder_dirty
         if self.edits == new_edits {
-            return effects;
+            return Ok(effects);
         }
 
         // We need to undo() all of the current commands in reverse order, then apply() all of the
@@ -898,22 +1108,23 @@ impl Map {
         }
 
         // Might need to update bus stops.
+        let mut orphaned_stop = None;
         if enforce_valid {
             for id in &effects.changed_roads {
                 let stops = self.get_r(*id).all_bus_stops(self);
                 for s in stops {
                     let sidewalk_pos = self.get_bs(s).sidewalk_pos;
-                    // Must exist, because we aren't allowed to orphan a bus stop.
-                    let driving_lane = self
-                        .get_r(*id)
-                        .find_closest_lane(
-                            sidewalk_pos.lane(),
-                            |l| PathConstraints::Bus.can_use(l, self),
-                            self,
-                        )
-                        .unwrap();
-                    let driving_pos = sidewalk_pos.equiv_pos(driving_lane, self);
-                    self.bus_stops.get_mut(&s).unwrap().driving_pos = driving_pos;
+                    // We aren't allowed to orphan a bus stop.
+                    if let Some(driving_lane) = self.get_r(*id).find_closest_lane(
+                        sidewalk_pos.lane(),
+                        |l| PathConstraints::Bus.can_use(l, self),
+                        self,
+                    ) {
+                        let driving_pos = sidewalk_pos.equiv_pos(driving_lane, self);
+                        self.bus_stops.get_mut(&s).unwrap().driving_pos = driving_pos;
+                    } else {
+                        orphaned_stop = Some(s);
+                    }
                 }
             }
         }
@@ -948,7 +1159,10 @@ impl Map {
 
         self.recalculate_road_to_buildings();
 
-        effects
+        if let Some(s) = orphaned_stop {
+            bail!("edits leave bus stop {} without a lane for buses", s);
+        }
+        Ok(effects)
     }
 
     /// This can expensive, so don't constantly do it while editing in the UI. But this must happen
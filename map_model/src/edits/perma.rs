@@ -9,7 +9,10 @@ use geom::Time;
 
 use crate::edits::{EditCmd, EditIntersection, EditRoad, MapEdits};
 use crate::raw::OriginalRoad;
-use crate::{osm, ControlStopSign, IntersectionID, Map};
+use crate::{
+    osm, BikeDock, ControlStopSign, DirectedRoadID, Direction, IntersectionID, LaneType, Map,
+    MovementBan, MovementID, ParkingArea, ParkingPricing,
+};
 
 /// MapEdits are converted to this before serializing. Referencing things like LaneID in a Map won't
 /// work if the basemap is rebuilt from new OSM data, so instead we use stabler OSM IDs that're less
@@ -39,6 +42,7 @@ pub enum PermanentEditIntersection {
             deserialize_with = "deserialize_btreemap"
         )]
         must_stop: BTreeMap<OriginalRoad, bool>,
+        roundabout: bool,
     },
     TrafficSignal(traffic_signal_data::TrafficSignal),
     Closed,
@@ -62,6 +66,60 @@ pub enum PermanentEditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    ChangeParkingPricing {
+        area: PermanentParkingArea,
+        old: ParkingPricing,
+        new: ParkingPricing,
+    },
+    ChangeChargingSpots {
+        area: PermanentParkingArea,
+        old: usize,
+        new: usize,
+    },
+    ChangeBikeDock {
+        b: osm::OsmID,
+        old: Option<BikeDock>,
+        new: Option<BikeDock>,
+    },
+    ChangeMovementBan {
+        i: osm::NodeID,
+        from: (OriginalRoad, Direction),
+        to: (OriginalRoad, Direction),
+        old: Option<MovementBan>,
+        new: Option<MovementBan>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum PermanentParkingArea {
+    /// The index of the parking lane, counting left-to-right
+    Lane(OriginalRoad, usize),
+    Lot(osm::OsmID),
+}
+
+impl PermanentParkingArea {
+    fn new(area: ParkingArea, map: &Map) -> PermanentParkingArea {
+        match area {
+            ParkingArea::Lane(r, idx) => PermanentParkingArea::Lane(map.get_r(r).orig_id, idx),
+            ParkingArea::Lot(pl) => PermanentParkingArea::Lot(map.get_pl(pl).osm_id),
+        }
+    }
+
+    fn into_area(self, map: &Map) -> Result<ParkingArea> {
+        match self {
+            PermanentParkingArea::Lane(r, idx) => {
+                let id = map.find_r_by_osm_id(r)?;
+                match map.get_r(id).lanes_ltr().get(idx) {
+                    Some((_, _, LaneType::Parking)) => Ok(ParkingArea::Lane(id, idx)),
+                    _ => bail!("lane {} of {} isn't a parking lane anymore", idx, r),
+                }
+            }
+            PermanentParkingArea::Lot(osm_id) => Ok(ParkingArea::Lot(
+                map.find_pl_by_osm_id(osm_id)
+                    .ok_or_else(|| anyhow!("can't find parking lot {}", osm_id))?,
+            )),
+        }
+    }
 }
 
 impl EditCmd {
@@ -84,6 +142,32 @@ impl EditCmd {
                     new: new.clone(),
                 }
             }
+            EditCmd::ChangeParkingPricing { area, old, new } => {
+                PermanentEditCmd::ChangeParkingPricing {
+                    area: PermanentParkingArea::new(*area, map),
+                    old: *old,
+                    new: *new,
+                }
+            }
+            EditCmd::ChangeChargingSpots { area, old, new } => {
+                PermanentEditCmd::ChangeChargingSpots {
+                    area: PermanentParkingArea::new(*area, map),
+                    old: *old,
+                    new: *new,
+                }
+            }
+            EditCmd::ChangeBikeDock { b, old, new } => PermanentEditCmd::ChangeBikeDock {
+                b: map.get_b(*b).orig_id,
+                old: *old,
+                new: *new,
+            },
+            EditCmd::ChangeMovementBan { mvmnt, old, new } => PermanentEditCmd::ChangeMovementBan {
+                i: map.get_i(mvmnt.parent).orig_id,
+                from: (map.get_r(mvmnt.from.id).orig_id, mvmnt.from.dir),
+                to: (map.get_r(mvmnt.to.id).orig_id, mvmnt.to.dir),
+                old: *old,
+                new: *new,
+            },
         }
     }
 }
@@ -128,6 +212,57 @@ impl PermanentEditCmd {
                     .ok_or_else(|| anyhow!("can't find {}", osm_rel_id))?;
                 Ok(EditCmd::ChangeRouteSchedule { id, old, new })
             }
+            PermanentEditCmd::ChangeParkingPricing { area, old, new } => {
+                Ok(EditCmd::ChangeParkingPricing {
+                    area: area.into_area(map)?,
+                    old,
+                    new,
+                })
+            }
+            PermanentEditCmd::ChangeChargingSpots { area, old, new } => {
+                Ok(EditCmd::ChangeChargingSpots {
+                    area: area.into_area(map)?,
+                    old,
+                    new,
+                })
+            }
+            PermanentEditCmd::ChangeBikeDock { b, old, new } => Ok(EditCmd::ChangeBikeDock {
+                b: map
+                    .find_b_by_osm_id(b)
+                    .ok_or_else(|| anyhow!("can't find building {}", b))?,
+                old,
+                new,
+            }),
+            PermanentEditCmd::ChangeMovementBan {
+                i,
+                from,
+                to,
+                old,
+                new,
+            } => {
+                let parent = map.find_i_by_osm_id(i)?;
+                let from = DirectedRoadID {
+                    id: map.find_r_by_osm_id(from.0)?,
+                    dir: from.1,
+                };
+                let to = DirectedRoadID {
+                    id: map.find_r_by_osm_id(to.0)?,
+                    dir: to.1,
+                };
+                if from.dst_i(map) != parent || to.src_i(map) != parent {
+                    bail!("turn from {} to {} doesn't go through {}", from, to, i);
+                }
+                Ok(EditCmd::ChangeMovementBan {
+                    mvmnt: MovementID {
+                        from,
+                        to,
+                        parent,
+                        crosswalk: false,
+                    },
+                    old,
+                    new,
+                })
+            }
         }
     }
 }
@@ -139,7 +274,7 @@ impl MapEdits {
             map_name: map.get_name().clone(),
             edits_name: self.edits_name.clone(),
             // Increase this every time there's a schema change
-            version: 11,
+            version: 13,
             proposal_description: self.proposal_description.clone(),
             proposal_link: self.proposal_link.clone(),
             commands: self.commands.iter().map(|cmd| cmd.to_perma(map)).collect(),
@@ -166,6 +301,10 @@ impl PermanentMapEdits {
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            changed_parking_pricing: BTreeSet::new(),
+            changed_charging_spots: BTreeSet::new(),
+            changed_bike_docks: BTreeSet::new(),
+            changed_movement_bans: BTreeSet::new(),
         };
         edits.update_derived(map);
         Ok(edits)
@@ -188,6 +327,10 @@ impl PermanentMapEdits {
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            changed_parking_pricing: BTreeSet::new(),
+            changed_charging_spots: BTreeSet::new(),
+            changed_bike_docks: BTreeSet::new(),
+            changed_movement_bans: BTreeSet::new(),
         };
         edits.update_derived(map);
         edits
@@ -203,6 +346,7 @@ impl EditIntersection {
                     .iter()
                     .map(|(r, val)| (map.get_r(*r).orig_id, val.must_stop))
                     .collect(),
+                roundabout: ss.roundabout,
             },
             EditIntersection::TrafficSignal(ref raw_ts) => {
                 PermanentEditIntersection::TrafficSignal(raw_ts.clone())
@@ -215,7 +359,10 @@ impl EditIntersection {
 impl PermanentEditIntersection {
     fn with_permanent(self, i: IntersectionID, map: &Map) -> Result<EditIntersection> {
         match self {
-            PermanentEditIntersection::StopSign { must_stop } => {
+            PermanentEditIntersection::StopSign {
+                must_stop,
+                roundabout,
+            } => {
                 let mut translated_must_stop = BTreeMap::new();
                 for (r, stop) in must_stop {
                     translated_must_stop.insert(map.find_r_by_osm_id(r)?, stop);
@@ -237,6 +384,9 @@ impl PermanentEditIntersection {
                         bail!("{} doesn't connect to {}", i, r);
                     }
                 }
+                // Roundabouts can be converted to stop signs and back. Edits made before
+                // roundabouts existed treat everything as stop signs, just like before.
+                ss.roundabout = roundabout;
 
                 Ok(EditIntersection::StopSign(ss))
             }
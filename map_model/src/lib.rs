@@ -38,32 +38,34 @@ use geom::{Bounds, GPSBounds, Polygon};
 
 pub use crate::city::City;
 pub use crate::edits::{
-    EditCmd, EditEffects, EditIntersection, EditRoad, MapEdits, PermanentMapEdits,
+    EditCmd, EditEffects, EditIntersection, EditRoad, MapEdits, PermanentEditCmd, PermanentMapEdits,
 };
 pub use crate::make::RawToMapOptions;
 pub use crate::map::{DrivingSide, MapConfig};
 pub use crate::objects::area::{Area, AreaID, AreaType};
 pub use crate::objects::building::{
-    Amenity, AmenityType, Building, BuildingID, BuildingType, NamePerLanguage, OffstreetParking,
+    Amenity, AmenityType, BikeDock, Building, BuildingID, BuildingType, NamePerLanguage,
+    OffstreetParking, SharedVehicleType,
 };
-pub use crate::objects::bus_stop::{BusRoute, BusRouteID, BusStop, BusStopID};
+pub use crate::objects::bus_stop::{BusRoute, BusRouteID, BusStop, BusStopID, TransitCapacity};
 pub use crate::objects::intersection::{Intersection, IntersectionID, IntersectionType};
 pub use crate::objects::lane::{
     BufferType, Lane, LaneID, LaneSpec, LaneType, NORMAL_LANE_THICKNESS, PARKING_LOT_SPOT_LENGTH,
     SIDEWALK_THICKNESS,
 };
-pub use crate::objects::parking_lot::{ParkingLot, ParkingLotID};
+pub use crate::objects::parking_lot::{ParkingArea, ParkingLot, ParkingLotID, ParkingPricing};
 pub use crate::objects::road::{DirectedRoadID, Direction, Road, RoadID};
 pub use crate::objects::stop_signs::{ControlStopSign, RoadWithStopSign};
-pub use crate::objects::traffic_signals::{ControlTrafficSignal, Stage, StageType};
+pub use crate::objects::traffic_signals::{ControlTrafficSignal, Stage, StageType, TimingPlan};
 pub use crate::objects::turn::{
-    CompressedMovementID, Movement, MovementID, Turn, TurnID, TurnPriority, TurnType,
+    CompressedMovementID, Movement, MovementBan, MovementID, Turn, TurnID, TurnPriority, TurnType,
 };
 pub use crate::objects::zone::{AccessRestrictions, Zone};
 pub use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn};
 use crate::pathfind::Pathfinder;
 pub use crate::pathfind::{
-    Path, PathConstraints, PathRequest, PathStep, PathStepV2, PathV2, RoutingParams,
+    Path, PathConstraints, PathRequest, PathStep, PathStepV2, PathV2, RoutingParams, TransitAccess,
+    TransitChoice,
 };
 pub use crate::traversable::{Position, Traversable, MAX_BIKE_SPEED, MAX_WALKING_SPEED};
 
@@ -80,6 +82,12 @@ mod traversable;
 
 // The map used by the simulation and UI. This struct is declared here so that the rest of the
 // crate can reach into private fields.
+//
+// Maps are saved with bincode, which can't skip missing fields, so adding a field to anything
+// reachable from here makes existing map files unreadable. New fields still get #[serde(default)]
+// for the JSON forms of these types, but binary maps have to be imported again: run
+// `./import.sh --regen_all`, or `cargo run --bin updater` to fetch released maps built with the
+// current format.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Map {
     roads: Vec<Road>,
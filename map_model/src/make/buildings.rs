@@ -114,6 +114,8 @@ pub fn make_all_buildings(
 
                 sidewalk_pos: *sidewalk_pos,
                 driveway_geom: sidewalk_line.to_polyline(),
+
+                bike_dock: None,
             });
         }
     }
@@ -141,7 +141,7 @@ fn should_collapse(r1: OriginalRoad, r2: OriginalRoad, raw: &RawMap) -> Result<(
 fn is_cycleway(lanes: &[LaneSpec]) -> bool {
     let mut bike = false;
     for spec in lanes {
-        if spec.lt == LaneType::Biking {
+        if spec.lt == LaneType::Biking || spec.lt == LaneType::SharedUse {
             bike = true;
         } else if spec.lt != LaneType::Shoulder {
             return false;
@@ -24,8 +24,7 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
     if tags.is(osm::HIGHWAY, "steps") {
         return vec![fwd(LaneType::Sidewalk)];
     }
-    // Eventually, we should have some kind of special LaneType for shared walking/cycling paths of
-    // different kinds. Until then, model by making bike lanes and a shoulder for walking.
+    // Paths off the road network, shared by people walking and cycling
     if tags.is_any(
         osm::HIGHWAY,
         vec!["cycleway", "footway", "path", "pedestrian", "track"],
@@ -39,21 +38,19 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
         {
             return vec![fwd(LaneType::Sidewalk)];
         }
-        // Otherwise, there'll always be a bike lane.
-
-        let mut fwd_side = vec![fwd(LaneType::Biking)];
-        let mut back_side = if tags.is("oneway", "yes") {
+        // Otherwise, bikes are allowed. If pedestrians are too, it's a shared-use path; people can
+        // walk either direction on it, so oneway only restricts bikes.
+        let lt = if tags.is("foot", "no") {
+            LaneType::Biking
+        } else {
+            LaneType::SharedUse
+        };
+        let fwd_side = vec![fwd(lt)];
+        let back_side = if tags.is("oneway", "yes") {
             vec![]
         } else {
-            vec![back(LaneType::Biking)]
+            vec![back(lt)]
         };
-
-        if !tags.is("foot", "no") {
-            fwd_side.push(fwd(LaneType::Shoulder));
-            if !back_side.is_empty() {
-                back_side.push(back(LaneType::Shoulder));
-            }
-        }
         return assemble_ltr(fwd_side, back_side, cfg.driving_side);
     }
 
@@ -204,11 +201,23 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
                 back_side.push(back(LaneType::Biking));
             }
         }
-        if tags.is("cycleway:left", "opposite_lane") || tags.is("cycleway", "opposite_lane") {
+        // Contraflow bike lanes go against the direction of a one-way road. When the side isn't
+        // specified, put it where traffic in that direction would normally be.
+        if tags.is("cycleway", "opposite_lane") {
+            back_side.push(back(LaneType::Biking));
+        }
+        if tags.is("cycleway:left", "opposite_lane") {
             if cfg.driving_side == DrivingSide::Right {
                 back_side.push(back(LaneType::Biking));
             } else {
-                fwd_side.push(fwd(LaneType::Biking));
+                fwd_side.push(back(LaneType::Biking));
+            }
+        }
+        if tags.is("cycleway:right", "opposite_lane") {
+            if cfg.driving_side == DrivingSide::Right {
+                fwd_side.push(back(LaneType::Biking));
+            } else {
+                back_side.push(back(LaneType::Biking));
             }
         }
         if tags.is_any("cycleway:left", vec!["lane", "opposite_track", "track"]) {
@@ -232,6 +241,10 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
         }
     }
 
+    // One-way roads where bikes may go both ways without a marked contraflow lane (like
+    // oneway:bicycle=no or cycleway=opposite) don't get a lane here; there's no physical space for
+    // one. See Road::contraflow_bikes_allowed.
+
     // My brain hurts. How does the above combinatorial explosion play with
     // https://wiki.openstreetmap.org/wiki/Proposed_features/cycleway:separation? Let's take the
     // "post-processing" approach.
@@ -271,11 +284,25 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
             || tags.is_any(osm::PARKING_BOTH, has_parking.clone());
         let parking_lane_back = tags.is_any(osm::PARKING_LEFT, has_parking.clone())
             || tags.is_any(osm::PARKING_BOTH, has_parking);
+        // Some curbside space is only for loading and unloading
+        let loading = vec!["loading", "loading_only"];
+        let loading_fwd = tags.is_any("parking:condition:right", loading.clone())
+            || tags.is_any("parking:condition:both", loading.clone());
+        let loading_back = tags.is_any("parking:condition:left", loading.clone())
+            || tags.is_any("parking:condition:both", loading);
         if parking_lane_fwd {
-            fwd_side.push(fwd(LaneType::Parking));
+            fwd_side.push(fwd(if loading_fwd {
+                LaneType::LoadingZone
+            } else {
+                LaneType::Parking
+            }));
         }
         if parking_lane_back {
-            back_side.push(back(LaneType::Parking));
+            back_side.push(back(if loading_back {
+                LaneType::LoadingZone
+            } else {
+                LaneType::Parking
+            }));
         }
     }
 
@@ -488,6 +515,38 @@ mod tests {
                 "sddbbs",
                 "v^^v^^",
             ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:cycleway%3Dopposite_lane",
+                vec!["oneway=yes", "sidewalk=both", "cycleway=opposite_lane"],
+                DrivingSide::Right,
+                "sbds",
+                "vv^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Key:oneway:bicycle",
+                vec!["oneway=yes", "sidewalk=both", "oneway:bicycle=no"],
+                DrivingSide::Right,
+                "sds",
+                "v^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:cycleway%3Dopposite",
+                vec!["oneway=yes", "sidewalk=both", "cycleway=opposite"],
+                DrivingSide::Right,
+                "sds",
+                "v^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:cycleway%3Dopposite_lane",
+                vec![
+                    "oneway=yes",
+                    "sidewalk=both",
+                    "cycleway:right=opposite_lane",
+                ],
+                DrivingSide::Left,
+                "sdbs",
+                "^^vv",
+            ),
             (
                 "https://www.openstreetmap.org/way/777565028",
                 vec!["highway=residential", "oneway=no", "sidewalk=both"],
@@ -544,6 +603,77 @@ mod tests {
                 "SddS",
                 "vv^^",
             ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Key:parking:condition",
+                vec![
+                    "lanes=2",
+                    "sidewalk=both",
+                    "parking:lane:both=parallel",
+                    "parking:condition:right=loading",
+                ],
+                DrivingSide::Right,
+                "spddLs",
+                "vvv^^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Key:parking:condition",
+                vec![
+                    "lanes=2",
+                    "sidewalk=both",
+                    "parking:lane:both=parallel",
+                    "parking:condition:left=loading_only",
+                ],
+                DrivingSide::Right,
+                "sLddps",
+                "vvv^^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Key:parking:condition",
+                vec![
+                    "lanes=2",
+                    "sidewalk=both",
+                    "parking:lane:both=parallel",
+                    "parking:condition:both=loading",
+                ],
+                DrivingSide::Right,
+                "sLddLs",
+                "vvv^^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dpath",
+                vec!["highway=path"],
+                DrivingSide::Right,
+                "uu",
+                "v^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dpath",
+                vec!["highway=path", "oneway=yes"],
+                DrivingSide::Right,
+                "u",
+                "^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dcycleway",
+                vec!["highway=cycleway", "foot=no"],
+                DrivingSide::Right,
+                "bb",
+                "v^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dfootway",
+                vec!["highway=footway"],
+                DrivingSide::Right,
+                "s",
+                "^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dfootway",
+                vec!["highway=footway", "bicycle=designated"],
+                DrivingSide::Left,
+                "uu",
+                "^v",
+            ),
         ] {
             let cfg = MapConfig {
                 driving_side,
@@ -112,6 +112,7 @@ impl Map {
                 outgoing_lanes: Vec::new(),
                 roads: i.roads.iter().map(|id| road_id_mapping[id]).collect(),
                 merged: !raw.intersections[&i.id].trim_roads_for_merging.is_empty(),
+                movement_bans: BTreeMap::new(),
             });
             intersection_id_mapping.insert(i.id, id);
         }
@@ -110,6 +110,7 @@ fn new(id: IntersectionID, map: &Map) -> ControlTrafficSignal {
         id,
         stages: Vec::new(),
         offset: Duration::ZERO,
+        other_plans: Vec::new(),
         movements: Movement::for_i(id, map).unwrap(),
     }
 }
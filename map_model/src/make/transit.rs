@@ -9,6 +9,7 @@ use crate::make::match_points_to_lanes;
 use crate::raw::{RawBusRoute, RawBusStop};
 use crate::{
     BusRoute, BusRouteID, BusStop, BusStopID, LaneID, LaneType, Map, PathConstraints, Position,
+    TransitCapacity,
 };
 
 /// Construct the final model of bus/train stops and routes. This is quite broken currently, so not
@@ -147,8 +148,9 @@ fn make_route(
         route_type,
         start,
         end_border,
-        spawn_times: default_spawn_times(),
-        orig_spawn_times: default_spawn_times(),
+        spawn_times: r.spawn_times.clone().unwrap_or_else(default_spawn_times),
+        orig_spawn_times: r.spawn_times.clone().unwrap_or_else(default_spawn_times),
+        capacity: TransitCapacity::default_for(route_type),
     };
 
     let mut debug_route = "All parts of the route:".to_string();
@@ -11,11 +11,18 @@ use crate::{Intersection, Lane, LaneID, LaneType, Map, RoadID, Turn, TurnID, Tur
 pub fn make_all_turns(map: &Map, i: &Intersection) -> Vec<Turn> {
     let mut raw_turns: Vec<Turn> = Vec::new();
     raw_turns.extend(make_vehicle_turns(i, map));
-    raw_turns.extend(crate::make::walking_turns::filter_turns(
-        crate::make::walking_turns::make_walking_turns(map, i),
-        map,
-        i,
-    ));
+    // Shared-use paths get both vehicle and walking turns between the same lanes. Pedestrians can
+    // use the vehicle turn just as well, so skip the duplicate.
+    let vehicle_turns: HashSet<TurnID> = raw_turns.iter().map(|t| t.id).collect();
+    raw_turns.extend(
+        crate::make::walking_turns::filter_turns(
+            crate::make::walking_turns::make_walking_turns(map, i),
+            map,
+            i,
+        )
+        .into_iter()
+        .filter(|t| !vehicle_turns.contains(&t.id)),
+    );
     let unique_turns = ensure_unique(raw_turns);
     // Never allow turns that go against road-level turn restrictions; that upstream OSM data is
     // usually not extremely broken.
@@ -116,10 +123,10 @@ pub fn verify_vehicle_connectivity(turns: &[Turn], i: &Intersection, map: &Map)
             outgoing_missing.remove(&turn.id.dst);
         }
 
-        if src_lt == LaneType::Biking || src_lt == LaneType::Bus {
+        if src_lt == LaneType::Biking || src_lt == LaneType::SharedUse || src_lt == LaneType::Bus {
             incoming_missing.remove(&turn.id.src);
         }
-        if dst_lt == LaneType::Biking || dst_lt == LaneType::Bus {
+        if dst_lt == LaneType::Biking || dst_lt == LaneType::SharedUse || dst_lt == LaneType::Bus {
             outgoing_missing.remove(&turn.id.dst);
         }
     }
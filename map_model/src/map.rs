@@ -14,9 +14,9 @@ use crate::raw::{OriginalRoad, RawMap};
 use crate::{
     osm, Area, AreaID, AreaType, Building, BuildingID, BuildingType, BusRoute, BusRouteID, BusStop,
     BusStopID, ControlStopSign, ControlTrafficSignal, DirectedRoadID, Intersection, IntersectionID,
-    Lane, LaneID, LaneType, Map, MapEdits, MovementID, OffstreetParking, ParkingLot, ParkingLotID,
-    Path, PathConstraints, PathRequest, PathV2, Pathfinder, Position, Road, RoadID, RoutingParams,
-    Turn, TurnID, TurnType, Zone,
+    Lane, LaneID, LaneType, Map, MapEdits, MovementID, OffstreetParking, ParkingArea, ParkingLot,
+    ParkingLotID, ParkingPricing, Path, PathConstraints, PathRequest, PathV2, Pathfinder, Road,
+    RoadID, RoutingParams, TransitAccess, TransitChoice, Turn, TurnID, TurnType, Zone,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -210,6 +210,15 @@ impl Map {
         &self.buildings
     }
 
+    /// All buildings with a bike-share or scooter-share dock out front
+    pub fn all_bike_docks(&self) -> Vec<BuildingID> {
+        self.buildings
+            .iter()
+            .filter(|b| b.bike_dock.is_some())
+            .map(|b| b.id)
+            .collect()
+    }
+
     pub fn all_areas(&self) -> &Vec<Area> {
         &self.areas
     }
@@ -306,6 +315,26 @@ impl Map {
         &self.parking_lots[id.0]
     }
 
+    pub fn get_parking_pricing(&self, area: ParkingArea) -> ParkingPricing {
+        match area {
+            ParkingArea::Lane(r, idx) => {
+                let l = self.get_r(r).lanes_ltr()[idx].0;
+                self.get_l(l).parking_pricing
+            }
+            ParkingArea::Lot(pl) => self.get_pl(pl).pricing,
+        }
+    }
+
+    pub fn get_charging_spots(&self, area: ParkingArea) -> usize {
+        match area {
+            ParkingArea::Lane(r, idx) => {
+                let l = self.get_r(r).lanes_ltr()[idx].0;
+                self.get_l(l).charging_spots
+            }
+            ParkingArea::Lot(pl) => self.get_pl(pl).charging_spots,
+        }
+    }
+
     pub fn get_stop_sign(&self, id: IntersectionID) -> &ControlStopSign {
         &self.stop_signs[&id]
     }
@@ -388,7 +417,10 @@ impl Map {
         let mut turns: Vec<&Turn> = self
             .get_next_turns_and_lanes(from, self.get_l(from).dst_i)
             .into_iter()
-            .filter(|(_, l)| constraints.can_use(l, self))
+            .filter(|(t, l)| {
+                constraints.can_use(l, self)
+                    && (constraints == PathConstraints::Pedestrian || !t.between_sidewalks())
+            })
             .map(|(t, _)| t)
             .collect();
         // Sidewalks are bidirectional
@@ -403,20 +435,32 @@ impl Map {
         turns
     }
 
-    /// Find all movements from one road to another that're usable by someone.
+    /// Find all movements from one road to another that're usable by someone. Movements banned
+    /// all day for this vehicle type are excluded; part-time bans are left for the caller to
+    /// handle.
     pub fn get_movements_for(
         &self,
         from: DirectedRoadID,
         constraints: PathConstraints,
     ) -> Vec<MovementID> {
         let mut result = BTreeSet::new();
-        for t in &self.get_i(from.dst_i(self)).turns {
+        let i = self.get_i(from.dst_i(self));
+        for t in &i.turns {
             let src = self.get_l(t.id.src);
+            // Bikes can use shared-use paths, but not the crosswalks between them
             if src.get_directed_parent() == from
+                && !t.between_sidewalks()
                 && constraints.can_use(src, self)
                 && constraints.can_use(self.get_l(t.id.dst), self)
             {
-                result.insert(t.id.to_movement(self));
+                let mvmnt = t.id.to_movement(self);
+                let banned = i
+                    .get_movement_ban(mvmnt, constraints)
+                    .map(|ban| ban.time_window.is_none())
+                    .unwrap_or(false);
+                if !banned {
+                    result.insert(mvmnt);
+                }
             }
         }
         // TODO Sidewalks are bidirectional
@@ -597,13 +641,14 @@ impl Map {
             .pathfind_with_params(req.clone(), params, self)
             .ok_or_else(|| anyhow!("can't fulfill {}", req))
     }
+    /// Decide if and how to use public transit. See `Pathfinder::should_use_transit`.
     pub fn should_use_transit(
         &self,
-        start: Position,
-        end: Position,
-    ) -> Option<(BusStopID, Option<BusStopID>, BusRouteID)> {
+        access: &[TransitAccess],
+        egress: &[TransitAccess],
+    ) -> Option<TransitChoice> {
         assert!(!self.pathfinder_dirty);
-        self.pathfinder.should_use_transit(self, start, end)
+        self.pathfinder.should_use_transit(self, access, egress)
     }
 
     /// Return the cost of a single path, and also a mapping from every directed road to the cost
@@ -660,6 +705,15 @@ impl Map {
         None
     }
 
+    pub fn find_pl_by_osm_id(&self, id: osm::OsmID) -> Option<ParkingLotID> {
+        for pl in self.all_parking_lots() {
+            if pl.osm_id == id {
+                return Some(pl.id);
+            }
+        }
+        None
+    }
+
     pub fn find_br(&self, id: osm::RelationID) -> Option<BusRouteID> {
         for br in self.all_bus_routes() {
             if br.osm_rel_id == id {
@@ -51,6 +51,10 @@ pub struct Building {
     pub sidewalk_pos: Position,
     /// Goes from building to sidewalk
     pub driveway_geom: PolyLine,
+
+    /// A bike-share or scooter-share dock out front. These only come from map edits.
+    #[serde(default)]
+    pub bike_dock: Option<BikeDock>,
 }
 
 /// A business located inside a building.
@@ -94,6 +98,32 @@ impl BuildingType {
     }
 }
 
+/// A place to pick up and return shared bikes or scooters, located on the sidewalk in front of a
+/// building.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BikeDock {
+    pub vehicle_type: SharedVehicleType,
+    /// How many vehicles fit in the dock
+    pub capacity: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SharedVehicleType {
+    Bike,
+    Scooter,
+}
+
+impl BikeDock {
+    pub fn describe(&self) -> String {
+        match self.vehicle_type {
+            SharedVehicleType::Bike => format!("bike-share dock for {} bikes", self.capacity),
+            SharedVehicleType::Scooter => {
+                format!("scooter-share dock for {} scooters", self.capacity)
+            }
+        }
+    }
+}
+
 /// None corresponds to the native name
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct NamePerLanguage(
@@ -217,6 +247,11 @@ impl Building {
 }
 
 fn sidewalk_to_bike(sidewalk_pos: Position, map: &Map) -> Option<(Position, Position)> {
+    // Shared-use paths can be biked on directly
+    let sidewalk = map.get_l(sidewalk_pos.lane());
+    if sidewalk.is_shared_use() && !sidewalk.biking_blackhole {
+        return Some((sidewalk_pos, sidewalk_pos));
+    }
     let lane = map.get_parent(sidewalk_pos.lane()).find_closest_lane(
         sidewalk_pos.lane(),
         |l| !l.biking_blackhole && PathConstraints::Bike.can_use(l, map),
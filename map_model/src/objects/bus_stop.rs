@@ -67,6 +67,41 @@ pub struct BusRoute {
     /// Explicitly store whatever the original was, since this can't be reconstructed without side
     /// input.
     pub orig_spawn_times: Vec<Time>,
+    /// How many passengers each vehicle serving this route can carry. Routes saved without this
+    /// assume a standard bus.
+    #[serde(default = "TransitCapacity::bus")]
+    pub capacity: TransitCapacity,
+}
+
+/// How many passengers fit on one bus or train.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TransitCapacity {
+    pub seated: usize,
+    pub standing: usize,
+}
+
+impl TransitCapacity {
+    /// A reasonable default for a standard 40-foot bus or a 2-car light rail train.
+    pub fn default_for(route_type: PathConstraints) -> TransitCapacity {
+        match route_type {
+            PathConstraints::Train => TransitCapacity {
+                seated: 148,
+                standing: 252,
+            },
+            _ => TransitCapacity {
+                seated: 38,
+                standing: 32,
+            },
+        }
+    }
+
+    fn bus() -> TransitCapacity {
+        TransitCapacity::default_for(PathConstraints::Bus)
+    }
+
+    pub fn total(self) -> usize {
+        self.seated + self.standing
+    }
 }
 
 impl BusRoute {
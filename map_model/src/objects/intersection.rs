@@ -1,12 +1,14 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::{deserialize_usize, serialize_usize};
+use abstutil::{deserialize_btreemap, deserialize_usize, serialize_btreemap, serialize_usize};
 use geom::{Distance, Polygon};
 
-use crate::{osm, DirectedRoadID, LaneID, Map, PathConstraints, Road, RoadID, Turn};
+use crate::{
+    osm, DirectedRoadID, LaneID, Map, MovementBan, MovementID, PathConstraints, Road, RoadID, Turn,
+};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct IntersectionID(
@@ -57,6 +59,14 @@ pub struct Intersection {
 
     /// Was a short road adjacent to this intersection merged?
     pub merged: bool,
+
+    /// Movements banned through map edits.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap",
+        default
+    )]
+    pub movement_bans: BTreeMap<MovementID, MovementBan>,
 }
 
 impl Intersection {
@@ -98,6 +108,19 @@ impl Intersection {
         self.roads.iter().all(|r| map.get_r(*r).is_cycleway())
     }
 
+    /// If a movement is banned for some kind of vehicle (maybe only part of the day), returns the
+    /// ban.
+    pub fn get_movement_ban(
+        &self,
+        mvmnt: MovementID,
+        constraints: PathConstraints,
+    ) -> Option<MovementBan> {
+        self.movement_bans
+            .get(&mvmnt)
+            .filter(|ban| ban.applies_to(constraints))
+            .cloned()
+    }
+
     pub fn get_incoming_lanes(&self, map: &Map, constraints: PathConstraints) -> Vec<LaneID> {
         self.incoming_lanes
             .iter()
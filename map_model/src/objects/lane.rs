@@ -7,8 +7,8 @@ use abstutil::{deserialize_usize, serialize_usize, wraparound_get, Tags};
 use geom::{Distance, Line, PolyLine, Polygon, Pt2D, Ring};
 
 use crate::{
-    osm, BusStopID, DirectedRoadID, Direction, IntersectionID, Map, MapConfig, Road, RoadID,
-    TurnType,
+    osm, BusStopID, DirectedRoadID, Direction, IntersectionID, Map, MapConfig, ParkingPricing,
+    Road, RoadID, TurnType,
 };
 
 /// From some manually audited cases in Seattle, the length of parallel street parking spots is a
@@ -39,11 +39,16 @@ impl fmt::Display for LaneID {
 pub enum LaneType {
     Driving,
     Parking,
+    /// Curbside space reserved for vans to stop while loading and unloading deliveries
+    LoadingZone,
     Sidewalk,
     // Walkable like a Sidewalk, but very narrow. Used to model pedestrians walking on roads
     // without sidewalks.
     Shoulder,
     Biking,
+    /// A path off the road network shared by pedestrians and cyclists, like a trail. Bikes slow
+    /// down when pedestrians are on it.
+    SharedUse,
     Bus,
     SharedLeftTurn,
     Construction,
@@ -70,8 +75,10 @@ impl LaneType {
         match self {
             LaneType::Driving => true,
             LaneType::Biking => true,
+            LaneType::SharedUse => true,
             LaneType::Bus => true,
             LaneType::Parking => false,
+            LaneType::LoadingZone => false,
             LaneType::Sidewalk => false,
             LaneType::Shoulder => false,
             LaneType::SharedLeftTurn => false,
@@ -85,8 +92,10 @@ impl LaneType {
         match self {
             LaneType::Driving => true,
             LaneType::Biking => true,
+            LaneType::SharedUse => true,
             LaneType::Bus => true,
             LaneType::Parking => false,
+            LaneType::LoadingZone => false,
             LaneType::Sidewalk => true,
             LaneType::Shoulder => true,
             LaneType::SharedLeftTurn => false,
@@ -97,15 +106,17 @@ impl LaneType {
     }
 
     pub fn is_walkable(self) -> bool {
-        self == LaneType::Sidewalk || self == LaneType::Shoulder
+        self == LaneType::Sidewalk || self == LaneType::Shoulder || self == LaneType::SharedUse
     }
 
     pub fn describe(self) -> &'static str {
         match self {
             LaneType::Driving => "a general-purpose driving lane",
             LaneType::Biking => "a protected bike lane",
+            LaneType::SharedUse => "a shared-use path for walking and cycling",
             LaneType::Bus => "a bus-only lane",
             LaneType::Parking => "an on-street parking lane",
+            LaneType::LoadingZone => "a curbside loading zone",
             LaneType::Sidewalk => "a sidewalk",
             LaneType::Shoulder => "a shoulder",
             LaneType::SharedLeftTurn => "a shared left-turn lane",
@@ -123,8 +134,10 @@ impl LaneType {
         match self {
             LaneType::Driving => "driving lane",
             LaneType::Biking => "bike lane",
+            LaneType::SharedUse => "shared-use path",
             LaneType::Bus => "bus lane",
             LaneType::Parking => "parking lane",
+            LaneType::LoadingZone => "loading zone",
             LaneType::Sidewalk => "sidewalk",
             LaneType::Shoulder => "shoulder",
             LaneType::SharedLeftTurn => "left-turn lane",
@@ -142,8 +155,10 @@ impl LaneType {
         match x {
             "driving lane" => Some(LaneType::Driving),
             "bike lane" => Some(LaneType::Biking),
+            "shared-use path" => Some(LaneType::SharedUse),
             "bus lane" => Some(LaneType::Bus),
             "parking lane" => Some(LaneType::Parking),
+            "loading zone" => Some(LaneType::LoadingZone),
             "sidewalk" => Some(LaneType::Sidewalk),
             "shoulder" => Some(LaneType::Shoulder),
             "left-turn lane" => Some(LaneType::SharedLeftTurn),
@@ -163,8 +178,10 @@ impl LaneType {
         match self {
             LaneType::Driving => 'd',
             LaneType::Biking => 'b',
+            LaneType::SharedUse => 'u',
             LaneType::Bus => 'B',
             LaneType::Parking => 'p',
+            LaneType::LoadingZone => 'L',
             LaneType::Sidewalk => 's',
             LaneType::Shoulder => 'S',
             LaneType::SharedLeftTurn => 'C',
@@ -179,8 +196,10 @@ impl LaneType {
         match x {
             'd' => LaneType::Driving,
             'b' => LaneType::Biking,
+            'u' => LaneType::SharedUse,
             'B' => LaneType::Bus,
             'p' => LaneType::Parking,
+            'L' => LaneType::LoadingZone,
             's' => LaneType::Sidewalk,
             'S' => LaneType::Shoulder,
             'C' => LaneType::SharedLeftTurn,
@@ -212,6 +231,14 @@ pub struct Lane {
     /// graph, because this is near a border.
     pub driving_blackhole: bool,
     pub biking_blackhole: bool,
+
+    /// Only meaningful for parking lanes
+    #[serde(default = "ParkingPricing::free")]
+    pub parking_pricing: ParkingPricing,
+    /// Only meaningful for parking lanes. The first this many spots have chargers for electric
+    /// vehicles.
+    #[serde(default)]
+    pub charging_spots: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -274,8 +301,9 @@ impl Lane {
 
     // TODO different types for each lane type might be reasonable
 
+    /// Loading zones are divided into spots just like parking lanes.
     pub fn number_parking_spots(&self, cfg: &MapConfig) -> usize {
-        assert_eq!(self.lane_type, LaneType::Parking);
+        assert!(self.is_parking() || self.is_loading_zone());
         // No spots next to intersections
         let spots = (self.length() / cfg.street_parking_spot_length).floor() - 2.0;
         if spots >= 1.0 {
@@ -297,6 +325,10 @@ impl Lane {
         self.lane_type == LaneType::Bus
     }
 
+    pub fn is_shared_use(&self) -> bool {
+        self.lane_type == LaneType::SharedUse
+    }
+
     pub fn is_walkable(&self) -> bool {
         self.lane_type.is_walkable()
     }
@@ -313,6 +345,10 @@ impl Lane {
         self.lane_type == LaneType::Parking
     }
 
+    pub fn is_loading_zone(&self) -> bool {
+        self.lane_type == LaneType::LoadingZone
+    }
+
     pub fn is_light_rail(&self) -> bool {
         self.lane_type == LaneType::LightRail
     }
@@ -489,6 +525,12 @@ impl LaneSpec {
                 (Distance::meters(2.0), "standard"),
                 (Distance::meters(1.5), "absolute minimum"),
             ],
+            // https://www.gov.uk/government/publications/cycle-infrastructure-design-ltn-120 table
+            // 6-3
+            LaneType::SharedUse => vec![
+                (Distance::meters(3.0), "standard"),
+                (Distance::meters(2.0), "absolute minimum"),
+            ],
             // https://nacto.org/publication/urban-street-design-guide/street-design-elements/transit-streets/dedicated-curbside-offset-bus-lanes/
             LaneType::Bus => vec![
                 (Distance::feet(12.0), "normal"),
@@ -507,6 +549,11 @@ impl LaneSpec {
                 }
                 choices
             }
+            // Room for a box truck to open its doors
+            LaneType::LoadingZone => vec![
+                (Distance::feet(10.0), "typical"),
+                (Distance::feet(15.0), "wide"),
+            ],
             // Just a guess
             LaneType::SharedLeftTurn => vec![(NORMAL_LANE_THICKNESS, "default")],
             // These're often converted from existing lanes, so just retain that width
@@ -3,9 +3,9 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize};
-use geom::{Angle, Line, PolyLine, Polygon, Pt2D};
+use geom::{Angle, Duration, Line, PolyLine, Polygon, Pt2D};
 
-use crate::{osm, Position};
+use crate::{osm, LaneID, Map, Position, RoadID};
 
 // TODO For now, ignore the mapped roads linking things and just use the same driveway approach
 // that buildings use.
@@ -47,6 +47,12 @@ pub struct ParkingLot {
     /// Lot to sidewalk
     pub sidewalk_line: Line,
     pub sidewalk_pos: Position,
+
+    #[serde(default = "ParkingPricing::free")]
+    pub pricing: ParkingPricing,
+    /// The first this many spots have chargers for electric vehicles.
+    #[serde(default)]
+    pub charging_spots: usize,
 }
 
 impl ParkingLot {
@@ -54,3 +60,72 @@ impl ParkingLot {
         self.spots.len() + self.extra_spots
     }
 }
+
+/// What it costs to park somewhere, and for how long it's allowed. Everything is free and
+/// unlimited by default; nothing is imported from OSM yet.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParkingPricing {
+    pub cents_per_hour: usize,
+    /// Drivers staying longer than this are counted as overstaying, but nobody gets towed.
+    pub time_limit: Option<Duration>,
+}
+
+impl ParkingPricing {
+    pub fn free() -> ParkingPricing {
+        ParkingPricing {
+            cents_per_hour: 0,
+            time_limit: None,
+        }
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.cents_per_hour == 0
+    }
+
+    /// In cents, rounded up to the nearest cent
+    pub fn cost(&self, duration: Duration) -> usize {
+        (duration.inner_seconds() / 3600.0 * (self.cents_per_hour as f64)).ceil() as usize
+    }
+
+    pub fn describe(&self) -> String {
+        let price = if self.is_free() {
+            "free".to_string()
+        } else {
+            format!(
+                "{}/hour",
+                ParkingPricing::describe_cents(self.cents_per_hour)
+            )
+        };
+        if let Some(limit) = self.time_limit {
+            format!("{}, {} limit", price, limit)
+        } else {
+            price
+        }
+    }
+
+    pub fn describe_cents(cents: usize) -> String {
+        format!("${:.2}", (cents as f64) / 100.0)
+    }
+}
+
+impl std::default::Default for ParkingPricing {
+    fn default() -> ParkingPricing {
+        ParkingPricing::free()
+    }
+}
+
+/// Somewhere that parking can be priced or have chargers: an on-street parking lane or a parking lot. LaneIDs
+/// change when a road is edited, so on-street parking is identified by the road and the lane's
+/// left-to-right index.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ParkingArea {
+    Lane(RoadID, usize),
+    Lot(ParkingLotID),
+}
+
+impl ParkingArea {
+    pub fn from_lane(l: LaneID, map: &Map) -> ParkingArea {
+        let r = map.get_parent(l);
+        ParkingArea::Lane(r.id, r.offset(l))
+    }
+}
@@ -11,7 +11,7 @@ use geom::{Distance, PolyLine, Polygon, Speed};
 use crate::raw::{OriginalRoad, RestrictionType};
 use crate::{
     osm, AccessRestrictions, BusStopID, DrivingSide, IntersectionID, Lane, LaneID, LaneSpec,
-    LaneType, Map, PathConstraints, Zone,
+    LaneType, Map, ParkingPricing, PathConstraints, Zone,
 };
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -430,7 +430,7 @@ impl Road {
     pub fn is_cycleway(&self) -> bool {
         let mut bike = false;
         for (_, _, lt) in self.lanes_ltr() {
-            if lt == LaneType::Biking {
+            if lt == LaneType::Biking || lt == LaneType::SharedUse {
                 bike = true;
             } else if lt != LaneType::Shoulder {
                 return false;
@@ -439,6 +439,21 @@ impl Road {
         bike
     }
 
+    /// Does OSM say bikes may ride against traffic on this one-way road, without a marked
+    /// contraflow lane? This is only recorded as a permission; bikes can't actually go the other
+    /// way until a contraflow bike lane is added through map edits.
+    pub fn contraflow_bikes_allowed(&self) -> bool {
+        let oneway = self.osm_tags.is_any("oneway", vec!["yes", "reversible"])
+            || self.osm_tags.is("junction", "roundabout");
+        oneway
+            && (self.osm_tags.is("oneway:bicycle", "no")
+                || self
+                    .osm_tags
+                    .is_any("cycleway", vec!["opposite", "opposite_share_busway"])
+                || self.osm_tags.is("cycleway:left", "opposite")
+                || self.osm_tags.is("cycleway:right", "opposite"))
+    }
+
     pub fn common_endpt(&self, other: &Road) -> IntersectionID {
         #![allow(clippy::suspicious_operation_groupings)] // false positive
         if self.src_i == other.src_i || self.src_i == other.dst_i {
@@ -571,6 +586,8 @@ impl Road {
                 bus_stops: BTreeSet::new(),
                 driving_blackhole: false,
                 biking_blackhole: false,
+                parking_pricing: ParkingPricing::free(),
+                charging_spots: 0,
             });
         }
         lanes
@@ -605,7 +622,7 @@ impl Road {
         let mut can_use = false;
         // Can a bike even use it, or is it a highway?
         for (l, _, lt) in self.lanes_ltr() {
-            if lt == LaneType::Biking {
+            if lt == LaneType::Biking || lt == LaneType::SharedUse {
                 bike_lanes = true;
             }
             if PathConstraints::Bike.can_use(map.get_l(l), map) {
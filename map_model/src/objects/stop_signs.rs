@@ -42,6 +42,10 @@ pub struct ControlStopSign {
         deserialize_with = "deserialize_btreemap"
     )]
     pub roads: BTreeMap<RoadID, RoadWithStopSign>,
+    /// Is this a mini-roundabout or a node along a larger roundabout? If so, nobody stops.
+    /// Vehicles entering yield to circulating traffic instead.
+    #[serde(default)]
+    pub roundabout: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -55,6 +59,7 @@ impl ControlStopSign {
         let mut ss = ControlStopSign {
             id,
             roads: BTreeMap::new(),
+            roundabout: false,
         };
         // One-way outbound roads don't need a stop sign, so skip them entirely.
         for r in map.get_i(id).get_sorted_incoming_roads(map) {
@@ -93,15 +98,14 @@ impl ControlStopSign {
             }
         }
 
+        // Along a roundabout, entering yields to circulating traffic. The stop signs below are
+        // still worked out, in case the roundabout is later converted to stop signs.
+        ss.roundabout = ss.roads.keys().any(|r| is_circulating(map, *r));
+
         // Degenerate roads and deadends don't need any stop signs. But be careful with
         // roundabouts; we want it to be lower priority to enter a roundabout than continue through
         // it.
-        if ss.roads.len() <= 2
-            && ss
-                .roads
-                .keys()
-                .all(|r| !map.get_r(*r).osm_tags.is("junction", "roundabout"))
-        {
+        if ss.roads.len() <= 2 && !ss.roads.keys().any(|r| is_circulating(map, *r)) {
             return ss;
         }
         if map.get_i(id).is_cycleway(map) {
@@ -120,7 +124,7 @@ impl ControlStopSign {
             // Lower number is lower priority
             let priority = if r.is_cycleway() || r.osm_tags.is(osm::HIGHWAY, "service") {
                 0
-            } else if r.osm_tags.is("junction", "roundabout") {
+            } else if is_circulating(map, r.id) {
                 3
             } else if r
                 .osm_tags
@@ -162,7 +166,14 @@ impl ControlStopSign {
             // TODO This actually feels like a policy bit that should be flippable.
             TurnType::Crosswalk => TurnPriority::Protected,
             _ => {
-                if self.roads[&map.get_l(turn.src).parent].must_stop {
+                let r = map.get_l(turn.src).parent;
+                if self.roundabout {
+                    if is_circulating(map, r) {
+                        TurnPriority::Protected
+                    } else {
+                        TurnPriority::Yield
+                    }
+                } else if self.roads[&r].must_stop {
                     TurnPriority::Yield
                 } else {
                     TurnPriority::Protected
@@ -175,4 +186,64 @@ impl ControlStopSign {
         let ss = self.roads.get_mut(&r).unwrap();
         ss.must_stop = !ss.must_stop;
     }
+
+    /// Replace the stop signs with a mini-roundabout. There's no ring of roads, so everybody
+    /// entering yields to traffic coming from the side that circulating traffic would.
+    pub fn convert_to_mini_roundabout(&mut self) {
+        self.roundabout = true;
+        for cfg in self.roads.values_mut() {
+            cfg.must_stop = false;
+        }
+    }
+
+    /// Stop using roundabout rules. Along a ring of roads, circulating traffic still has priority
+    /// through the stop signs.
+    pub fn convert_to_stop_signs(&mut self, map: &Map) {
+        *self = ControlStopSign::new(map, self.id);
+        self.roundabout = false;
+    }
+
+    /// A mini-roundabout is a single intersection, not a node along a ring of roads.
+    pub fn is_mini_roundabout(&self, map: &Map) -> bool {
+        self.roundabout && !self.roads.keys().any(|r| is_circulating(map, *r))
+    }
+
+    /// For a vehicle entering a roundabout from this road, which incoming roads does it yield to?
+    /// Along a ring of roads, that's the ring. At a mini-roundabout, it's the road to the left
+    /// (when driving on the right), since circulating traffic comes from there.
+    pub fn roundabout_yields_to(&self, from: RoadID, map: &Map) -> Vec<RoadID> {
+        if !self.roundabout || is_circulating(map, from) {
+            return Vec::new();
+        }
+        let ring: Vec<RoadID> = self
+            .roads
+            .keys()
+            .filter(|r| is_circulating(map, **r))
+            .cloned()
+            .collect();
+        if !ring.is_empty() {
+            return ring;
+        }
+        // Otherwise, it's a mini-roundabout
+
+        let circulating_from = if map.get_config().driving_side == DrivingSide::Right {
+            TurnType::Left
+        } else {
+            TurnType::Right
+        };
+        let mut result = Vec::new();
+        for turn in map.get_turns_in_intersection(self.id) {
+            if turn.turn_type == circulating_from && map.get_l(turn.id.src).parent == from {
+                let to = map.get_l(turn.id.dst).parent;
+                if self.roads.contains_key(&to) && !result.contains(&to) {
+                    result.push(to);
+                }
+            }
+        }
+        result
+    }
+}
+
+fn is_circulating(map: &Map, r: RoadID) -> bool {
+    map.get_r(r).osm_tags.is("junction", "roundabout")
 }
@@ -5,7 +5,7 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::{Distance, Duration, Speed};
+use geom::{Distance, Duration, Speed, Time};
 
 use crate::make::traffic_signals::get_possible_policies;
 use crate::raw::OriginalRoad;
@@ -21,11 +21,18 @@ const CROSSWALK_PACE: Speed = Speed::const_meters_per_second(1.4);
 /// A traffic signal consists of a sequence of Stages that repeat in a cycle. Most Stages last for a
 /// fixed duration. During a single Stage, some movements are protected (can proceed with the
 /// highest priority), while others are permitted (have to yield before proceeding).
+///
+/// A signal may also switch between different timing plans throughout the day. `stages` and
+/// `offset` describe the plan starting at midnight.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ControlTrafficSignal {
     pub id: IntersectionID,
     pub stages: Vec<Stage>,
     pub offset: Duration,
+    /// Plans that take effect later in the day, sorted by start time. Each one lasts until the
+    /// next starts, and the plan described by `stages` resumes at midnight.
+    #[serde(default)]
+    pub other_plans: Vec<TimingPlan>,
 
     #[serde(
         serialize_with = "serialize_btreemap",
@@ -52,6 +59,15 @@ pub enum StageType {
     Variable(Duration, Duration, Duration),
 }
 
+/// A time-of-day plan, like an AM peak or night plan.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TimingPlan {
+    /// The plan takes effect at this time after midnight.
+    pub start_time: Duration,
+    pub stages: Vec<Stage>,
+    pub offset: Duration,
+}
+
 impl StageType {
     // TODO Maybe don't have this; force callers to acknowledge different policies
     pub fn simple_duration(&self) -> Duration {
@@ -90,9 +106,13 @@ impl ControlTrafficSignal {
         get_possible_policies(map, id, false)
     }
 
-    pub fn get_min_crossing_time(&self, idx: usize) -> Duration {
+    pub fn get_min_crossing_time(&self, plan: usize, idx: usize) -> Duration {
+        self.get_min_crossing_time_for_stage(&self.get_plan_stages(plan)[idx])
+    }
+
+    fn get_min_crossing_time_for_stage(&self, stage: &Stage) -> Duration {
         let mut max_distance = Distance::meters(0.0);
-        for movement in &self.stages[idx].protected_movements {
+        for movement in &stage.protected_movements {
             if movement.crosswalk {
                 max_distance =
                     max_distance.max(self.movements.get(movement).unwrap().geom.length());
@@ -105,10 +125,28 @@ impl ControlTrafficSignal {
     }
 
     pub fn validate(&self) -> Result<()> {
+        let mut last_start_time = Duration::ZERO;
+        for plan in &self.other_plans {
+            if plan.start_time <= last_start_time || plan.start_time >= Duration::hours(24) {
+                bail!(
+                    "Traffic signal {} has plans that aren't sorted by start time within one day",
+                    self.id
+                );
+            }
+            last_start_time = plan.start_time;
+        }
+
+        for plan in 0..self.num_plans() {
+            self.validate_stages(self.get_plan_stages(plan))?;
+        }
+        Ok(())
+    }
+
+    fn validate_stages(&self, stages: &[Stage]) -> Result<()> {
         // Does the assignment cover the correct set of movements?
         let expected_movements: BTreeSet<MovementID> = self.movements.keys().cloned().collect();
         let mut actual_movements: BTreeSet<MovementID> = BTreeSet::new();
-        for stage in &self.stages {
+        for stage in stages {
             actual_movements.extend(stage.protected_movements.iter());
             actual_movements.extend(stage.yield_movements.iter());
         }
@@ -126,7 +164,7 @@ impl ControlTrafficSignal {
                     .collect::<Vec<_>>()
             );
         }
-        for (stage_index, stage) in self.stages.iter().enumerate() {
+        for (stage_index, stage) in stages.iter().enumerate() {
             // Do any of the priority movements in one stage conflict?
             for m1 in stage.protected_movements.iter().map(|m| &self.movements[m]) {
                 for m2 in stage.protected_movements.iter().map(|m| &self.movements[m]) {
@@ -146,7 +184,7 @@ impl ControlTrafficSignal {
                 assert!(m.turn_type != TurnType::Crosswalk);
             }
             // Is there enough time in each stage to walk across the crosswalk
-            let min_crossing_time = self.get_min_crossing_time(stage_index);
+            let min_crossing_time = self.get_min_crossing_time_for_stage(stage);
             if stage.stage_type.simple_duration() < min_crossing_time {
                 bail!(
                     "Traffic signal does not allow enough time in stage to complete the \
@@ -283,9 +321,10 @@ impl ControlTrafficSignal {
         }
     }
 
-    pub fn missing_turns(&self) -> BTreeSet<MovementID> {
+    /// Movements not covered by some stage of the given plan.
+    pub fn missing_turns(&self, plan: usize) -> BTreeSet<MovementID> {
         let mut missing: BTreeSet<MovementID> = self.movements.keys().cloned().collect();
-        for stage in &self.stages {
+        for stage in self.get_plan_stages(plan) {
             for m in &stage.protected_movements {
                 missing.remove(m);
             }
@@ -313,8 +352,61 @@ impl ControlTrafficSignal {
 
     /// How long a full cycle of the signal lasts, assuming no actuated timings.
     pub fn simple_cycle_duration(&self) -> Duration {
+        self.simple_plan_cycle_duration(0)
+    }
+
+    /// The number of time-of-day plans, including the one starting at midnight. Plan 0 is always
+    /// the one described by `stages` and `offset`.
+    pub fn num_plans(&self) -> usize {
+        1 + self.other_plans.len()
+    }
+
+    pub fn get_plan_stages(&self, plan: usize) -> &Vec<Stage> {
+        if plan == 0 {
+            &self.stages
+        } else {
+            &self.other_plans[plan - 1].stages
+        }
+    }
+
+    pub fn get_plan_stages_mut(&mut self, plan: usize) -> &mut Vec<Stage> {
+        if plan == 0 {
+            &mut self.stages
+        } else {
+            &mut self.other_plans[plan - 1].stages
+        }
+    }
+
+    pub fn get_plan_offset(&self, plan: usize) -> Duration {
+        if plan == 0 {
+            self.offset
+        } else {
+            self.other_plans[plan - 1].offset
+        }
+    }
+
+    pub fn get_plan_start_time(&self, plan: usize) -> Duration {
+        if plan == 0 {
+            Duration::ZERO
+        } else {
+            self.other_plans[plan - 1].start_time
+        }
+    }
+
+    /// Which plan is in effect at some time? Every day uses the same plans.
+    pub fn plan_at(&self, time: Time) -> usize {
+        let time_of_day = (time - Time::START_OF_DAY) % Duration::hours(24);
+        self.other_plans
+            .iter()
+            .rposition(|plan| plan.start_time <= time_of_day)
+            .map(|idx| idx + 1)
+            .unwrap_or(0)
+    }
+
+    /// Like `simple_cycle_duration`, but for any plan.
+    pub fn simple_plan_cycle_duration(&self, plan: usize) -> Duration {
         let mut total = Duration::ZERO;
-        for s in &self.stages {
+        for s in self.get_plan_stages(plan) {
             total += s.stage_type.simple_duration();
         }
         total
@@ -425,98 +517,45 @@ impl ControlTrafficSignal {
     pub fn export(&self, map: &Map) -> traffic_signal_data::TrafficSignal {
         traffic_signal_data::TrafficSignal {
             intersection_osm_node_id: map.get_i(self.id).orig_id.0,
-            plans: vec![traffic_signal_data::Plan {
-                start_time_seconds: 0,
-                stages: self
-                    .stages
-                    .iter()
-                    .map(|s| traffic_signal_data::Stage {
-                        protected_turns: s
-                            .protected_movements
-                            .iter()
-                            .map(|t| export_movement(t, map))
-                            .collect(),
-                        permitted_turns: s
-                            .yield_movements
-                            .iter()
-                            .map(|t| export_movement(t, map))
-                            .collect(),
-                        stage_type: match s.stage_type {
-                            StageType::Fixed(d) => {
-                                traffic_signal_data::StageType::Fixed(d.inner_seconds() as usize)
-                            }
-                            StageType::Variable(min, delay, additional) => {
-                                traffic_signal_data::StageType::Variable(
-                                    min.inner_seconds() as usize,
-                                    delay.inner_seconds() as usize,
-                                    additional.inner_seconds() as usize,
-                                )
-                            }
-                        },
-                    })
-                    .collect(),
-                offset_seconds: self.offset.inner_seconds() as usize,
-            }],
+            plans: (0..self.num_plans())
+                .map(|plan| traffic_signal_data::Plan {
+                    start_time_seconds: self.get_plan_start_time(plan).inner_seconds() as usize,
+                    stages: export_stages(self.get_plan_stages(plan), map),
+                    offset_seconds: self.get_plan_offset(plan).inner_seconds() as usize,
+                })
+                .collect(),
         }
     }
 
     pub(crate) fn import(
-        mut raw: traffic_signal_data::TrafficSignal,
+        raw: traffic_signal_data::TrafficSignal,
         id: IntersectionID,
         map: &Map,
     ) -> Result<ControlTrafficSignal> {
-        // TODO Only import the first plan. Will import all of them later.
-        let plan = raw.plans.remove(0);
-        let mut stages = Vec::new();
-        for s in plan.stages {
-            let mut errors = Vec::new();
-            let mut protected_movements = BTreeSet::new();
-            for t in s.protected_turns {
-                match import_movement(t, map) {
-                    Ok(mvmnt) => {
-                        protected_movements.insert(mvmnt);
-                    }
-                    Err(err) => {
-                        errors.push(err.to_string());
-                    }
-                }
-            }
-            let mut permitted_movements = BTreeSet::new();
-            for t in s.permitted_turns {
-                match import_movement(t, map) {
-                    Ok(mvmnt) => {
-                        permitted_movements.insert(mvmnt);
-                    }
-                    Err(err) => {
-                        errors.push(err.to_string());
-                    }
-                }
-            }
-            if errors.is_empty() {
-                stages.push(Stage {
-                    protected_movements,
-                    yield_movements: permitted_movements,
-                    stage_type: match s.stage_type {
-                        traffic_signal_data::StageType::Fixed(d) => {
-                            StageType::Fixed(Duration::seconds(d as f64))
-                        }
-                        traffic_signal_data::StageType::Variable(min, delay, additional) => {
-                            StageType::Variable(
-                                Duration::seconds(min as f64),
-                                Duration::seconds(delay as f64),
-                                Duration::seconds(additional as f64),
-                            )
-                        }
-                    },
-                });
-            } else {
-                bail!("{}", errors.join("; "));
-            }
+        let mut plans = raw.plans.into_iter();
+        let first = match plans.next() {
+            Some(plan) => plan,
+            None => bail!("Traffic signal {} has no plans", id),
+        };
+        if first.start_time_seconds != 0 {
+            bail!(
+                "The first plan for traffic signal {} doesn't start at midnight",
+                id
+            );
+        }
+        let mut other_plans = Vec::new();
+        for plan in plans {
+            other_plans.push(TimingPlan {
+                start_time: Duration::seconds(plan.start_time_seconds as f64),
+                stages: import_stages(plan.stages, map)?,
+                offset: Duration::seconds(plan.offset_seconds as f64),
+            });
         }
         let ts = ControlTrafficSignal {
             id,
-            stages,
-            offset: Duration::seconds(plan.offset_seconds as f64),
+            stages: import_stages(first.stages, map)?,
+            offset: Duration::seconds(first.offset_seconds as f64),
+            other_plans,
             movements: Movement::for_i(id, map).unwrap(),
         };
         ts.validate()?;
@@ -524,6 +563,86 @@ impl ControlTrafficSignal {
     }
 }
 
+fn export_stages(stages: &[Stage], map: &Map) -> Vec<traffic_signal_data::Stage> {
+    stages
+        .iter()
+        .map(|s| traffic_signal_data::Stage {
+            protected_turns: s
+                .protected_movements
+                .iter()
+                .map(|t| export_movement(t, map))
+                .collect(),
+            permitted_turns: s
+                .yield_movements
+                .iter()
+                .map(|t| export_movement(t, map))
+                .collect(),
+            stage_type: match s.stage_type {
+                StageType::Fixed(d) => {
+                    traffic_signal_data::StageType::Fixed(d.inner_seconds() as usize)
+                }
+                StageType::Variable(min, delay, additional) => {
+                    traffic_signal_data::StageType::Variable(
+                        min.inner_seconds() as usize,
+                        delay.inner_seconds() as usize,
+                        additional.inner_seconds() as usize,
+                    )
+                }
+            },
+        })
+        .collect()
+}
+
+fn import_stages(raw: Vec<traffic_signal_data::Stage>, map: &Map) -> Result<Vec<Stage>> {
+    let mut stages = Vec::new();
+    for s in raw {
+        let mut errors = Vec::new();
+        let mut protected_movements = BTreeSet::new();
+        for t in s.protected_turns {
+            match import_movement(t, map) {
+                Ok(mvmnt) => {
+                    protected_movements.insert(mvmnt);
+                }
+                Err(err) => {
+                    errors.push(err.to_string());
+                }
+            }
+        }
+        let mut permitted_movements = BTreeSet::new();
+        for t in s.permitted_turns {
+            match import_movement(t, map) {
+                Ok(mvmnt) => {
+                    permitted_movements.insert(mvmnt);
+                }
+                Err(err) => {
+                    errors.push(err.to_string());
+                }
+            }
+        }
+        if errors.is_empty() {
+            stages.push(Stage {
+                protected_movements,
+                yield_movements: permitted_movements,
+                stage_type: match s.stage_type {
+                    traffic_signal_data::StageType::Fixed(d) => {
+                        StageType::Fixed(Duration::seconds(d as f64))
+                    }
+                    traffic_signal_data::StageType::Variable(min, delay, additional) => {
+                        StageType::Variable(
+                            Duration::seconds(min as f64),
+                            Duration::seconds(delay as f64),
+                            Duration::seconds(additional as f64),
+                        )
+                    }
+                },
+            });
+        } else {
+            bail!("{}", errors.join("; "));
+        }
+    }
+    Ok(stages)
+}
+
 fn export_movement(id: &MovementID, map: &Map) -> traffic_signal_data::Turn {
     let from = map.get_r(id.from.id).orig_id;
     let to = map.get_r(id.to.id).orig_id;
@@ -5,7 +5,7 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstutil::MultiMap;
-use geom::{Angle, Distance, PolyLine, Pt2D};
+use geom::{Angle, Distance, Duration, PolyLine, Pt2D, Time};
 
 use crate::raw::RestrictionType;
 use crate::{
@@ -264,6 +264,70 @@ impl MovementID {
     }
 }
 
+/// A turn restriction added through map edits. Restrictions from OSM are handled during import by
+/// never creating the turns; these instead keep the turns around, so that exempt vehicles can
+/// still use them.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MovementBan {
+    /// Buses may still make this movement.
+    pub except_buses: bool,
+    /// Bikes may still make this movement.
+    #[serde(default)]
+    pub except_bikes: bool,
+    /// If present, the ban only applies between these times of day. Otherwise it always applies.
+    pub time_window: Option<(Time, Time)>,
+}
+
+impl MovementBan {
+    /// Does this ban apply to some kind of vehicle, at least part of the day?
+    pub fn applies_to(&self, constraints: PathConstraints) -> bool {
+        match constraints {
+            PathConstraints::Bus => !self.except_buses,
+            PathConstraints::Bike => !self.except_bikes,
+            _ => true,
+        }
+    }
+
+    /// Is the ban in effect at this time? Multi-day simulations wrap around to the time of day.
+    pub fn is_active(&self, now: Time) -> bool {
+        match self.time_window {
+            Some((start, end)) => {
+                let time_of_day = time_of_day(now);
+                time_of_day >= start && time_of_day < end
+            }
+            None => true,
+        }
+    }
+
+    /// Assuming the ban is in effect at this time, when does it end? None means never.
+    pub fn active_until(&self, now: Time) -> Option<Time> {
+        let (_, end) = self.time_window?;
+        Some(now + (end - time_of_day(now)))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut result = "banned".to_string();
+        if let Some((start, end)) = self.time_window {
+            result = format!(
+                "{} from {} to {}",
+                result,
+                start.ampm_tostring(),
+                end.ampm_tostring()
+            );
+        }
+        match (self.except_buses, self.except_bikes) {
+            (true, true) => format!("{}, except for buses and bikes", result),
+            (true, false) => format!("{}, except for buses", result),
+            (false, true) => format!("{}, except for bikes", result),
+            (false, false) => result,
+        }
+    }
+}
+
+fn time_of_day(now: Time) -> Time {
+    Time::START_OF_DAY + Duration::seconds(now.inner_seconds() % 86400.0)
+}
+
 /// This is cheaper to store than a MovementID. It simply indexes into the list of movements.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct CompressedMovementID {
@@ -456,7 +520,40 @@ impl TurnID {
             from: map.get_l(self.src).get_directed_parent(),
             to: map.get_l(self.dst).get_directed_parent(),
             parent: self.parent,
-            crosswalk: map.get_l(self.src).is_walkable(),
+            crosswalk: map.get_t(self).between_sidewalks(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ban_time_window() {
+        let at = |hours| Time::START_OF_DAY + Duration::hours(hours);
+        let ban = MovementBan {
+            except_buses: false,
+            except_bikes: false,
+            time_window: Some((at(7), at(9))),
+        };
+        assert!(!ban.is_active(at(6)));
+        assert!(ban.is_active(at(7)));
+        assert!(ban.is_active(at(8) + Duration::minutes(59)));
+        assert!(!ban.is_active(at(9)));
+        // The next day
+        assert!(ban.is_active(at(24 + 8)));
+        assert_eq!(ban.active_until(at(24 + 8)), Some(at(24 + 9)));
+
+        let always = MovementBan {
+            except_buses: true,
+            except_bikes: true,
+            time_window: None,
+        };
+        assert!(always.is_active(at(3)));
+        assert_eq!(always.active_until(at(3)), None);
+        assert!(always.applies_to(PathConstraints::Car));
+        assert!(!always.applies_to(PathConstraints::Bus));
+        assert!(!always.applies_to(PathConstraints::Bike));
+    }
+}
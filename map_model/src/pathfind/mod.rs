@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use geom::Duration;
 
 pub use self::engine::CreateEngine;
-pub use self::pathfinder::Pathfinder;
+pub use self::pathfinder::{Pathfinder, TransitAccess, TransitChoice};
 pub use self::v1::{Path, PathRequest, PathStep};
 pub use self::v2::{PathStepV2, PathV2};
 pub use self::vehicles::vehicle_cost;
@@ -50,7 +50,8 @@ impl PathConstraints {
         match lt {
             LaneType::Sidewalk | LaneType::Shoulder => PathConstraints::Pedestrian,
             LaneType::Driving => PathConstraints::Car,
-            LaneType::Biking => PathConstraints::Bike,
+            // Pedestrians can use these too, but they mainly matter for bike connectivity
+            LaneType::Biking | LaneType::SharedUse => PathConstraints::Bike,
             LaneType::Bus => PathConstraints::Bus,
             LaneType::LightRail => PathConstraints::Train,
             _ => panic!("PathConstraints::from_lt({:?}) doesn't make sense", lt),
@@ -66,7 +67,7 @@ impl PathConstraints {
             }
             PathConstraints::Car => lane.is_driving(),
             PathConstraints::Bike => {
-                if lane.is_biking() {
+                if lane.is_biking() || lane.is_shared_use() {
                     true
                 } else if lane.is_driving() || (lane.is_bus() && map.config.bikes_can_use_bus_lanes)
                 {
@@ -119,7 +120,10 @@ impl PathConstraints {
             let just_bike_lanes: Vec<LaneID> = choices
                 .iter()
                 .copied()
-                .filter(|l| map.get_l(*l).is_biking())
+                .filter(|l| {
+                    let lane = map.get_l(*l);
+                    lane.is_biking() || lane.is_shared_use()
+                })
                 .collect();
             if !just_bike_lanes.is_empty() {
                 return just_bike_lanes;
@@ -164,6 +168,15 @@ pub struct RoutingParams {
     pub bike_lane_penalty: f64,
     pub bus_lane_penalty: f64,
     pub driving_lane_penalty: f64,
+    // For all vehicles. Routing doesn't know the time of day, so a movement banned for only part
+    // of the day is still allowed, but this is added to its cost all day. While the ban is active,
+    // vehicles detour when they reach the turn.
+    #[serde(default = "default_part_time_ban_penalty")]
+    pub part_time_ban_penalty: Duration,
+}
+
+fn default_part_time_ban_penalty() -> Duration {
+    RoutingParams::default().part_time_ban_penalty
 }
 
 impl RoutingParams {
@@ -175,6 +188,7 @@ impl RoutingParams {
             bike_lane_penalty: 1.0,
             bus_lane_penalty: 1.1,
             driving_lane_penalty: 1.5,
+            part_time_ban_penalty: Duration::const_seconds(300.0),
         }
     }
 }
@@ -13,6 +13,10 @@ use crate::{
     RoutingParams,
 };
 
+/// Besides walking, only consider this many of the cheapest access and egress options each, to
+/// bound the number of combinations that have to be pathfound.
+const MAX_TRANSIT_OPTIONS: usize = 3;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Pathfinder {
     car_graph: VehiclePathfinder,
@@ -25,6 +29,31 @@ pub struct Pathfinder {
     params: RoutingParams,
 }
 
+/// Somewhere the walking-and-transit part of a trip could begin or end, along with the
+/// generalized cost of getting between there and the trip's real start or end some other way --
+/// driving and parking, biking, or riding bike-share, for instance.
+#[derive(Clone, Debug)]
+pub struct TransitAccess {
+    pub sidewalk_pos: Position,
+    pub cost: Duration,
+}
+
+/// The cheapest way to use public transit, among some options for reaching the first stop and
+/// leaving the last one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransitChoice {
+    /// Indexes into the access options
+    pub access: usize,
+    /// Indexes into the egress options
+    pub egress: usize,
+    pub stop1: BusStopID,
+    /// If there's no stop 2, then ride the bus off the border.
+    pub maybe_stop2: Option<BusStopID>,
+    pub route: BusRouteID,
+    /// The generalized cost of the entire trip, including access and egress
+    pub cost: Duration,
+}
+
 impl Pathfinder {
     /// Quickly create an invalid pathfinder, just to make borrow checking / initialization order
     /// work.
@@ -148,15 +177,21 @@ impl Pathfinder {
         Some((req_cost, all_costs))
     }
 
+    /// Try combinations of access and egress options, and pick the one using transit with the
+    /// lowest generalized cost. The first option of each should be walking directly from the
+    /// start or to the end; if walking the whole way is cheaper than any way of using transit,
+    /// returns None. Besides walking, only the few cheapest access and egress options are tried.
     // TODO Consider returning the walking-only path in the failure case, to avoid wasting work
     pub fn should_use_transit(
         &self,
         map: &Map,
-        start: Position,
-        end: Position,
-    ) -> Option<(BusStopID, Option<BusStopID>, BusRouteID)> {
-        self.walking_with_transit_graph
-            .should_use_transit(map, start, end)
+        access: &[TransitAccess],
+        egress: &[TransitAccess],
+    ) -> Option<TransitChoice> {
+        choose_transit(access, egress, |start, end| {
+            self.walking_with_transit_graph
+                .should_use_transit(map, start, end)
+        })
     }
 
     pub fn apply_edits(&mut self, map: &Map, timer: &mut Timer) {
@@ -186,3 +221,172 @@ impl Pathfinder {
         timer.stop("apply edits to pedestrian using transit pathfinding");
     }
 }
+
+/// The cost of walking and riding transit between two positions, and the stops and route used, if
+/// any.
+type TransitCost = Option<(Duration, Option<(BusStopID, Option<BusStopID>, BusRouteID)>)>;
+
+/// The core of `should_use_transit`, with the pathfinding between each access and egress option
+/// passed in.
+fn choose_transit<F: Fn(Position, Position) -> TransitCost>(
+    access: &[TransitAccess],
+    egress: &[TransitAccess],
+    pathfind: F,
+) -> Option<TransitChoice> {
+    let mut walking_cost = None;
+    let mut best: Option<TransitChoice> = None;
+    let egress_options = cheapest_options(egress);
+    for idx1 in cheapest_options(access) {
+        let a = &access[idx1];
+        for idx2 in egress_options.iter().copied() {
+            let e = &egress[idx2];
+            let (cost, maybe_transit) = match pathfind(a.sidewalk_pos, e.sidewalk_pos) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let cost = a.cost + cost + e.cost;
+            match maybe_transit {
+                Some((stop1, maybe_stop2, route)) => {
+                    if best.as_ref().map(|b| cost < b.cost).unwrap_or(true) {
+                        best = Some(TransitChoice {
+                            access: idx1,
+                            egress: idx2,
+                            stop1,
+                            maybe_stop2,
+                            route,
+                            cost,
+                        });
+                    }
+                }
+                None => {
+                    if idx1 == 0 && idx2 == 0 {
+                        walking_cost = Some(cost);
+                    }
+                }
+            }
+        }
+    }
+    let best = best?;
+    if walking_cost.map(|c| c <= best.cost).unwrap_or(false) {
+        return None;
+    }
+    Some(best)
+}
+
+/// Indices of the options worth trying: the first one, which is always walking, and then the few
+/// cheapest others.
+fn cheapest_options(options: &[TransitAccess]) -> Vec<usize> {
+    let mut rest: Vec<usize> = (1..options.len()).collect();
+    rest.sort_by_key(|idx| (options[*idx].cost, *idx));
+    rest.truncate(MAX_TRANSIT_OPTIONS);
+    (0..options.len().min(1)).chain(rest).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use geom::Distance;
+
+    use super::*;
+    use crate::LaneID;
+
+    fn option(lane: usize, cost: f64) -> TransitAccess {
+        TransitAccess {
+            sidewalk_pos: Position::new(LaneID(lane), Distance::ZERO),
+            cost: Duration::seconds(cost),
+        }
+    }
+
+    fn stop(idx: usize) -> BusStopID {
+        BusStopID {
+            sidewalk: LaneID(100),
+            idx,
+        }
+    }
+
+    #[test]
+    fn test_walk_only() {
+        // With only the walking option on each end, the result should just reflect the single
+        // path through the walking-and-transit graph
+        for result in vec![
+            None,
+            Some((Duration::seconds(600.0), None)),
+            Some((
+                Duration::seconds(600.0),
+                Some((stop(1), Some(stop(2)), BusRouteID(0))),
+            )),
+            Some((
+                Duration::seconds(600.0),
+                Some((stop(1), None, BusRouteID(0))),
+            )),
+        ] {
+            let expected = result.and_then(|(cost, transit)| {
+                transit.map(|(stop1, maybe_stop2, route)| TransitChoice {
+                    access: 0,
+                    egress: 0,
+                    stop1,
+                    maybe_stop2,
+                    route,
+                    cost,
+                })
+            });
+            assert_eq!(
+                choose_transit(&[option(0, 0.0)], &[option(1, 0.0)], |_, _| result),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_prune_options() {
+        let access = vec![
+            option(0, 0.0),
+            option(1, 50.0),
+            option(2, 10.0),
+            option(3, 40.0),
+            option(4, 20.0),
+            option(5, 30.0),
+        ];
+        let egress = vec![option(10, 0.0), option(11, 5.0)];
+        let tried = RefCell::new(Vec::new());
+        let choice = choose_transit(&access, &egress, |start, end| {
+            tried.borrow_mut().push((start.lane().0, end.lane().0));
+            if start.lane().0 == 0 && end.lane().0 == 10 {
+                // Walking the whole way
+                Some((Duration::seconds(1000.0), None))
+            } else {
+                Some((
+                    Duration::seconds(100.0),
+                    Some((stop(start.lane().0), None, BusRouteID(0))),
+                ))
+            }
+        })
+        .unwrap();
+
+        // Walking, and then the 3 cheapest other ways to reach transit
+        let mut tried_access: Vec<usize> = tried.into_inner().into_iter().map(|(a, _)| a).collect();
+        tried_access.dedup();
+        assert_eq!(tried_access, vec![0, 2, 4, 5]);
+
+        // Walking straight to transit at the start and end is cheapest
+        assert_eq!(choice.access, 0);
+        assert_eq!(choice.egress, 1);
+        assert_eq!(choice.cost, Duration::seconds(105.0));
+
+        // If walking the whole way is cheaper, don't use transit
+        assert_eq!(
+            choose_transit(&access, &egress, |start, end| {
+                if start.lane().0 == 0 && end.lane().0 == 10 {
+                    Some((Duration::seconds(60.0), None))
+                } else {
+                    Some((
+                        Duration::seconds(100.0),
+                        Some((stop(start.lane().0), None, BusRouteID(0))),
+                    ))
+                }
+            }),
+            None
+        );
+    }
+}
@@ -315,6 +315,45 @@ impl Path {
             .unwrap_or(false)
     }
 
+    /// Replace everything after the current lane: make a different turn, then follow another path
+    /// that starts at the beginning of that turn's destination lane. The caller must not be in the
+    /// middle of an uber-turn.
+    pub fn detour(&mut self, turn: TurnID, rest: Path, map: &Map) {
+        assert!(self.currently_inside_ut.is_none());
+        assert_eq!(self.steps[0], PathStep::Lane(turn.src));
+        assert_eq!(rest.steps[0], PathStep::Lane(turn.dst));
+
+        let mut abandoned = Distance::ZERO;
+        for step in self.steps.iter().skip(1) {
+            abandoned += self.dist_crossed_from_step(map, step);
+        }
+        self.total_length -= abandoned;
+        self.steps.truncate(1);
+        self.steps.push_back(PathStep::Turn(turn));
+        self.total_length += map.get_t(turn).geom.length() + rest.total_length;
+        self.steps.extend(rest.steps);
+        self.uber_turns = rest.uber_turns;
+    }
+
+    /// Replace everything after the current lane with new steps, starting with a turn from the
+    /// current lane. The caller must not be in the middle of an uber-turn.
+    pub fn replace_rest(&mut self, steps: Vec<PathStep>, map: &Map) {
+        assert!(self.currently_inside_ut.is_none());
+
+        let mut abandoned = Distance::ZERO;
+        for step in self.steps.iter().skip(1) {
+            abandoned += self.dist_crossed_from_step(map, step);
+        }
+        self.total_length -= abandoned;
+        self.steps.truncate(1);
+        for step in steps {
+            // TODO We assume we'll be going along the full length of each new step
+            self.total_length += step.as_traversable().get_polyline(map).length();
+            self.steps.push_back(step);
+        }
+        self.uber_turns.clear();
+    }
+
     /// Trusting the caller to do this in valid ways.
     pub fn modify_step(&mut self, idx: usize, step: PathStep, map: &Map) {
         assert!(self.currently_inside_ut.is_none());
@@ -88,7 +88,8 @@ impl VehiclePathfinder {
     }
 
     pub fn pathfind(&self, req: PathRequest, map: &Map) -> Option<PathV2> {
-        assert!(!map.get_l(req.start.lane()).is_walkable());
+        let start_lane = map.get_l(req.start.lane());
+        assert!(!start_lane.is_walkable() || start_lane.is_shared_use());
         let mut starts = vec![(
             self.nodes.get(Node::Road(
                 map.get_l(req.start.lane()).get_directed_parent(),
@@ -201,11 +202,14 @@ fn make_input_graph(
         // vehicle.
         // TODO Need to test editing lanes inside an IntersectionCluster very carefully. See Mercer
         // and Dexter.
-        if ut
-            .path
-            .iter()
-            .all(|mvmnt| !mvmnt.to.lanes(constraints, map).is_empty())
-        {
+        if ut.path.iter().all(|mvmnt| {
+            !mvmnt.to.lanes(constraints, map).is_empty()
+                && map
+                    .get_i(mvmnt.parent)
+                    .get_movement_ban(*mvmnt, constraints)
+                    .map(|ban| ban.time_window.is_some())
+                    .unwrap_or(true)
+        }) {
             uber_turn_entrances.insert(ut.entry(), idx);
         }
     }
@@ -291,13 +295,14 @@ pub fn vehicle_cost(
 
             // TODO Prefer bike lanes, then bus lanes, then driving lanes. For now, express that by
             // multiplying the base cost.
-            let lt_penalty = if dr.has_lanes(LaneType::Biking, map) {
-                params.bike_lane_penalty
-            } else if dr.has_lanes(LaneType::Bus, map) {
-                params.bus_lane_penalty
-            } else {
-                params.driving_lane_penalty
-            };
+            let lt_penalty =
+                if dr.has_lanes(LaneType::Biking, map) || dr.has_lanes(LaneType::SharedUse, map) {
+                    params.bike_lane_penalty
+                } else if dr.has_lanes(LaneType::Bus, map) {
+                    params.bus_lane_penalty
+                } else {
+                    params.driving_lane_penalty
+                };
 
             lt_penalty * (t1 + t2)
         }
@@ -314,9 +319,20 @@ pub fn vehicle_cost(
     };
 
     // Penalize unprotected turns at a stop sign from smaller to larger roads.
-    if map.is_unprotected_turn(dr.id, mvmnt.to.id, mvmnt_turn_type) {
+    let mut cost = if map.is_unprotected_turn(dr.id, mvmnt.to.id, mvmnt_turn_type) {
         base + params.unprotected_turn_penalty
     } else {
         base
+    };
+    // Movements banned all day are excluded from the graph entirely. Routing doesn't know the time
+    // of day, so part-time bans are just discouraged; drivers detour around them while active.
+    if map
+        .get_i(mvmnt.parent)
+        .get_movement_ban(mvmnt, constraints)
+        .map(|ban| ban.time_window.is_some())
+        .unwrap_or(false)
+    {
+        cost += params.part_time_ban_penalty;
     }
+    cost
 }
@@ -126,17 +126,18 @@ impl SidewalkPathfinder {
         Some(PathV2::new(steps, req, cost, Vec::new()))
     }
 
-    /// Attempt the pathfinding and see if we should ride a bus. If so, says (stop1, optional stop
-    /// 2, route). If there's no stop 2, then ride the bus off the border.
+    /// Attempt the pathfinding and see if we should ride a bus. Returns the cost of the best path,
+    /// and if it involves a bus, (stop1, optional stop 2, route). If there's no stop 2, then ride
+    /// the bus off the border.
     pub fn should_use_transit(
         &self,
         map: &Map,
         start: Position,
         end: Position,
-    ) -> Option<(BusStopID, Option<BusStopID>, BusRouteID)> {
+    ) -> Option<(Duration, Option<(BusStopID, Option<BusStopID>, BusRouteID)>)> {
         assert!(self.use_transit);
 
-        let (_, raw_nodes) = self.engine.calculate_path(
+        let (raw_weight, raw_nodes) = self.engine.calculate_path(
             self.nodes.get(WalkingNode::closest(start, map)),
             self.nodes.get(WalkingNode::end_transit(end, map)),
         )?;
@@ -152,71 +153,7 @@ impl SidewalkPathfinder {
             }
         }
 
-        let mut first_stop = None;
-        let mut last_stop = None;
-        let mut possible_routes: Vec<&BusRoute> = Vec::new();
-        for n in &nodes {
-            match n {
-                WalkingNode::RideBus(stop2) => {
-                    if let Some(stop1) = first_stop {
-                        // Keep riding the same route?
-                        // We need to do this check, because some transfers might be instantaneous
-                        // at the same stop and involve no walking.
-                        // Also need to make sure the stops are in the proper order. We might have
-                        // a transfer, then try to hop on the first route again, but starting from
-                        // a different point.
-                        let mut filtered = possible_routes.clone();
-                        filtered.retain(|r| {
-                            let idx1 = r.stops.iter().position(|s| *s == stop1).unwrap();
-                            let idx2 = r.stops.iter().position(|s| s == stop2);
-                            idx2.map(|idx2| idx1 < idx2).unwrap_or(false)
-                        });
-                        if filtered.is_empty() {
-                            // Aha, a transfer!
-                            return Some((
-                                first_stop.unwrap(),
-                                // TODO I thought this should be impossible, but huge_seattle hits
-                                // it. Workaround for now by just walking.
-                                Some(last_stop?),
-                                possible_routes[0].id,
-                            ));
-                        }
-                        last_stop = Some(*stop2);
-                        possible_routes = filtered;
-                    } else {
-                        first_stop = Some(*stop2);
-                        possible_routes = map.get_routes_serving_stop(*stop2);
-                        assert!(!possible_routes.is_empty());
-                    }
-                }
-                WalkingNode::LeaveMap(i) => {
-                    // Make sure the route actually leaves via the correct border!
-                    if let Some(r) = possible_routes.iter().find(|r| {
-                        r.end_border
-                            .map(|l| map.get_l(l).dst_i == *i)
-                            .unwrap_or(false)
-                    }) {
-                        return Some((first_stop.unwrap(), None, r.id));
-                    }
-                    // We can get close to the border, but should hop off at some stop.
-                    return Some((
-                        first_stop.unwrap(),
-                        Some(last_stop.expect("impossible transit transfer")),
-                        possible_routes[0].id,
-                    ));
-                }
-                WalkingNode::SidewalkEndpoint(_, _) => {
-                    if let Some(stop1) = first_stop {
-                        return Some((
-                            stop1,
-                            Some(last_stop.expect("impossible transit transfer")),
-                            possible_routes[0].id,
-                        ));
-                    }
-                }
-            }
-        }
-        None
+        Some((unround(raw_weight), transit_stops(&nodes, map)))
     }
 
     pub fn all_costs_from(&self, start: Position, map: &Map) -> HashMap<DirectedRoadID, Duration> {
@@ -245,6 +182,78 @@ impl SidewalkPathfinder {
     }
 }
 
+/// Given a path through the walking-with-transit graph, figure out the first bus to ride, if any.
+fn transit_stops(
+    nodes: &[WalkingNode],
+    map: &Map,
+) -> Option<(BusStopID, Option<BusStopID>, BusRouteID)> {
+    let mut first_stop = None;
+    let mut last_stop = None;
+    let mut possible_routes: Vec<&BusRoute> = Vec::new();
+    for n in nodes {
+        match n {
+            WalkingNode::RideBus(stop2) => {
+                if let Some(stop1) = first_stop {
+                    // Keep riding the same route?
+                    // We need to do this check, because some transfers might be instantaneous
+                    // at the same stop and involve no walking.
+                    // Also need to make sure the stops are in the proper order. We might have
+                    // a transfer, then try to hop on the first route again, but starting from
+                    // a different point.
+                    let mut filtered = possible_routes.clone();
+                    filtered.retain(|r| {
+                        let idx1 = r.stops.iter().position(|s| *s == stop1).unwrap();
+                        let idx2 = r.stops.iter().position(|s| s == stop2);
+                        idx2.map(|idx2| idx1 < idx2).unwrap_or(false)
+                    });
+                    if filtered.is_empty() {
+                        // Aha, a transfer!
+                        return Some((
+                            first_stop.unwrap(),
+                            // TODO I thought this should be impossible, but huge_seattle hits
+                            // it. Workaround for now by just walking.
+                            Some(last_stop?),
+                            possible_routes[0].id,
+                        ));
+                    }
+                    last_stop = Some(*stop2);
+                    possible_routes = filtered;
+                } else {
+                    first_stop = Some(*stop2);
+                    possible_routes = map.get_routes_serving_stop(*stop2);
+                    assert!(!possible_routes.is_empty());
+                }
+            }
+            WalkingNode::LeaveMap(i) => {
+                // Make sure the route actually leaves via the correct border!
+                if let Some(r) = possible_routes.iter().find(|r| {
+                    r.end_border
+                        .map(|l| map.get_l(l).dst_i == *i)
+                        .unwrap_or(false)
+                }) {
+                    return Some((first_stop.unwrap(), None, r.id));
+                }
+                // We can get close to the border, but should hop off at some stop.
+                return Some((
+                    first_stop.unwrap(),
+                    Some(last_stop.expect("impossible transit transfer")),
+                    possible_routes[0].id,
+                ));
+            }
+            WalkingNode::SidewalkEndpoint(_, _) => {
+                if let Some(stop1) = first_stop {
+                    return Some((
+                        stop1,
+                        Some(last_stop.expect("impossible transit transfer")),
+                        possible_routes[0].id,
+                    ));
+                }
+            }
+        }
+    }
+    None
+}
+
 fn make_input_graph(
     nodes: &NodeMap<WalkingNode>,
     use_transit: Option<(&VehiclePathfinder, &VehiclePathfinder)>,
@@ -279,7 +288,10 @@ fn make_input_graph(
     }
 
     for t in map.all_turns() {
-        if t.between_sidewalks() {
+        // Vehicle turns between shared-use paths are walkable too
+        if t.between_sidewalks()
+            || (map.get_l(t.id.src).is_walkable() && map.get_l(t.id.dst).is_walkable())
+        {
             let src = map.get_l(t.id.src);
             let dst = map.get_l(t.id.dst);
             let from =
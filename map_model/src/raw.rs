@@ -11,7 +11,7 @@ use serde::{Deserialize, Serialize};
 
 use abstio::{CityName, MapName};
 use abstutil::{deserialize_btreemap, serialize_btreemap, Tags, Timer};
-use geom::{Distance, GPSBounds, PolyLine, Polygon, Pt2D};
+use geom::{Distance, GPSBounds, PolyLine, Polygon, Pt2D, Time};
 
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
 use crate::{
@@ -625,7 +625,7 @@ impl RawRoad {
         // Don't repeat the logic looking at the tags, just see what lanes we'll create
         let mut bike = false;
         for spec in get_lane_specs_ltr(&self.osm_tags, cfg) {
-            if spec.lt == LaneType::Biking {
+            if spec.lt == LaneType::Biking || spec.lt == LaneType::SharedUse {
                 bike = true;
             } else if spec.lt != LaneType::Shoulder {
                 return false;
@@ -734,6 +734,9 @@ pub struct RawBusRoute {
     pub border_end: Option<osm::NodeID>,
     /// This is guaranteed to be in order and contiguous.
     pub all_pts: Vec<(osm::NodeID, Pt2D)>,
+    /// When a vehicle should begin at the start of the route, in order. Only known when the route
+    /// comes from GTFS; otherwise a default schedule is used.
+    pub spawn_times: Option<Vec<Time>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -3,14 +3,16 @@ use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use serde::{Deserialize, Serialize};
 
 use abstutil::Counter;
-use geom::{Duration, Time};
+use geom::{Distance, Duration, Pt2D, Time};
 use map_model::{
-    BusRouteID, BusStopID, CompressedMovementID, IntersectionID, LaneID, Map, MovementID,
-    ParkingLotID, Path, PathRequest, RoadID, Traversable, TurnID, TurnType,
+    BuildingID, BusRouteID, BusStopID, CompressedMovementID, IntersectionID, LaneID, Map,
+    MovementID, ParkingLotID, ParkingPricing, Path, PathRequest, RoadID, Traversable, TurnID,
+    TurnType,
 };
 
 use crate::{
-    AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID, TripMode, TripPhaseType,
+    AgentID, AgentType, AlertLocation, CarID, Emissions, Event, LevelOfService, ParkingSpot,
+    RideHailRide, TransitPriorityType, TripID, TripMode, TripPhaseType,
 };
 
 /// As a simulation runs, different pieces emit Events. The Analytics object listens to these,
@@ -39,6 +41,17 @@ pub struct Analytics {
     /// For each passenger boarding, how long did they wait at the stop?
     pub passengers_boarding: BTreeMap<BusStopID, Vec<(Time, BusRouteID, Duration)>>,
     pub passengers_alighting: BTreeMap<BusStopID, Vec<(Time, BusRouteID)>>,
+    /// Every time a traffic signal gives priority to a transit vehicle, how much time was granted,
+    /// and how many agents were waiting for movements that the priority works against. Multiply
+    /// the two to estimate the cost to cross traffic.
+    pub transit_signal_priority: Vec<(
+        Time,
+        IntersectionID,
+        BusRouteID,
+        TransitPriorityType,
+        Duration,
+        usize,
+    )>,
 
     pub started_trips: BTreeMap<TripID, Time>,
     /// Finish time, ID, mode, trip duration if successful (or None if cancelled)
@@ -57,6 +70,40 @@ pub struct Analytics {
     /// Per parking lane or lot, when does a spot become filled (true) or free (false)
     pub parking_lane_changes: BTreeMap<LaneID, Vec<(Time, bool)>>,
     pub parking_lot_changes: BTreeMap<ParkingLotID, Vec<(Time, bool)>>,
+    /// Per parking lane or lot, every time a car leaves a priced spot: how much it paid (in
+    /// cents), and whether it overstayed the time limit.
+    pub parking_lane_revenue: BTreeMap<LaneID, Vec<(Time, usize, bool)>>,
+    pub parking_lot_revenue: BTreeMap<ParkingLotID, Vec<(Time, usize, bool)>>,
+    /// Every time an electric car leaves a spot with a charger: how much energy it gained (in
+    /// kWh), and how long it was plugged in.
+    pub ev_charging: Vec<(Time, ParkingSpot, f64, Duration)>,
+    /// How far has each trip driven while cruising for parking?
+    pub parking_search_per_trip: BTreeMap<TripID, Distance>,
+    /// How far have vehicles driven along each road while cruising for parking?
+    pub parking_search_per_road: BTreeMap<RoadID, Distance>,
+    /// Energy used and pollution emitted by vehicles along each road or through each
+    /// intersection
+    pub emissions_per_road: BTreeMap<RoadID, Emissions>,
+    pub emissions_per_intersection: BTreeMap<IntersectionID, Emissions>,
+    /// Energy used and pollution emitted by the vehicle used for each trip. Doesn't include
+    /// transit.
+    pub emissions_per_trip: BTreeMap<TripID, Emissions>,
+    /// Per road, every time a delivery van stopped: the loading zone spot it used (or None if it
+    /// double-parked and blocked a driving lane), and how long it stayed.
+    pub delivery_stops: BTreeMap<RoadID, Vec<(Time, CarID, Option<ParkingSpot>, Duration)>>,
+    /// Every ride-hail ride, recorded when the passenger is dropped off
+    pub ride_hail_rides: Vec<(Time, RideHailRide)>,
+    /// Every time shared bikes and scooters were rebalanced, how many were moved between docks
+    pub bike_share_rebalances: Vec<(Time, usize)>,
+    /// Per sidewalk, every time its pedestrian level of service changed. Only recorded if crowding
+    /// is modeled.
+    pub sidewalk_los: BTreeMap<LaneID, Vec<(Time, LevelOfService)>>,
+    /// Every trip that mode choice switched away from the scenario's mode: (trip, original mode,
+    /// chosen mode)
+    pub mode_shifts: Vec<(Time, TripID, TripMode, TripMode)>,
+    /// Trips currently in the SearchingForParking phase, and the lane they're cruising along. Only
+    /// fully traversed lanes count, so the current one isn't added until the vehicle leaves it.
+    searching_for_parking: BTreeMap<TripID, Option<LaneID>>,
 
     pub(crate) alerts: Vec<(Time, AlertLocation, String)>,
 
@@ -74,6 +121,22 @@ pub enum Problem {
     ArterialIntersectionCrossing(TurnID),
     /// Another vehicle wanted to over-take this cyclist somewhere on this lane or turn.
     OvertakeDesired(Traversable),
+    /// A bus or train arrived at this stop, but was too full for the passenger to board.
+    TransitBoardingDenied(BusStopID, BusRouteID),
+    /// An electric car with a low battery couldn't find a free charger near this lane, so it
+    /// parked without one.
+    NoChargerAvailable(LaneID),
+    /// An electric car ran out of energy somewhere on this lane or turn.
+    BatteryDepleted(Traversable),
+    /// There was no shared bike or scooter left at the dock in front of this building, so the
+    /// person walked instead.
+    DockEmpty(BuildingID),
+    /// The dock in front of this building was full, so the person had to return their shared
+    /// vehicle somewhere else.
+    DockFull(BuildingID),
+    /// No ride-hail vehicle could find a route to the pickup outside this building, so the trip
+    /// was cancelled.
+    RideHailUnreachable(BuildingID),
 }
 
 impl Analytics {
@@ -86,6 +149,7 @@ impl Analytics {
             bus_arrivals: Vec::new(),
             passengers_boarding: BTreeMap::new(),
             passengers_alighting: BTreeMap::new(),
+            transit_signal_priority: Vec::new(),
             started_trips: BTreeMap::new(),
             finished_trips: Vec::new(),
             problems_per_trip: BTreeMap::new(),
@@ -93,6 +157,20 @@ impl Analytics {
             intersection_delays: BTreeMap::new(),
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
+            parking_lane_revenue: BTreeMap::new(),
+            parking_lot_revenue: BTreeMap::new(),
+            ev_charging: Vec::new(),
+            parking_search_per_trip: BTreeMap::new(),
+            parking_search_per_road: BTreeMap::new(),
+            emissions_per_road: BTreeMap::new(),
+            emissions_per_intersection: BTreeMap::new(),
+            emissions_per_trip: BTreeMap::new(),
+            delivery_stops: BTreeMap::new(),
+            ride_hail_rides: Vec::new(),
+            bike_share_rebalances: Vec::new(),
+            sidewalk_los: BTreeMap::new(),
+            mode_shifts: Vec::new(),
+            searching_for_parking: BTreeMap::new(),
             alerts: Vec::new(),
             record_anything,
         }
@@ -159,6 +237,26 @@ impl Analytics {
             self.bus_arrivals.push((time, bus, route, stop));
         }
 
+        // Transit signal priority
+        if let Event::TransitSignalPriority {
+            intersection,
+            route,
+            priority,
+            granted,
+            delayed_agents,
+            ..
+        } = ev
+        {
+            self.transit_signal_priority.push((
+                time,
+                intersection,
+                route,
+                priority,
+                granted,
+                delayed_agents,
+            ));
+        }
+
         // Passengers boarding/alighting
         if let Event::PassengerBoardsTransit(_, _, route, stop, waiting) = ev {
             self.passengers_boarding
@@ -178,6 +276,13 @@ impl Analytics {
             self.started_trips.entry(id).or_insert(time);
         }
 
+        // Cruising for parking
+        if let Some((trip, l, dist)) = cruised_along(&mut self.searching_for_parking, &ev, |l| {
+            map.get_l(l).length()
+        }) {
+            self.record_parking_search(trip, l, dist, map);
+        }
+
         // Finished trips
         if let Event::TripFinished {
             trip,
@@ -235,7 +340,7 @@ impl Analytics {
                     .push((time, true));
             }
         }
-        if let Event::CarLeftParkingSpot(_, spot) = ev {
+        if let Event::CarLeftParkingSpot(_, spot, parked_since) = ev {
             if let ParkingSpot::Onstreet(l, _) = spot {
                 self.parking_lane_changes
                     .entry(l)
@@ -247,6 +352,65 @@ impl Analytics {
                     .or_insert_with(Vec::new)
                     .push((time, false));
             }
+
+            // Parking revenue
+            let pricing = spot.pricing(map);
+            if pricing != ParkingPricing::free() {
+                let duration = time - parked_since;
+                let paid = pricing.cost(duration);
+                let overstayed = pricing
+                    .time_limit
+                    .map(|limit| duration > limit)
+                    .unwrap_or(false);
+                if let ParkingSpot::Onstreet(l, _) = spot {
+                    self.parking_lane_revenue
+                        .entry(l)
+                        .or_insert_with(Vec::new)
+                        .push((time, paid, overstayed));
+                } else if let ParkingSpot::Lot(pl, _) = spot {
+                    self.parking_lot_revenue
+                        .entry(pl)
+                        .or_insert_with(Vec::new)
+                        .push((time, paid, overstayed));
+                }
+            }
+        }
+
+        // EV charging
+        if let Event::VehicleCharged(_, spot, energy, duration) = ev {
+            self.ev_charging.push((time, spot, energy, duration));
+        }
+
+        // Curb use by delivery vans
+        if let Event::DeliveryStop(car, _, lane, spot, duration) = ev {
+            self.delivery_stops
+                .entry(map.get_l(lane).parent)
+                .or_insert_with(Vec::new)
+                .push((time, car, spot, duration));
+        }
+
+        // Emissions
+        if let Event::VehicleEmitted(_, trip, on, emitted) = ev {
+            match on {
+                Traversable::Lane(l) => {
+                    *self
+                        .emissions_per_road
+                        .entry(map.get_l(l).parent)
+                        .or_insert(Emissions::ZERO) += emitted;
+                }
+                Traversable::Turn(t) => {
+                    *self
+                        .emissions_per_intersection
+                        .entry(t.parent)
+                        .or_insert(Emissions::ZERO) += emitted;
+                }
+            }
+            if let Some(trip) = trip {
+                *self
+                    .emissions_per_trip
+                    .entry(trip)
+                    .or_insert(Emissions::ZERO) += emitted;
+            }
         }
 
         // Safety metrics
@@ -297,10 +461,53 @@ impl Analytics {
                     .or_insert_with(Vec::new)
                     .push((time, problem));
             }
+            Event::RideHailRideFinished(ride) => {
+                self.ride_hail_rides.push((time, ride));
+            }
+            Event::BikeShareRebalanced(moved) => {
+                self.bike_share_rebalances.push((time, moved));
+            }
+            Event::SidewalkLevelOfService(l, los) => {
+                self.sidewalk_los
+                    .entry(l)
+                    .or_insert_with(Vec::new)
+                    .push((time, los));
+            }
+            Event::ModeChosen(trip, from, to) => {
+                self.mode_shifts.push((time, trip, from, to));
+            }
             _ => {}
         }
     }
 
+    fn record_parking_search(&mut self, trip: TripID, l: LaneID, dist: Distance, map: &Map) {
+        *self
+            .parking_search_per_trip
+            .entry(trip)
+            .or_insert(Distance::ZERO) += dist;
+        *self
+            .parking_search_per_road
+            .entry(map.get_l(l).parent)
+            .or_insert(Distance::ZERO) += dist;
+    }
+
+    /// Sums up cruising for parking over a grid of square areas, `area_size` on each side, to find
+    /// neighborhoods where parking is scarce. Each road belongs to the area containing its middle.
+    /// Returns each area with some cruising as (x, y), meaning it starts at `(x * area_size, y *
+    /// area_size)`, along with its roads and the total distance.
+    pub fn parking_search_per_area(
+        &self,
+        area_size: Distance,
+        map: &Map,
+    ) -> Vec<((i64, i64), Vec<RoadID>, Distance)> {
+        group_into_grid(
+            self.parking_search_per_road
+                .iter()
+                .map(|(r, dist)| (*r, map.get_r(*r).center_pts.middle(), *dist)),
+            area_size,
+        )
+    }
+
     pub fn record_demand(&mut self, path: &Path, map: &Map) {
         for step in path.get_steps() {
             if let Traversable::Turn(t) = step.as_traversable() {
@@ -449,6 +656,20 @@ impl Analytics {
         pts
     }
 
+    /// Returns the total revenue (in cents) and the number of cars that overstayed the time limit,
+    /// given the payments for one parking lane or lot.
+    pub fn total_parking_revenue(payments: Option<&Vec<(Time, usize, bool)>>) -> (usize, usize) {
+        let mut revenue = 0;
+        let mut overstays = 0;
+        for (_, paid, overstayed) in payments.into_iter().flatten() {
+            revenue += paid;
+            if *overstayed {
+                overstays += 1;
+            }
+        }
+        (revenue, overstays)
+    }
+
     /// Returns the free spots over time
     pub fn parking_lane_availability(
         &self,
@@ -696,3 +917,163 @@ impl SlidingWindow {
         }
     }
 }
+
+/// Follows trips cruising for parking, tracking the lane each one is currently cruising along.
+/// Returns the distance a trip just finished cruising along some lane. Lanes only count once the
+/// vehicle leaves them, except for the one where the driver finds a spot, which counts up to that
+/// spot.
+fn cruised_along<F: Fn(LaneID) -> Distance>(
+    searching: &mut BTreeMap<TripID, Option<LaneID>>,
+    ev: &Event,
+    lane_length: F,
+) -> Option<(TripID, LaneID, Distance)> {
+    match ev {
+        Event::TripPhaseStarting(id, _, _, TripPhaseType::SearchingForParking) => {
+            searching.insert(*id, None);
+            None
+        }
+        Event::TripPhaseStarting(id, _, maybe_req, _) => {
+            // The driver found a spot partway along the lane they were cruising
+            let l = searching.remove(id)??;
+            let req = maybe_req.as_ref()?;
+            if req.start.lane() == l {
+                Some((*id, l, req.start.dist_along()))
+            } else {
+                None
+            }
+        }
+        Event::TripCancelled(id, _) | Event::TripFinished { trip: id, .. } => {
+            searching.remove(id);
+            None
+        }
+        Event::AgentEntersTraversable(_, Some(trip), Traversable::Lane(l), _) => {
+            let prev = searching.get_mut(trip)?.replace(*l)?;
+            Some((*trip, prev, lane_length(prev)))
+        }
+        _ => None,
+    }
+}
+
+/// Sums distances over a grid of square areas, `area_size` on each side.
+fn group_into_grid<T, I: Iterator<Item = (T, Pt2D, Distance)>>(
+    items: I,
+    area_size: Distance,
+) -> Vec<((i64, i64), Vec<T>, Distance)> {
+    let size = area_size.inner_meters();
+    let mut areas: BTreeMap<(i64, i64), (Vec<T>, Distance)> = BTreeMap::new();
+    for (id, pt, dist) in items {
+        let key = (
+            (pt.x() / size).floor() as i64,
+            (pt.y() / size).floor() as i64,
+        );
+        let area = areas
+            .entry(key)
+            .or_insert_with(|| (Vec::new(), Distance::ZERO));
+        area.0.push(id);
+        area.1 += dist;
+    }
+    areas
+        .into_iter()
+        .map(|(key, (ids, total))| (key, ids, total))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use map_model::{PathConstraints, Position};
+
+    use super::*;
+    use crate::{CarID, PersonID, VehicleType};
+
+    fn phase(trip: TripID, start: Position, phase: TripPhaseType) -> Event {
+        Event::TripPhaseStarting(
+            trip,
+            PersonID(0),
+            Some(PathRequest::vehicle(start, start, PathConstraints::Car)),
+            phase,
+        )
+    }
+
+    fn enter(trip: TripID, l: LaneID) -> Event {
+        Event::AgentEntersTraversable(
+            AgentID::Car(CarID {
+                id: 0,
+                vehicle_type: VehicleType::Car,
+            }),
+            Some(trip),
+            Traversable::Lane(l),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_partial_lane_cruising() {
+        let trip = TripID(0);
+        let lane_length = |_: LaneID| Distance::meters(100.0);
+        let mut searching = BTreeMap::new();
+        let mut cruised = |ev: Event| cruised_along(&mut searching, &ev, lane_length);
+
+        // Entering lanes before cruising starts doesn't count
+        assert_eq!(cruised(enter(trip, LaneID(1))), None);
+        let start = Position::new(LaneID(1), Distance::meters(40.0));
+        assert_eq!(
+            cruised(phase(trip, start, TripPhaseType::SearchingForParking)),
+            None
+        );
+
+        // The lane where cruising started isn't counted, nor is a lane the driver is still on
+        assert_eq!(cruised(enter(trip, LaneID(2))), None);
+        // Once they leave it, the whole lane counts
+        assert_eq!(
+            cruised(enter(trip, LaneID(3))),
+            Some((trip, LaneID(2), Distance::meters(100.0)))
+        );
+        // Finding a spot partway along the current lane only counts up to there
+        let spot = Position::new(LaneID(3), Distance::meters(25.0));
+        assert_eq!(
+            cruised(phase(trip, spot, TripPhaseType::Parking)),
+            Some((trip, LaneID(3), Distance::meters(25.0)))
+        );
+        // Nothing else counts after that
+        assert_eq!(cruised(enter(trip, LaneID(4))), None);
+    }
+
+    #[test]
+    fn test_cancelled_cruising() {
+        let trip = TripID(0);
+        let lane_length = |_: LaneID| Distance::meters(100.0);
+        let mut searching = BTreeMap::new();
+        let mut cruised = |ev: Event| cruised_along(&mut searching, &ev, lane_length);
+
+        let start = Position::new(LaneID(1), Distance::meters(40.0));
+        cruised(phase(trip, start, TripPhaseType::SearchingForParking));
+        cruised(enter(trip, LaneID(2)));
+        // The partly cruised lane doesn't count when the trip is cancelled
+        assert_eq!(cruised(Event::TripCancelled(trip, TripMode::Drive)), None);
+        assert_eq!(cruised(enter(trip, LaneID(3))), None);
+    }
+
+    #[test]
+    fn test_group_into_grid() {
+        let items = vec![
+            (0, Pt2D::new(10.0, 10.0), Distance::meters(1.0)),
+            (1, Pt2D::new(499.0, 499.0), Distance::meters(2.0)),
+            // Exactly on the boundary belongs to the next area
+            (2, Pt2D::new(500.0, 10.0), Distance::meters(4.0)),
+            (3, Pt2D::new(1200.0, 700.0), Distance::meters(8.0)),
+        ];
+        assert_eq!(
+            group_into_grid(items.clone().into_iter(), Distance::meters(500.0)),
+            vec![
+                ((0, 0), vec![0, 1], Distance::meters(3.0)),
+                ((1, 0), vec![2], Distance::meters(4.0)),
+                ((2, 1), vec![3], Distance::meters(8.0)),
+            ]
+        );
+        // Bigger areas merge everything
+        assert_eq!(
+            group_into_grid(items.into_iter(), Distance::meters(2000.0)),
+            vec![((0, 0), vec![0, 1, 2, 3], Distance::meters(15.0))]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Energy use and charging for electric cars.
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration};
+
+/// Chargers in parking spots deliver energy at this rate, in kilowatts. This is a typical level 2
+/// charger.
+const CHARGER_POWER_KW: f64 = 7.2;
+/// Electric cars below this fraction of their capacity will try to park at a charger.
+const LOW_BATTERY: f64 = 0.3;
+/// Climbing 1km at a 100% grade takes this much extra energy, in kWh. Based on lifting a 1,500kg
+/// car: 1500kg * 9.81m/s^2 * 1000m = 14.7MJ, or about 4.1kWh.
+const KWH_PER_KM_CLIMB: f64 = 4.1;
+/// Regenerative braking recovers this fraction of the energy going downhill.
+const REGEN_EFFICIENCY: f64 = 0.6;
+
+/// The energy state of an electric car. Energy is measured in kilowatt-hours.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Battery {
+    pub capacity_kwh: f64,
+    pub charge_kwh: f64,
+    /// Energy used to drive 1km on flat ground
+    pub kwh_per_km: f64,
+}
+
+impl Battery {
+    /// A typical mid-size electric car, starting with some fraction of a full charge
+    pub fn typical(fraction_charged: f64) -> Battery {
+        let capacity_kwh = 60.0;
+        Battery {
+            capacity_kwh,
+            charge_kwh: capacity_kwh * fraction_charged,
+            kwh_per_km: 0.18,
+        }
+    }
+
+    /// From 0 to 1
+    pub fn fraction_charged(&self) -> f64 {
+        self.charge_kwh / self.capacity_kwh
+    }
+
+    pub fn needs_charging(&self) -> bool {
+        self.fraction_charged() < LOW_BATTERY
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.charge_kwh <= 0.0
+    }
+
+    /// Drive some distance up (positive) or down (negative) an incline. The battery never drops
+    /// below empty; the caller should strand the car once it's depleted. Returns the energy used.
+    pub(crate) fn drive(&mut self, dist: Distance, percent_incline: f64) -> f64 {
+        let climb = if percent_incline > 0.0 {
+            KWH_PER_KM_CLIMB * percent_incline
+        } else {
+            KWH_PER_KM_CLIMB * percent_incline * REGEN_EFFICIENCY
+        };
+        let used = ((self.kwh_per_km + climb) * dist.inner_meters() / 1000.0)
+            .max(0.0)
+            .min(self.charge_kwh);
+        self.charge_kwh -= used;
+        used
+    }
+
+    /// Plug into a charger for some time. Returns the energy added.
+    pub(crate) fn charge(&mut self, duration: Duration) -> f64 {
+        let added = (CHARGER_POWER_KW * duration.inner_seconds() / 3600.0)
+            .min(self.capacity_kwh - self.charge_kwh);
+        self.charge_kwh += added;
+        added
+    }
+
+    /// How long to charge in order to drive some distance on flat ground and still not need
+    /// charging at the end? Never charges past full.
+    pub(crate) fn time_to_charge_for(&self, dist: Distance) -> Duration {
+        let goal_kwh = (LOW_BATTERY * self.capacity_kwh
+            + self.kwh_per_km * dist.inner_meters() / 1000.0)
+            .min(self.capacity_kwh);
+        let needed_kwh = (goal_kwh - self.charge_kwh).max(0.0);
+        Duration::seconds(needed_kwh / CHARGER_POWER_KW * 3600.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx_eq(x: f64, y: f64) -> bool {
+        (x - y).abs() < 1e-9
+    }
+
+    #[test]
+    fn drive_on_inclines() {
+        let mut battery = Battery::typical(0.5);
+        assert!(approx_eq(
+            battery.drive(Distance::meters(1000.0), 0.0),
+            0.18
+        ));
+        assert!(approx_eq(battery.charge_kwh, 30.0 - 0.18));
+
+        // Climbing a 5% grade
+        let mut uphill = Battery::typical(0.5);
+        assert!(approx_eq(
+            uphill.drive(Distance::meters(1000.0), 0.05),
+            0.18 + 4.1 * 0.05
+        ));
+
+        // Going down a 2% grade recovers some energy with regenerative braking
+        let mut downhill = Battery::typical(0.5);
+        assert!(approx_eq(
+            downhill.drive(Distance::meters(1000.0), -0.02),
+            0.18 - 4.1 * 0.02 * 0.6
+        ));
+
+        // A steep enough descent doesn't charge the battery
+        let mut steep = Battery::typical(0.5);
+        assert!(approx_eq(steep.drive(Distance::meters(1000.0), -0.5), 0.0));
+        assert!(approx_eq(steep.charge_kwh, 30.0));
+    }
+
+    #[test]
+    fn drive_until_depleted() {
+        let mut battery = Battery::typical(0.01);
+        assert!(!battery.is_depleted());
+        // 0.6kWh is only enough for about 3.3km
+        assert!(approx_eq(battery.drive(Distance::meters(5000.0), 0.0), 0.6));
+        assert!(battery.is_depleted());
+        assert!(approx_eq(battery.charge_kwh, 0.0));
+    }
+
+    #[test]
+    fn charge_rate() {
+        let mut battery = Battery::typical(0.0);
+        assert!(approx_eq(battery.charge(Duration::hours(1)), 7.2));
+        assert!(approx_eq(battery.charge(Duration::minutes(30)), 3.6));
+        assert!(approx_eq(battery.charge_kwh, 10.8));
+
+        // Never past full
+        let mut almost_full = Battery::typical(0.99);
+        assert!(approx_eq(almost_full.charge(Duration::hours(1)), 0.6));
+        assert!(approx_eq(almost_full.fraction_charged(), 1.0));
+    }
+
+    #[test]
+    fn charge_for_trip() {
+        // 20% is 12kWh. 10km needs 1.8kWh more than the 18kWh low threshold.
+        let battery = Battery::typical(0.2);
+        let duration = battery.time_to_charge_for(Distance::meters(10_000.0));
+        assert!(approx_eq(duration.inner_seconds(), 7.8 / 7.2 * 3600.0));
+        let mut charged = battery;
+        charged.charge(duration);
+        assert!(!charged.needs_charging());
+
+        // Already enough energy
+        assert_eq!(
+            Battery::typical(0.9).time_to_charge_for(Distance::meters(1000.0)),
+            Duration::ZERO
+        );
+        // Capped at full
+        let empty = Battery::typical(0.0);
+        assert!(approx_eq(
+            empty
+                .time_to_charge_for(Distance::meters(1_000_000.0))
+                .inner_seconds(),
+            60.0 / 7.2 * 3600.0
+        ));
+    }
+}
@@ -0,0 +1,532 @@
+//! Shared micromobility: people pick up a bike or scooter from a dock near where their trip starts,
+//! ride it to a dock near their destination, and walk the rest of the way. Docks are placed in
+//! front of buildings through map edits, and each one has limited space. Somebody arriving at an
+//! empty dock has to walk instead, and somebody arriving at a full dock has to ride on to another
+//! one. Optionally, a crew periodically moves vehicles between docks to even things out.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Pt2D, Speed, Time};
+use map_model::{BuildingID, Map, SharedVehicleType, MAX_BIKE_SPEED};
+
+use crate::{
+    CarID, Command, Event, Scheduler, SimOptions, TripID, TripManager, Vehicle, VehicleSpec,
+    VehicleType, BIKE_LENGTH,
+};
+
+/// People will walk this far, as the crow flies, to reach a bike-share dock.
+pub const MAX_WALK_TO_DOCK: Distance = Distance::const_meters(800.0);
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Dock {
+    vehicle_type: SharedVehicleType,
+    capacity: usize,
+    docked: Vec<Vehicle>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CheckedOut {
+    vehicle: Vehicle,
+    trip: TripID,
+    /// Where the vehicle came from. If the trip is cancelled, it goes back here.
+    from: BuildingID,
+}
+
+/// Manages the docks for shared bikes and scooters, and the vehicles currently checked out.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct BikeShareSimState {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    docks: BTreeMap<BuildingID, Dock>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    in_use: BTreeMap<CarID, CheckedOut>,
+    rebalance_every: Option<Duration>,
+
+    events: Vec<Event>,
+}
+
+impl BikeShareSimState {
+    pub fn new(
+        map: &Map,
+        opts: &SimOptions,
+        trips: &mut TripManager,
+        scheduler: &mut Scheduler,
+    ) -> BikeShareSimState {
+        let mut state = BikeShareSimState {
+            docks: BTreeMap::new(),
+            in_use: BTreeMap::new(),
+            rebalance_every: opts.bike_share_rebalance_every,
+            events: Vec::new(),
+        };
+        state.sync_docks(map, trips);
+        if let Some(every) = state.rebalance_every {
+            scheduler.push(Time::START_OF_DAY + every, Command::RebalanceBikeShare);
+        }
+        state
+    }
+
+    /// Match the docks to the map, after live edits. New docks start half full. Vehicles that no
+    /// longer fit at their dock, or whose dock was removed or changed to a different type, are
+    /// moved to other docks.
+    pub fn sync_docks(&mut self, map: &Map, trips: &mut TripManager) {
+        let mut docks = BTreeMap::new();
+        let mut displaced = Vec::new();
+        for b in map.all_bike_docks() {
+            let spec = map.get_b(b).bike_dock.unwrap();
+            let pt = map.get_b(b).polygon.center();
+            let dock = match self.docks.remove(&b) {
+                Some(mut dock) if dock.vehicle_type == spec.vehicle_type => {
+                    dock.capacity = spec.capacity;
+                    if dock.docked.len() > spec.capacity {
+                        for vehicle in dock.docked.split_off(spec.capacity) {
+                            displaced.push((pt, dock.vehicle_type, vehicle));
+                        }
+                    }
+                    dock
+                }
+                old => {
+                    if let Some(old) = old {
+                        for vehicle in old.docked {
+                            displaced.push((pt, old.vehicle_type, vehicle));
+                        }
+                    }
+                    Dock {
+                        vehicle_type: spec.vehicle_type,
+                        capacity: spec.capacity,
+                        docked: (0..spec.capacity / 2)
+                            .map(|_| new_vehicle(spec.vehicle_type, trips.new_car_id()))
+                            .collect(),
+                    }
+                }
+            };
+            docks.insert(b, dock);
+        }
+        // Docks that were removed entirely
+        for (b, dock) in std::mem::take(&mut self.docks) {
+            let pt = map.get_b(b).polygon.center();
+            for vehicle in dock.docked {
+                displaced.push((pt, dock.vehicle_type, vehicle));
+            }
+        }
+        self.docks = docks;
+
+        let locations: BTreeMap<BuildingID, Pt2D> = self
+            .docks
+            .keys()
+            .map(|b| (*b, map.get_b(*b).polygon.center()))
+            .collect();
+        self.redock(displaced, &locations);
+    }
+
+    /// Move each displaced vehicle to the nearest dock for its type with space. If they're all
+    /// full, overfill the nearest one, and let rebalancing sort it out later. Vehicles are only
+    /// taken out of service when there's no dock for their type left at all.
+    fn redock(
+        &mut self,
+        displaced: Vec<(Pt2D, SharedVehicleType, Vehicle)>,
+        locations: &BTreeMap<BuildingID, Pt2D>,
+    ) {
+        for (pt, vehicle_type, vehicle) in displaced {
+            let candidates: Vec<(BuildingID, bool)> = self
+                .docks
+                .iter()
+                .filter(|(_, dock)| dock.vehicle_type == vehicle_type)
+                .map(|(b, dock)| (*b, dock.docked.len() < dock.capacity))
+                .collect();
+            let nearest = |with_space: bool| {
+                candidates
+                    .iter()
+                    .filter(|(_, has_space)| *has_space || !with_space)
+                    .min_by_key(|(b, _)| locations[b].dist_to(pt))
+                    .map(|(b, _)| *b)
+            };
+            if let Some(b) = nearest(true).or_else(|| nearest(false)) {
+                self.docks.get_mut(&b).unwrap().docked.push(vehicle);
+            }
+        }
+    }
+
+    /// Somebody wants to pick up a vehicle here. Returns None if the dock is empty.
+    pub fn check_out(&mut self, dock: BuildingID, trip: TripID) -> Option<Vehicle> {
+        let vehicle = self.docks.get_mut(&dock)?.docked.pop()?;
+        self.in_use.insert(
+            vehicle.id,
+            CheckedOut {
+                vehicle: vehicle.clone(),
+                trip,
+                from: dock,
+            },
+        );
+        Some(vehicle)
+    }
+
+    pub fn is_shared(&self, id: CarID) -> bool {
+        self.in_use.contains_key(&id)
+    }
+
+    /// Only for vehicles that're checked out.
+    pub fn get_vehicle(&self, id: CarID) -> Vehicle {
+        self.in_use[&id].vehicle.clone()
+    }
+
+    /// Try to return a vehicle to a dock. Returns false if there's no space there, in which case
+    /// the vehicle is still checked out.
+    pub fn return_vehicle(&mut self, id: CarID, dock: BuildingID) -> bool {
+        if let Some(d) = self.docks.get_mut(&dock) {
+            if d.docked.len() < d.capacity {
+                d.docked.push(self.in_use.remove(&id).unwrap().vehicle);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Leave a vehicle at a dock, even if it's already full. If the dock no longer exists, the
+    /// vehicle is taken out of service.
+    pub fn force_return(&mut self, id: CarID, dock: BuildingID) {
+        let vehicle = self.in_use.remove(&id).unwrap().vehicle;
+        if let Some(d) = self.docks.get_mut(&dock) {
+            d.docked.push(vehicle);
+        }
+    }
+
+    /// Find the dock closest to `near` with some space left, as the crow flies.
+    pub fn nearest_dock_with_space(&self, near: BuildingID, map: &Map) -> Option<BuildingID> {
+        let pt = map.get_b(near).polygon.center();
+        self.docks
+            .iter()
+            .filter(|(b, dock)| {
+                **b != near
+                    && dock.docked.len() < dock.capacity
+                    && map.get_b(**b).biking_connection(map).is_some()
+            })
+            .min_by_key(|(b, _)| map.get_b(**b).polygon.center().dist_to(pt))
+            .map(|(b, _)| *b)
+    }
+
+    /// Any vehicle used by a cancelled trip goes back to where it came from.
+    pub fn trip_cancelled(&mut self, trip: TripID) {
+        let ids: Vec<CarID> = self
+            .in_use
+            .iter()
+            .filter(|(_, x)| x.trip == trip)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            let from = self.in_use[&id].from;
+            self.force_return(id, from);
+        }
+    }
+
+    /// Redistribute the docked vehicles of each type in proportion to each dock's capacity, then
+    /// schedule the next round.
+    pub fn rebalance(&mut self, now: Time, scheduler: &mut Scheduler) {
+        let mut moved = 0;
+        for vehicle_type in vec![SharedVehicleType::Bike, SharedVehicleType::Scooter] {
+            let ids: Vec<BuildingID> = self
+                .docks
+                .iter()
+                .filter(|(_, dock)| dock.vehicle_type == vehicle_type)
+                .map(|(b, _)| *b)
+                .collect();
+            let total_vehicles: usize = ids.iter().map(|b| self.docks[b].docked.len()).sum();
+            let total_capacity: usize = ids.iter().map(|b| self.docks[b].capacity).sum();
+            if total_capacity == 0 {
+                continue;
+            }
+            // Forced returns can leave more vehicles than there's space for, but never plan to
+            // overfill a dock.
+            let target =
+                |capacity: usize| (total_vehicles * capacity / total_capacity).min(capacity);
+
+            // Pick up everything above each dock's share...
+            let mut surplus: Vec<(BuildingID, Vehicle)> = Vec::new();
+            for b in &ids {
+                let dock = self.docks.get_mut(b).unwrap();
+                while dock.docked.len() > target(dock.capacity) {
+                    surplus.push((*b, dock.docked.pop().unwrap()));
+                }
+            }
+            // ...drop it off wherever's below...
+            for b in &ids {
+                let dock = self.docks.get_mut(b).unwrap();
+                while dock.docked.len() < target(dock.capacity) {
+                    if let Some((from, vehicle)) = surplus.pop() {
+                        if from != *b {
+                            moved += 1;
+                        }
+                        dock.docked.push(vehicle);
+                    } else {
+                        break;
+                    }
+                }
+            }
+            // ...and then rounding leaves a few, so put them anywhere with space.
+            for b in &ids {
+                let dock = self.docks.get_mut(b).unwrap();
+                while dock.docked.len() < dock.capacity {
+                    if let Some((from, vehicle)) = surplus.pop() {
+                        if from != *b {
+                            moved += 1;
+                        }
+                        dock.docked.push(vehicle);
+                    } else {
+                        break;
+                    }
+                }
+            }
+            // If docks were over capacity from forced returns, leave the rest where they were.
+            for (from, vehicle) in surplus {
+                self.docks.get_mut(&from).unwrap().docked.push(vehicle);
+            }
+        }
+
+        if moved > 0 {
+            self.events.push(Event::BikeShareRebalanced(moved));
+        }
+        if let Some(every) = self.rebalance_every {
+            scheduler.push(now + every, Command::RebalanceBikeShare);
+        }
+    }
+
+    pub fn collect_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Returns (vehicles docked, capacity)
+    pub fn dock_status(&self, dock: BuildingID) -> Option<(usize, usize)> {
+        let dock = self.docks.get(&dock)?;
+        Some((dock.docked.len(), dock.capacity))
+    }
+
+    pub fn num_in_use(&self) -> usize {
+        self.in_use.len()
+    }
+}
+
+fn new_vehicle(vehicle_type: SharedVehicleType, id: usize) -> Vehicle {
+    // Shared scooters use bike lanes and behave like bikes, just a bit slower
+    let id = CarID {
+        id,
+        vehicle_type: VehicleType::Bike,
+    };
+    VehicleSpec {
+        vehicle_type: VehicleType::Bike,
+        length: BIKE_LENGTH,
+        max_speed: Some(match vehicle_type {
+            SharedVehicleType::Bike => MAX_BIKE_SPEED,
+            SharedVehicleType::Scooter => Speed::miles_per_hour(8.0),
+        }),
+        passenger_capacity: None,
+        battery: None,
+    }
+    .make(id, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each dock is (vehicle type, capacity, number of vehicles docked), placed in front of
+    /// BuildingID(index).
+    fn make_state(docks: Vec<(SharedVehicleType, usize, usize)>) -> BikeShareSimState {
+        let mut next_id = 0;
+        let mut state = BikeShareSimState {
+            docks: BTreeMap::new(),
+            in_use: BTreeMap::new(),
+            rebalance_every: None,
+            events: Vec::new(),
+        };
+        for (idx, (vehicle_type, capacity, num_docked)) in docks.into_iter().enumerate() {
+            let docked = (0..num_docked)
+                .map(|_| {
+                    next_id += 1;
+                    new_vehicle(vehicle_type, next_id)
+                })
+                .collect();
+            state.docks.insert(
+                BuildingID(idx),
+                Dock {
+                    vehicle_type,
+                    capacity,
+                    docked,
+                },
+            );
+        }
+        state
+    }
+
+    fn total_vehicles(state: &BikeShareSimState) -> usize {
+        state
+            .docks
+            .values()
+            .map(|dock| dock.docked.len())
+            .sum::<usize>()
+            + state.num_in_use()
+    }
+
+    fn status(state: &BikeShareSimState) -> Vec<(usize, usize)> {
+        (0..state.docks.len())
+            .map(|idx| state.dock_status(BuildingID(idx)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_check_out_and_return() {
+        let mut state = make_state(vec![
+            (SharedVehicleType::Bike, 2, 1),
+            (SharedVehicleType::Bike, 1, 1),
+        ]);
+
+        let bike = state.check_out(BuildingID(0), TripID(0)).unwrap();
+        assert!(state.is_shared(bike.id));
+        // The dock is empty now
+        assert!(state.check_out(BuildingID(0), TripID(1)).is_none());
+        assert_eq!(status(&state), vec![(0, 2), (1, 1)]);
+
+        // The other dock is full, and there's no dock at all in front of BuildingID(5)
+        assert!(!state.return_vehicle(bike.id, BuildingID(1)));
+        assert!(!state.return_vehicle(bike.id, BuildingID(5)));
+        assert!(state.is_shared(bike.id));
+        assert_eq!(state.num_in_use(), 1);
+
+        assert!(state.return_vehicle(bike.id, BuildingID(0)));
+        assert!(!state.is_shared(bike.id));
+        assert_eq!(state.num_in_use(), 0);
+        assert_eq!(status(&state), vec![(1, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn test_force_return() {
+        let mut state = make_state(vec![
+            (SharedVehicleType::Bike, 2, 2),
+            (SharedVehicleType::Bike, 1, 1),
+        ]);
+
+        // Forcing a return overfills the dock
+        let bike1 = state.check_out(BuildingID(0), TripID(0)).unwrap();
+        state.force_return(bike1.id, BuildingID(1));
+        assert_eq!(status(&state), vec![(1, 2), (2, 1)]);
+
+        // Returning to a dock that no longer exists takes the vehicle out of service
+        let bike2 = state.check_out(BuildingID(0), TripID(1)).unwrap();
+        state.force_return(bike2.id, BuildingID(5));
+        assert!(!state.is_shared(bike2.id));
+        assert_eq!(status(&state), vec![(0, 2), (2, 1)]);
+
+        // A cancelled trip puts the vehicle back where it came from
+        let bike3 = state.check_out(BuildingID(1), TripID(2)).unwrap();
+        state.trip_cancelled(TripID(2));
+        assert!(!state.is_shared(bike3.id));
+        assert_eq!(status(&state), vec![(0, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_rebalance() {
+        let mut scheduler = Scheduler::new();
+
+        // 12 bikes over 40 spaces, so each dock should be 30% full. The scooter dock is separate.
+        let mut state = make_state(vec![
+            (SharedVehicleType::Bike, 10, 10),
+            (SharedVehicleType::Bike, 10, 0),
+            (SharedVehicleType::Bike, 20, 2),
+            (SharedVehicleType::Scooter, 4, 4),
+        ]);
+        state.rebalance(Time::START_OF_DAY, &mut scheduler);
+        assert_eq!(status(&state), vec![(3, 10), (3, 10), (6, 20), (4, 4)]);
+        assert_eq!(state.collect_events(), vec![Event::BikeShareRebalanced(7)]);
+
+        // Already balanced
+        state.rebalance(Time::START_OF_DAY, &mut scheduler);
+        assert!(state.collect_events().is_empty());
+
+        // Rounding down leaves one bike over, which stays where it was
+        let mut state = make_state(vec![
+            (SharedVehicleType::Bike, 3, 3),
+            (SharedVehicleType::Bike, 3, 0),
+        ]);
+        state.rebalance(Time::START_OF_DAY, &mut scheduler);
+        assert_eq!(status(&state), vec![(2, 3), (1, 3)]);
+        assert_eq!(state.collect_events(), vec![Event::BikeShareRebalanced(1)]);
+
+        // Bikes beyond the total capacity stay at the overfilled dock
+        let mut state = make_state(vec![
+            (SharedVehicleType::Bike, 1, 3),
+            (SharedVehicleType::Bike, 1, 1),
+        ]);
+        state.rebalance(Time::START_OF_DAY, &mut scheduler);
+        assert_eq!(status(&state), vec![(3, 1), (1, 1)]);
+        assert!(state.collect_events().is_empty());
+
+        // Nothing was scheduled yet
+        assert_eq!(scheduler.peek_next_time(), None);
+        state.rebalance_every = Some(Duration::hours(1));
+        state.rebalance(Time::START_OF_DAY, &mut scheduler);
+        assert_eq!(
+            scheduler.peek_next_time(),
+            Some(Time::START_OF_DAY + Duration::hours(1))
+        );
+    }
+
+    #[test]
+    fn test_redock_conserves_vehicles() {
+        // Docks in a row, 100m apart
+        let mut state = make_state(vec![
+            (SharedVehicleType::Bike, 2, 2),
+            (SharedVehicleType::Bike, 2, 1),
+            (SharedVehicleType::Bike, 5, 0),
+            (SharedVehicleType::Scooter, 5, 0),
+        ]);
+        let locations: BTreeMap<BuildingID, Pt2D> = (0..4)
+            .map(|idx| (BuildingID(idx), Pt2D::new(100.0 * (idx as f64), 0.0)))
+            .collect();
+        let bike = state.check_out(BuildingID(0), TripID(0)).unwrap();
+        let before = total_vehicles(&state);
+
+        // Dock 0 shrank, so its bike and 3 more from a removed dock next to it need a new home.
+        // The nearest dock with space fills up first, then the next.
+        let dock = state.docks.get_mut(&BuildingID(0)).unwrap();
+        dock.capacity = 0;
+        let mut displaced: Vec<(Pt2D, SharedVehicleType, Vehicle)> = dock
+            .docked
+            .drain(..)
+            .map(|vehicle| (Pt2D::new(0.0, 0.0), SharedVehicleType::Bike, vehicle))
+            .collect();
+        for idx in 0..3 {
+            displaced.push((
+                Pt2D::new(0.0, 0.0),
+                SharedVehicleType::Bike,
+                new_vehicle(SharedVehicleType::Bike, 100 + idx),
+            ));
+        }
+        state.redock(displaced, &locations);
+        assert_eq!(total_vehicles(&state), before + 3);
+        assert_eq!(status(&state), vec![(0, 0), (2, 2), (3, 5), (0, 5)]);
+
+        // Fill the last spaces, then overfill the nearest dock instead of losing any bikes
+        let displaced: Vec<(Pt2D, SharedVehicleType, Vehicle)> = (0..3)
+            .map(|idx| {
+                (
+                    Pt2D::new(250.0, 0.0),
+                    SharedVehicleType::Bike,
+                    new_vehicle(SharedVehicleType::Bike, 200 + idx),
+                )
+            })
+            .collect();
+        state.redock(displaced, &locations);
+        assert_eq!(total_vehicles(&state), before + 6);
+        assert_eq!(status(&state), vec![(0, 0), (2, 2), (6, 5), (0, 5)]);
+
+        // The bike that was checked out can still come back
+        state.force_return(bike.id, BuildingID(1));
+        assert_eq!(total_vehicles(&state), before + 6);
+    }
+}
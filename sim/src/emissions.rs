@@ -0,0 +1,325 @@
+//! Estimates the energy used and the pollution emitted by vehicles. Models are evaluated every
+//! time a vehicle finishes crossing a lane or turn and when it finishes its trip, using only its
+//! average speed, the change from its average speed over the previous step, and the grade of what
+//! it just crossed, so the numbers are only rough estimates.
+
+use std::ops::{Add, AddAssign};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration};
+
+use crate::VehicleType;
+
+/// Grams of CO2 released by burning one liter of gasoline
+const CO2_PER_LITER_GASOLINE: f64 = 2310.0;
+/// Grams of CO2 released by burning one liter of diesel
+const CO2_PER_LITER_DIESEL: f64 = 2680.0;
+/// Energy content of one liter of gasoline
+const KWH_PER_LITER_GASOLINE: f64 = 8.9;
+/// Energy content of one liter of diesel
+const KWH_PER_LITER_DIESEL: f64 = 10.0;
+/// Light rail draws power from overhead wires.
+const TRAIN_KWH_PER_KM: f64 = 10.0;
+/// A bus is much heavier than a car and burns diesel.
+const BUS_CO2_FACTOR: f64 = 4.5;
+const BUS_NOX_FACTOR: f64 = 12.0;
+/// Delivery vans are smaller than buses, but also burn diesel.
+const DELIVERY_CO2_FACTOR: f64 = 2.0;
+const DELIVERY_NOX_FACTOR: f64 = 3.0;
+
+/// Energy used and pollutants emitted, either by one vehicle crossing one lane or turn, or summed
+/// over a trip or road.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Emissions {
+    pub co2_grams: f64,
+    pub nox_grams: f64,
+    /// Fuel or electricity used
+    pub energy_kwh: f64,
+}
+
+impl Emissions {
+    pub const ZERO: Emissions = Emissions {
+        co2_grams: 0.0,
+        nox_grams: 0.0,
+        energy_kwh: 0.0,
+    };
+}
+
+impl Add for Emissions {
+    type Output = Emissions;
+
+    fn add(self, other: Emissions) -> Emissions {
+        Emissions {
+            co2_grams: self.co2_grams + other.co2_grams,
+            nox_grams: self.nox_grams + other.nox_grams,
+            energy_kwh: self.energy_kwh + other.energy_kwh,
+        }
+    }
+}
+
+impl AddAssign for Emissions {
+    fn add_assign(&mut self, other: Emissions) {
+        *self = *self + other;
+    }
+}
+
+/// Estimates what a typical gasoline car emits. `evaluate` scales this for other vehicle types.
+pub trait EmissionsModel {
+    /// Returns grams of (CO2, NOx) emitted crossing some distance over some (nonzero) time, with
+    /// some average acceleration in m/s^2, up (positive) or down (negative) an incline.
+    fn typical_car(
+        &self,
+        dist: Distance,
+        duration: Duration,
+        accel: f64,
+        percent_incline: f64,
+    ) -> (f64, f64);
+}
+
+/// Bin the vehicle specific power (the power needed per ton of vehicle to accelerate and to
+/// overcome rolling resistance, drag and grade) and look up an emission rate per second, like EPA's
+/// MOVES. Time spent idling in traffic counts.
+pub struct VehicleSpecificPower;
+
+impl EmissionsModel for VehicleSpecificPower {
+    fn typical_car(
+        &self,
+        dist: Distance,
+        duration: Duration,
+        accel: f64,
+        percent_incline: f64,
+    ) -> (f64, f64) {
+        let speed = dist.inner_meters() / duration.inner_seconds();
+        let vsp = speed * (1.1 * accel + 9.81 * percent_incline + 0.132) + 0.000302 * speed.powi(3);
+        let (co2_per_sec, nox_per_sec) = if vsp < 0.0 {
+            (1.0, 0.0005)
+        } else if vsp < 3.0 {
+            (1.6, 0.001)
+        } else if vsp < 6.0 {
+            (2.8, 0.002)
+        } else if vsp < 9.0 {
+            (3.8, 0.003)
+        } else if vsp < 12.0 {
+            (4.8, 0.004)
+        } else if vsp < 18.0 {
+            (6.0, 0.006)
+        } else if vsp < 24.0 {
+            (7.6, 0.008)
+        } else {
+            (9.5, 0.011)
+        };
+        let secs = duration.inner_seconds();
+        (co2_per_sec * secs, nox_per_sec * secs)
+    }
+}
+
+/// Look up emission factors per km from the average speed, like COPERT. Congested traffic with low
+/// average speeds emits more per km. The factors already include typical stop-and-go driving, so
+/// acceleration is ignored.
+pub struct AverageSpeed;
+
+impl EmissionsModel for AverageSpeed {
+    fn typical_car(
+        &self,
+        dist: Distance,
+        duration: Duration,
+        _: f64,
+        percent_incline: f64,
+    ) -> (f64, f64) {
+        let km = dist.inner_meters() / 1000.0;
+        // Below walking pace, the curves blow up
+        let kph = (km / (duration.inner_seconds() / 3600.0)).max(5.0);
+        let co2_per_km = 100.0 + 1800.0 / kph + 0.01 * kph * kph;
+        let nox_per_km = 0.05 + 0.5 / kph + 0.00002 * kph * kph;
+        // Climbing costs more, descending a bit less
+        let grade = (1.0 + 8.0 * percent_incline).max(0.5);
+        (co2_per_km * km * grade, nox_per_km * km * grade)
+    }
+}
+
+/// Which `EmissionsModel` a simulation uses.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EmissionsMethod {
+    VehicleSpecificPower,
+    AverageSpeed,
+}
+
+impl EmissionsMethod {
+    pub fn model(self) -> Box<dyn EmissionsModel> {
+        match self {
+            EmissionsMethod::VehicleSpecificPower => Box::new(VehicleSpecificPower),
+            EmissionsMethod::AverageSpeed => Box::new(AverageSpeed),
+        }
+    }
+}
+
+/// Estimate what a vehicle emits crossing some distance over some time, with some average
+/// acceleration in m/s^2, up (positive) or down (negative) an incline. Electric cars and bikes have
+/// no tailpipe; the caller is responsible for recording the energy an electric car takes from its
+/// battery.
+pub fn evaluate(
+    model: &dyn EmissionsModel,
+    vehicle_type: VehicleType,
+    electric: bool,
+    dist: Distance,
+    duration: Duration,
+    accel: f64,
+    percent_incline: f64,
+) -> Emissions {
+    let (co2_factor, nox_factor) = match vehicle_type {
+        VehicleType::Car => {
+            if electric {
+                return Emissions::ZERO;
+            }
+            (1.0, 1.0)
+        }
+        VehicleType::RideHail => (1.0, 1.0),
+        VehicleType::Bus => (BUS_CO2_FACTOR, BUS_NOX_FACTOR),
+        VehicleType::Delivery => (DELIVERY_CO2_FACTOR, DELIVERY_NOX_FACTOR),
+        VehicleType::Train => {
+            return Emissions {
+                co2_grams: 0.0,
+                nox_grams: 0.0,
+                energy_kwh: TRAIN_KWH_PER_KM * dist.inner_meters() / 1000.0,
+            };
+        }
+        VehicleType::Bike => {
+            return Emissions::ZERO;
+        }
+    };
+    if duration == Duration::ZERO {
+        return Emissions::ZERO;
+    }
+
+    let (co2_grams, nox_grams) = model.typical_car(dist, duration, accel, percent_incline);
+    let (co2_per_liter, kwh_per_liter) =
+        if matches!(vehicle_type, VehicleType::Bus | VehicleType::Delivery) {
+            (CO2_PER_LITER_DIESEL, KWH_PER_LITER_DIESEL)
+        } else {
+            (CO2_PER_LITER_GASOLINE, KWH_PER_LITER_GASOLINE)
+        };
+    let co2_grams = co2_grams * co2_factor;
+    Emissions {
+        co2_grams,
+        nox_grams: nox_grams * nox_factor,
+        energy_kwh: co2_grams / co2_per_liter * kwh_per_liter,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn car(
+        model: &dyn EmissionsModel,
+        meters: f64,
+        secs: f64,
+        accel: f64,
+        percent_incline: f64,
+    ) -> Emissions {
+        evaluate(
+            model,
+            VehicleType::Car,
+            false,
+            Distance::meters(meters),
+            Duration::seconds(secs),
+            accel,
+            percent_incline,
+        )
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_vsp_bins() {
+        let vsp = &VehicleSpecificPower;
+        // Idling and 10m/s on the flat both fall in the [0, 3) bin
+        assert_close(car(vsp, 0.0, 10.0, 0.0, 0.0).co2_grams, 16.0);
+        assert_close(car(vsp, 100.0, 10.0, 0.0, 0.0).co2_grams, 16.0);
+        assert_close(car(vsp, 100.0, 10.0, 0.0, 0.0).nox_grams, 0.01);
+        // 20m/s on the flat is in [3, 6)
+        assert_close(car(vsp, 200.0, 10.0, 0.0, 0.0).co2_grams, 28.0);
+        // 30m/s on the flat is in [12, 18)
+        assert_close(car(vsp, 300.0, 10.0, 0.0, 0.0).co2_grams, 60.0);
+        // 10m/s up a 5% grade is in [6, 9), and down it is negative
+        assert_close(car(vsp, 100.0, 10.0, 0.0, 0.05).co2_grams, 38.0);
+        assert_close(car(vsp, 100.0, 10.0, 0.0, -0.05).co2_grams, 10.0);
+    }
+
+    #[test]
+    fn test_vsp_acceleration() {
+        let vsp = &VehicleSpecificPower;
+        // Speeding up at 1m/s^2 around 10m/s is in [12, 18), and slowing down is negative
+        assert_close(car(vsp, 100.0, 10.0, 1.0, 0.0).co2_grams, 60.0);
+        assert_close(car(vsp, 100.0, 10.0, -1.0, 0.0).co2_grams, 10.0);
+
+        // The average speed model ignores acceleration
+        let avg = &AverageSpeed;
+        assert_eq!(
+            car(avg, 1000.0, 120.0, 1.0, 0.0),
+            car(avg, 1000.0, 120.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_average_speed_curve() {
+        let avg = &AverageSpeed;
+        // 1km at 30km/h: 100 + 1800 / 30 + 0.01 * 30^2
+        let flat = car(avg, 1000.0, 120.0, 0.0, 0.0);
+        assert_close(flat.co2_grams, 169.0);
+        assert_close(flat.nox_grams, 0.05 + 0.5 / 30.0 + 0.00002 * 900.0);
+        assert_close(
+            flat.energy_kwh,
+            169.0 / CO2_PER_LITER_GASOLINE * KWH_PER_LITER_GASOLINE,
+        );
+        // Congestion emits more per km
+        assert_close(car(avg, 1000.0, 360.0, 0.0, 0.0).co2_grams, 281.0);
+        // Below 5km/h, the curve is clamped
+        assert_close(car(avg, 10.0, 60.0, 0.0, 0.0).co2_grams, 4.6025);
+        // Climbing costs more, and descending saves at most half
+        assert_close(car(avg, 1000.0, 120.0, 0.0, 0.05).co2_grams, 169.0 * 1.4);
+        assert_close(car(avg, 1000.0, 120.0, 0.0, -0.1).co2_grams, 169.0 * 0.5);
+    }
+
+    #[test]
+    fn test_no_tailpipe() {
+        for method in vec![
+            EmissionsMethod::VehicleSpecificPower,
+            EmissionsMethod::AverageSpeed,
+        ] {
+            let model = method.model();
+            let model = model.as_ref();
+            let dist = Distance::meters(1000.0);
+            let duration = Duration::seconds(120.0);
+            assert_eq!(
+                evaluate(model, VehicleType::Car, true, dist, duration, 0.0, 0.0),
+                Emissions::ZERO
+            );
+            assert_eq!(
+                evaluate(model, VehicleType::Bike, false, dist, duration, 0.0, 0.05),
+                Emissions::ZERO
+            );
+            let train = evaluate(model, VehicleType::Train, false, dist, duration, 0.0, 0.0);
+            assert_eq!(train.co2_grams, 0.0);
+            assert_eq!(train.nox_grams, 0.0);
+            assert_close(train.energy_kwh, TRAIN_KWH_PER_KM);
+
+            // Buses burn diesel
+            let bus = evaluate(model, VehicleType::Bus, false, dist, duration, 0.0, 0.0);
+            let gas_car = evaluate(model, VehicleType::Car, false, dist, duration, 0.0, 0.0);
+            assert_close(bus.co2_grams, gas_car.co2_grams * BUS_CO2_FACTOR);
+            assert_close(
+                bus.energy_kwh,
+                bus.co2_grams / CO2_PER_LITER_DIESEL * KWH_PER_LITER_DIESEL,
+            );
+        }
+    }
+}
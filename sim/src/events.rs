@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
 
-use geom::Duration;
+use geom::{Duration, Time};
 use map_model::{
     BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, Path, PathRequest, Traversable,
     TurnID,
 };
 
-use crate::{AgentID, CarID, ParkingSpot, PedestrianID, PersonID, Problem, TripID, TripMode};
+use crate::{
+    AgentID, CarID, Emissions, LevelOfService, ParkingSpot, PedestrianID, PersonID, Problem,
+    RideHailRide, TripID, TripMode,
+};
 
 /// As a simulation runs, different systems emit Events. This cleanly separates the internal
 /// mechanics of the simulation from consumers that just want to know what's happening.
@@ -19,7 +22,11 @@ use crate::{AgentID, CarID, ParkingSpot, PedestrianID, PersonID, Problem, TripID
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Event {
     CarReachedParkingSpot(CarID, ParkingSpot),
-    CarLeftParkingSpot(CarID, ParkingSpot),
+    /// Also includes when the car originally parked.
+    CarLeftParkingSpot(CarID, ParkingSpot, Time),
+    /// An electric car is leaving a spot with a charger. How much energy (in kWh) did it gain, and
+    /// how long was it plugged in?
+    VehicleCharged(CarID, ParkingSpot, f64, Duration),
 
     BusArrivedAtStop(CarID, BusRouteID, BusStopID),
     BusDepartedFromStop(CarID, BusRouteID, BusStopID),
@@ -36,12 +43,26 @@ pub enum Event {
     PedReachedParkingSpot(PedestrianID, ParkingSpot),
 
     BikeStoppedAtSidewalk(CarID, LaneID),
+    /// A delivery van stopped on some driving lane to load or unload. If it found a free loading
+    /// zone spot, that's included; otherwise it double-parked and blocked the lane for the
+    /// duration.
+    DeliveryStop(CarID, TripID, LaneID, Option<ParkingSpot>, Duration),
+    /// A ride-hail vehicle dropped off its passenger.
+    RideHailRideFinished(RideHailRide),
+    /// A crew moved this many shared bikes and scooters between docks.
+    BikeShareRebalanced(usize),
+    /// A sidewalk became more or less crowded. Only emitted if pedestrian crowding is modeled.
+    SidewalkLevelOfService(LaneID, LevelOfService),
+    /// Mode choice switched a trip from the scenario's mode to another one.
+    ModeChosen(TripID, TripMode, TripMode),
 
     ProblemEncountered(TripID, Problem),
 
     /// If the agent is a transit vehicle, then include a count of how many passengers are on
     /// board.
     AgentEntersTraversable(AgentID, Option<TripID>, Traversable, Option<usize>),
+    /// A vehicle finished crossing a lane or turn. The trip is None for buses and trains.
+    VehicleEmitted(CarID, Option<TripID>, Traversable, Emissions),
     /// TripID, TurnID (Where the delay was encountered), Time spent waiting at that turn
     IntersectionDelayMeasured(TripID, TurnID, AgentID, Duration),
 
@@ -59,6 +80,25 @@ pub enum Event {
     PathAmended(Path),
 
     Alert(AlertLocation, String),
+
+    /// A traffic signal gave priority to a transit vehicle. `delayed_agents` counts the agents
+    /// waiting at the intersection for movements that priority works against.
+    TransitSignalPriority {
+        intersection: IntersectionID,
+        vehicle: CarID,
+        route: BusRouteID,
+        priority: TransitPriorityType,
+        granted: Duration,
+        delayed_agents: usize,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum TransitPriorityType {
+    /// The stage serving the vehicle was extended.
+    GreenExtension,
+    /// The stage not serving the vehicle was ended early.
+    EarlyGreen,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -75,12 +115,18 @@ pub enum TripPhaseType {
     Walking,
     Biking,
     Parking,
+    /// Driving around because there was no free spot near the destination
+    SearchingForParking,
     WaitingForBus(BusRouteID, BusStopID),
     /// What stop did they board at?
     RidingBus(BusRouteID, BusStopID, CarID),
     Cancelled,
     Finished,
     DelayedStart,
+    /// A delivery van is stopped, loading or unloading for this building
+    Delivering(BuildingID),
+    WaitingForRideHail,
+    RidingRideHail(CarID),
 }
 
 impl TripPhaseType {
@@ -90,6 +136,7 @@ impl TripPhaseType {
             TripPhaseType::Walking => "Walking".to_string(),
             TripPhaseType::Biking => "Biking".to_string(),
             TripPhaseType::Parking => "Parking".to_string(),
+            TripPhaseType::SearchingForParking => "Searching for parking".to_string(),
             TripPhaseType::WaitingForBus(r, _) => {
                 format!("Waiting for bus {}", map.get_br(r).full_name)
             }
@@ -97,6 +144,9 @@ impl TripPhaseType {
             TripPhaseType::Cancelled => "Trip was cancelled due to some bug".to_string(),
             TripPhaseType::Finished => "Trip finished".to_string(),
             TripPhaseType::DelayedStart => "Delayed by a previous trip taking too long".to_string(),
+            TripPhaseType::Delivering(b) => format!("Delivering to {}", map.get_b(b).address),
+            TripPhaseType::WaitingForRideHail => "Waiting for a ride-hail".to_string(),
+            TripPhaseType::RidingRideHail(car) => format!("Riding in {}", car),
         }
     }
 }
@@ -22,10 +22,10 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize};
-use geom::{Distance, Speed, Time};
+use geom::{Distance, Duration, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, Path,
-    PathConstraints, Position,
+    BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, ParkingPricing,
+    Path, PathConstraints, Position, TransitCapacity,
 };
 
 pub use crate::render::{
@@ -34,34 +34,56 @@ pub use crate::render::{
 };
 
 pub use self::analytics::{Analytics, Problem, SlidingWindow, TripPhase};
-pub(crate) use self::events::Event;
-pub use self::events::{AlertLocation, TripPhaseType};
+pub use self::battery::Battery;
+pub(crate) use self::bike_share::BikeShareSimState;
+pub use self::bike_share::MAX_WALK_TO_DOCK;
+pub use self::emissions::{
+    AverageSpeed, Emissions, EmissionsMethod, EmissionsModel, VehicleSpecificPower,
+};
+pub use self::events::{AlertLocation, Event, TransitPriorityType, TripPhaseType};
 pub use self::make::{
-    fork_rng, BorderSpawnOverTime, ExternalPerson, ExternalTrip, ExternalTripEndpoint, IndividTrip,
-    MapBorders, PersonSpec, Scenario, ScenarioGenerator, ScenarioModifier, SimFlags, SpawnOverTime,
-    TripEndpoint, TripPurpose,
+    fork_rng, BorderSpawnOverTime, DeliveryStop, ExternalPerson, ExternalTrip,
+    ExternalTripEndpoint, IndividTrip, MapBorders, PersonSpec, Scenario, ScenarioGenerator,
+    ScenarioModifier, SimFlags, SpawnOverTime, TripEndpoint, TripPurpose,
+};
+pub(crate) use self::make::{
+    ParkedCarAccess, StartTripArgs, TransitAccessOptions, TripSpec, MAX_WALK_TO_CAR,
 };
-pub(crate) use self::make::{StartTripArgs, TripSpec};
+pub use self::mechanics::LevelOfService;
 pub(crate) use self::mechanics::{
     DrivingSimState, IntersectionSimState, ParkingSim, ParkingSimState, WalkingSimState,
 };
+pub use self::mode_choice::ModeChoiceModel;
 pub(crate) use self::pandemic::PandemicModel;
 pub(crate) use self::recorder::TrafficRecorder;
+pub(crate) use self::ride_hail::{CurbStop, RideHailSimState};
+pub use self::ride_hail::{
+    DispatchPolicy, Dispatcher, IdleVehicle, LongestIdle, NearestVehicle, RideHailRide, RideRequest,
+};
 pub(crate) use self::router::{ActionAtEnd, Router};
 pub(crate) use self::scheduler::{Command, Scheduler};
-pub use self::sim::{AgentProperties, AlertHandler, DelayCause, Sim, SimCallback, SimOptions};
+pub use self::sim::{
+    AgentProperties, AlertHandler, DelayCause, GapAcceptance, ParkingSearchStrategy, Sim,
+    SimCallback, SimOptions, TransitSignalPriority,
+};
+pub use self::transit::transit_dwell_time;
 pub(crate) use self::transit::TransitSimState;
 pub use self::trips::TripMode;
 pub use self::trips::{CommutersVehiclesCounts, Person, PersonState, TripInfo, TripResult};
 pub(crate) use self::trips::{TripLeg, TripManager};
 
 mod analytics;
+mod battery;
+mod bike_share;
+mod emissions;
 mod events;
 mod make;
 mod mechanics;
+mod mode_choice;
 mod pandemic;
 mod recorder;
 mod render;
+mod ride_hail;
 mod router;
 mod scheduler;
 mod sim;
@@ -75,6 +97,8 @@ pub(crate) const MAX_CAR_LENGTH: Distance = Distance::const_meters(6.5);
 // Note this is more than MAX_CAR_LENGTH
 pub(crate) const BUS_LENGTH: Distance = Distance::const_meters(12.5);
 pub(crate) const LIGHT_RAIL_LENGTH: Distance = Distance::const_meters(60.0);
+// A typical box truck or large panel van
+pub(crate) const DELIVERY_VAN_LENGTH: Distance = Distance::const_meters(7.0);
 
 /// At all speeds (including at rest), cars must be at least this far apart, measured from front of
 /// one car to the back of the other.
@@ -104,6 +128,8 @@ impl fmt::Display for CarID {
             VehicleType::Bus => write!(f, "Bus #{}", self.id),
             VehicleType::Train => write!(f, "Train #{}", self.id),
             VehicleType::Bike => write!(f, "Bike #{}", self.id),
+            VehicleType::Delivery => write!(f, "Delivery van #{}", self.id),
+            VehicleType::RideHail => write!(f, "Ride-hail #{}", self.id),
         }
     }
 }
@@ -128,6 +154,7 @@ pub enum AgentID {
     Car(CarID),
     Pedestrian(PedestrianID),
     // TODO Rename...
+    /// Riding a bus, train, or ride-hail vehicle
     BusPassenger(PersonID, CarID),
 }
 
@@ -146,6 +173,8 @@ impl AgentID {
                 VehicleType::Bike => AgentType::Bike,
                 VehicleType::Bus => AgentType::Bus,
                 VehicleType::Train => AgentType::Train,
+                VehicleType::Delivery => AgentType::Delivery,
+                VehicleType::RideHail => AgentType::RideHail,
             },
             AgentID::Pedestrian(_) => AgentType::Pedestrian,
             AgentID::BusPassenger(_, _) => AgentType::TransitRider,
@@ -183,10 +212,13 @@ impl fmt::Display for AgentID {
 #[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
 pub enum AgentType {
     Car,
+    Delivery,
+    RideHail,
     Bike,
     Bus,
     Train,
     Pedestrian,
+    /// Passengers of buses, trains, and ride-hail vehicles
     TransitRider,
 }
 
@@ -194,6 +226,8 @@ impl AgentType {
     pub fn all() -> Vec<AgentType> {
         vec![
             AgentType::Car,
+            AgentType::Delivery,
+            AgentType::RideHail,
             AgentType::Bike,
             AgentType::Bus,
             AgentType::Train,
@@ -205,6 +239,8 @@ impl AgentType {
     pub fn noun(self) -> &'static str {
         match self {
             AgentType::Car => "Car",
+            AgentType::Delivery => "Delivery van",
+            AgentType::RideHail => "Ride-hail vehicle",
             AgentType::Bike => "Bike",
             AgentType::Bus => "Bus",
             AgentType::Train => "Train",
@@ -216,6 +252,8 @@ impl AgentType {
     pub fn plural_noun(self) -> &'static str {
         match self {
             AgentType::Car => "cars",
+            AgentType::Delivery => "delivery vans",
+            AgentType::RideHail => "ride-hail vehicles",
             AgentType::Bike => "bikes",
             AgentType::Bus => "buses",
             AgentType::Train => "trains",
@@ -227,8 +265,9 @@ impl AgentType {
     pub fn ongoing_verb(self) -> &'static str {
         match self {
             AgentType::Car => "driving",
+            AgentType::Delivery => "delivering",
             AgentType::Bike => "biking",
-            AgentType::Bus | AgentType::Train => unreachable!(),
+            AgentType::RideHail | AgentType::Bus | AgentType::Train => unreachable!(),
             AgentType::Pedestrian => "walking",
             AgentType::TransitRider => "riding transit",
         }
@@ -287,6 +326,8 @@ pub enum VehicleType {
     Bus,
     Train,
     Bike,
+    Delivery,
+    RideHail,
 }
 
 impl fmt::Display for VehicleType {
@@ -296,6 +337,8 @@ impl fmt::Display for VehicleType {
             VehicleType::Bus => write!(f, "bus"),
             VehicleType::Train => write!(f, "train"),
             VehicleType::Bike => write!(f, "bike"),
+            VehicleType::Delivery => write!(f, "delivery van"),
+            VehicleType::RideHail => write!(f, "ride-hail vehicle"),
         }
     }
 }
@@ -307,6 +350,8 @@ impl VehicleType {
             VehicleType::Bus => PathConstraints::Bus,
             VehicleType::Train => PathConstraints::Train,
             VehicleType::Bike => PathConstraints::Bike,
+            VehicleType::Delivery => PathConstraints::Car,
+            VehicleType::RideHail => PathConstraints::Car,
         }
     }
 
@@ -316,6 +361,8 @@ impl VehicleType {
             VehicleType::Bus => true,
             VehicleType::Train => true,
             VehicleType::Bike => false,
+            VehicleType::Delivery => false,
+            VehicleType::RideHail => false,
         }
     }
 }
@@ -327,6 +374,10 @@ pub struct Vehicle {
     pub vehicle_type: VehicleType,
     pub length: Distance,
     pub max_speed: Option<Speed>,
+    /// Only set for buses and trains.
+    pub passenger_capacity: Option<TransitCapacity>,
+    /// Only set for electric cars.
+    pub battery: Option<Battery>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -334,6 +385,10 @@ pub struct VehicleSpec {
     pub vehicle_type: VehicleType,
     pub length: Distance,
     pub max_speed: Option<Speed>,
+    /// Only set for buses and trains.
+    pub passenger_capacity: Option<TransitCapacity>,
+    /// Only set for electric cars.
+    pub battery: Option<Battery>,
 }
 
 impl VehicleSpec {
@@ -345,6 +400,8 @@ impl VehicleSpec {
             vehicle_type: self.vehicle_type,
             length: self.length,
             max_speed: self.max_speed,
+            passenger_capacity: self.passenger_capacity,
+            battery: self.battery,
         }
     }
 }
@@ -358,6 +415,26 @@ pub enum ParkingSpot {
     Lot(ParkingLotID, usize),
 }
 
+impl ParkingSpot {
+    /// Private spots in buildings are always free.
+    pub fn pricing(&self, map: &Map) -> ParkingPricing {
+        match self {
+            ParkingSpot::Onstreet(l, _) => map.get_l(*l).parking_pricing,
+            ParkingSpot::Offstreet(_, _) => ParkingPricing::free(),
+            ParkingSpot::Lot(pl, _) => map.get_pl(*pl).pricing,
+        }
+    }
+
+    /// Private spots in buildings never have chargers.
+    pub fn has_charger(&self, map: &Map) -> bool {
+        match self {
+            ParkingSpot::Onstreet(l, idx) => *idx < map.get_l(*l).charging_spots,
+            ParkingSpot::Offstreet(_, _) => false,
+            ParkingSpot::Lot(pl, idx) => *idx < map.get_pl(*pl).charging_spots,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ParkedCar {
     pub vehicle: Vehicle,
@@ -369,12 +446,15 @@ pub struct ParkedCar {
 pub(crate) enum DrivingGoal {
     ParkNear(BuildingID),
     Border(IntersectionID, LaneID),
+    /// Stop near a building for some time, then continue to the next leg of the trip. Only
+    /// delivery vans use this.
+    Deliver(BuildingID, Duration),
 }
 
 impl DrivingGoal {
     pub fn goal_pos(&self, constraints: PathConstraints, map: &Map) -> Option<Position> {
         match self {
-            DrivingGoal::ParkNear(b) => match constraints {
+            DrivingGoal::ParkNear(b) | DrivingGoal::Deliver(b, _) => match constraints {
                 PathConstraints::Car => {
                     let driving_lane = map.find_driving_lane_near_building(*b);
                     let sidewalk_pos = map.get_b(*b).sidewalk_pos;
@@ -405,6 +485,7 @@ impl DrivingGoal {
             DrivingGoal::Border(i, last_lane) => {
                 Router::end_at_border(owner, path, map.get_l(*last_lane).length(), *i)
             }
+            DrivingGoal::Deliver(_, dwell) => Router::stop_at_curb(owner, path, *dwell),
         }
     }
 }
@@ -0,0 +1,96 @@
+//! Goods movement: delivery vans leave a depot in the morning, stop at a handful of buildings to
+//! drop off packages, then return to the depot.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+
+use geom::{Duration, Time};
+use map_model::{BuildingID, BuildingType, Map};
+
+use crate::{
+    DeliveryStop, IndividTrip, PersonSpec, ScenarioGenerator, TripEndpoint, TripMode, TripPurpose,
+};
+
+impl ScenarioGenerator {
+    /// Creates one person per tour, driving a delivery van from a commercial building acting as a
+    /// depot. Stops are picked randomly and visited in a greedy nearest-neighbor order.
+    pub fn delivery_tours(
+        map: &Map,
+        num_tours: usize,
+        stops_per_tour: usize,
+        rng: &mut XorShiftRng,
+    ) -> Vec<PersonSpec> {
+        // Vans need to be able to reach every stop
+        let reachable: Vec<BuildingID> = map
+            .all_buildings()
+            .iter()
+            .filter(|b| b.driving_connection(map).is_some())
+            .map(|b| b.id)
+            .collect();
+        if reachable.len() < 2 || stops_per_tour == 0 {
+            return Vec::new();
+        }
+        let mut depots: Vec<BuildingID> = reachable
+            .iter()
+            .filter(|b| matches!(map.get_b(**b).bldg_type, BuildingType::Commercial(_)))
+            .cloned()
+            .collect();
+        if depots.is_empty() {
+            depots = reachable.clone();
+        }
+
+        let mut people = Vec::new();
+        for _ in 0..num_tours {
+            let depot = *depots.choose(rng).unwrap();
+            let mut stops: Vec<BuildingID> = reachable
+                .choose_multiple(rng, stops_per_tour + 1)
+                .filter(|b| **b != depot)
+                .take(stops_per_tour)
+                .cloned()
+                .collect();
+            order_stops(depot, &mut stops, map);
+
+            let mut trip = IndividTrip::new(
+                rand_time(
+                    rng,
+                    Time::START_OF_DAY + Duration::hours(7),
+                    Time::START_OF_DAY + Duration::hours(10),
+                ),
+                TripPurpose::Delivery,
+                TripEndpoint::Bldg(depot),
+                TripEndpoint::Bldg(depot),
+                TripMode::Drive,
+            );
+            trip.delivery_stops = stops
+                .into_iter()
+                .map(|bldg| DeliveryStop {
+                    bldg,
+                    duration: Duration::seconds(rng.gen_range(120.0..600.0)),
+                })
+                .collect();
+            people.push(PersonSpec {
+                orig_id: None,
+                trips: vec![trip],
+            });
+        }
+        people
+    }
+}
+
+/// Starting from the depot, always head to the closest stop not yet visited.
+fn order_stops(depot: BuildingID, stops: &mut [BuildingID], map: &Map) {
+    let mut current = map.get_b(depot).label_center;
+    for i in 0..stops.len() {
+        let next = (i..stops.len())
+            .min_by_key(|j| map.get_b(stops[*j]).label_center.dist_to(current))
+            .unwrap();
+        stops.swap(i, next);
+        current = map.get_b(stops[i]).label_center;
+    }
+}
+
+fn rand_time(rng: &mut XorShiftRng, low: Time, high: Time) -> Time {
+    assert!(high > low);
+    Time::START_OF_DAY + Duration::seconds(rng.gen_range(low.inner_seconds()..high.inner_seconds()))
+}
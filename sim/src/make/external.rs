@@ -188,8 +188,10 @@ impl MapBorders {
     ) {
         match mode {
             TripMode::Walk | TripMode::Transit => (&self.incoming_walking, &self.outgoing_walking),
-            TripMode::Drive => (&self.incoming_driving, &self.outgoing_driving),
-            TripMode::Bike => (&self.incoming_biking, &self.outgoing_biking),
+            TripMode::Drive | TripMode::RideHail => {
+                (&self.incoming_driving, &self.outgoing_driving)
+            }
+            TripMode::Bike | TripMode::BikeShare => (&self.incoming_biking, &self.outgoing_biking),
         }
     }
 }
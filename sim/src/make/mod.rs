@@ -8,11 +8,14 @@ pub use self::external::{ExternalPerson, ExternalTrip, ExternalTripEndpoint, Map
 pub use self::generator::{BorderSpawnOverTime, ScenarioGenerator, SpawnOverTime};
 pub use self::load::SimFlags;
 pub use self::modifier::ScenarioModifier;
-pub use self::scenario::{IndividTrip, PersonSpec, Scenario, TripPurpose};
+pub use self::scenario::{DeliveryStop, IndividTrip, PersonSpec, Scenario, TripPurpose};
 pub use self::spawner::TripEndpoint;
-pub(crate) use self::spawner::{StartTripArgs, TripSpec};
+pub(crate) use self::spawner::{
+    ParkedCarAccess, StartTripArgs, TransitAccessOptions, TripSpec, MAX_WALK_TO_CAR,
+};
 
 mod activity_model;
+mod delivery;
 mod external;
 mod generator;
 mod load;
@@ -2,11 +2,14 @@ use std::collections::BTreeSet;
 
 use serde::{Deserialize, Serialize};
 
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
 use abstutil::Timer;
 use geom::{Duration, Time};
 use map_model::Map;
 
-use crate::{Scenario, TripMode};
+use crate::{Scenario, ScenarioGenerator, TripMode};
 
 /// Transforms an existing Scenario before instantiating it.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
@@ -21,6 +24,11 @@ pub enum ScenarioModifier {
     },
     /// Scenario name
     AddExtraTrips(String),
+    /// Add people driving delivery vans from depots, each visiting some buildings
+    AddDeliveryTours {
+        num_tours: usize,
+        stops_per_tour: usize,
+    },
 }
 
 impl ScenarioModifier {
@@ -53,6 +61,10 @@ impl ScenarioModifier {
                         if trip.depart < departure_filter.0 || trip.depart > departure_filter.1 {
                             continue;
                         }
+                        // Delivery vans don't have a choice
+                        if !trip.delivery_stops.is_empty() {
+                            continue;
+                        }
                         if !from_modes.contains(&trip.mode) {
                             continue;
                         }
@@ -84,6 +96,21 @@ impl ScenarioModifier {
                 }
                 s
             }
+            ScenarioModifier::AddDeliveryTours {
+                num_tours,
+                stops_per_tour,
+            } => {
+                let mut rng = XorShiftRng::seed_from_u64(42);
+                for mut p in
+                    ScenarioGenerator::delivery_tours(map, *num_tours, *stops_per_tour, &mut rng)
+                {
+                    for trip in &mut p.trips {
+                        trip.modified = true;
+                    }
+                    s.people.push(p);
+                }
+                s
+            }
         }
     }
 
@@ -105,6 +132,13 @@ impl ScenarioModifier {
                 to_mode.map(|m| m.verb())
             ),
             ScenarioModifier::AddExtraTrips(name) => format!("Add extra trips from {}", name),
+            ScenarioModifier::AddDeliveryTours {
+                num_tours,
+                stops_per_tour,
+            } => format!(
+                "Add {} delivery tours, each with {} stops",
+                num_tours, stops_per_tour
+            ),
         }
     }
 }
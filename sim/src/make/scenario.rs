@@ -9,13 +9,13 @@ use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
 use abstutil::{prettyprint_usize, Counter, Timer};
-use geom::{Distance, Speed, Time};
+use geom::{Distance, Duration, Speed, Time};
 use map_model::{BuildingID, Map, OffstreetParking, RoadID};
 
 use crate::make::fork_rng;
 use crate::{
     OrigPersonID, ParkingSpot, Sim, StartTripArgs, TripEndpoint, TripInfo, TripMode, Vehicle,
-    VehicleSpec, VehicleType, BIKE_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH,
+    VehicleSpec, VehicleType, BIKE_LENGTH, DELIVERY_VAN_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH,
 };
 
 /// A Scenario describes all the input to a simulation. Usually a scenario covers one day.
@@ -49,6 +49,9 @@ pub struct IndividTrip {
     pub cancelled: bool,
     /// Did a ScenarioModifier affect this?
     pub modified: bool,
+    /// Delivery vans visit these buildings in order, between the origin and destination. Only
+    /// driving trips can have stops, and the trip will use a delivery van instead of a car.
+    pub delivery_stops: Vec<DeliveryStop>,
 }
 
 impl IndividTrip {
@@ -67,10 +70,19 @@ impl IndividTrip {
             purpose,
             cancelled: false,
             modified: false,
+            delivery_stops: Vec::new(),
         }
     }
 }
 
+/// One stop on a delivery tour
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeliveryStop {
+    pub bldg: BuildingID,
+    /// How long the van stays to load or unload
+    pub duration: Duration,
+}
+
 /// Lifted from Seattle's Soundcast model, but seems general enough to use anyhere.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum TripPurpose {
@@ -85,6 +97,7 @@ pub enum TripPurpose {
     Recreation,
     Medical,
     ParkAndRideTransfer,
+    Delivery,
 }
 
 impl fmt::Display for TripPurpose {
@@ -105,6 +118,7 @@ impl fmt::Display for TripPurpose {
                 TripPurpose::Recreation => "recreation",
                 TripPurpose::Medical => "medical",
                 TripPurpose::ParkAndRideTransfer => "park-and-ride transfer",
+                TripPurpose::Delivery => "delivery",
             }
         )
     }
@@ -174,6 +188,7 @@ impl Scenario {
                         } else {
                             None
                         },
+                        delivery_stops: trip.delivery_stops.clone(),
                     },
                     StartTripArgs {
                         retry_if_no_room,
@@ -213,6 +228,18 @@ impl Scenario {
             vehicle_type: VehicleType::Car,
             length,
             max_speed: None,
+            passenger_capacity: None,
+            battery: None,
+        }
+    }
+
+    fn delivery_van() -> VehicleSpec {
+        VehicleSpec {
+            vehicle_type: VehicleType::Delivery,
+            length: DELIVERY_VAN_LENGTH,
+            max_speed: None,
+            passenger_capacity: None,
+            battery: None,
         }
     }
 
@@ -226,6 +253,8 @@ impl Scenario {
             vehicle_type: VehicleType::Bike,
             length: BIKE_LENGTH,
             max_speed,
+            passenger_capacity: None,
+            battery: None,
         }
     }
 
@@ -311,7 +340,13 @@ fn seed_parked_cars(
         BTreeMap::new();
     for spot in sim.get_all_parking_spots().1 {
         let (r, restriction) = match spot {
-            ParkingSpot::Onstreet(l, _) => (map.get_l(l).parent, None),
+            ParkingSpot::Onstreet(l, _) => {
+                // Loading zones are only for delivery vans stopping briefly
+                if map.get_l(l).is_loading_zone() {
+                    continue;
+                }
+                (map.get_l(l).parent, None)
+            }
             ParkingSpot::Offstreet(b, _) => (
                 map.get_l(map.get_b(b).sidewalk()).parent,
                 match map.get_b(b).parking {
@@ -435,7 +470,8 @@ impl PersonSpec {
         }
 
         for trip in &self.trips {
-            if trip.origin == trip.destination {
+            // A delivery tour can return to its depot
+            if trip.origin == trip.destination && trip.delivery_stops.is_empty() {
                 bail!(
                     "Person ({:?}) has a trip from/to the same place: {:?}",
                     self.orig_id,
@@ -466,7 +502,9 @@ impl PersonSpec {
         // TODO If the trip is cancelled, this should be affected...
         for trip in &self.trips {
             let use_for_trip = match trip.mode {
-                TripMode::Walk | TripMode::Transit => None,
+                TripMode::Walk | TripMode::Transit | TripMode::RideHail | TripMode::BikeShare => {
+                    None
+                }
                 TripMode::Bike => {
                     if bike_idx.is_none() {
                         bike_idx = Some(vehicle_specs.len());
@@ -479,18 +517,27 @@ impl PersonSpec {
                         TripEndpoint::Bldg(b) => Some(b),
                         _ => None,
                     };
+                    let need_van = !trip.delivery_stops.is_empty();
 
-                    // Any available cars in the right spot?
+                    // Any available cars of the right type in the right spot?
                     let idx = if let Some(idx) = car_locations
                         .iter()
-                        .find(|(_, parked_at)| *parked_at == need_parked_at)
+                        .find(|(idx, parked_at)| {
+                            *parked_at == need_parked_at
+                                && (vehicle_specs[*idx].vehicle_type == VehicleType::Delivery)
+                                    == need_van
+                        })
                         .map(|(idx, _)| *idx)
                     {
                         idx
                     } else {
                         // Need a new car, starting in the right spot
                         let idx = vehicle_specs.len();
-                        vehicle_specs.push(Scenario::rand_car(rng));
+                        vehicle_specs.push(if need_van {
+                            Scenario::delivery_van()
+                        } else {
+                            Scenario::rand_car(rng)
+                        });
                         if let Some(b) = need_parked_at {
                             cars_initially_parked_at.push((idx, b));
                         }
@@ -1,15 +1,32 @@
 //! Intermediate structures used to instantiate a Scenario. Badly needs simplification:
 //! https://github.com/a-b-street/abstreet/issues/258
 
+use std::collections::BTreeSet;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use geom::Pt2D;
+use geom::{Distance, Duration, Pt2D};
 use map_model::{
     BuildingID, BusRouteID, BusStopID, IntersectionID, Map, PathConstraints, PathRequest, Position,
+    TransitAccess, MAX_WALKING_SPEED,
+};
+
+use crate::{
+    CarID, DrivingGoal, SidewalkSpot, TripLeg, TripMode, VehicleType, MAX_WALK_TO_DOCK, SPAWN_DIST,
 };
 
-use crate::{CarID, DrivingGoal, SidewalkSpot, TripLeg, TripMode, VehicleType, SPAWN_DIST};
+/// Only consider driving or biking to this many of the closest stops.
+const MAX_ACCESS_STOPS: usize = 3;
+/// A car parked further than this from the start of a transit trip won't be used to reach the
+/// first stop, but might be used to finish the trip from the last one.
+pub(crate) const MAX_WALK_TO_CAR: Distance = Distance::const_meters(800.0);
+/// How much worse than the travel time is it to find parking at a stop?
+const PARK_AND_RIDE_PENALTY: Duration = Duration::const_seconds(5.0 * 60.0);
+/// How much worse than the travel time is it to lock up a bike at a stop?
+const BIKE_AND_RIDE_PENALTY: Duration = Duration::const_seconds(2.0 * 60.0);
+/// How much worse than the travel time is it to check out and return a shared vehicle?
+const BIKE_SHARE_PENALTY: Duration = Duration::const_seconds(60.0);
 
 /// We need to remember a few things from scenario instantiation that're used for starting the
 /// trip.
@@ -56,9 +73,78 @@ pub(crate) enum TripSpec {
         route: BusRouteID,
         stop1: BusStopID,
         maybe_stop2: Option<BusStopID>,
+        /// How to reach stop1 and leave stop2
+        access: TransitLeg,
+        egress: TransitLeg,
+    },
+    UsingRideHail {
+        start: BuildingID,
+        goal: BuildingID,
+        /// Where the vehicle stops at the curb outside each building
+        pickup: Position,
+        dropoff: Position,
+    },
+    UsingBikeShare {
+        start: BuildingID,
+        goal: BuildingID,
+        /// Where to pick up and return the shared vehicle
+        from_dock: BuildingID,
+        to_dock: BuildingID,
     },
 }
 
+/// How somebody gets to the first stop of a transit trip, or from the last one to their
+/// destination.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub(crate) enum TransitLeg {
+    Walk,
+    /// Walk to a parked car, then drive it and park near a building
+    Drive(CarID, BuildingID),
+    /// Walk to a bike rack, then bike and lock up near a building. Only for access, since the bike
+    /// is left at the first stop.
+    Bike(CarID, SidewalkSpot, BuildingID),
+    /// Ride a shared vehicle between two docks
+    BikeShare(BuildingID, BuildingID),
+}
+
+/// Besides walking, the ways somebody could get to and from transit.
+#[derive(Default)]
+pub(crate) struct TransitAccessOptions {
+    pub car: Option<ParkedCarAccess>,
+    pub bike: Option<CarID>,
+    pub bike_share: bool,
+}
+
+/// A parked car somebody could drive to or from transit.
+pub(crate) struct ParkedCarAccess {
+    pub car: CarID,
+    /// Where to walk to reach the car
+    pub sidewalk_pos: Position,
+    /// Where the car starts driving
+    pub driving_pos: Position,
+}
+
+impl TransitLeg {
+    /// The legs before walking to the first stop, or after leaving the last one
+    fn to_legs(&self, map: &Map) -> Vec<TripLeg> {
+        match self {
+            TransitLeg::Walk => Vec::new(),
+            TransitLeg::Drive(car, b) => vec![
+                TripLeg::Walk(SidewalkSpot::deferred_parking_spot()),
+                TripLeg::Drive(*car, DrivingGoal::ParkNear(*b)),
+            ],
+            TransitLeg::Bike(bike, rack, b) => vec![
+                TripLeg::Walk(rack.clone()),
+                TripLeg::Drive(*bike, DrivingGoal::ParkNear(*b)),
+            ],
+            TransitLeg::BikeShare(from_dock, to_dock) => vec![
+                TripLeg::Walk(SidewalkSpot::bike_rack(*from_dock, map).unwrap()),
+                TripLeg::BikeShare(*from_dock, *to_dock),
+            ],
+        }
+    }
+}
+
 impl TripSpec {
     pub fn into_plan(self, map: &Map) -> (TripSpec, Vec<TripLeg>) {
         // TODO We'll want to repeat this validation when we spawn stuff later for a second leg...
@@ -112,11 +198,8 @@ impl TripSpec {
             TripSpec::UsingParkedCar { car, goal, .. } => {
                 legs.push(TripLeg::Walk(SidewalkSpot::deferred_parking_spot()));
                 legs.push(TripLeg::Drive(*car, goal.clone()));
-                match goal {
-                    DrivingGoal::ParkNear(b) => {
-                        legs.push(TripLeg::Walk(SidewalkSpot::building(*b, map)));
-                    }
-                    DrivingGoal::Border(_, _) => {}
+                if let DrivingGoal::ParkNear(b) = goal {
+                    legs.push(TripLeg::Walk(SidewalkSpot::building(*b, map)));
                 }
             }
             TripSpec::JustWalking { start, goal, .. } => {
@@ -141,6 +224,8 @@ impl TripSpec {
                             goal,
                         })
                     }
+                    // Only delivery stops use this, and they're never the final goal
+                    DrivingGoal::Deliver(_, _) => unreachable!(),
                 };
 
                 if let Some(start_spot) = SidewalkSpot::bike_rack(*start, map) {
@@ -165,11 +250,8 @@ impl TripSpec {
 
                     legs.push(TripLeg::Walk(start_spot));
                     legs.push(TripLeg::Drive(*bike, goal.clone()));
-                    match goal {
-                        DrivingGoal::ParkNear(b) => {
-                            legs.push(TripLeg::Walk(SidewalkSpot::building(*b, map)));
-                        }
-                        DrivingGoal::Border(_, _) => {}
+                    if let DrivingGoal::ParkNear(b) = goal {
+                        legs.push(TripLeg::Walk(SidewalkSpot::building(*b, map)));
                     }
                 } else if let Some(plan) = backup_plan {
                     info!("Can't start biking from {}. Walking instead", start);
@@ -190,17 +272,52 @@ impl TripSpec {
                 stop1,
                 maybe_stop2,
                 goal,
+                access,
+                egress,
                 ..
             } => {
-                let walk_to = SidewalkSpot::bus_stop(*stop1, map);
+                legs.extend(access.to_legs(map));
+                legs.push(TripLeg::Walk(SidewalkSpot::bus_stop(*stop1, map)));
                 if let Some(stop2) = maybe_stop2 {
-                    legs = vec![
-                        TripLeg::Walk(walk_to),
-                        TripLeg::RideBus(*route, Some(*stop2)),
-                        TripLeg::Walk(goal.clone()),
-                    ];
+                    legs.push(TripLeg::RideBus(*route, Some(*stop2)));
+                    legs.extend(egress.to_legs(map));
+                    legs.push(TripLeg::Walk(goal.clone()));
                 } else {
-                    legs = vec![TripLeg::Walk(walk_to), TripLeg::RideBus(*route, None)];
+                    legs.push(TripLeg::RideBus(*route, None));
+                }
+            }
+            TripSpec::UsingRideHail { goal, .. } => {
+                legs.push(TripLeg::RideHail(*goal));
+            }
+            TripSpec::UsingBikeShare {
+                start,
+                goal,
+                from_dock,
+                to_dock,
+            } => {
+                match (
+                    SidewalkSpot::bike_rack(*from_dock, map),
+                    SidewalkSpot::bike_rack(*to_dock, map),
+                ) {
+                    (Some(spot1), Some(spot2))
+                        if spot1.sidewalk_pos.lane() != spot2.sidewalk_pos.lane() =>
+                    {
+                        legs.push(TripLeg::Walk(spot1));
+                        legs.push(TripLeg::BikeShare(*from_dock, *to_dock));
+                        legs.push(TripLeg::Walk(SidewalkSpot::building(*goal, map)));
+                    }
+                    _ => {
+                        info!(
+                            "Bike-share trip from {} to {} will just walk; the docks are on the \
+                             same sidewalk or unreachable",
+                            start, goal
+                        );
+                        return TripSpec::JustWalking {
+                            start: SidewalkSpot::building(*start, map),
+                            goal: SidewalkSpot::building(*goal, map),
+                        }
+                        .into_plan(map);
+                    }
                 }
             }
         };
@@ -216,6 +333,7 @@ impl TripSpec {
         mode: TripMode,
         use_vehicle: Option<CarID>,
         retry_if_no_room: bool,
+        transit_access: &TransitAccessOptions,
         map: &Map,
     ) -> Result<TripSpec> {
         Ok(match mode {
@@ -274,15 +392,65 @@ impl TripSpec {
             TripMode::Transit => {
                 let start = from.start_sidewalk_spot(map)?;
                 let goal = to.end_sidewalk_spot(map)?;
-                if let Some((stop1, maybe_stop2, route)) =
-                    map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)
-                {
+                let mut access = vec![(
+                    TransitLeg::Walk,
+                    TransitAccess {
+                        sidewalk_pos: start.sidewalk_pos,
+                        cost: Duration::ZERO,
+                    },
+                )];
+                let mut egress = vec![(
+                    TransitLeg::Walk,
+                    TransitAccess {
+                        sidewalk_pos: goal.sidewalk_pos,
+                        cost: Duration::ZERO,
+                    },
+                )];
+                // A car parked near the start can be driven to the first stop. Otherwise, it
+                // might be waiting near some stop to finish the trip.
+                let (car_for_access, car_for_egress) = match transit_access.car {
+                    Some(ref parked)
+                        if parked.sidewalk_pos.pt(map).dist_to(from.pt(map)) <= MAX_WALK_TO_CAR =>
+                    {
+                        (Some(parked), None)
+                    }
+                    Some(ref parked)
+                        if parked.sidewalk_pos.pt(map).dist_to(to.pt(map)) > MAX_WALK_TO_CAR =>
+                    {
+                        (None, Some(parked))
+                    }
+                    _ => (None, None),
+                };
+                if let TripEndpoint::Bldg(b) = from {
+                    access.extend(transit_access_options(
+                        b,
+                        car_for_access,
+                        transit_access,
+                        map,
+                    ));
+                }
+                if let TripEndpoint::Bldg(b) = to {
+                    egress.extend(transit_egress_options(
+                        b,
+                        car_for_egress,
+                        transit_access,
+                        map,
+                    ));
+                }
+
+                let access_pts: Vec<TransitAccess> =
+                    access.iter().map(|(_, x)| x.clone()).collect();
+                let egress_pts: Vec<TransitAccess> =
+                    egress.iter().map(|(_, x)| x.clone()).collect();
+                if let Some(choice) = map.should_use_transit(&access_pts, &egress_pts) {
                     TripSpec::UsingTransit {
                         start,
                         goal,
-                        route,
-                        stop1,
-                        maybe_stop2,
+                        route: choice.route,
+                        stop1: choice.stop1,
+                        maybe_stop2: choice.maybe_stop2,
+                        access: access.swap_remove(choice.access).0,
+                        egress: egress.swap_remove(choice.egress).0,
                     }
                 } else {
                     //warn!("{:?} not actually using transit, because pathfinding didn't find any
@@ -290,6 +458,47 @@ impl TripSpec {
                     TripSpec::JustWalking { start, goal }
                 }
             }
+            TripMode::RideHail => match (from, to) {
+                (TripEndpoint::Bldg(start), TripEndpoint::Bldg(goal)) => {
+                    let pickup = map
+                        .get_b(start)
+                        .driving_connection(map)
+                        .ok_or_else(|| anyhow!("{} can't be reached by car", start))?
+                        .0;
+                    let dropoff = map
+                        .get_b(goal)
+                        .driving_connection(map)
+                        .ok_or_else(|| anyhow!("{} can't be reached by car", goal))?
+                        .0;
+                    TripSpec::UsingRideHail {
+                        start,
+                        goal,
+                        pickup,
+                        dropoff,
+                    }
+                }
+                _ => bail!("ride-hail trips must start and end at buildings"),
+            },
+            TripMode::BikeShare => match (from, to) {
+                (TripEndpoint::Bldg(start), TripEndpoint::Bldg(goal)) => {
+                    match (nearest_dock(start, map), nearest_dock(goal, map)) {
+                        (Some(from_dock), Some(to_dock)) if from_dock != to_dock => {
+                            TripSpec::UsingBikeShare {
+                                start,
+                                goal,
+                                from_dock,
+                                to_dock,
+                            }
+                        }
+                        // No docks close enough to be useful
+                        _ => TripSpec::JustWalking {
+                            start: SidewalkSpot::building(start, map),
+                            goal: SidewalkSpot::building(goal, map),
+                        },
+                    }
+                }
+                _ => bail!("bike-share trips must start and end at buildings"),
+            },
         })
     }
 }
@@ -318,7 +527,10 @@ impl TripEndpoint {
         let end = to.pos(mode, false, map)?;
         Some(match mode {
             TripMode::Walk | TripMode::Transit => PathRequest::walking(start, end),
-            TripMode::Bike => PathRequest::vehicle(start, end, PathConstraints::Bike),
+            TripMode::Bike | TripMode::BikeShare => {
+                PathRequest::vehicle(start, end, PathConstraints::Bike)
+            }
+            TripMode::RideHail => PathRequest::vehicle(start, end, PathConstraints::Car),
             // Only cars leaving from a building might turn out from the driveway in a special way
             TripMode::Drive => {
                 if matches!(from, TripEndpoint::Bldg(_)) {
@@ -377,7 +589,7 @@ impl TripEndpoint {
             })
             .ok()
             .map(|spot| spot.sidewalk_pos),
-            TripMode::Drive | TripMode::Bike => {
+            TripMode::Drive | TripMode::Bike | TripMode::RideHail | TripMode::BikeShare => {
                 if from {
                     match self {
                         // Fall through and use DrivingGoal also to start.
@@ -410,3 +622,202 @@ impl TripEndpoint {
         }
     }
 }
+
+/// The closest bike-share dock within walking distance of a building, if any.
+fn nearest_dock(b: BuildingID, map: &Map) -> Option<BuildingID> {
+    nearest_dock_to(map.get_b(b).polygon.center(), map)
+}
+
+fn nearest_dock_to(pt: Pt2D, map: &Map) -> Option<BuildingID> {
+    map.all_bike_docks()
+        .into_iter()
+        .filter(|dock| map.get_b(*dock).biking_connection(map).is_some())
+        .map(|dock| (dock, map.get_b(dock).polygon.center().dist_to(pt)))
+        .filter(|(_, dist)| *dist <= MAX_WALK_TO_DOCK)
+        .min_by_key(|(_, dist)| *dist)
+        .map(|(dock, _)| dock)
+}
+
+/// Besides walking, ways to reach transit from a building. Each one ends somewhere near a stop.
+fn transit_access_options(
+    start: BuildingID,
+    car: Option<&ParkedCarAccess>,
+    opts: &TransitAccessOptions,
+    map: &Map,
+) -> Vec<(TransitLeg, TransitAccess)> {
+    let mut results = Vec::new();
+    let start_pt = map.get_b(start).polygon.center();
+    let stops = candidate_stops(start_pt, map);
+
+    if let Some(parked) = car {
+        let walk = walking_time(start_pt, parked.sidewalk_pos.pt(map));
+        for stop in &stops {
+            if let Some(b) = bldg_near_stop(*stop, PathConstraints::Car, map) {
+                let end = DrivingGoal::ParkNear(b)
+                    .goal_pos(PathConstraints::Car, map)
+                    .unwrap();
+                if let Some(cost) = travel_cost(parked.driving_pos, end, PathConstraints::Car, map)
+                {
+                    results.push((
+                        TransitLeg::Drive(parked.car, b),
+                        TransitAccess {
+                            sidewalk_pos: map.get_b(b).sidewalk_pos,
+                            cost: walk + cost + PARK_AND_RIDE_PENALTY,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    if let (Some(bike), Some(rack)) = (opts.bike, SidewalkSpot::bike_rack(start, map)) {
+        let (bike_pos, _) = map.get_b(start).biking_connection(map).unwrap();
+        for stop in &stops {
+            if let Some(b) = bldg_near_stop(*stop, PathConstraints::Bike, map) {
+                let (end, sidewalk_pos) = map.get_b(b).biking_connection(map).unwrap();
+                if let Some(cost) = travel_cost(bike_pos, end, PathConstraints::Bike, map) {
+                    results.push((
+                        TransitLeg::Bike(bike, rack.clone(), b),
+                        TransitAccess {
+                            sidewalk_pos,
+                            cost: cost + BIKE_AND_RIDE_PENALTY,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    if opts.bike_share {
+        if let Some(from_dock) = nearest_dock(start, map) {
+            let walk = walking_time(start_pt, map.get_b(from_dock).polygon.center());
+            let (bike_pos, _) = map.get_b(from_dock).biking_connection(map).unwrap();
+            for to_dock in docks_near_stops(&stops, from_dock, map) {
+                let (end, sidewalk_pos) = map.get_b(to_dock).biking_connection(map).unwrap();
+                if let Some(cost) = travel_cost(bike_pos, end, PathConstraints::Bike, map) {
+                    results.push((
+                        TransitLeg::BikeShare(from_dock, to_dock),
+                        TransitAccess {
+                            sidewalk_pos,
+                            cost: walk + cost + BIKE_SHARE_PENALTY,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    results
+}
+
+/// Besides walking, ways to finish a transit trip at a building. Each one starts somewhere near a
+/// stop.
+fn transit_egress_options(
+    goal: BuildingID,
+    car: Option<&ParkedCarAccess>,
+    opts: &TransitAccessOptions,
+    map: &Map,
+) -> Vec<(TransitLeg, TransitAccess)> {
+    let mut results = Vec::new();
+    let goal_pt = map.get_b(goal).polygon.center();
+
+    if let Some(parked) = car {
+        let end = DrivingGoal::ParkNear(goal)
+            .goal_pos(PathConstraints::Car, map)
+            .unwrap();
+        if let Some(cost) = travel_cost(parked.driving_pos, end, PathConstraints::Car, map) {
+            results.push((
+                TransitLeg::Drive(parked.car, goal),
+                TransitAccess {
+                    sidewalk_pos: parked.sidewalk_pos,
+                    cost: cost + PARK_AND_RIDE_PENALTY,
+                },
+            ));
+        }
+    }
+
+    if opts.bike_share {
+        if let Some(to_dock) = nearest_dock(goal, map) {
+            let walk = walking_time(map.get_b(to_dock).polygon.center(), goal_pt);
+            let (end, _) = map.get_b(to_dock).biking_connection(map).unwrap();
+            let stops = candidate_stops(goal_pt, map);
+            for from_dock in docks_near_stops(&stops, to_dock, map) {
+                let (bike_pos, sidewalk_pos) = map.get_b(from_dock).biking_connection(map).unwrap();
+                if let Some(cost) = travel_cost(bike_pos, end, PathConstraints::Bike, map) {
+                    results.push((
+                        TransitLeg::BikeShare(from_dock, to_dock),
+                        TransitAccess {
+                            sidewalk_pos,
+                            cost: cost + walk + BIKE_SHARE_PENALTY,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    results
+}
+
+/// The stops somebody might drive or bike to or from: the closest stop along each route, limited to
+/// the few closest overall.
+fn candidate_stops(pt: Pt2D, map: &Map) -> Vec<BusStopID> {
+    let dist = |stop: BusStopID| map.get_bs(stop).sidewalk_pos.pt(map).dist_to(pt);
+    let mut stops: Vec<BusStopID> = map
+        .all_bus_routes()
+        .iter()
+        .filter_map(|r| r.stops.iter().copied().min_by_key(|s| dist(*s)))
+        .collect();
+    stops.sort_by_key(|s| (dist(*s), *s));
+    stops.dedup();
+    stops.truncate(MAX_ACCESS_STOPS);
+    stops
+}
+
+/// The building on the same road as a stop and closest to it where a car can park or a bike can
+/// lock up.
+fn bldg_near_stop(stop: BusStopID, constraints: PathConstraints, map: &Map) -> Option<BuildingID> {
+    let pos = map.get_bs(stop).sidewalk_pos;
+    let pt = pos.pt(map);
+    map.road_to_buildings(map.get_l(pos.lane()).parent)
+        .iter()
+        .filter(|b| {
+            let bldg = map.get_b(**b);
+            if constraints == PathConstraints::Car {
+                bldg.driving_connection(map).is_some()
+            } else {
+                bldg.biking_connection(map).is_some()
+            }
+        })
+        .min_by_key(|b| map.get_b(**b).polygon.center().dist_to(pt))
+        .copied()
+}
+
+/// The distinct bike-share docks within walking distance of some stops, besides one to exclude.
+fn docks_near_stops(stops: &[BusStopID], exclude: BuildingID, map: &Map) -> BTreeSet<BuildingID> {
+    stops
+        .iter()
+        .filter_map(|stop| nearest_dock_to(map.get_bs(*stop).sidewalk_pos.pt(map), map))
+        .filter(|dock| *dock != exclude)
+        .collect()
+}
+
+/// The cost of driving or biking between two positions, if it's possible and worthwhile.
+fn travel_cost(
+    start: Position,
+    end: Position,
+    constraints: PathConstraints,
+    map: &Map,
+) -> Option<Duration> {
+    // Biking to a different part of the same lane is silly
+    if start.lane() == end.lane() {
+        return None;
+    }
+    map.pathfind_v2(PathRequest::vehicle(start, end, constraints))
+        .ok()
+        .map(|path| path.get_cost())
+}
+
+fn walking_time(pt1: Pt2D, pt2: Pt2D) -> Duration {
+    pt1.dist_to(pt2) / MAX_WALKING_SPEED
+}
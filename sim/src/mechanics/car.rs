@@ -1,8 +1,8 @@
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Distance, Duration, PolyLine, Time, EPSILON_DIST};
+use geom::{Distance, Duration, PolyLine, Speed, Time, EPSILON_DIST};
 use map_model::{Direction, LaneID, Map, Traversable};
 
 use crate::{
@@ -10,6 +10,17 @@ use crate::{
     TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
 };
 
+/// How fast bikes go on a shared-use path when anybody is walking on it. This is 15 km/h, the
+/// courtesy limit commonly signposted for cyclists on shared paths. Not calibrated against any
+/// observations.
+const SHARED_USE_PASSING_SPEED: Speed = Speed::const_meters_per_second(4.2);
+/// Bikes slow down more as a shared-use path gets busier, but never below this. It's a little
+/// faster than most people walk, so a cyclist stuck behind pedestrians can still creep past.
+const SHARED_USE_MIN_SPEED: Speed = Speed::const_meters_per_second(1.5);
+/// For each person walking per 100m of shared-use path, how much the passing speed is divided by,
+/// beyond 1. With 10 people per 100m, bikes go half the passing speed.
+const SHARED_USE_SLOWDOWN_PER_PED_PER_100M: f64 = 0.1;
+
 /// Represents a single vehicle. Note "car" is a misnomer; it could also be a bus or bike.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct Car {
@@ -21,6 +32,11 @@ pub(crate) struct Car {
     pub trip_and_person: Option<(TripID, PersonID)>,
     pub started_at: Time,
     pub total_blocked_time: Duration,
+    /// When the vehicle started its current lane or turn. Used to estimate emissions.
+    pub step_started_at: Time,
+    /// The average speed over the previous lane or turn, or zero when starting from rest. Used to
+    /// estimate acceleration for emissions.
+    pub last_step_speed: Speed,
 
     /// In reverse order -- most recently left is first. The sum length of these must be >=
     /// vehicle.length.
@@ -33,7 +49,13 @@ pub(crate) struct Car {
 
 impl Car {
     /// Assumes the current head of the path is the thing to cross.
-    pub fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> CarState {
+    pub fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        map: &Map,
+        peds_on_shared_use: &BTreeMap<LaneID, usize>,
+    ) -> CarState {
         let dist_int = DistanceInterval::new_driving(
             start_dist,
             if self.router.last_step() {
@@ -42,7 +64,7 @@ impl Car {
                 self.router.head().get_polyline(map).length()
             },
         );
-        self.crossing_state_with_end_dist(dist_int, start_time, map)
+        self.crossing_state_with_end_dist(dist_int, start_time, map, peds_on_shared_use)
     }
 
     pub fn crossing_state_with_end_dist(
@@ -50,8 +72,9 @@ impl Car {
         dist_int: DistanceInterval,
         start_time: Time,
         map: &Map,
+        peds_on_shared_use: &BTreeMap<LaneID, usize>,
     ) -> CarState {
-        let (speed, percent_incline) = self
+        let (mut speed, percent_incline) = self
             .router
             .get_path()
             .current_step()
@@ -60,6 +83,17 @@ impl Car {
                 self.vehicle.vehicle_type.to_constraints(),
                 map,
             );
+        // Bikes sharing a path with pedestrians slow down to pass them. The more people per meter,
+        // the slower.
+        if let Traversable::Lane(l) = self.router.head() {
+            if let Some(peds) = peds_on_shared_use.get(&l) {
+                let per_100m = (*peds as f64) / (map.get_l(l).length().inner_meters() / 100.0);
+                let passing_speed = (SHARED_USE_PASSING_SPEED
+                    * (1.0 / (1.0 + SHARED_USE_SLOWDOWN_PER_PED_PER_100M * per_100m)))
+                    .max(SHARED_USE_MIN_SPEED);
+                speed = speed.min(passing_speed);
+            }
+        }
         let dt = (dist_int.end - dist_int.start) / speed;
         CarState::Crossing {
             time_int: TimeInterval::new(start_time, start_time + dt),
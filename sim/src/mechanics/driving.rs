@@ -3,20 +3,21 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_hashmap, serialize_hashmap, FixedMap, IndexableKey};
-use geom::{Distance, Duration, PolyLine, Time};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
 use map_model::{DrivingSide, IntersectionID, LaneID, Map, Path, PathStep, Position, Traversable};
 
+use crate::emissions;
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::queue::{Queue, QueueEntry, Queued};
 use crate::sim::Ctx;
 use crate::{
-    ActionAtEnd, AgentID, AgentProperties, CarID, CarStatus, Command, CreateCar, DelayCause,
-    DistanceInterval, DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSim,
-    ParkingSpot, PersonID, Problem, SimOptions, TimeInterval, TransitSimState, TripID, TripManager,
-    UnzoomedAgent, Vehicle, VehicleType, WalkingSimState, FOLLOWING_DISTANCE, MAX_CAR_LENGTH,
+    ActionAtEnd, AgentID, AgentProperties, Battery, CarID, CarStatus, Command, CreateCar,
+    DelayCause, DistanceInterval, DrawCarInput, EmissionsMethod, Event, IntersectionSimState,
+    ParkedCar, ParkingSearchStrategy, ParkingSim, ParkingSpot, PersonID, Problem, SimOptions,
+    TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent, Vehicle, VehicleType,
+    WalkingSimState, FOLLOWING_DISTANCE, MAX_CAR_LENGTH,
 };
 
-const TIME_TO_WAIT_AT_BUS_STOP: Duration = Duration::const_seconds(10.0);
 const TIME_TO_CHANGE_LANES: Duration = Duration::const_seconds(1.0);
 
 // TODO Do something else.
@@ -45,6 +46,8 @@ pub(crate) struct DrivingSimState {
 
     recalc_lanechanging: bool,
     handle_uber_turns: bool,
+    parking_search: ParkingSearchStrategy,
+    emissions: EmissionsMethod,
 
     time_to_unpark_onstreet: Duration,
     time_to_park_onstreet: Duration,
@@ -61,6 +64,8 @@ impl DrivingSimState {
             events: Vec::new(),
             recalc_lanechanging: opts.recalc_lanechanging,
             handle_uber_turns: opts.handle_uber_turns,
+            parking_search: opts.parking_search,
+            emissions: opts.emissions,
             waiting_to_spawn: BTreeMap::new(),
 
             time_to_unpark_onstreet: Duration::seconds(10.0),
@@ -185,6 +190,8 @@ impl DrivingSimState {
                 },
                 last_steps: VecDeque::new(),
                 started_at: now,
+                step_started_at: now,
+                last_step_speed: Speed::ZERO,
                 total_blocked_time: Duration::ZERO,
                 trip_and_person: params.trip_and_person,
                 wants_to_overtake: BTreeSet::new(),
@@ -233,10 +240,14 @@ impl DrivingSimState {
                         &car.vehicle,
                         ctx.parking,
                         ctx.map,
+                        self.parking_search,
                         car.trip_and_person,
                         &mut self.events,
                     ) {
-                        None | Some(ActionAtEnd::GotoLaneEnd) => {}
+                        // A ride-hail vehicle might be summoned to exactly where it's idling
+                        None
+                        | Some(ActionAtEnd::GotoLaneEnd)
+                        | Some(ActionAtEnd::DoublePark(_)) => {}
                         x => {
                             panic!(
                                 "Car with one-step route {:?} had unexpected result from \
@@ -262,7 +273,7 @@ impl DrivingSimState {
                     }
                 }
 
-                car.state = car.crossing_state(start_dist, now, ctx.map);
+                car.state = car.crossing_state(start_dist, now, ctx.map, ctx.peds_on_shared_use);
             }
             ctx.scheduler
                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -312,6 +323,10 @@ impl DrivingSimState {
         transit: &mut TransitSimState,
         walking: &mut WalkingSimState,
     ) {
+        if self.strand_if_depleted(id, now, ctx, trips) {
+            return;
+        }
+
         let mut need_distances = {
             let car = &self.cars[&id];
             match car.state {
@@ -357,6 +372,97 @@ impl DrivingSimState {
         }
     }
 
+    /// An electric car that doesn't have enough energy left to finish its current lane is stranded
+    /// at the end of it. The car is towed away and the trip is cancelled. Returns true if so.
+    fn strand_if_depleted(
+        &mut self,
+        id: CarID,
+        now: Time,
+        ctx: &mut Ctx,
+        trips: &mut TripManager,
+    ) -> bool {
+        let car = &self.cars[&id];
+        if !matches!(car.state, CarState::WaitingToAdvance { .. }) {
+            return false;
+        }
+        let (mut battery, trip) = match (car.vehicle.battery, car.trip_and_person) {
+            (Some(battery), Some((trip, _))) => (battery, trip),
+            _ => {
+                return false;
+            }
+        };
+        let current_step = car.router.get_path().current_step();
+        let (_, percent_incline) = current_step.max_speed_and_incline_along(
+            car.vehicle.max_speed,
+            car.vehicle.vehicle_type.to_constraints(),
+            ctx.map,
+        );
+        let current = car.router.head();
+        battery.drive(current.get_polyline(ctx.map).length(), percent_incline);
+        if !battery.is_depleted() {
+            return false;
+        }
+
+        self.events.push(Event::ProblemEncountered(
+            trip,
+            Problem::BatteryDepleted(current),
+        ));
+        let vehicle = self.delete_car(id, now, ctx);
+        trips.cancel_trip(
+            now,
+            trip,
+            format!("{} ran out of energy", id),
+            Some(vehicle),
+            ctx,
+        );
+        true
+    }
+
+    /// Account for the energy used and pollution emitted since the car started its current step,
+    /// having crossed some distance along it. Acceleration is estimated from the change in average
+    /// speed since the previous step.
+    fn record_emissions(&mut self, car: &mut Car, dist: Distance, now: Time, map: &Map) {
+        let (_, percent_incline) = car
+            .router
+            .get_path()
+            .current_step()
+            .max_speed_and_incline_along(
+                car.vehicle.max_speed,
+                car.vehicle.vehicle_type.to_constraints(),
+                map,
+            );
+        let duration = now - car.step_started_at;
+        let (speed, accel) = if duration == Duration::ZERO {
+            (car.last_step_speed, 0.0)
+        } else {
+            let speed = Speed::meters_per_second(dist.inner_meters() / duration.inner_seconds());
+            let accel = (speed.inner_meters_per_second()
+                - car.last_step_speed.inner_meters_per_second())
+                / duration.inner_seconds();
+            (speed, accel)
+        };
+        let mut emitted = emissions::evaluate(
+            self.emissions.model().as_ref(),
+            car.vehicle.vehicle_type,
+            car.vehicle.battery.is_some(),
+            dist,
+            duration,
+            accel,
+            percent_incline,
+        );
+        if let Some(ref mut battery) = car.vehicle.battery {
+            emitted.energy_kwh = battery.drive(dist, percent_incline);
+        }
+        self.events.push(Event::VehicleEmitted(
+            car.vehicle.id,
+            car.trip_and_person.map(|(t, _)| t),
+            car.router.head(),
+            emitted,
+        ));
+        car.step_started_at = now;
+        car.last_step_speed = speed;
+    }
+
     // If this returns true, we need to immediately run update_car_with_distances. If we don't,
     // then the car will briefly be Queued and might immediately become something else, which
     // affects how leaders update followers.
@@ -451,16 +557,25 @@ impl DrivingSimState {
                         &car.vehicle,
                         ctx.parking,
                         ctx.map,
+                        self.parking_search,
                         car.trip_and_person,
                         &mut self.events,
                     );
                 }
-                car.state = car.crossing_state(front, now, ctx.map);
+                car.state = car.crossing_state(front, now, ctx.map, ctx.peds_on_shared_use);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
             }
             CarState::WaitingToAdvance { blocked_since } => {
                 // 'car' is the leader.
+                if let Some(Traversable::Turn(banned)) = car.router.maybe_next() {
+                    if car.router.detour_around_ban(now, &car.vehicle, ctx.map) {
+                        ctx.intersections
+                            .cancel_request(AgentID::Car(car.vehicle.id), banned);
+                        self.events
+                            .push(Event::PathAmended(car.router.get_path().clone()));
+                    }
+                }
                 let from = car.router.head();
                 let goto = car.router.next();
                 assert!(from != goto);
@@ -500,15 +615,20 @@ impl DrivingSimState {
                 // We do NOT need to update the follower. If they were Queued, they'll remain that
                 // way, until laggy_head is None.
 
+                // Account for the energy used and pollution emitted on the step just finished
+                self.record_emissions(car, from.get_polyline(ctx.map).length(), now, ctx.map);
+
                 let last_step = car.router.advance(
                     &car.vehicle,
                     ctx.parking,
                     ctx.map,
+                    self.parking_search,
                     car.trip_and_person,
                     &mut self.events,
                 );
                 car.total_blocked_time += now - blocked_since;
-                car.state = car.crossing_state(Distance::ZERO, now, ctx.map);
+                car.state =
+                    car.crossing_state(Distance::ZERO, now, ctx.map, ctx.peds_on_shared_use);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.events.push(Event::AgentEntersTraversable(
@@ -521,6 +641,19 @@ impl DrivingSimState {
                         None
                     },
                 ));
+                // Transit vehicles may request priority at the next traffic signal
+                if car.vehicle.vehicle_type.is_transit() {
+                    if let Some(Traversable::Turn(t)) = car.router.maybe_next() {
+                        ctx.intersections.transit_vehicle_approaching(
+                            now,
+                            car.vehicle.id,
+                            transit.bus_route(car.vehicle.id),
+                            t,
+                            ctx.map,
+                            ctx.scheduler,
+                        );
+                    }
+                }
 
                 // Don't mark turn_finished until our back is out of the turn.
                 car.last_steps.push_front(last_step);
@@ -536,6 +669,7 @@ impl DrivingSimState {
                         ),
                         now,
                         ctx.map,
+                        ctx.peds_on_shared_use,
                     )
                     .get_end_time(),
                     Command::UpdateLaggyHead(car.vehicle.id),
@@ -614,14 +748,26 @@ impl DrivingSimState {
                     return true;
                 }
 
-                match car.router.maybe_handle_end(
+                let action = car.router.maybe_handle_end(
                     our_dist,
                     &car.vehicle,
                     ctx.parking,
                     ctx.map,
+                    self.parking_search,
                     car.trip_and_person,
                     &mut self.events,
+                );
+                if matches!(
+                    action,
+                    Some(ActionAtEnd::VanishAtBorder(_))
+                        | Some(ActionAtEnd::GiveUpOnParking)
+                        | Some(ActionAtEnd::StartParking(_))
+                        | Some(ActionAtEnd::StopBiking(_))
                 ) {
+                    // The vehicle won't finish another step, so account for the last one now
+                    self.record_emissions(car, our_dist, now, ctx.map);
+                }
+                match action {
                     Some(ActionAtEnd::VanishAtBorder(i)) => {
                         car.total_blocked_time += now - blocked_since;
                         // Don't do this for buses
@@ -670,7 +816,8 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::GotoLaneEnd) => {
                         car.total_blocked_time += now - blocked_since;
-                        car.state = car.crossing_state(our_dist, now, ctx.map);
+                        car.state =
+                            car.crossing_state(our_dist, now, ctx.map, ctx.peds_on_shared_use);
                         ctx.scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                         true
@@ -689,10 +836,12 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::BusAtStop) => {
                         car.total_blocked_time += now - blocked_since;
-                        if transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx) {
+                        if let Some(dwell_time) =
+                            transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx)
+                        {
                             car.state = CarState::IdlingAtStop(
                                 our_dist,
-                                TimeInterval::new(now, now + TIME_TO_WAIT_AT_BUS_STOP),
+                                TimeInterval::new(now, now + dwell_time),
                             );
                             ctx.scheduler
                                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -702,6 +851,26 @@ impl DrivingSimState {
                             false
                         }
                     }
+                    Some(ActionAtEnd::DoublePark(mut dwell_time)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        if car.vehicle.vehicle_type == VehicleType::RideHail {
+                            dwell_time = trips.ride_hail_vehicle_stopped(now, car.vehicle.id, ctx);
+                        } else {
+                            trips.van_double_parked(
+                                car.vehicle.id,
+                                car.router.head().as_lane(),
+                                dwell_time,
+                            );
+                        }
+                        // The vehicle stays in the queue, blocking the lane behind it.
+                        car.state = CarState::IdlingAtStop(
+                            our_dist,
+                            TimeInterval::new(now, now + dwell_time),
+                        );
+                        ctx.scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        true
+                    }
                     None => {
                         ctx.scheduler.push(
                             now + BLIND_RETRY_TO_REACH_END_DIST,
@@ -743,15 +912,50 @@ impl DrivingSimState {
                     spot,
                     car.total_blocked_time,
                     car.router.get_path().total_length(),
+                    car.router.is_charging_stop(),
                     ctx,
                 );
                 false
             }
             CarState::IdlingAtStop(dist, _) => {
-                car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
+                if car.vehicle.vehicle_type.is_transit() {
+                    let extra_dwell_time = transit.take_extra_dwell_time(car.vehicle.id);
+                    if extra_dwell_time > Duration::ZERO {
+                        car.state = CarState::IdlingAtStop(
+                            dist,
+                            TimeInterval::new(now, now + extra_dwell_time),
+                        );
+                        ctx.scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        return true;
+                    }
+                    car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
+                } else if car.vehicle.vehicle_type == VehicleType::RideHail {
+                    if let Some(router) = trips.ride_hail_vehicle_departing(
+                        now,
+                        car.vehicle.id,
+                        Position::new(car.router.head().as_lane(), dist),
+                        ctx,
+                    ) {
+                        car.router = router;
+                    } else {
+                        // Nothing to do; wait off-map for the next request
+                        return false;
+                    }
+                } else if let Some(router) = trips.van_finished_delivery(
+                    now,
+                    car.vehicle.id,
+                    Position::new(car.router.head().as_lane(), dist),
+                    ctx,
+                ) {
+                    car.router = router;
+                } else {
+                    // The rest of the tour was cancelled
+                    return false;
+                }
                 self.events
                     .push(Event::PathAmended(car.router.get_path().clone()));
-                car.state = car.crossing_state(dist, now, ctx.map);
+                car.state = car.crossing_state(dist, now, ctx.map, ctx.peds_on_shared_use);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
 
@@ -874,7 +1078,12 @@ impl DrivingSimState {
 
                     // Prevent them from jumping forwards.
                     follower.total_blocked_time += now - blocked_since;
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state = follower.crossing_state(
+                        follower_dist,
+                        now,
+                        ctx.map,
+                        ctx.peds_on_shared_use,
+                    );
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -884,7 +1093,12 @@ impl DrivingSimState {
                     // If the follower was still Crossing, they might not've been blocked by the
                     // leader yet. But recalculating their Crossing state isn't necessarily a no-op
                     // -- this could prevent them from suddenly warping past a blockage.
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state = follower.crossing_state(
+                        follower_dist,
+                        now,
+                        ctx.map,
+                        ctx.peds_on_shared_use,
+                    );
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -904,6 +1118,7 @@ impl DrivingSimState {
                         DistanceInterval::new_driving(follower_dist, ctx.map.get_l(to).length()),
                         now,
                         ctx.map,
+                        ctx.peds_on_shared_use,
                     ) {
                         CarState::Crossing {
                             time_int, dist_int, ..
@@ -975,6 +1190,7 @@ impl DrivingSimState {
                     ),
                     now,
                     ctx.map,
+                    ctx.peds_on_shared_use,
                 )
                 .get_end_time();
             // Sometimes due to rounding, retry_at will be exactly time, but we really need to
@@ -1156,6 +1372,7 @@ impl DrivingSimState {
             DistanceInterval::new_driving(front_target_queue, ctx.map.get_l(target_lane).length()),
             now,
             ctx.map,
+            ctx.peds_on_shared_use,
         ) {
             CarState::Crossing {
                 time_int, dist_int, ..
@@ -1502,6 +1719,10 @@ impl DrivingSimState {
         car.vehicle.owner
     }
 
+    pub fn get_battery(&self, id: CarID) -> Option<Battery> {
+        self.cars.get(&id)?.vehicle.battery
+    }
+
     pub fn target_lane_penalty(&self, l: LaneID) -> (usize, usize) {
         self.queues[&Traversable::Lane(l)].target_lane_penalty()
     }
@@ -1559,6 +1780,38 @@ impl DrivingSimState {
         }
     }
 
+    /// Simulates presence detectors covering the last `detector_len` of every lane leading into
+    /// an intersection. Returns the lanes with some vehicle currently over the detector.
+    pub fn occupied_loop_detectors(
+        &self,
+        now: Time,
+        i: IntersectionID,
+        detector_len: Distance,
+        map: &Map,
+    ) -> BTreeSet<LaneID> {
+        let mut occupied = BTreeSet::new();
+        for l in &map.get_i(i).incoming_lanes {
+            let queue = match self.queues.get(&Traversable::Lane(*l)) {
+                Some(q) => q,
+                None => continue,
+            };
+            if queue.get_active_cars().is_empty() {
+                continue;
+            }
+            let detector_start = (queue.geom_len - detector_len).max(Distance::ZERO);
+            if queue
+                .get_car_positions(now, &self.cars, &self.queues)
+                .into_iter()
+                .any(|entry| {
+                    matches!(entry.member, Queued::Vehicle(_)) && entry.front >= detector_start
+                })
+            {
+                occupied.insert(*l);
+            }
+        }
+        occupied
+    }
+
     pub fn debug_queue_lengths(&self, l: LaneID) -> Option<(Distance, Distance)> {
         let queue = self.queues.get(&Traversable::Lane(l))?;
         Some((queue.reserved_length, queue.geom_len))
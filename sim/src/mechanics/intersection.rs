@@ -3,20 +3,25 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, prettyprint_usize, serialize_btreemap, FixedMap};
-use geom::{Duration, Time};
+use geom::{Distance, Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, StageType,
-    Traversable, TurnID, TurnPriority, TurnType, UberTurn,
+    BusRouteID, ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map,
+    MovementID, StageType, Traversable, TurnID, TurnPriority, TurnType, UberTurn,
 };
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::Queue;
 use crate::{
-    AgentID, AlertLocation, CarID, Command, DelayCause, Event, Scheduler, SimOptions, Speed,
+    AgentID, AlertLocation, CarID, Command, DelayCause, Event, GapAcceptance, Scheduler,
+    SimOptions, Speed, TransitPriorityType, TransitSignalPriority,
 };
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+// When giving priority to transit, extend stages by this much at a time, and never end a stage
+// before it's lasted this long.
+const TRANSIT_PRIORITY_EXTENSION_STEP: Duration = Duration::const_seconds(3.0);
+const TRANSIT_PRIORITY_MIN_GREEN: Duration = Duration::const_seconds(5.0);
 
 /// Manages conflicts at intersections. When an agent has reached the end of a lane, they call
 /// maybe_start_turn to make a Request. Based on the intersection type (stop sign, traffic signal,
@@ -34,6 +39,12 @@ pub(crate) struct IntersectionSimState {
     break_turn_conflict_cycles: bool,
     handle_uber_turns: bool,
     disable_turn_conflicts: bool,
+    // If present, variable stages are actuated by detectors covering this distance upstream of
+    // the stop line.
+    loop_detectors: Option<Distance>,
+    transit_signal_priority: Option<TransitSignalPriority>,
+    // If absent, only vehicles entering roundabouts look for gaps, using the default critical gap.
+    gap_acceptance: Option<GapAcceptance>,
     // (x, y) means x is blocked by y. It's a many-to-many relationship. TODO Better data
     // structure.
     blocked_by: BTreeSet<(CarID, CarID)>,
@@ -68,16 +79,29 @@ struct State {
     uber_turn_neighbors: Vec<IntersectionID>,
 
     signal: Option<SignalState>,
+    // Crosswalk movements where a pedestrian has pushed the call button, but hasn't been served
+    // yet. Only used with loop detectors.
+    ped_calls: BTreeSet<MovementID>,
+    // Transit vehicles approaching a traffic signal that've requested priority
+    transit_requests: BTreeMap<CarID, (BusRouteID, MovementID)>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct SignalState {
+    // The time-of-day plan currently in effect
+    current_plan: usize,
     // The current stage of the signal, zero based
     current_stage: usize,
     // The time when the signal is checked for advancing
     stage_ends_at: Time,
-    // The number of times a variable signal has been extended during the current stage.
-    extensions_count: usize,
+    // How long a variable stage has been extended past its minimum duration
+    extended: Duration,
+    // The last time a vehicle arrived for a protected movement of the current stage. Each arrival
+    // resets the passage timer of a variable stage.
+    last_arrival: Option<Time>,
+    // How much the current stage has been extended or shortened for transit priority
+    priority_extension: Duration,
+    priority_truncation: Duration,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Debug)]
@@ -96,6 +120,9 @@ impl IntersectionSimState {
             break_turn_conflict_cycles: opts.break_turn_conflict_cycles,
             handle_uber_turns: opts.handle_uber_turns,
             disable_turn_conflicts: opts.disable_turn_conflicts,
+            loop_detectors: opts.loop_detectors,
+            transit_signal_priority: opts.transit_signal_priority.clone(),
+            gap_acceptance: opts.gap_acceptance.clone(),
             blocked_by: BTreeSet::new(),
             events: Vec::new(),
 
@@ -115,6 +142,8 @@ impl IntersectionSimState {
                 reserved: BTreeSet::new(),
                 uber_turn_neighbors: Vec::new(),
                 signal: None,
+                ped_calls: BTreeSet::new(),
+                transit_requests: BTreeMap::new(),
             };
             if i.is_traffic_signal() {
                 state.signal = Some(SignalState::new(i.id, Time::START_OF_DAY, map, scheduler));
@@ -159,12 +188,13 @@ impl IntersectionSimState {
         }
     }
 
-    /// For deleting cars
+    /// For deleting cars, or when they change their mind about the next turn
     pub fn cancel_request(&mut self, agent: AgentID, turn: TurnID) {
         let state = self.state.get_mut(&turn.parent).unwrap();
         state.waiting.remove(&Request { agent, turn });
-        if self.break_turn_conflict_cycles {
-            if let AgentID::Car(car) = agent {
+        if let AgentID::Car(car) = agent {
+            state.transit_requests.remove(&car);
+            if self.break_turn_conflict_cycles {
                 self.blocked_by.retain(|(c1, c2)| *c1 != car && *c2 != car);
             }
         }
@@ -197,6 +227,96 @@ impl IntersectionSimState {
         }
     }
 
+    /// A bus or train just entered the last lane before an intersection, and will next do this
+    /// turn. If transit signal priority is enabled, the signal may end the current stage early to
+    /// serve the vehicle sooner. If the current stage already serves the vehicle, it may be
+    /// extended later.
+    pub fn transit_vehicle_approaching(
+        &mut self,
+        now: Time,
+        car: CarID,
+        route: BusRouteID,
+        turn: TurnID,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        let tsp = match self.transit_signal_priority {
+            Some(ref tsp) => tsp,
+            None => {
+                return;
+            }
+        };
+        let signal = match map.maybe_get_traffic_signal(turn.parent) {
+            Some(signal) => signal,
+            None => {
+                return;
+            }
+        };
+        let movement = signal.turn_to_movement(turn);
+        let state = self.state.get_mut(&turn.parent).unwrap();
+        state.transit_requests.insert(car, (route, movement));
+
+        let signal_state = state.signal.as_mut().unwrap();
+        let stage = &signal.get_plan_stages(signal_state.current_plan)[signal_state.current_stage];
+        if stage.get_priority_of_movement(movement) != TurnPriority::Banned {
+            return;
+        }
+        // Ending this stage early only helps if the next one serves the vehicle. Crosswalk-only
+        // stages might be skipped, but don't bother predicting that.
+        let stages = signal.get_plan_stages(signal_state.current_plan);
+        let next_stage = &stages[(signal_state.current_stage + 1) % stages.len()];
+        if next_stage.get_priority_of_movement(movement) == TurnPriority::Banned {
+            return;
+        }
+
+        // End the current stage early, but give it some minimum time, and let crosswalks finish.
+        // If the stage has been extended, this underestimates how long it's been running, which
+        // is fine.
+        let min_duration = std::cmp::max(
+            TRANSIT_PRIORITY_MIN_GREEN,
+            signal.get_min_crossing_time(signal_state.current_plan, signal_state.current_stage),
+        );
+        let stage_duration = stage.stage_type.simple_duration();
+        if stage_duration <= min_duration || signal_state.priority_truncation >= tsp.max_early_green
+        {
+            return;
+        }
+        let granted = std::cmp::min(
+            std::cmp::min(
+                tsp.max_early_green - signal_state.priority_truncation,
+                stage_duration - min_duration,
+            ),
+            signal_state.stage_ends_at - now,
+        );
+        if granted == Duration::ZERO {
+            return;
+        }
+        signal_state.priority_truncation += granted;
+        signal_state.stage_ends_at = signal_state.stage_ends_at - granted;
+        scheduler.update(
+            signal_state.stage_ends_at,
+            Command::UpdateIntersection(turn.parent),
+        );
+
+        // Agents waiting to use this stage, who won't be served by the next one, have less time
+        let delayed_agents = state
+            .waiting
+            .keys()
+            .filter(|req| {
+                stage.get_priority_of_turn(req.turn, signal) != TurnPriority::Banned
+                    && next_stage.get_priority_of_turn(req.turn, signal) == TurnPriority::Banned
+            })
+            .count();
+        self.events.push(Event::TransitSignalPriority {
+            intersection: turn.parent,
+            vehicle: car,
+            route,
+            priority: TransitPriorityType::EarlyGreen,
+            granted,
+            delayed_agents,
+        });
+    }
+
     fn wakeup_waiting(&self, now: Time, i: IntersectionID, scheduler: &mut Scheduler, map: &Map) {
         let mut all: Vec<(Request, Time, bool)> = self.state[&i]
             .waiting
@@ -219,8 +339,9 @@ impl IntersectionSimState {
                 protected.push(req);
             }
         } else if let Some(signal) = map.maybe_get_traffic_signal(i) {
-            let current_stage = self.state[&i].signal.as_ref().unwrap().current_stage;
-            let stage = &signal.stages[current_stage];
+            let signal_state = self.state[&i].signal.as_ref().unwrap();
+            let stage =
+                &signal.get_plan_stages(signal_state.current_plan)[signal_state.current_stage];
             let reserved = &self.state[&i].reserved;
             for (req, _, _) in all {
                 match stage.get_priority_of_turn(req.turn, signal) {
@@ -271,13 +392,15 @@ impl IntersectionSimState {
         }
     }
 
-    /// This is only triggered for traffic signals.
+    /// This is only triggered for traffic signals. If loop detectors are enabled, the caller
+    /// passes in the incoming lanes with a vehicle currently over the detector.
     pub fn update_intersection(
         &mut self,
         now: Time,
         id: IntersectionID,
         map: &Map,
         scheduler: &mut Scheduler,
+        occupied_detectors: Option<BTreeSet<LaneID>>,
     ) {
         // trivial function that advances the signal stage and returns duration
         fn advance(
@@ -285,80 +408,163 @@ impl IntersectionSimState {
             signal: &ControlTrafficSignal,
             allow_crosswalk_skip: bool,
         ) -> Duration {
-            signal_state.current_stage = (signal_state.current_stage + 1) % signal.stages.len();
-            let stage = &signal.stages[signal_state.current_stage];
+            let stages = signal.get_plan_stages(signal_state.current_plan);
+            signal_state.current_stage = (signal_state.current_stage + 1) % stages.len();
+            signal_state.extended = Duration::ZERO;
+            signal_state.last_arrival = None;
+            signal_state.priority_extension = Duration::ZERO;
+            signal_state.priority_truncation = Duration::ZERO;
+            let stage = &stages[signal_state.current_stage];
             // only skip for variable all-walk crosswalk
             if let StageType::Variable(_, _, _) = stage.stage_type {
                 if allow_crosswalk_skip && stage.max_crosswalk_time(&signal.movements).is_some() {
                     // we can skip this stage, as its all walk and we're allowed to skip (no
                     // pedestrian waiting).
-                    signal_state.current_stage =
-                        (signal_state.current_stage + 1) % signal.stages.len();
+                    signal_state.current_stage = (signal_state.current_stage + 1) % stages.len();
                 }
             }
-            signal.stages[signal_state.current_stage]
+            stages[signal_state.current_stage]
                 .stage_type
                 .simple_duration()
         }
         let state = self.state.get_mut(&id).unwrap();
         let signal_state = state.signal.as_mut().unwrap();
         let signal = map.get_traffic_signal(id);
-        let ped_waiting = state.waiting.keys().any(|req| {
-            if let AgentID::Pedestrian(_) = req.agent {
-                return true;
+        assert_eq!(now, signal_state.stage_ends_at);
+
+        // Switch to a new time-of-day plan once the current stage finishes
+        let plan = signal.plan_at(now);
+        if plan != signal_state.current_plan {
+            signal_state.start_plan(plan, now, signal, &mut state.ped_calls);
+            scheduler.push(signal_state.stage_ends_at, Command::UpdateIntersection(id));
+            self.wakeup_waiting(now, id, scheduler, map);
+            return;
+        }
+        let stages = signal.get_plan_stages(signal_state.current_plan);
+
+        // Extend the current stage for an approaching transit vehicle?
+        if let Some(ref tsp) = self.transit_signal_priority {
+            let stage = &stages[signal_state.current_stage];
+            if let Some((car, (route, _))) = state
+                .transit_requests
+                .iter()
+                .find(|(_, (_, m))| stage.get_priority_of_movement(*m) != TurnPriority::Banned)
+            {
+                if signal_state.priority_extension < tsp.max_extension {
+                    let granted = std::cmp::min(
+                        TRANSIT_PRIORITY_EXTENSION_STEP,
+                        tsp.max_extension - signal_state.priority_extension,
+                    );
+                    signal_state.priority_extension += granted;
+                    signal_state.stage_ends_at = now + granted;
+                    scheduler.push(signal_state.stage_ends_at, Command::UpdateIntersection(id));
+
+                    // Agents waiting for a conflicting movement are held longer
+                    let delayed_agents = state
+                        .waiting
+                        .keys()
+                        .filter(|req| {
+                            stage.get_priority_of_turn(req.turn, signal) == TurnPriority::Banned
+                        })
+                        .count();
+                    self.events.push(Event::TransitSignalPriority {
+                        intersection: id,
+                        vehicle: *car,
+                        route: *route,
+                        priority: TransitPriorityType::GreenExtension,
+                        granted,
+                        delayed_agents,
+                    });
+                    return;
+                }
             }
-            false
-        });
+        }
+
+        // With detectors, only a pushed call button counts. Otherwise, anybody waiting does.
+        let ped_waiting = if occupied_detectors.is_some() {
+            !state.ped_calls.is_empty()
+        } else {
+            state.waiting.keys().any(|req| {
+                if let AgentID::Pedestrian(_) = req.agent {
+                    return true;
+                }
+                false
+            })
+        };
         let duration: Duration;
         // Switch to a new stage?
-        assert_eq!(now, signal_state.stage_ends_at);
-        let old_stage = &signal.stages[signal_state.current_stage];
+        let old_stage = &stages[signal_state.current_stage];
         match old_stage.stage_type {
             StageType::Fixed(_) => {
                 duration = advance(signal_state, signal, !ped_waiting);
             }
             StageType::Variable(min, delay, additional) => {
-                // test if anyone is waiting in current stage, and if so, extend the signal cycle.
+                // A vehicle still present for a protected movement counts as arriving now.
                 // Filter out pedestrians, as they've had their chance and the delay
                 // could be short enough to keep them on the curb.
-                let delay = std::cmp::max(Duration::const_seconds(1.0), delay);
-                // Only extend for the fixed additional time
-                if signal_state.extensions_count as f64 * delay.inner_seconds()
-                    >= additional.inner_seconds()
-                {
-                    self.events.push(Event::Alert(
-                        AlertLocation::Intersection(id),
-                        format!(
-                            "exhausted a variable stage {},{},{},{}",
-                            min, delay, additional, signal_state.extensions_count
-                        ),
-                    ));
-                    duration = advance(signal_state, signal, !ped_waiting);
-                    signal_state.extensions_count = 0;
-                } else if state.waiting.keys().all(|req| {
-                    if let AgentID::Pedestrian(_) = req.agent {
-                        return true;
+                let vehicle_present = match occupied_detectors {
+                    Some(ref occupied) => old_stage.protected_movements.iter().any(|m| {
+                        !m.crosswalk
+                            && signal.movements[m]
+                                .members
+                                .iter()
+                                .any(|t| occupied.contains(&t.src))
+                    }),
+                    None => state.waiting.keys().any(|req| {
+                        if let AgentID::Pedestrian(_) = req.agent {
+                            return false;
+                        }
+                        // Should we only allow protected to extend or any not banned?
+                        // currently only the protected demand control extended.
+                        old_stage.get_priority_of_turn(req.turn, signal) == TurnPriority::Protected
+                    }),
+                };
+                if vehicle_present {
+                    signal_state.last_arrival = Some(now);
+                }
+
+                let passage_time = std::cmp::max(Duration::const_seconds(1.0), delay);
+                match actuate_variable_stage(
+                    now,
+                    passage_time,
+                    additional,
+                    signal_state.extended,
+                    signal_state.last_arrival,
+                ) {
+                    Actuation::Extend(dt) => {
+                        signal_state.extended += dt;
+                        duration = dt;
+                        self.events.push(Event::Alert(
+                            AlertLocation::Intersection(id),
+                            format!(
+                                "Extending a variable stage {},{},{} by {} (extended {} so far)",
+                                min, delay, additional, dt, signal_state.extended
+                            ),
+                        ));
+                    }
+                    Actuation::GapOut => {
+                        duration = advance(signal_state, signal, !ped_waiting);
+                    }
+                    Actuation::MaxOut => {
+                        self.events.push(Event::Alert(
+                            AlertLocation::Intersection(id),
+                            format!(
+                                "exhausted a variable stage {},{},{}",
+                                min, delay, additional
+                            ),
+                        ));
+                        duration = advance(signal_state, signal, !ped_waiting);
                     }
-                    // Should we only allow protected to extend or any not banned?
-                    // currently only the protected demand control extended.
-                    old_stage.get_priority_of_turn(req.turn, signal) != TurnPriority::Protected
-                }) {
-                    signal_state.extensions_count = 0;
-                    duration = advance(signal_state, signal, !ped_waiting);
-                } else {
-                    signal_state.extensions_count += 1;
-                    duration = delay;
-                    self.events.push(Event::Alert(
-                        AlertLocation::Intersection(id),
-                        format!(
-                            "Extending a variable stage {},{},{},{}",
-                            min, delay, additional, signal_state.extensions_count
-                        ),
-                    ));
                 }
             }
         }
 
+        // Pedestrians served by the new stage don't need to keep calling
+        let new_stage = &stages[signal_state.current_stage];
+        state
+            .ped_calls
+            .retain(|m| !new_stage.protected_movements.contains(m));
+
         signal_state.stage_ends_at = now + duration;
         scheduler.push(signal_state.stage_ends_at, Command::UpdateIntersection(id));
         self.wakeup_waiting(now, id, scheduler, map);
@@ -404,6 +610,21 @@ impl IntersectionSimState {
         };
         entry.or_insert((now, urgent));
 
+        // Vehicles reaching the stop line for a protected movement reset the passage timer of a
+        // variable stage.
+        if let AgentID::Car(_) = agent {
+            if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
+                if let Some(ref mut signal_state) = self.state.get_mut(&turn.parent).unwrap().signal
+                {
+                    let stage = &signal.get_plan_stages(signal_state.current_plan)
+                        [signal_state.current_stage];
+                    if stage.get_priority_of_turn(turn, signal) == TurnPriority::Protected {
+                        signal_state.last_arrival = Some(now);
+                    }
+                }
+            }
+        }
+
         if repeat_request {
             self.total_repeat_requests += 1;
         }
@@ -445,9 +666,11 @@ impl IntersectionSimState {
             // If we made it this far, we don't conflict with an accepted turn
             true
         } else if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
-            self.traffic_signal_policy(&req, map, signal, speed, now, Some(scheduler))
+            self.traffic_signal_policy(&req, map, signal, speed, now, Some(&mut *scheduler))
+                && self.accept_gap(&req, map, now, scheduler, readonly_pair)
         } else if let Some(sign) = map.maybe_get_stop_sign(turn.parent) {
             self.stop_sign_policy(&req, map, sign, now, scheduler)
+                && self.accept_gap(&req, map, now, scheduler, readonly_pair)
         } else {
             unreachable!()
         };
@@ -455,6 +678,20 @@ impl IntersectionSimState {
             if repeat_request {
                 self.not_allowed_requests += 1;
             }
+            // Push the call button
+            if self.loop_detectors.is_some() {
+                if let AgentID::Pedestrian(_) = agent {
+                    if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
+                        if map.get_t(turn).turn_type == TurnType::Crosswalk {
+                            self.state
+                                .get_mut(&turn.parent)
+                                .unwrap()
+                                .ped_calls
+                                .insert(signal.turn_to_movement(turn));
+                        }
+                    }
+                }
+            }
             // remove the reservation if we're about to start a UT and can't move
             if self.handle_uber_turns {
                 if let Some(ut) = maybe_cars_and_queues
@@ -557,8 +794,9 @@ impl IntersectionSimState {
         let state = self.state.get_mut(&turn.parent).unwrap();
         state.waiting.remove(&req).unwrap();
         state.accepted.insert(req);
-        if self.break_turn_conflict_cycles {
-            if let AgentID::Car(car) = agent {
+        if let AgentID::Car(car) = agent {
+            state.transit_requests.remove(&car);
+            if self.break_turn_conflict_cycles {
                 self.blocked_by.retain(|(c, _)| *c != car);
             }
         }
@@ -581,7 +819,14 @@ impl IntersectionSimState {
                 state.signal.as_mut(),
             ) {
                 (Some(ts), Some(signal_state)) => {
-                    if signal_state.current_stage >= ts.stages.len() {
+                    if signal_state.current_plan >= ts.num_plans() {
+                        // The next stage change will switch to the right plan
+                        signal_state.current_plan = 0;
+                        signal_state.current_stage = 0;
+                    }
+                    if signal_state.current_stage
+                        >= ts.get_plan_stages(signal_state.current_plan).len()
+                    {
                         // Just jump back to the first one. Shrug.
                         signal_state.current_stage = 0;
                         println!(
@@ -600,6 +845,10 @@ impl IntersectionSimState {
                 }
                 (None, None) => {}
             }
+            // Movements may have changed; pedestrians will push the button again, but approaching
+            // transit vehicles lose their priority request
+            state.ped_calls.clear();
+            state.transit_requests.clear();
 
             // It's unlikely, but the player might create/destroy traffic signals close together and
             // change the uber-turns that exist. To be safe, recalculate everywhere.
@@ -662,6 +911,14 @@ impl IntersectionSimState {
         }
     }
 
+    pub fn loop_detectors(&self) -> Option<Distance> {
+        self.loop_detectors
+    }
+
+    pub fn get_pedestrian_calls(&self, id: IntersectionID) -> BTreeSet<MovementID> {
+        self.state[&id].ped_calls.clone()
+    }
+
     pub fn get_accepted_agents(&self, id: IntersectionID) -> Vec<(AgentID, TurnID)> {
         self.state[&id]
             .accepted
@@ -712,6 +969,12 @@ impl IntersectionSimState {
         (state.current_stage, state.stage_ends_at - now)
     }
 
+    /// Which time-of-day plan is a traffic signal currently using? This may lag behind the plan
+    /// scheduled for the current time, since plans only change at the end of a stage.
+    pub fn current_signal_plan(&self, i: IntersectionID) -> usize {
+        self.state[&i].signal.as_ref().unwrap().current_plan
+    }
+
     pub fn describe_stats(&self) -> Vec<String> {
         vec![
             "intersection stats".to_string(),
@@ -811,6 +1074,12 @@ impl IntersectionSimState {
         assert!(our_priority != TurnPriority::Banned);
         let (our_time, _) = self.state[&req.turn.parent].waiting[req];
 
+        if sign.roundabout {
+            // Nobody stops at a roundabout. accept_gap makes vehicles entering wait for a big
+            // enough gap in circulating traffic.
+            return true;
+        }
+
         if our_priority == TurnPriority::Yield && now < our_time + WAIT_AT_STOP_SIGN {
             // Since we have "ownership" of scheduling for req.agent, don't need to use
             // scheduler.update.
@@ -833,10 +1102,98 @@ impl IntersectionSimState {
         // that events are processed in time order mean that case #2 is magically handled anyway.
         // If a case #1 could've started by now, then they would have. Since they didn't, they must
         // be blocked.
+        //
+        // accept_gap makes sure a higher-priority vehicle isn't about to arrive.
+
+        true
+    }
+
+    /// A yielding vehicle only starts its turn if no approaching vehicle with priority over it
+    /// will reach the intersection before the critical gap elapses. If one will, try again once
+    /// it arrives.
+    fn accept_gap(
+        &self,
+        req: &Request,
+        map: &Map,
+        now: Time,
+        scheduler: &mut Scheduler,
+        maybe_cars_and_queues: Option<(&FixedMap<CarID, Car>, &HashMap<Traversable, Queue>)>,
+    ) -> bool {
+        // Pedestrians don't look for gaps
+        let pair = match maybe_cars_and_queues {
+            Some(pair) => pair,
+            None => {
+                return true;
+            }
+        };
+        let i = map.get_i(req.turn.parent);
+        let turn = map.get_t(req.turn);
+        let default_gaps = GapAcceptance::default();
+        let gaps = self.gap_acceptance.as_ref().unwrap_or(&default_gaps);
 
-        // TODO Make sure we can optimistically finish this turn before an approaching
-        // higher-priority vehicle wants to begin.
+        let mut priority_turns = BTreeSet::new();
+        let critical_gap = if let Some(signal) = map.maybe_get_traffic_signal(i.id) {
+            if self.gap_acceptance.is_none() {
+                return true;
+            }
+            let signal_state = self.state[&i.id].signal.as_ref().unwrap();
+            let stage =
+                &signal.get_plan_stages(signal_state.current_plan)[signal_state.current_stage];
+            if stage.get_priority_of_turn(req.turn, signal) != TurnPriority::Yield {
+                return true;
+            }
+            for t in &i.turns {
+                if stage.get_priority_of_turn(t.id, signal) == TurnPriority::Protected {
+                    priority_turns.insert(t.id);
+                }
+            }
+            gaps.permissive_turn
+        } else if let Some(sign) = map.maybe_get_stop_sign(i.id) {
+            if sign.get_priority(req.turn, map) != TurnPriority::Yield {
+                return true;
+            }
+            if sign.roundabout {
+                let yield_to = sign.roundabout_yields_to(map.get_l(req.turn.src).parent, map);
+                for t in &i.turns {
+                    if yield_to.contains(&map.get_l(t.id.src).parent) {
+                        priority_turns.insert(t.id);
+                    }
+                }
+                gaps.roundabout
+            } else {
+                if self.gap_acceptance.is_none() {
+                    return true;
+                }
+                for t in &i.turns {
+                    if sign.get_priority(t.id, map) == TurnPriority::Protected {
+                        priority_turns.insert(t.id);
+                    }
+                }
+                gaps.from_minor_road(turn.turn_type, map.get_config().driving_side)
+            }
+        } else {
+            return true;
+        };
+        let critical_gap = gaps
+            .per_movement
+            .get(&req.turn.to_movement(map))
+            .cloned()
+            .unwrap_or(critical_gap);
+
+        priority_turns.retain(|t| {
+            let other = map.get_t(*t);
+            !other.between_sidewalks() && other.conflicts_with(turn)
+        });
+        if priority_turns.is_empty() {
+            return true;
+        }
 
+        if let Some(arrival) = next_conflicting_arrival(&priority_turns, critical_gap, now, pair) {
+            // If the conflicting vehicle starts its turn, turn_finished will wake us up again
+            // anyway.
+            scheduler.update(arrival, Command::update_agent(req.agent));
+            return false;
+        }
         true
     }
 
@@ -853,7 +1210,7 @@ impl IntersectionSimState {
 
         let state = &self.state[&req.turn.parent];
         let signal_state = state.signal.as_ref().unwrap();
-        let stage = &signal.stages[signal_state.current_stage];
+        let stage = &signal.get_plan_stages(signal_state.current_plan)[signal_state.current_stage];
         let full_stage_duration = stage.stage_type.simple_duration();
         let remaining_stage_time = signal_state.stage_ends_at - now;
         let (our_time, _) = state.waiting[req];
@@ -883,9 +1240,6 @@ impl IntersectionSimState {
         // Priority vehicles getting scheduled first just requires a little tweak in
         // update_intersection.
 
-        // TODO Make sure we can optimistically finish this turn before an approaching
-        // higher-priority vehicle wants to begin.
-
         // Optimistically if nobody else is in the way, this is how long it'll take to finish the
         // turn. Don't start the turn if we won't finish by the time the light changes. If we get
         // it wrong, that's fine -- block the box a bit.
@@ -1031,32 +1385,120 @@ impl IntersectionSimState {
 impl SignalState {
     fn new(id: IntersectionID, now: Time, map: &Map, scheduler: &mut Scheduler) -> SignalState {
         let mut state = SignalState {
+            current_plan: 0,
             current_stage: 0,
             stage_ends_at: now,
-            extensions_count: 0,
+            extended: Duration::ZERO,
+            last_arrival: None,
+            priority_extension: Duration::ZERO,
+            priority_truncation: Duration::ZERO,
         };
 
         let signal = map.get_traffic_signal(id);
-        // What stage are we starting with?
-        let mut offset = (now - Time::START_OF_DAY) + signal.offset;
+        state.start_plan(signal.plan_at(now), now, signal, &mut BTreeSet::new());
+        scheduler.push(state.stage_ends_at, Command::UpdateIntersection(id));
+        state
+    }
+
+    /// Figure out what stage of a plan should be running now, based on its offset. Like any other
+    /// stage change, pedestrians served by that stage don't need to keep calling.
+    fn start_plan(
+        &mut self,
+        plan: usize,
+        now: Time,
+        signal: &ControlTrafficSignal,
+        ped_calls: &mut BTreeSet<MovementID>,
+    ) {
+        let stages = signal.get_plan_stages(plan);
+        self.current_plan = plan;
+        self.current_stage = 0;
+        self.extended = Duration::ZERO;
+        self.last_arrival = None;
+        self.priority_extension = Duration::ZERO;
+        self.priority_truncation = Duration::ZERO;
+        let mut offset = (now - Time::START_OF_DAY) + signal.get_plan_offset(plan);
         loop {
-            let dt = signal.stages[state.current_stage]
-                .stage_type
-                .simple_duration();
+            let dt = stages[self.current_stage].stage_type.simple_duration();
             if offset >= dt {
                 offset -= dt;
-                state.current_stage += 1;
-                if state.current_stage == signal.stages.len() {
-                    state.current_stage = 0;
+                self.current_stage += 1;
+                if self.current_stage == stages.len() {
+                    self.current_stage = 0;
                 }
             } else {
-                state.stage_ends_at = now + dt - offset;
+                self.stage_ends_at = now + dt - offset;
                 break;
             }
         }
-        scheduler.push(state.stage_ends_at, Command::UpdateIntersection(id));
-        state
+
+        let stage = &stages[self.current_stage];
+        ped_calls.retain(|m| !stage.protected_movements.contains(m));
+    }
+}
+
+/// Walk one queue of vehicles, from the front, and find when the first one doing one of the
+/// priority turns arrives, if that's within the critical gap. Each vehicle has the time it'll reach
+/// the end of the lane (or None if it's stopped) and its next turn. Don't just look at the lead
+/// vehicle; it might not be doing a conflicting turn, but somebody right behind it could be.
+fn first_arrival_in_queue<I: Iterator<Item = (Option<Time>, Option<TurnID>)>>(
+    approaching: I,
+    priority_turns: &BTreeSet<TurnID>,
+    critical_gap: Duration,
+    now: Time,
+) -> Option<Time> {
+    // Nobody can arrive before the vehicle in front of them
+    let mut earliest_possible = now;
+    for (arrival, next_turn) in approaching {
+        let arrival = match arrival {
+            Some(t) => t.max(earliest_possible),
+            // Everybody behind a stopped vehicle has to wait for it to move first
+            None => {
+                return None;
+            }
+        };
+        if arrival > now + critical_gap {
+            return None;
+        }
+        earliest_possible = arrival;
+        if let Some(t) = next_turn {
+            if priority_turns.contains(&t) {
+                return Some(arrival);
+            }
+        }
+    }
+    None
+}
+
+/// What a variable stage does once its green time so far runs out.
+#[derive(Debug, PartialEq)]
+enum Actuation {
+    /// Keep the stage going this much longer
+    Extend(Duration),
+    /// Nobody has arrived within the passage time
+    GapOut,
+    /// Vehicles keep arriving, but the stage can't be extended any more
+    MaxOut,
+}
+
+/// The passage (or gap) timer of an actuated signal restarts every time a vehicle arrives. The
+/// stage is extended until the timer runs out, but never by more than `max_extension` in total.
+fn actuate_variable_stage(
+    now: Time,
+    passage_time: Duration,
+    max_extension: Duration,
+    extended: Duration,
+    last_arrival: Option<Time>,
+) -> Actuation {
+    let gap_ends_at = match last_arrival {
+        Some(t) if t + passage_time > now => t + passage_time,
+        _ => {
+            return Actuation::GapOut;
+        }
+    };
+    if extended >= max_extension {
+        return Actuation::MaxOut;
     }
+    Actuation::Extend(std::cmp::min(gap_ends_at - now, max_extension - extended))
 }
 
 fn allow_block_the_box(i: &Intersection) -> bool {
@@ -1108,3 +1550,178 @@ fn allow_block_the_box(i: &Intersection) -> bool {
     }
     false
 }
+
+/// Look for a vehicle approaching the intersection, about to make one of the specified turns. If
+/// it'll arrive before the critical gap elapses, return the time it'll arrive. Vehicles already
+/// stopped at the intersection don't count; they're waiting on something themselves.
+fn next_conflicting_arrival(
+    priority_turns: &BTreeSet<TurnID>,
+    critical_gap: Duration,
+    now: Time,
+    (cars, queues): (&FixedMap<CarID, Car>, &HashMap<Traversable, Queue>),
+) -> Option<Time> {
+    let lanes: BTreeSet<LaneID> = priority_turns.iter().map(|t| t.src).collect();
+    let mut result: Option<Time> = None;
+    for l in lanes {
+        let queue = match queues.get(&Traversable::Lane(l)) {
+            Some(q) => q,
+            None => continue,
+        };
+        let approaching = queue.get_active_cars().into_iter().map(|id| {
+            let car = &cars[&id];
+            let arrival = match car.state {
+                CarState::Crossing { ref time_int, .. } => Some(time_int.end),
+                CarState::ChangingLanes { ref new_time, .. } => Some(new_time.end),
+                _ => None,
+            };
+            let next_turn = match car.router.maybe_next() {
+                Some(Traversable::Turn(t)) => Some(t),
+                _ => None,
+            };
+            (arrival, next_turn)
+        });
+        if let Some(arrival) =
+            first_arrival_in_queue(approaching, priority_turns, critical_gap, now)
+        {
+            result = Some(result.map(|x| x.min(arrival)).unwrap_or(arrival));
+        }
+    }
+    // Even if the other vehicle is basically there, don't try again at the same instant
+    result.map(|t| t.max(now + Duration::EPSILON))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(secs: f64) -> Time {
+        Time::START_OF_DAY + Duration::seconds(secs)
+    }
+
+    fn turn(src: usize, dst: usize) -> TurnID {
+        TurnID {
+            parent: IntersectionID(0),
+            src: LaneID(src),
+            dst: LaneID(dst),
+        }
+    }
+
+    #[test]
+    fn circulating_car_blocks_entry() {
+        // Entering a roundabout from lane 0 to 1, yielding to circulating traffic from lane 2
+        let circulating = turn(2, 3);
+        let priority_turns: BTreeSet<TurnID> = vec![circulating].into_iter().collect();
+        let gap = Duration::seconds(4.0);
+        let now = t(10.0);
+
+        // A circulating car arriving within the critical gap blocks the entry until it arrives
+        assert_eq!(
+            first_arrival_in_queue(
+                vec![(Some(t(12.0)), Some(circulating))].into_iter(),
+                &priority_turns,
+                gap,
+                now
+            ),
+            Some(t(12.0))
+        );
+        // Not once it's further away than that
+        assert_eq!(
+            first_arrival_in_queue(
+                vec![(Some(t(15.0)), Some(circulating))].into_iter(),
+                &priority_turns,
+                gap,
+                now
+            ),
+            None
+        );
+        // The lead car is leaving the roundabout, but the one right behind it is still circulating
+        assert_eq!(
+            first_arrival_in_queue(
+                vec![
+                    (Some(t(11.0)), Some(turn(2, 5))),
+                    (Some(t(10.5)), Some(circulating)),
+                ]
+                .into_iter(),
+                &priority_turns,
+                gap,
+                now
+            ),
+            Some(t(11.0))
+        );
+        // Nobody behind a stopped car will arrive soon
+        assert_eq!(
+            first_arrival_in_queue(
+                vec![(None, Some(turn(2, 5))), (Some(t(11.0)), Some(circulating))].into_iter(),
+                &priority_turns,
+                gap,
+                now
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn arrivals_extend_variable_stage() {
+        let passage = Duration::seconds(3.0);
+        let max = Duration::seconds(20.0);
+        // A vehicle arrived 1s before the minimum green ran out
+        assert_eq!(
+            actuate_variable_stage(t(10.0), passage, max, Duration::ZERO, Some(t(9.0))),
+            Actuation::Extend(Duration::seconds(2.0))
+        );
+        // Another arrival restarts the passage timer
+        assert_eq!(
+            actuate_variable_stage(t(12.0), passage, max, Duration::seconds(2.0), Some(t(11.5))),
+            Actuation::Extend(Duration::seconds(2.5))
+        );
+        // Extensions never go past the maximum
+        assert_eq!(
+            actuate_variable_stage(
+                t(30.0),
+                passage,
+                max,
+                Duration::seconds(19.0),
+                Some(t(30.0))
+            ),
+            Actuation::Extend(Duration::seconds(1.0))
+        );
+    }
+
+    #[test]
+    fn variable_stage_gaps_out() {
+        let passage = Duration::seconds(3.0);
+        let max = Duration::seconds(20.0);
+        // Nobody came
+        assert_eq!(
+            actuate_variable_stage(t(10.0), passage, max, Duration::ZERO, None),
+            Actuation::GapOut
+        );
+        // The last vehicle arrived a full passage time ago
+        assert_eq!(
+            actuate_variable_stage(t(14.5), passage, max, Duration::seconds(4.5), Some(t(11.5))),
+            Actuation::GapOut
+        );
+    }
+
+    #[test]
+    fn variable_stage_maxes_out() {
+        let passage = Duration::seconds(3.0);
+        let max = Duration::seconds(20.0);
+        // Vehicles keep coming, but the stage has been extended as much as it can be
+        assert_eq!(
+            actuate_variable_stage(t(30.0), passage, max, max, Some(t(29.5))),
+            Actuation::MaxOut
+        );
+        // No extensions allowed at all
+        assert_eq!(
+            actuate_variable_stage(
+                t(10.0),
+                passage,
+                Duration::ZERO,
+                Duration::ZERO,
+                Some(t(10.0))
+            ),
+            Actuation::MaxOut
+        );
+    }
+}
@@ -1,7 +1,8 @@
 pub(crate) use self::driving::DrivingSimState;
 pub(crate) use self::intersection::IntersectionSimState;
-pub(crate) use self::parking::{ParkingSim, ParkingSimState};
+pub(crate) use self::parking::{parking_cost, walking_dist_to, ParkingSim, ParkingSimState};
 pub(crate) use self::queue::Queue;
+pub use self::walking::LevelOfService;
 pub(crate) use self::walking::WalkingSimState;
 
 mod car;
@@ -9,14 +9,54 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, MultiMap,
     Timer,
 };
-use geom::{Distance, PolyLine, Pt2D};
+use geom::{Distance, Duration, PolyLine, Pt2D, Speed};
 use map_model::{
     BuildingID, Lane, LaneID, LaneType, Map, OffstreetParking, ParkingLotID, PathConstraints,
-    PathStep, Position, Traversable, TurnID,
+    PathStep, Position, Traversable, TurnID, MAX_WALKING_SPEED,
 };
 
 use crate::{CarID, CarStatus, DrawCarInput, Event, ParkedCar, ParkingSpot, PersonID, Vehicle};
 
+/// When choosing between parking spots, drivers trade off paying more against driving and walking
+/// further. This is what an hour of their time is worth, in cents.
+const VALUE_OF_TIME_CENTS_PER_HOUR: f64 = 1500.0;
+/// Drivers don't know exactly how long they'll stay when they choose a spot, so assume this.
+const EXPECTED_PARKING_DURATION: Duration = Duration::const_seconds(2.0 * 3600.0);
+/// Drivers estimate how long it'll take to reach a spot assuming they'll crawl along at this speed
+/// while looking for parking.
+const CRUISING_SPEED: Speed = Speed::const_meters_per_second(5.0);
+/// After finding the first lane with a free spot, drivers consider spots up to this much further
+/// away, in case they're cheaper.
+const MAX_PARKING_DETOUR: Distance = Distance::const_meters(800.0);
+
+/// The cost of driving some distance to a spot, parking there, and walking some distance from
+/// there, expressed as time. Spots with a time limit shorter than the expected stay are only used
+/// as a last resort.
+pub(crate) fn parking_cost(
+    spot: ParkingSpot,
+    driving_dist: Distance,
+    walking_dist: Distance,
+    map: &Map,
+) -> (bool, Duration) {
+    let pricing = spot.pricing(map);
+    let too_short = pricing
+        .time_limit
+        .map(|limit| limit < EXPECTED_PARKING_DURATION)
+        .unwrap_or(false);
+    let price = pricing.cost(EXPECTED_PARKING_DURATION) as f64;
+    (
+        too_short,
+        driving_dist / CRUISING_SPEED
+            + walking_dist / MAX_WALKING_SPEED
+            + Duration::seconds(3600.0 * price / VALUE_OF_TIME_CENTS_PER_HOUR),
+    )
+}
+
+/// Roughly how far somebody parked at some position has to walk to a building.
+pub(crate) fn walking_dist_to(pos: Position, target: BuildingID, map: &Map) -> Distance {
+    pos.pt(map).dist_to(map.get_b(target).sidewalk_pos.pt(map))
+}
+
 /// Manages the state of parked cars. There are two implementations:
 /// - NormalParkingSimState allows only one vehicle per ParkingSpot defined in the map
 /// - InfiniteParkingSimState pretends every building has infinite capacity, and onstreet parking is
@@ -53,17 +93,27 @@ pub trait ParkingSim {
         target: BuildingID,
         map: &Map,
     ) -> Vec<(ParkingSpot, Position)>;
+    /// Like get_all_free_spots, but only for curbside loading zones, which are reserved for
+    /// delivery vans.
+    fn get_free_loading_zone_spots(
+        &self,
+        driving_pos: Position,
+        vehicle: &Vehicle,
+        map: &Map,
+    ) -> Vec<(ParkingSpot, Position)>;
     fn spot_to_driving_pos(&self, spot: ParkingSpot, vehicle: &Vehicle, map: &Map) -> Position;
     fn spot_to_sidewalk_pos(&self, spot: ParkingSpot, map: &Map) -> Position;
     fn get_owner_of_car(&self, id: CarID) -> Option<PersonID>;
     fn lookup_parked_car(&self, id: CarID) -> Option<&ParkedCar>;
-    /// (Filled, available)
+    /// (Filled, available). Loading zones are included.
     fn get_all_parking_spots(&self) -> (Vec<ParkingSpot>, Vec<ParkingSpot>);
     /// Unrealistically assumes the driver has knowledge of currently free parking spots, even if
     /// they're far away. Since they don't reserve the spot in advance, somebody else can still beat
     /// them there, producing some nice, realistic churn if there's too much contention. But
     /// the implementation has some internal jitter between different vehicles, to discourage
-    /// everybody near one spot from all competing for it.
+    /// everybody near one spot from all competing for it. Among the spots a little past the first
+    /// free one, the driver picks the one with the lowest `parking_cost`, so they'll drive further
+    /// to avoid an expensive block.
     /// Note the first PathStep is the turn after start, NOT PathStep::Lane(start).
     fn path_to_free_parking_spot(
         &self,
@@ -121,7 +171,7 @@ pub struct NormalParkingSimState {
     )]
     reserved_spots: BTreeMap<ParkingSpot, CarID>,
 
-    // On-street
+    // On-street, including loading zones
     onstreet_lanes: BTreeMap<LaneID, ParkingLane>,
     // TODO Really this could be 0, 1, or 2 lanes. Full MultiMap is overkill.
     #[serde(
@@ -129,6 +179,12 @@ pub struct NormalParkingSimState {
         deserialize_with = "deserialize_multimap"
     )]
     driving_to_parking_lanes: MultiMap<LaneID, LaneID>,
+    // Only delivery vans may use these
+    #[serde(
+        serialize_with = "serialize_multimap",
+        deserialize_with = "deserialize_multimap"
+    )]
+    driving_to_loading_zones: MultiMap<LaneID, LaneID>,
 
     // Off-street
     num_spots_per_offstreet: BTreeMap<BuildingID, usize>,
@@ -159,6 +215,7 @@ impl NormalParkingSimState {
 
             onstreet_lanes: BTreeMap::new(),
             driving_to_parking_lanes: MultiMap::new(),
+            driving_to_loading_zones: MultiMap::new(),
             num_spots_per_offstreet: BTreeMap::new(),
             driving_to_offstreet: MultiMap::new(),
             num_spots_per_lot: BTreeMap::new(),
@@ -168,7 +225,11 @@ impl NormalParkingSimState {
         };
         for l in map.all_lanes().values() {
             if let Some(lane) = ParkingLane::new(l, map) {
-                sim.driving_to_parking_lanes.insert(lane.driving_lane, l.id);
+                if l.is_loading_zone() {
+                    sim.driving_to_loading_zones.insert(lane.driving_lane, l.id);
+                } else {
+                    sim.driving_to_parking_lanes.insert(lane.driving_lane, l.id);
+                }
                 sim.onstreet_lanes.insert(lane.parking_lane, lane);
             }
         }
@@ -223,6 +284,7 @@ impl ParkingSim for NormalParkingSimState {
         // Use the new spots
         self.onstreet_lanes = new.onstreet_lanes;
         self.driving_to_parking_lanes = new.driving_to_parking_lanes;
+        self.driving_to_loading_zones = new.driving_to_loading_zones;
         self.num_spots_per_offstreet = new.num_spots_per_offstreet;
         self.driving_to_offstreet = new.driving_to_offstreet;
         self.num_spots_per_lot = new.num_spots_per_lot;
@@ -317,8 +379,11 @@ impl ParkingSim for NormalParkingSimState {
         if self.occupants.remove(&p.spot).is_none() {
             panic!("remove_parked_car {:?} missing from occupants", p);
         }
-        self.events
-            .push(Event::CarLeftParkingSpot(p.vehicle.id, p.spot));
+        self.events.push(Event::CarLeftParkingSpot(
+            p.vehicle.id,
+            p.spot,
+            p.parked_since,
+        ));
     }
 
     fn add_parked_car(&mut self, p: ParkedCar) {
@@ -495,6 +560,27 @@ impl ParkingSim for NormalParkingSimState {
             .collect()
     }
 
+    fn get_free_loading_zone_spots(
+        &self,
+        driving_pos: Position,
+        vehicle: &Vehicle,
+        map: &Map,
+    ) -> Vec<(ParkingSpot, Position)> {
+        let mut candidates = Vec::new();
+        for l in self.driving_to_loading_zones.get(driving_pos.lane()) {
+            for spot in self.onstreet_lanes[l].spots() {
+                if !self.is_free(spot) {
+                    continue;
+                }
+                let pos = self.spot_to_driving_pos(spot, vehicle, map);
+                if driving_pos.dist_along() <= pos.dist_along() {
+                    candidates.push((spot, pos));
+                }
+            }
+        }
+        candidates
+    }
+
     fn spot_to_driving_pos(&self, spot: ParkingSpot, vehicle: &Vehicle, map: &Map) -> Position {
         match spot {
             ParkingSpot::Onstreet(l, idx) => {
@@ -566,6 +652,9 @@ impl ParkingSim for NormalParkingSimState {
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
         let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
+        // How far the driver goes from the end of the start lane to reach the start of each lane
+        let mut driven: HashMap<LaneID, Distance> = HashMap::new();
+        driven.insert(start, Distance::ZERO);
         // Don't travel far.
         // This is a max-heap, so negate all distances. Tie breaker is lane ID, arbitrary but
         // deterministic.
@@ -578,36 +667,50 @@ impl ParkingSim for NormalParkingSimState {
         // then they might always prefer the first or third turn the most or whatever.
         let mut rng = XorShiftRng::seed_from_u64((vehicle.id.id + start.0) as u64);
 
-        while !queue.is_empty() {
-            let (dist_so_far, current) = queue.pop().unwrap();
+        // The cheapest spot found so far. Once there's one, keep looking a bit further in case
+        // there's a cheaper spot.
+        let mut best: Option<((bool, Duration), ParkingSpot, Position)> = None;
+        let mut search_limit: Option<Distance> = None;
+
+        while let Some((dist_so_far, current)) = queue.pop() {
+            let driven_so_far = driven[&current];
+            if search_limit
+                .map(|limit| driven_so_far > limit)
+                .unwrap_or(false)
+            {
+                continue;
+            }
             // If the current lane has a spot open, we wouldn't be asking. This can happen if a spot
             // opens up on the 'start' lane, but behind the car.
             if current != start {
-                // Pick the closest to the start of the lane, since that's closest to where we came
-                // from
-                if let Some((spot, pos)) = self
-                    .get_all_free_spots(Position::start(current), vehicle, target, map)
-                    .into_iter()
-                    .min_by_key(|(_, pos)| pos.dist_along())
+                for (spot, pos) in
+                    self.get_all_free_spots(Position::start(current), vehicle, target, map)
                 {
-                    let mut steps = vec![PathStep::Lane(current)];
-                    let mut current = current;
-                    loop {
-                        if current == start {
-                            // Don't include PathStep::Lane(start)
-                            steps.pop();
-                            steps.reverse();
-                            return Some((steps, spot, pos));
-                        }
-                        let turn = backrefs[&current];
-                        steps.push(PathStep::Turn(turn));
-                        steps.push(PathStep::Lane(turn.src));
-                        current = turn.src;
+                    let cost = parking_cost(
+                        spot,
+                        driven_so_far + pos.dist_along(),
+                        walking_dist_to(pos, target, map),
+                        map,
+                    );
+                    if best.map(|(c, _, _)| cost < c).unwrap_or(true) {
+                        best = Some((cost, spot, pos));
                     }
                 }
+                if best.is_some() && search_limit.is_none() {
+                    search_limit = Some(driven_so_far + MAX_PARKING_DETOUR);
+                }
             }
+            let current_length = if current == start {
+                Distance::ZERO
+            } else {
+                map.get_l(current).length()
+            };
             for turn in map.get_turns_for(current, PathConstraints::Car) {
                 if let Entry::Vacant(e) = backrefs.entry(turn.id.dst) {
+                    driven.insert(
+                        turn.id.dst,
+                        driven_so_far + current_length + turn.geom.length(),
+                    );
                     let dist_this_step = turn.geom.length() + map.get_l(current).length();
                     // When vehicles search away from the first lane for a spot, don't all go in
                     // the same direction! Do this by jittering which turn they explore.
@@ -624,7 +727,21 @@ impl ParkingSim for NormalParkingSimState {
             }
         }
 
-        None
+        let (_, spot, pos) = best?;
+        let mut steps = vec![PathStep::Lane(pos.lane())];
+        let mut current = pos.lane();
+        loop {
+            if current == start {
+                // Don't include PathStep::Lane(start)
+                steps.pop();
+                steps.reverse();
+                return Some((steps, spot, pos));
+            }
+            let turn = backrefs[&current];
+            steps.push(PathStep::Turn(turn));
+            steps.push(PathStep::Lane(turn.src));
+            current = turn.src;
+        }
     }
 
     fn collect_events(&mut self) -> Vec<Event> {
@@ -666,7 +783,7 @@ struct ParkingLane {
 
 impl ParkingLane {
     fn new(lane: &Lane, map: &Map) -> Option<ParkingLane> {
-        if lane.lane_type != LaneType::Parking {
+        if lane.lane_type != LaneType::Parking && lane.lane_type != LaneType::LoadingZone {
             return None;
         }
 
@@ -871,8 +988,11 @@ impl ParkingSim for InfiniteParkingSimState {
         self.occupants
             .remove(&p.spot)
             .expect("remove_parked_car missing from occupants");
-        self.events
-            .push(Event::CarLeftParkingSpot(p.vehicle.id, p.spot));
+        self.events.push(Event::CarLeftParkingSpot(
+            p.vehicle.id,
+            p.spot,
+            p.parked_since,
+        ));
 
         if let ParkingSpot::Offstreet(b, _) = p.spot {
             *self.num_occupants_per_offstreet.get_mut(&b).unwrap() -= 1;
@@ -959,6 +1079,16 @@ impl ParkingSim for InfiniteParkingSimState {
         }
     }
 
+    fn get_free_loading_zone_spots(
+        &self,
+        _: Position,
+        _: &Vehicle,
+        _: &Map,
+    ) -> Vec<(ParkingSpot, Position)> {
+        // Onstreet parking, including loading zones, isn't modeled
+        Vec::new()
+    }
+
     fn spot_to_driving_pos(&self, spot: ParkingSpot, _: &Vehicle, map: &Map) -> Position {
         match spot {
             ParkingSpot::Offstreet(b, _) => map.get_b(b).driving_connection(map).unwrap().0,
@@ -2,11 +2,14 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::{deserialize_multimap, serialize_multimap, FixedMap, IndexableKey, MultiMap};
+use abstutil::{
+    deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, FixedMap,
+    IndexableKey, MultiMap,
+};
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, DrivingSide, Map, ParkingLotID, Path, PathConstraints, PathStep,
-    Traversable, SIDEWALK_THICKNESS,
+    BuildingID, BusRouteID, DrivingSide, LaneID, Map, ParkingLotID, Path, PathConstraints,
+    PathStep, Traversable, SIDEWALK_THICKNESS,
 };
 
 use crate::sim::Ctx;
@@ -14,31 +17,37 @@ use crate::{
     AgentID, AgentProperties, Command, CommutersVehiclesCounts, CreatePedestrian, DistanceInterval,
     DrawPedCrowdInput, DrawPedestrianInput, Event, Intent, IntersectionSimState, ParkedCar,
     ParkingSpot, PedCrowdLocation, PedestrianID, PersonID, Scheduler, SidewalkPOI, SidewalkSpot,
-    TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent,
+    SimOptions, TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent,
 };
 
 const TIME_TO_START_BIKING: Duration = Duration::const_seconds(30.0);
 const TIME_TO_FINISH_BIKING: Duration = Duration::const_seconds(45.0);
 
+/// When crowding is modeled, nobody steps onto a sidewalk or crosswalk with less than this much
+/// space per person, in square meters. They wait at the edge instead.
+const MIN_SPACE_PER_PED: f64 = 0.5;
+/// How often somebody waiting for space to open up checks again
+const CROWDED_RETRY: Duration = Duration::const_seconds(1.0);
+/// After waiting this long for space, people squeeze in anyway, so gridlock can't form.
+const MAX_CROWDED_WAIT: Duration = Duration::const_seconds(60.0);
+
 /// Simulates pedestrians. Unlike vehicles, pedestrians can move bidirectionally on sidewalks and
-/// just "ghost" through each other. There's no queueing or slowdown when many people are
-/// overlapping. They're simply grouped together into a DrawPedCrowdInput for rendering.
+/// just "ghost" through each other. By default there's no queueing or slowdown when many people
+/// are overlapping; they're simply grouped together into a DrawPedCrowdInput for rendering. If
+/// `SimOptions::pedestrian_crowding` is enabled, people walk slower on crowded sidewalks and
+/// crosswalks, and wait at the edge of any that're packed full.
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct WalkingSimState {
     peds: FixedMap<PedestrianID, Pedestrian>,
-    #[serde(
-        serialize_with = "serialize_multimap",
-        deserialize_with = "deserialize_multimap"
-    )]
-    peds_per_traversable: MultiMap<Traversable, PedestrianID>,
+    peds_per_traversable: PedsPerTraversable,
     events: Vec<Event>,
 }
 
 impl WalkingSimState {
-    pub fn new() -> WalkingSimState {
+    pub fn new(opts: &SimOptions) -> WalkingSimState {
         WalkingSimState {
             peds: FixedMap::new(),
-            peds_per_traversable: MultiMap::new(),
+            peds_per_traversable: PedsPerTraversable::new(opts.pedestrian_crowding),
             events: Vec::new(),
         }
     }
@@ -93,7 +102,12 @@ impl WalkingSimState {
                 Line::must_new(driving_pos.pt(map), params.start.sidewalk_pos.pt(map)),
                 TimeInterval::new(now, now + TIME_TO_FINISH_BIKING),
             ),
-            _ => ped.crossing_state(params.start.sidewalk_pos.dist_along(), now, map),
+            _ => ped.crossing_state(
+                params.start.sidewalk_pos.dist_along(),
+                now,
+                map,
+                &self.peds_per_traversable,
+            ),
         };
 
         scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
@@ -101,7 +115,9 @@ impl WalkingSimState {
         self.peds_per_traversable.insert(
             Traversable::Lane(params.start.sidewalk_pos.lane()),
             params.id,
+            map,
         );
+        self.update_crowded_speeds(now, map, scheduler);
     }
 
     pub fn get_draw_ped(
@@ -145,8 +161,11 @@ impl WalkingSimState {
                                 ctx.scheduler
                                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                             } else {
-                                self.peds_per_traversable
-                                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                                self.peds_per_traversable.remove(
+                                    ped.path.current_step().as_traversable(),
+                                    ped.id,
+                                    ctx.map,
+                                );
                                 trips.ped_reached_parking_spot(
                                     now,
                                     ped.id,
@@ -181,14 +200,20 @@ impl WalkingSimState {
                             ) {
                                 ped.state = PedState::WaitingForBus(route, now);
                             } else {
-                                self.peds_per_traversable
-                                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                                self.peds_per_traversable.remove(
+                                    ped.path.current_step().as_traversable(),
+                                    ped.id,
+                                    ctx.map,
+                                );
                                 self.peds.remove(&id);
                             }
                         }
                         SidewalkPOI::Border(i) => {
-                            self.peds_per_traversable
-                                .remove(ped.path.current_step().as_traversable(), ped.id);
+                            self.peds_per_traversable.remove(
+                                ped.path.current_step().as_traversable(),
+                                ped.id,
+                                ctx.map,
+                            );
                             trips.ped_reached_border(
                                 now,
                                 ped.id,
@@ -227,6 +252,7 @@ impl WalkingSimState {
 
                     let dist = dist_int.end;
                     if ped.maybe_transition(
+                        now,
                         now,
                         ctx.map,
                         ctx.intersections,
@@ -237,7 +263,9 @@ impl WalkingSimState {
                         ctx.scheduler
                             .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                     } else {
-                        // Must've failed because we can't turn yet. Don't schedule a retry here.
+                        // Must've failed because we can't turn yet, or the next step is too
+                        // crowded. maybe_transition schedules a retry for the latter; the
+                        // intersection wakes us up for the former.
                         ped.state = PedState::WaitingToTurn(dist, now);
                     }
                 }
@@ -245,6 +273,7 @@ impl WalkingSimState {
             PedState::WaitingToTurn(_, blocked_since) => {
                 if ped.maybe_transition(
                     now,
+                    blocked_since,
                     ctx.map,
                     ctx.intersections,
                     &mut self.peds_per_traversable,
@@ -254,23 +283,34 @@ impl WalkingSimState {
                     ctx.scheduler
                         .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                     ped.total_blocked_time += now - blocked_since;
-                    self.events.push(Event::IntersectionDelayMeasured(
-                        ped.trip,
-                        ped.path.current_step().as_turn(),
-                        AgentID::Pedestrian(id),
-                        now - blocked_since,
-                    ));
+                    // If we were waiting for a crowded sidewalk to clear out, then we didn't just
+                    // start a turn.
+                    if let PathStep::Turn(t) = ped.path.current_step() {
+                        self.events.push(Event::IntersectionDelayMeasured(
+                            ped.trip,
+                            t,
+                            AgentID::Pedestrian(id),
+                            now - blocked_since,
+                        ));
+                    }
                 }
             }
             PedState::LeavingBuilding(b, _) => {
-                ped.state =
-                    ped.crossing_state(ctx.map.get_b(b).sidewalk_pos.dist_along(), now, ctx.map);
+                ped.state = ped.crossing_state(
+                    ctx.map.get_b(b).sidewalk_pos.dist_along(),
+                    now,
+                    ctx.map,
+                    &self.peds_per_traversable,
+                );
                 ctx.scheduler
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
             PedState::EnteringBuilding(bldg, _) => {
-                self.peds_per_traversable
-                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                self.peds_per_traversable.remove(
+                    ped.path.current_step().as_traversable(),
+                    ped.id,
+                    ctx.map,
+                );
                 trips.ped_reached_building(
                     now,
                     ped.id,
@@ -282,14 +322,21 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::LeavingParkingLot(pl, _) => {
-                ped.state =
-                    ped.crossing_state(ctx.map.get_pl(pl).sidewalk_pos.dist_along(), now, ctx.map);
+                ped.state = ped.crossing_state(
+                    ctx.map.get_pl(pl).sidewalk_pos.dist_along(),
+                    now,
+                    ctx.map,
+                    &self.peds_per_traversable,
+                );
                 ctx.scheduler
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
             PedState::EnteringParkingLot(_, _) => {
-                self.peds_per_traversable
-                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                self.peds_per_traversable.remove(
+                    ped.path.current_step().as_traversable(),
+                    ped.id,
+                    ctx.map,
+                );
                 trips.ped_reached_parking_spot(
                     now,
                     ped.id,
@@ -304,8 +351,11 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::StartingToBike(ref spot, _, _) => {
-                self.peds_per_traversable
-                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                self.peds_per_traversable.remove(
+                    ped.path.current_step().as_traversable(),
+                    ped.id,
+                    ctx.map,
+                );
                 trips.ped_ready_to_bike(
                     now,
                     ped.id,
@@ -317,20 +367,48 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::FinishingBiking(ref spot, _, _) => {
-                ped.state = ped.crossing_state(spot.sidewalk_pos.dist_along(), now, ctx.map);
+                ped.state = ped.crossing_state(
+                    spot.sidewalk_pos.dist_along(),
+                    now,
+                    ctx.map,
+                    &self.peds_per_traversable,
+                );
                 ctx.scheduler
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
             PedState::WaitingForBus(_, _) => unreachable!(),
         }
+
+        self.update_crowded_speeds(now, ctx.map, ctx.scheduler);
     }
 
-    pub fn ped_boarded_bus(&mut self, now: Time, id: PedestrianID) {
+    /// When somebody enters or leaves a sidewalk or crosswalk, everybody else walking there speeds
+    /// up or slows down for the rest of the way. Changes from somebody boarding a bus or being
+    /// deleted get picked up the next time anybody moves.
+    fn update_crowded_speeds(&mut self, now: Time, map: &Map, scheduler: &mut Scheduler) {
+        for on in std::mem::take(&mut self.peds_per_traversable.crowding_changed) {
+            for id in self.peds_per_traversable.get(on).clone() {
+                let ped = self.peds.get_mut(&id).unwrap();
+                if ped.path.current_step().as_traversable() != on {
+                    continue;
+                }
+                match ped.state {
+                    PedState::Crossing { ref time_int, .. } if time_int.end > now => {}
+                    _ => continue,
+                }
+                let dist = ped.get_dist_along(now, map);
+                ped.state = ped.crossing_state(dist, now, map, &self.peds_per_traversable);
+                scheduler.update(ped.state.get_end_time(), Command::UpdatePed(id));
+            }
+        }
+    }
+
+    pub fn ped_boarded_bus(&mut self, now: Time, id: PedestrianID, map: &Map) {
         let mut ped = self.peds.remove(&id).unwrap();
         match ped.state {
             PedState::WaitingForBus(_, blocked_since) => {
                 self.peds_per_traversable
-                    .remove(ped.path.current_step().as_traversable(), id);
+                    .remove(ped.path.current_step().as_traversable(), id, map);
                 ped.total_blocked_time += now - blocked_since;
             }
             _ => unreachable!(),
@@ -342,7 +420,7 @@ impl WalkingSimState {
     pub fn delete_ped(&mut self, id: PedestrianID, ctx: &mut Ctx) {
         let ped = self.peds.remove(&id).unwrap();
         self.peds_per_traversable
-            .remove(ped.path.current_step().as_traversable(), id);
+            .remove(ped.path.current_step().as_traversable(), id, ctx.map);
         ctx.scheduler.cancel(Command::UpdatePed(id));
 
         if let PathStep::Turn(t) = ped.path.current_step() {
@@ -544,10 +622,21 @@ impl WalkingSimState {
         (loners, crowds)
     }
 
-    pub fn collect_events(&mut self) -> Vec<Event> {
+    pub fn collect_events(
+        &mut self,
+        map: &Map,
+        peds_on_shared_use: &mut BTreeMap<LaneID, usize>,
+    ) -> Vec<Event> {
+        self.peds_per_traversable
+            .update_changed_lanes(map, &mut self.events, peds_on_shared_use);
         std::mem::take(&mut self.events)
     }
 
+    /// None if crowding isn't modeled.
+    pub fn sidewalk_level_of_service(&self, l: LaneID) -> Option<LevelOfService> {
+        self.peds_per_traversable.level_of_service(l)
+    }
+
     pub fn find_trips_to_parking(&self, evicted_cars: Vec<ParkedCar>) -> Vec<(AgentID, TripID)> {
         let goals: BTreeSet<SidewalkPOI> = evicted_cars
             .into_iter()
@@ -619,7 +708,13 @@ struct Pedestrian {
 }
 
 impl Pedestrian {
-    fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> PedState {
+    fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        map: &Map,
+        peds_per_traversable: &PedsPerTraversable,
+    ) -> PedState {
         let end_dist = if self.path.is_last_step() {
             self.goal.sidewalk_pos.dist_along()
         } else {
@@ -636,6 +731,12 @@ impl Pedestrian {
             PathConstraints::Pedestrian,
             map,
         );
+        let speed = speed
+            * peds_per_traversable.speed_factor(
+                self.path.current_step().as_traversable(),
+                self.id,
+                map,
+            );
         let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / speed);
         PedState::Crossing {
             dist_int,
@@ -774,7 +875,11 @@ impl Pedestrian {
             pos,
             facing,
             waiting_for_turn: match self.state {
-                PedState::WaitingToTurn(_, _) => Some(self.path.next_step().as_turn()),
+                PedState::WaitingToTurn(_, _) => match self.path.next_step() {
+                    PathStep::Turn(t) => Some(t),
+                    // Waiting for a crowded sidewalk to clear out
+                    _ => None,
+                },
                 _ => None,
             },
             intent,
@@ -792,13 +897,23 @@ impl Pedestrian {
     fn maybe_transition(
         &mut self,
         now: Time,
+        blocked_since: Time,
         map: &Map,
         intersections: &mut IntersectionSimState,
-        peds_per_traversable: &mut MultiMap<Traversable, PedestrianID>,
+        peds_per_traversable: &mut PedsPerTraversable,
         events: &mut Vec<Event>,
         scheduler: &mut Scheduler,
     ) -> bool {
-        if let PathStep::Turn(t) = self.path.next_step() {
+        let next = self.path.next_step();
+        if now - blocked_since < MAX_CROWDED_WAIT
+            && !peds_per_traversable.has_room(next.as_traversable(), map)
+        {
+            // The intersection may also wake us up earlier; that's fine, we'll just check again.
+            scheduler.update(now + CROWDED_RETRY, Command::UpdatePed(self.id));
+            return false;
+        }
+
+        if let PathStep::Turn(t) = next {
             if !intersections.maybe_start_turn(
                 AgentID::Pedestrian(self.id),
                 t,
@@ -816,15 +931,15 @@ impl Pedestrian {
             }
         }
 
-        peds_per_traversable.remove(self.path.current_step().as_traversable(), self.id);
+        peds_per_traversable.remove(self.path.current_step().as_traversable(), self.id, map);
         self.path.shift(map);
         let start_dist = match self.path.current_step() {
             PathStep::Lane(_) => Distance::ZERO,
             PathStep::ContraflowLane(l) => map.get_l(l).length(),
             PathStep::Turn(_) => Distance::ZERO,
         };
-        self.state = self.crossing_state(start_dist, now, map);
-        peds_per_traversable.insert(self.path.current_step().as_traversable(), self.id);
+        self.state = self.crossing_state(start_dist, now, map, peds_per_traversable);
+        peds_per_traversable.insert(self.path.current_step().as_traversable(), self.id, map);
         events.push(Event::AgentEntersTraversable(
             AgentID::Pedestrian(self.id),
             Some(self.trip),
@@ -877,6 +992,206 @@ impl PedState {
     }
 }
 
+/// How crowded a sidewalk is, following Fruin's pedestrian level of service. A is free-flowing, F
+/// is a standstill.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LevelOfService {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+}
+
+impl LevelOfService {
+    /// Classify based on the walkable area per person, in square meters.
+    pub fn from_space(per_ped: f64) -> LevelOfService {
+        if per_ped > 3.3 {
+            LevelOfService::A
+        } else if per_ped > 2.3 {
+            LevelOfService::B
+        } else if per_ped > 1.4 {
+            LevelOfService::C
+        } else if per_ped > 0.9 {
+            LevelOfService::D
+        } else if per_ped > 0.5 {
+            LevelOfService::E
+        } else {
+            LevelOfService::F
+        }
+    }
+}
+
+impl std::fmt::Display for LevelOfService {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Tracks who's on each sidewalk and crosswalk. When crowding is modeled, this also decides how
+/// fast people can walk there and tracks the level of service of each sidewalk.
+#[derive(Serialize, Deserialize, Clone)]
+struct PedsPerTraversable {
+    #[serde(
+        serialize_with = "serialize_multimap",
+        deserialize_with = "deserialize_multimap"
+    )]
+    peds: MultiMap<Traversable, PedestrianID>,
+    model_crowding: bool,
+    /// Only sidewalks worse than LevelOfService::A are stored.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    los: BTreeMap<LaneID, LevelOfService>,
+    /// Sidewalks somebody entered or left since the last time events were collected
+    changed: BTreeSet<LaneID>,
+    /// When crowding is modeled, everywhere somebody entered or left since the speed of people
+    /// walking there was last updated
+    crowding_changed: BTreeSet<Traversable>,
+}
+
+impl PedsPerTraversable {
+    fn new(model_crowding: bool) -> PedsPerTraversable {
+        PedsPerTraversable {
+            peds: MultiMap::new(),
+            model_crowding,
+            los: BTreeMap::new(),
+            changed: BTreeSet::new(),
+            crowding_changed: BTreeSet::new(),
+        }
+    }
+
+    fn get(&self, on: Traversable) -> &BTreeSet<PedestrianID> {
+        self.peds.get(on)
+    }
+
+    fn insert(&mut self, on: Traversable, id: PedestrianID, map: &Map) {
+        self.peds.insert(on, id);
+        self.mark_changed(on, map);
+    }
+
+    fn remove(&mut self, on: Traversable, id: PedestrianID, map: &Map) {
+        self.peds.remove(on, id);
+        self.mark_changed(on, map);
+    }
+
+    /// Only sidewalks with a level of service or shared with bikes need to be updated later.
+    fn mark_changed(&mut self, on: Traversable, map: &Map) {
+        if self.model_crowding {
+            self.crowding_changed.insert(on);
+        }
+        if let Traversable::Lane(l) = on {
+            // If live edits deleted the lane, update_changed_lanes cleans up after it
+            if self.model_crowding
+                || map
+                    .maybe_get_l(l)
+                    .map(|lane| lane.is_shared_use())
+                    .unwrap_or(true)
+            {
+                self.changed.insert(l);
+            }
+        }
+    }
+
+    /// Scales somebody's usual walking speed, based on how crowded it is where they're going.
+    fn speed_factor(&self, on: Traversable, id: PedestrianID, map: &Map) -> f64 {
+        if !self.model_crowding {
+            return 1.0;
+        }
+        let peds = self.peds.get(on);
+        let mut count = peds.len();
+        if !peds.contains(&id) {
+            count += 1;
+        }
+        weidmann_speed_factor((count as f64) / walkable_area(on, map))
+    }
+
+    /// Is there space for one more person here?
+    fn has_room(&self, on: Traversable, map: &Map) -> bool {
+        if !self.model_crowding {
+            return true;
+        }
+        walkable_area(on, map) / ((self.peds.get(on).len() + 1) as f64) >= MIN_SPACE_PER_PED
+    }
+
+    fn level_of_service(&self, l: LaneID) -> Option<LevelOfService> {
+        if !self.model_crowding {
+            return None;
+        }
+        Some(self.los.get(&l).cloned().unwrap_or(LevelOfService::A))
+    }
+
+    /// For every sidewalk that changed, update the number of people on shared-use paths, so bikes
+    /// there can slow down. When crowding is modeled, also recalculate the level of service, and
+    /// record an event for each one that got better or worse.
+    fn update_changed_lanes(
+        &mut self,
+        map: &Map,
+        events: &mut Vec<Event>,
+        peds_on_shared_use: &mut BTreeMap<LaneID, usize>,
+    ) {
+        for l in std::mem::take(&mut self.changed) {
+            // Live map edits may have deleted the sidewalk
+            let lane = if let Some(lane) = map.maybe_get_l(l) {
+                lane
+            } else {
+                self.los.remove(&l);
+                peds_on_shared_use.remove(&l);
+                continue;
+            };
+            let on = Traversable::Lane(l);
+            let count = self.peds.get(on).len();
+            if lane.is_shared_use() && count > 0 {
+                peds_on_shared_use.insert(l, count);
+            } else {
+                peds_on_shared_use.remove(&l);
+            }
+            if !self.model_crowding {
+                continue;
+            }
+
+            let los = if count == 0 {
+                LevelOfService::A
+            } else {
+                LevelOfService::from_space(walkable_area(on, map) / (count as f64))
+            };
+            let prev = self.los.get(&l).cloned().unwrap_or(LevelOfService::A);
+            if los == prev {
+                continue;
+            }
+            if los == LevelOfService::A {
+                self.los.remove(&l);
+            } else {
+                self.los.insert(l, los);
+            }
+            events.push(Event::SidewalkLevelOfService(l, los));
+        }
+    }
+}
+
+/// Weidmann's speed-density relationship: how much slower than usual people walk, given the number
+/// of people per square meter. It's a standstill at 5.4 people per square meter, but keep
+/// shuffling forward slowly even then.
+fn weidmann_speed_factor(density: f64) -> f64 {
+    (1.0 - (-1.913 * (1.0 / density - 1.0 / 5.4)).exp()).max(0.1)
+}
+
+/// The area of a sidewalk or crosswalk, in square meters
+fn walkable_area(on: Traversable, map: &Map) -> f64 {
+    let (length, width) = match on {
+        Traversable::Lane(l) => {
+            let lane = map.get_l(l);
+            (lane.length(), lane.width)
+        }
+        // Assume crosswalks are as wide as the sidewalk they start from
+        Traversable::Turn(t) => (map.get_t(t).geom.length(), map.get_l(t.src).width),
+    };
+    // Tiny slivers of sidewalk shouldn't stop everybody
+    (length.inner_meters() * width.inner_meters()).max(1.0)
+}
+
 // The crowds returned here may have low/high values extending up to radius past the real geometry.
 fn find_crowds(
     input: Vec<(PedestrianID, Distance)>,
@@ -927,3 +1242,35 @@ impl IndexableKey for PedestrianID {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_of_service_thresholds() {
+        assert_eq!(LevelOfService::from_space(10.0), LevelOfService::A);
+        assert_eq!(LevelOfService::from_space(3.3), LevelOfService::B);
+        assert_eq!(LevelOfService::from_space(2.5), LevelOfService::B);
+        assert_eq!(LevelOfService::from_space(2.3), LevelOfService::C);
+        assert_eq!(LevelOfService::from_space(1.4), LevelOfService::D);
+        assert_eq!(LevelOfService::from_space(0.9), LevelOfService::E);
+        assert_eq!(LevelOfService::from_space(0.5), LevelOfService::F);
+        assert_eq!(LevelOfService::from_space(0.1), LevelOfService::F);
+    }
+
+    #[test]
+    fn weidmann_slows_down_crowds() {
+        // Nearly alone, people walk at their usual speed
+        assert!(weidmann_speed_factor(0.01) > 0.999);
+        // About 1 person per square meter is noticeably slower
+        let factor = weidmann_speed_factor(1.0);
+        assert!((factor - (1.0 - (-1.913_f64 * (1.0 - 1.0 / 5.4)).exp())).abs() < 1e-9);
+        assert!(factor < 0.8 && factor > 0.7);
+        // More people, slower
+        assert!(weidmann_speed_factor(3.0) < factor);
+        // At and past the jam density, keep shuffling
+        assert!((weidmann_speed_factor(5.4) - 0.1).abs() < 1e-9);
+        assert!((weidmann_speed_factor(8.0) - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,291 @@
+//! Optionally, people decide how to travel right before each trip starts, instead of always using
+//! the mode fixed by the scenario. A multinomial logit model compares the generalized cost of
+//! walking, biking, taking transit, and driving between two buildings, using the current map and
+//! parking availability. This way, edits that make biking better can change who bikes.
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use geom::Duration;
+use map_model::{BuildingID, Map, PathConstraints, PathRequest, TransitAccess, MAX_BIKE_SPEED};
+
+use crate::{
+    CarID, ParkingSim, ParkingSimState, Person, TripEndpoint, TripID, TripInfo, TripMode,
+    VehicleType, MAX_WALK_TO_CAR,
+};
+
+/// The coefficients of the utility function for each mode. Utility is unitless; higher is better.
+/// Only differences in utility between modes matter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModeChoiceModel {
+    /// Utility of each minute of travel time. Should be negative.
+    pub per_minute: f64,
+    /// Utility of each meter climbed on a bike. Should be negative.
+    pub per_meter_climbed_biking: f64,
+    /// Utility of driving somewhere with no free parking nearby right now. Should be negative.
+    pub no_parking_at_destination: f64,
+    /// Constants capturing everything else about each mode, like comfort or the cost of a fare.
+    pub walk_constant: f64,
+    pub bike_constant: f64,
+    pub transit_constant: f64,
+    pub drive_constant: f64,
+}
+
+impl std::default::Default for ModeChoiceModel {
+    fn default() -> ModeChoiceModel {
+        // Rough numbers, not calibrated against any real travel survey
+        ModeChoiceModel {
+            per_minute: -0.05,
+            per_meter_climbed_biking: -0.02,
+            no_parking_at_destination: -1.0,
+            walk_constant: 0.0,
+            bike_constant: -0.5,
+            transit_constant: -0.3,
+            drive_constant: 0.5,
+        }
+    }
+}
+
+impl ModeChoiceModel {
+    /// Decide how somebody will make a trip, returning the mode and the vehicle to use. Returns
+    /// None if the trip isn't eligible for mode choice: only trips between two buildings that
+    /// originally walk, bike, take transit, or drive are. The same trip always rolls the same dice,
+    /// so when comparing two runs, only changes to the options affect the choice.
+    pub(crate) fn choose_mode(
+        &self,
+        id: TripID,
+        trip: &TripInfo,
+        person: &Person,
+        parking: &ParkingSimState,
+        map: &Map,
+    ) -> Option<(TripMode, Option<CarID>)> {
+        let (start, end) = match (trip.start, trip.end) {
+            (TripEndpoint::Bldg(b1), TripEndpoint::Bldg(b2)) => (b1, b2),
+            _ => {
+                return None;
+            }
+        };
+        if !matches!(
+            trip.mode,
+            TripMode::Walk | TripMode::Bike | TripMode::Transit | TripMode::Drive
+        ) || !trip.delivery_stops.is_empty()
+        {
+            return None;
+        }
+
+        let options = self.utilities(start, end, person, parking, map);
+        if options.is_empty() {
+            return None;
+        }
+        Some(logit_draw(id, &options))
+    }
+
+    /// The utility of each mode available for a trip between two buildings.
+    fn utilities(
+        &self,
+        start: BuildingID,
+        end: BuildingID,
+        person: &Person,
+        parking: &ParkingSimState,
+        map: &Map,
+    ) -> Vec<(TripMode, Option<CarID>, f64)> {
+        let mut options = Vec::new();
+        let from = TripEndpoint::Bldg(start);
+        let to = TripEndpoint::Bldg(end);
+
+        if let Some(path) = TripEndpoint::path_req(from, to, TripMode::Walk, map)
+            .and_then(|req| map.pathfind(req).ok())
+        {
+            let time = path.estimate_duration(map, Some(person.ped_speed));
+            options.push((
+                TripMode::Walk,
+                None,
+                self.walk_constant + self.per_minute * time.inner_seconds() / 60.0,
+            ));
+        }
+
+        if let Some(bike) = person
+            .vehicles
+            .iter()
+            .find(|v| v.vehicle_type == VehicleType::Bike)
+        {
+            if let Some(path) = TripEndpoint::path_req(from, to, TripMode::Bike, map)
+                .and_then(|req| map.pathfind(req).ok())
+            {
+                let time = path.estimate_duration(map, Some(MAX_BIKE_SPEED));
+                let (climbed, _) = path.get_total_elevation_change(map);
+                options.push((
+                    TripMode::Bike,
+                    Some(bike.id),
+                    self.bike_constant
+                        + self.per_minute * time.inner_seconds() / 60.0
+                        + self.per_meter_climbed_biking * climbed.inner_meters(),
+                ));
+            }
+        }
+
+        // Only worth considering if riding beats walking the whole way
+        let start_pos = map.get_b(start).sidewalk_pos;
+        let end_pos = map.get_b(end).sidewalk_pos;
+        if let Some(choice) = map.should_use_transit(
+            &[TransitAccess {
+                sidewalk_pos: start_pos,
+                cost: Duration::ZERO,
+            }],
+            &[TransitAccess {
+                sidewalk_pos: end_pos,
+                cost: Duration::ZERO,
+            }],
+        ) {
+            options.push((
+                TripMode::Transit,
+                None,
+                self.transit_constant + self.per_minute * choice.cost.inner_seconds() / 60.0,
+            ));
+        }
+
+        // Driving is only possible if one of their cars is parked close enough to walk to.
+        let start_pt = map.get_b(start).polygon.center();
+        if let Some((car, walk_dist, driving_pos)) = person
+            .vehicles
+            .iter()
+            .filter(|v| v.vehicle_type == VehicleType::Car)
+            .filter_map(|v| parking.lookup_parked_car(v.id))
+            .map(|p| {
+                (
+                    p.vehicle.id,
+                    parking
+                        .spot_to_sidewalk_pos(p.spot, map)
+                        .pt(map)
+                        .dist_to(start_pt),
+                    parking.spot_to_driving_pos(p.spot, &p.vehicle, map),
+                )
+            })
+            .filter(|(_, dist, _)| *dist <= MAX_WALK_TO_CAR)
+            .min_by_key(|(_, dist, _)| *dist)
+        {
+            if let Some(path) = map
+                .get_b(end)
+                .driving_connection(map)
+                .and_then(|(end_pos, _)| {
+                    map.pathfind(PathRequest::vehicle(
+                        driving_pos,
+                        end_pos,
+                        PathConstraints::Car,
+                    ))
+                    .ok()
+                })
+            {
+                let time = walk_dist / person.ped_speed + path.estimate_duration(map, None);
+                let mut utility =
+                    self.drive_constant + self.per_minute * time.inner_seconds() / 60.0;
+                if !parking_available(end, parking, map) {
+                    utility += self.no_parking_at_destination;
+                }
+                options.push((TripMode::Drive, Some(car), utility));
+            }
+        }
+
+        options
+    }
+}
+
+/// Pick one option, with logit probabilities based on each option's utility. The trip seeds the
+/// draw. There must be at least one option.
+fn logit_draw(id: TripID, options: &[(TripMode, Option<CarID>, f64)]) -> (TripMode, Option<CarID>) {
+    // Subtract the best utility first, so exp() can't overflow.
+    let best = options
+        .iter()
+        .map(|(_, _, u)| *u)
+        .fold(std::f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = options.iter().map(|(_, _, u)| (u - best).exp()).collect();
+    let mut rng = XorShiftRng::seed_from_u64(id.0 as u64);
+    let mut roll = rng.gen::<f64>() * weights.iter().sum::<f64>();
+    for ((mode, vehicle, _), weight) in options.iter().zip(weights) {
+        if roll < weight {
+            return (*mode, *vehicle);
+        }
+        roll -= weight;
+    }
+    // Floating point error
+    let (mode, vehicle, _) = options.last().unwrap();
+    (*mode, *vehicle)
+}
+
+/// Is there a free spot in the destination building or along its road right now?
+fn parking_available(b: BuildingID, parking: &ParkingSimState, map: &Map) -> bool {
+    if !parking.get_free_offstreet_spots(b).is_empty() {
+        return true;
+    }
+    map.get_parent(map.get_b(b).sidewalk())
+        .all_lanes()
+        .into_iter()
+        .any(|l| map.get_l(l).is_parking() && !parking.get_free_onstreet_spots(l).is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Vec<(TripMode, Option<CarID>, f64)> {
+        let car = CarID {
+            id: 7,
+            vehicle_type: VehicleType::Car,
+        };
+        vec![
+            (TripMode::Walk, None, -1.5),
+            (TripMode::Transit, None, -0.5),
+            (TripMode::Drive, Some(car), 0.5),
+        ]
+    }
+
+    #[test]
+    fn test_same_trip_same_draw() {
+        let options = options();
+        for id in 0..100 {
+            let first = logit_draw(TripID(id), &options);
+            for _ in 0..3 {
+                assert_eq!(logit_draw(TripID(id), &options), first);
+            }
+        }
+    }
+
+    #[test]
+    fn test_utility_ordering() {
+        let options = options();
+        let n = 10_000;
+        let mut counts = vec![0; options.len()];
+        for id in 0..n {
+            let (mode, vehicle) = logit_draw(TripID(id), &options);
+            let idx = options.iter().position(|(m, _, _)| *m == mode).unwrap();
+            assert_eq!(vehicle, options[idx].1);
+            counts[idx] += 1;
+        }
+        // Higher utility is chosen more often...
+        assert!(counts[0] < counts[1]);
+        assert!(counts[1] < counts[2]);
+        // ...close to the logit probabilities
+        let total: f64 = options.iter().map(|(_, _, u)| u.exp()).sum();
+        for ((_, _, utility), count) in options.iter().zip(counts) {
+            let expected = utility.exp() / total;
+            let actual = (count as f64) / (n as f64);
+            assert!(
+                (expected - actual).abs() < 0.02,
+                "expected {}, got {}",
+                expected,
+                actual
+            );
+        }
+
+        // An overwhelmingly better option always wins, no matter the draw
+        let lopsided = vec![
+            (TripMode::Walk, None, -30.0),
+            (TripMode::Bike, None, 10.0),
+            (TripMode::Transit, None, -25.0),
+        ];
+        for id in 0..100 {
+            assert_eq!(logit_draw(TripID(id), &lopsided), (TripMode::Bike, None));
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! Ride-hailing: a fleet of vehicles picks people up outside the building where their trip starts
+//! and drops them off at their destination. A person requests a ride when their trip starts, then
+//! waits at the curb. A `Dispatcher` matches waiting requests to idle vehicles.
+//!
+//! At both ends, vehicles stop at the curb like delivery vans do: in a free loading zone if there's
+//! one nearby, or double-parked in the driving lane otherwise. Between jobs, vehicles don't cruise
+//! or look for parking. They vanish wherever they dropped off their last passenger and reappear
+//! there when they're dispatched again.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Time};
+use map_model::{BuildingID, Map, PathConstraints, PathRequest, Position, Traversable};
+
+use crate::{
+    AgentID, CarID, Command, CreateCar, Event, PersonID, Router, Scheduler, SimOptions, TripID,
+    TripManager, Vehicle, VehicleSpec, VehicleType, MIN_CAR_LENGTH,
+};
+
+/// How long a vehicle waits at the curb while someone gets in.
+pub(crate) const PICKUP_TIME: Duration = Duration::const_seconds(30.0);
+/// How long a vehicle waits at the curb while someone gets out.
+pub(crate) const DROPOFF_TIME: Duration = Duration::const_seconds(20.0);
+/// Give up on somebody after this many dispatched vehicles couldn't find a route to them.
+const MAX_DISPATCH_ATTEMPTS: usize = 3;
+
+/// Somebody waiting to be picked up.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RideRequest {
+    pub trip: TripID,
+    pub person: PersonID,
+    pub from: BuildingID,
+    pub to: BuildingID,
+    /// Where the vehicle should stop to pick them up
+    pub pickup: Position,
+    /// Where the vehicle should stop to drop them off
+    pub dropoff: Position,
+    pub requested_at: Time,
+}
+
+/// A vehicle waiting off-map for its next job.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdleVehicle {
+    pub id: CarID,
+    /// Where the vehicle will reappear if it's dispatched
+    pub pos: Position,
+    pub idle_since: Time,
+}
+
+/// Decides which idle vehicle picks up which waiting person.
+pub trait Dispatcher {
+    /// Returns pairs of (index into `requests`, vehicle). Requests are sorted by the time they were
+    /// made. Each request and vehicle may appear at most once. Requests that aren't matched keep
+    /// waiting until the dispatcher runs again, which happens whenever somebody new requests a
+    /// ride or a vehicle becomes idle.
+    fn assign(
+        &self,
+        requests: &[RideRequest],
+        idle: &[IdleVehicle],
+        map: &Map,
+    ) -> Vec<(usize, CarID)>;
+}
+
+/// Serve requests in the order they were made, each by the closest idle vehicle, measured as the
+/// crow flies.
+pub struct NearestVehicle;
+
+impl Dispatcher for NearestVehicle {
+    fn assign(
+        &self,
+        requests: &[RideRequest],
+        idle: &[IdleVehicle],
+        map: &Map,
+    ) -> Vec<(usize, CarID)> {
+        nearest_first(requests, idle, |req, v| {
+            v.pos.pt(map).dist_to(req.pickup.pt(map))
+        })
+    }
+}
+
+/// Match each request in order to the closest vehicle that's still available.
+fn nearest_first<F: Fn(&RideRequest, &IdleVehicle) -> Distance>(
+    requests: &[RideRequest],
+    idle: &[IdleVehicle],
+    dist: F,
+) -> Vec<(usize, CarID)> {
+    let mut available: Vec<&IdleVehicle> = idle.iter().collect();
+    let mut assignments = Vec::new();
+    for (idx, req) in requests.iter().enumerate() {
+        if let Some(best) = (0..available.len()).min_by_key(|i| dist(req, available[*i])) {
+            assignments.push((idx, available.remove(best).id));
+        } else {
+            break;
+        }
+    }
+    assignments
+}
+
+/// Serve requests in the order they were made, each by the vehicle that's been idle the longest.
+/// This spreads work evenly over the fleet, at the cost of longer trips to the pickup.
+pub struct LongestIdle;
+
+impl Dispatcher for LongestIdle {
+    fn assign(
+        &self,
+        requests: &[RideRequest],
+        idle: &[IdleVehicle],
+        _: &Map,
+    ) -> Vec<(usize, CarID)> {
+        let mut available: Vec<&IdleVehicle> = idle.iter().collect();
+        available.sort_by_key(|v| v.idle_since);
+        requests
+            .iter()
+            .enumerate()
+            .zip(available)
+            .map(|((idx, _), v)| (idx, v.id))
+            .collect()
+    }
+}
+
+/// Which `Dispatcher` a simulation uses.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DispatchPolicy {
+    NearestVehicle,
+    LongestIdle,
+}
+
+impl DispatchPolicy {
+    pub fn dispatcher(self) -> Box<dyn Dispatcher> {
+        match self {
+            DispatchPolicy::NearestVehicle => Box::new(NearestVehicle),
+            DispatchPolicy::LongestIdle => Box::new(LongestIdle),
+        }
+    }
+}
+
+/// Describes one finished ride, for analytics.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RideHailRide {
+    pub trip: TripID,
+    pub vehicle: CarID,
+    /// From requesting the ride to getting picked up
+    pub wait: Duration,
+    /// Driven without a passenger, from being dispatched to the pickup
+    pub empty_dist: Distance,
+    /// Driven with the passenger
+    pub occupied_dist: Distance,
+    /// From being dispatched to finishing the dropoff
+    pub busy: Duration,
+}
+
+/// What happens when a ride-hail vehicle stops at the curb.
+pub(crate) enum CurbStop {
+    Pickup(RideRequest),
+    Dropoff(RideRequest),
+    /// The person's trip was cancelled while the vehicle was on the way.
+    NoShow,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct FleetVehicle {
+    vehicle: Vehicle,
+    state: FleetState,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+enum FleetState {
+    /// Off-map, since some time
+    Idle(Position, Time),
+    DrivingToPickup(Job),
+    AtPickup(Job),
+    DrivingToDropoff(Job),
+    AtDropoff(Job),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Job {
+    request: RideRequest,
+    dispatched_at: Time,
+    picked_up_at: Option<Time>,
+    empty_dist: Distance,
+    occupied_dist: Distance,
+    /// The lane or turn the vehicle is on, and how far along it was when it started there. Used
+    /// to measure the distance actually driven.
+    on: (Traversable, Distance),
+    cancelled: bool,
+}
+
+impl Job {
+    fn new(request: RideRequest, now: Time, start: Position) -> Job {
+        Job {
+            request,
+            dispatched_at: now,
+            picked_up_at: None,
+            empty_dist: Distance::ZERO,
+            occupied_dist: Distance::ZERO,
+            on: (Traversable::Lane(start.lane()), start.dist_along()),
+            cancelled: false,
+        }
+    }
+
+    /// The vehicle reached `dist` along whatever it's currently on. Count what it drove since the
+    /// last update.
+    fn driven(&mut self, occupied: bool, dist: Distance) {
+        let driven = dist - self.on.1;
+        self.on.1 = dist;
+        if occupied {
+            self.occupied_dist += driven;
+        } else {
+            self.empty_dist += driven;
+        }
+    }
+}
+
+/// Manages the ride-hail fleet and the people waiting for a ride.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct RideHailSimState {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    vehicles: BTreeMap<CarID, FleetVehicle>,
+    /// Sorted by the time the ride was requested
+    waiting: Vec<RideRequest>,
+    /// How many vehicles were dispatched to a waiting request, but couldn't find a route to it
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    failed_dispatches: BTreeMap<TripID, usize>,
+    /// Requests that no vehicle could reach. The caller should cancel these trips.
+    unreachable: Vec<RideRequest>,
+    policy: DispatchPolicy,
+
+    events: Vec<Event>,
+}
+
+impl RideHailSimState {
+    pub fn new(map: &Map, opts: &SimOptions, trips: &mut TripManager) -> RideHailSimState {
+        let mut vehicles = BTreeMap::new();
+        if opts.ride_hail_fleet > 0 {
+            // Spread the fleet out over every building a vehicle can stop in front of
+            let starts: Vec<Position> = map
+                .all_buildings()
+                .iter()
+                .filter_map(|b| b.driving_connection(map).map(|(pos, _)| pos))
+                .collect();
+            if !starts.is_empty() {
+                for i in 0..opts.ride_hail_fleet {
+                    let id = CarID {
+                        id: trips.new_car_id(),
+                        vehicle_type: VehicleType::RideHail,
+                    };
+                    let vehicle = VehicleSpec {
+                        vehicle_type: VehicleType::RideHail,
+                        length: MIN_CAR_LENGTH,
+                        max_speed: None,
+                        passenger_capacity: None,
+                        battery: None,
+                    }
+                    .make(id, None);
+                    let pos = starts[i * starts.len() / opts.ride_hail_fleet];
+                    vehicles.insert(
+                        id,
+                        FleetVehicle {
+                            vehicle,
+                            state: FleetState::Idle(pos, Time::START_OF_DAY),
+                        },
+                    );
+                }
+            }
+        }
+
+        RideHailSimState {
+            vehicles,
+            waiting: Vec::new(),
+            failed_dispatches: BTreeMap::new(),
+            unreachable: Vec::new(),
+            policy: opts.dispatch,
+            events: Vec::new(),
+        }
+    }
+
+    /// Somebody is waiting at the curb. Returns false if there's no fleet to serve them at all.
+    /// Afterwards, the caller should cancel any `take_unreachable` requests.
+    pub fn request_ride(
+        &mut self,
+        now: Time,
+        request: RideRequest,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if self.vehicles.is_empty() {
+            return false;
+        }
+        self.waiting.push(request);
+        for (id, router) in self.dispatch(now, map) {
+            self.spawn(now, id, router, scheduler);
+        }
+        true
+    }
+
+    /// A vehicle stopped at the curb, either double-parked or in a loading zone. Returns what
+    /// happens there and how long the vehicle stays.
+    pub fn vehicle_stopped(&mut self, now: Time, id: CarID) -> (CurbStop, Duration) {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        match v.state.clone() {
+            FleetState::DrivingToPickup(mut job) => {
+                if job.cancelled {
+                    v.state = FleetState::AtDropoff(job);
+                    return (CurbStop::NoShow, Duration::ZERO);
+                }
+                job.picked_up_at = Some(now);
+                let request = job.request.clone();
+                v.state = FleetState::AtPickup(job);
+                (CurbStop::Pickup(request), PICKUP_TIME)
+            }
+            FleetState::DrivingToDropoff(job) => {
+                let request = job.request.clone();
+                v.state = FleetState::AtDropoff(job);
+                (CurbStop::Dropoff(request), DROPOFF_TIME)
+            }
+            FleetState::Idle(_, _) | FleetState::AtPickup(_) | FleetState::AtDropoff(_) => {
+                unreachable!()
+            }
+        }
+    }
+
+    /// A vehicle is done at the curb. Returns the route to follow next, or None if the vehicle
+    /// should vanish and wait off-map for its next job. Also returns the passenger, if they're
+    /// stranded because the vehicle can't reach their destination. Afterwards, the caller should
+    /// cancel any `take_unreachable` requests.
+    pub fn vehicle_departing(
+        &mut self,
+        now: Time,
+        id: CarID,
+        start: Position,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> (Option<Router>, Option<RideRequest>) {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        let mut stranded = None;
+        match v.state.clone() {
+            FleetState::AtPickup(mut job) => {
+                job.driven(false, start.dist_along());
+                job.on = (Traversable::Lane(start.lane()), start.dist_along());
+                let req = PathRequest::vehicle(start, job.request.dropoff, PathConstraints::Car);
+                match map.pathfind(req) {
+                    Ok(path) => {
+                        v.state = FleetState::DrivingToDropoff(job);
+                        return (Some(Router::stop_at_curb(id, path, DROPOFF_TIME)), None);
+                    }
+                    Err(_) => {
+                        stranded = Some(job.request);
+                    }
+                }
+            }
+            FleetState::AtDropoff(mut job) => {
+                job.driven(!job.cancelled, start.dist_along());
+                if !job.cancelled {
+                    self.events.push(Event::RideHailRideFinished(RideHailRide {
+                        trip: job.request.trip,
+                        vehicle: id,
+                        wait: job.picked_up_at.unwrap() - job.request.requested_at,
+                        empty_dist: job.empty_dist,
+                        occupied_dist: job.occupied_dist,
+                        busy: now - job.dispatched_at,
+                    }));
+                }
+            }
+            FleetState::Idle(_, _)
+            | FleetState::DrivingToPickup(_)
+            | FleetState::DrivingToDropoff(_) => unreachable!(),
+        }
+        v.state = FleetState::Idle(start, now);
+
+        // The vehicle is free now. Maybe it can head straight to somebody waiting.
+        let mut next = None;
+        for (car, router) in self.dispatch(now, map) {
+            if car == id {
+                next = Some(router);
+            } else {
+                self.spawn(now, car, router, scheduler);
+            }
+        }
+        (next, stranded)
+    }
+
+    /// Somebody's trip was cancelled, so stop waiting for them.
+    pub fn trip_cancelled(&mut self, trip: TripID) {
+        self.waiting.retain(|req| req.trip != trip);
+        self.failed_dispatches.remove(&trip);
+        for v in self.vehicles.values_mut() {
+            if let FleetState::DrivingToPickup(ref mut job) = v.state {
+                if job.request.trip == trip {
+                    job.cancelled = true;
+                }
+            }
+        }
+    }
+
+    /// A vehicle had to be removed from the map before finishing its job, probably because of live
+    /// map edits. It goes idle where it was. Returns the rider whose trip can't continue, if any.
+    pub fn vehicle_removed(&mut self, now: Time, id: CarID, pos: Position) -> Option<RideRequest> {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        match std::mem::replace(&mut v.state, FleetState::Idle(pos, now)) {
+            FleetState::DrivingToPickup(job) if !job.cancelled => Some(job.request),
+            FleetState::AtPickup(job) | FleetState::DrivingToDropoff(job) => Some(job.request),
+            FleetState::Idle(_, _) | FleetState::DrivingToPickup(_) | FleetState::AtDropoff(_) => {
+                None
+            }
+        }
+    }
+
+    /// Count the distance fleet vehicles actually drive, lane by lane.
+    pub fn handle_event(&mut self, ev: &Event, map: &Map) {
+        if let Event::AgentEntersTraversable(AgentID::Car(car), _, on, _) = ev {
+            if let Some(v) = self.vehicles.get_mut(car) {
+                let (job, occupied) = match v.state {
+                    FleetState::DrivingToPickup(ref mut job) => (job, false),
+                    FleetState::DrivingToDropoff(ref mut job) => (job, true),
+                    _ => {
+                        return;
+                    }
+                };
+                let length = job.on.0.get_polyline(map).length();
+                job.driven(occupied, length);
+                job.on = (*on, Distance::ZERO);
+            }
+        }
+    }
+
+    /// Requests that no vehicle could find a route to, since the last call.
+    pub fn take_unreachable(&mut self) -> Vec<RideRequest> {
+        std::mem::take(&mut self.unreachable)
+    }
+
+    pub fn collect_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Match waiting requests to idle vehicles. Returns the route each dispatched vehicle should
+    /// follow to its pickup; the caller decides how the vehicle starts following it.
+    fn dispatch(&mut self, now: Time, map: &Map) -> Vec<(CarID, Router)> {
+        if self.waiting.is_empty() {
+            return Vec::new();
+        }
+        let idle: Vec<IdleVehicle> = self
+            .vehicles
+            .values()
+            .filter_map(|v| match v.state {
+                FleetState::Idle(pos, idle_since) => Some(IdleVehicle {
+                    id: v.vehicle.id,
+                    pos,
+                    idle_since,
+                }),
+                _ => None,
+            })
+            .collect();
+        if idle.is_empty() {
+            return Vec::new();
+        }
+
+        let mut served = Vec::new();
+        let mut give_up = Vec::new();
+        let mut dispatched = Vec::new();
+        for (idx, id) in self.policy.dispatcher().assign(&self.waiting, &idle, map) {
+            let v = self.vehicles.get_mut(&id).unwrap();
+            let start = match v.state {
+                FleetState::Idle(pos, _) => pos,
+                _ => panic!("Dispatcher assigned {}, but it isn't idle", id),
+            };
+            let request = self.waiting[idx].clone();
+            let req = PathRequest::vehicle(start, request.pickup, PathConstraints::Car);
+            match map.pathfind(req) {
+                Ok(path) => {
+                    self.failed_dispatches.remove(&request.trip);
+                    v.state = FleetState::DrivingToPickup(Job::new(request, now, start));
+                    dispatched.push((id, Router::stop_at_curb(id, path, PICKUP_TIME)));
+                    served.push(idx);
+                }
+                Err(err) => {
+                    warn!("Can't dispatch {} to {}: {}", id, request.trip, err);
+                    // Leave the request for some other vehicle, unless too many have already
+                    // failed to reach it
+                    let attempts = self.failed_dispatches.entry(request.trip).or_insert(0);
+                    *attempts += 1;
+                    if *attempts >= MAX_DISPATCH_ATTEMPTS {
+                        self.failed_dispatches.remove(&request.trip);
+                        give_up.push(idx);
+                    }
+                }
+            }
+        }
+        let mut idx = 0;
+        let mut unreachable = Vec::new();
+        self.waiting.retain(|req| {
+            idx += 1;
+            if give_up.contains(&(idx - 1)) {
+                unreachable.push(req.clone());
+            }
+            !served.contains(&(idx - 1)) && !give_up.contains(&(idx - 1))
+        });
+        self.unreachable.extend(unreachable);
+        dispatched
+    }
+
+    fn spawn(&self, now: Time, id: CarID, router: Router, scheduler: &mut Scheduler) {
+        scheduler.push(
+            now,
+            Command::SpawnCar(
+                CreateCar {
+                    vehicle: self.vehicles[&id].vehicle.clone(),
+                    router,
+                    maybe_parked_car: None,
+                    trip_and_person: None,
+                    maybe_route: None,
+                },
+                true,
+            ),
+        );
+    }
+}
+
+// Queries
+impl RideHailSimState {
+    pub fn fleet_size(&self) -> usize {
+        self.vehicles.len()
+    }
+
+    /// How many vehicles are currently busy with a job?
+    pub fn num_busy(&self) -> usize {
+        self.vehicles
+            .values()
+            .filter(|v| !matches!(v.state, FleetState::Idle(_, _)))
+            .count()
+    }
+
+    pub fn num_waiting(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn get_passenger(&self, id: CarID) -> Option<PersonID> {
+        match self.vehicles.get(&id)?.state {
+            FleetState::AtPickup(ref job) | FleetState::DrivingToDropoff(ref job) => {
+                Some(job.request.person)
+            }
+            FleetState::Idle(_, _) | FleetState::DrivingToPickup(_) | FleetState::AtDropoff(_) => {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map_model::LaneID;
+
+    fn pos(dist: f64) -> Position {
+        Position::new(LaneID(0), Distance::meters(dist))
+    }
+
+    fn car(id: usize) -> CarID {
+        CarID {
+            id,
+            vehicle_type: VehicleType::RideHail,
+        }
+    }
+
+    fn request(id: usize, pickup: f64) -> RideRequest {
+        RideRequest {
+            trip: TripID(id),
+            person: PersonID(id),
+            from: BuildingID(0),
+            to: BuildingID(1),
+            pickup: pos(pickup),
+            dropoff: pos(0.0),
+            requested_at: Time::START_OF_DAY + Duration::seconds(id as f64),
+        }
+    }
+
+    fn idle(id: usize, at: f64, idle_since: f64) -> IdleVehicle {
+        IdleVehicle {
+            id: car(id),
+            pos: pos(at),
+            idle_since: Time::START_OF_DAY + Duration::seconds(idle_since),
+        }
+    }
+
+    #[test]
+    fn test_nearest_vehicle() {
+        let vehicles = vec![idle(0, 0.0, 0.0), idle(1, 100.0, 0.0), idle(2, 500.0, 0.0)];
+        let along_lane = |req: &RideRequest, v: &IdleVehicle| {
+            (req.pickup.dist_along() - v.pos.dist_along()).abs()
+        };
+
+        // The earliest request gets served first, even if a later request is closer to the same
+        // vehicle
+        let requests = vec![request(0, 120.0), request(1, 90.0)];
+        assert_eq!(
+            nearest_first(&requests, &vehicles, along_lane),
+            vec![(0, car(1)), (1, car(0))]
+        );
+
+        // Requests beyond the size of the idle fleet keep waiting
+        let requests = vec![
+            request(0, 450.0),
+            request(1, 10.0),
+            request(2, 90.0),
+            request(3, 300.0),
+        ];
+        assert_eq!(
+            nearest_first(&requests, &vehicles, along_lane),
+            vec![(0, car(2)), (1, car(0)), (2, car(1))]
+        );
+
+        assert!(nearest_first(&requests, &[], along_lane).is_empty());
+    }
+
+    #[test]
+    fn test_longest_idle() {
+        let map = Map::blank();
+        let vehicles = vec![
+            idle(0, 0.0, 60.0),
+            idle(1, 100.0, 10.0),
+            idle(2, 500.0, 30.0),
+        ];
+
+        // Location doesn't matter, only how long each vehicle has been waiting
+        let requests = vec![request(0, 100.0), request(1, 500.0)];
+        assert_eq!(
+            LongestIdle.assign(&requests, &vehicles, &map),
+            vec![(0, car(1)), (1, car(2))]
+        );
+
+        let requests = vec![
+            request(0, 0.0),
+            request(1, 0.0),
+            request(2, 0.0),
+            request(3, 0.0),
+        ];
+        assert_eq!(
+            LongestIdle.assign(&requests, &vehicles, &map),
+            vec![(0, car(1)), (1, car(2)), (2, car(0))]
+        );
+    }
+}
@@ -3,18 +3,20 @@
 
 use std::collections::HashMap;
 
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
-use geom::Distance;
+use geom::{Distance, Duration, Pt2D, Time};
 use map_model::{
     BuildingID, IntersectionID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
     Position, Traversable, Turn, TurnID,
 };
 
-use crate::mechanics::Queue;
+use crate::mechanics::{parking_cost, walking_dist_to, Queue};
 use crate::{
-    AlertLocation, CarID, Event, ParkingSim, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot,
-    TripID, TripPhaseType, Vehicle, VehicleType,
+    AlertLocation, CarID, Event, ParkingSearchStrategy, ParkingSim, ParkingSimState, ParkingSpot,
+    PersonID, Problem, SidewalkSpot, TripID, TripPhaseType, Vehicle, VehicleType,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -23,6 +25,9 @@ pub(crate) struct Router {
     path: Path,
     goal: Goal,
     owner: CarID,
+    /// The last banned turn that had no legal detour, and when to look again. None means the ban
+    /// never ends.
+    gave_up_detour: Option<(TurnID, Option<Time>)>,
 }
 
 #[derive(Debug)]
@@ -33,6 +38,8 @@ pub(crate) enum ActionAtEnd {
     StopBiking(SidewalkSpot),
     BusAtStop,
     GiveUpOnParking,
+    /// No loading zone was free, so stop in the driving lane for this long.
+    DoublePark(Duration),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -44,6 +51,15 @@ enum Goal {
         /// No parking available at all!
         stuck_end_dist: Option<Distance>,
         started_looking: bool,
+        /// How many times the driver has had to look for parking beyond the lane where they first
+        /// tried
+        search_hops: usize,
+        /// Electric cars with a low battery look for a charger, until they fail to find one nearby
+        looking_for_charger: bool,
+        /// Set once an electric car runs low in the middle of the trip. True if it's heading for a
+        /// charger, after which the trip continues to the target. False if it decided not to
+        /// detour.
+        charging_stop: Option<bool>,
     },
     EndAtBorder {
         end_dist: Distance,
@@ -55,6 +71,16 @@ enum Goal {
     FollowBusRoute {
         end_dist: Distance,
     },
+    /// Stop at the curb for some time, preferably in a loading zone. Delivery vans and ride-hail
+    /// vehicles use this.
+    StopAtCurb {
+        dwell: Duration,
+        /// Where to double-park if no loading zone is free
+        end_dist: Distance,
+        /// Loading zone spot and cached distance along the last driving lane
+        spot: Option<(ParkingSpot, Distance)>,
+        started_looking: bool,
+    },
 }
 
 impl Router {
@@ -68,6 +94,7 @@ impl Router {
             path,
             goal: Goal::EndAtBorder { end_dist, i },
             owner,
+            gave_up_detour: None,
         }
     }
     pub fn vanish_bus(owner: CarID, start: Position, map: &Map) -> Router {
@@ -82,6 +109,7 @@ impl Router {
                 i: lane.dst_i,
             },
             owner,
+            gave_up_detour: None,
         }
     }
 
@@ -93,8 +121,12 @@ impl Router {
                 spot: None,
                 stuck_end_dist: None,
                 started_looking: false,
+                search_hops: 0,
+                looking_for_charger: true,
+                charging_stop: None,
             },
             owner,
+            gave_up_detour: None,
         }
     }
 
@@ -103,6 +135,7 @@ impl Router {
             goal: Goal::BikeThenStop { goal },
             path,
             owner,
+            gave_up_detour: None,
         }
     }
 
@@ -113,6 +146,21 @@ impl Router {
             },
             path,
             owner,
+            gave_up_detour: None,
+        }
+    }
+
+    pub fn stop_at_curb(owner: CarID, path: Path, dwell: Duration) -> Router {
+        Router {
+            goal: Goal::StopAtCurb {
+                dwell,
+                end_dist: path.get_req().end.dist_along(),
+                spot: None,
+                started_looking: false,
+            },
+            path,
+            owner,
+            gave_up_detour: None,
         }
     }
 
@@ -144,6 +192,7 @@ impl Router {
             } => stuck_end_dist.unwrap_or_else(|| spot.unwrap().1),
             Goal::BikeThenStop { ref goal } => goal.sidewalk_pos.dist_along(),
             Goal::FollowBusRoute { end_dist } => end_dist,
+            Goal::StopAtCurb { end_dist, spot, .. } => spot.map(|(_, d)| d).unwrap_or(end_dist),
         }
     }
 
@@ -157,10 +206,12 @@ impl Router {
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
+        parking_search: ParkingSearchStrategy,
         trip_and_person: Option<(TripID, PersonID)>,
         events: &mut Vec<Event>,
     ) -> Traversable {
         let prev = self.path.shift(map).as_traversable();
+        self.maybe_detour_to_charger(vehicle, parking, map, events);
         if self.last_step() {
             // Do this to trigger the side-effect of looking for parking.
             self.maybe_handle_end(
@@ -168,6 +219,7 @@ impl Router {
                 vehicle,
                 parking,
                 map,
+                parking_search,
                 trip_and_person,
                 events,
             );
@@ -196,6 +248,7 @@ impl Router {
         parking: &ParkingSimState,
         map: &Map,
         // TODO Not so nice to plumb all of this here
+        parking_search: ParkingSearchStrategy,
         trip_and_person: Option<(TripID, PersonID)>,
         events: &mut Vec<Event>,
     ) -> Option<ActionAtEnd> {
@@ -214,6 +267,8 @@ impl Router {
                 ref mut stuck_end_dist,
                 target,
                 ref mut started_looking,
+                ref mut search_hops,
+                ref mut looking_for_charger,
             } => {
                 if let Some(d) = stuck_end_dist {
                     if *d == front {
@@ -230,33 +285,122 @@ impl Router {
                 if need_new_spot {
                     *started_looking = true;
                     let current_lane = self.path.current_step().as_lane();
-                    let candidates = parking.get_all_free_spots(
+                    let mut candidates = parking.get_all_free_spots(
                         Position::new(current_lane, front),
                         vehicle,
                         target,
                         map,
                     );
-                    let best =
-                        if let Some((driving_pos, _)) = map.get_b(target).driving_connection(map) {
-                            if driving_pos.lane() == current_lane {
-                                let target_dist = driving_pos.dist_along();
-                                // Closest to the building
-                                candidates
-                                    .into_iter()
-                                    .min_by_key(|(_, pos)| (pos.dist_along() - target_dist).abs())
-                            } else {
-                                // Closest to the road endpoint, I guess
-                                candidates
-                                    .into_iter()
-                                    .min_by_key(|(_, pos)| pos.dist_along())
+                    if *looking_for_charger
+                        && vehicle
+                            .battery
+                            .map(|battery| battery.needs_charging())
+                            .unwrap_or(false)
+                    {
+                        let chargers: Vec<(ParkingSpot, Position)> = candidates
+                            .iter()
+                            .filter(|(spot, _)| spot.has_charger(map))
+                            .cloned()
+                            .collect();
+                        if !chargers.is_empty() {
+                            candidates = chargers;
+                        } else if let Some((new_path_steps, new_spot, new_pos)) =
+                            path_to_charger(current_lane, vehicle, target, parking, map)
+                        {
+                            for step in new_path_steps {
+                                self.path.add(step, map);
+                            }
+                            *spot = Some((new_spot, new_pos.dist_along()));
+                            events.push(Event::PathAmended(self.path.clone()));
+                            if let Some((t, p)) = trip_and_person {
+                                events.push(Event::TripPhaseStarting(
+                                    t,
+                                    p,
+                                    Some(PathRequest::vehicle(
+                                        Position::new(current_lane, front),
+                                        new_pos,
+                                        PathConstraints::Car,
+                                    )),
+                                    TripPhaseType::Parking,
+                                ));
                             }
+                            return Some(ActionAtEnd::GotoLaneEnd);
                         } else {
-                            // Closest to the road endpoint, I guess
-                            candidates
-                                .into_iter()
-                                .min_by_key(|(_, pos)| pos.dist_along())
-                        };
-                    if let Some((new_spot, new_pos)) = best {
+                            // Settle for a regular spot
+                            *looking_for_charger = false;
+                            if let Some((t, _)) = trip_and_person {
+                                events.push(Event::ProblemEncountered(
+                                    t,
+                                    Problem::NoChargerAvailable(current_lane),
+                                ));
+                            }
+                        }
+                    }
+                    let target_dist = match map.get_b(target).driving_connection(map) {
+                        Some((driving_pos, _)) if driving_pos.lane() == current_lane => {
+                            Some(driving_pos.dist_along())
+                        }
+                        _ => None,
+                    };
+                    let best = candidates
+                        .into_iter()
+                        .map(|(spot, pos)| {
+                            let walking_dist = if let Some(dist) = target_dist {
+                                (pos.dist_along() - dist).abs()
+                            } else {
+                                walking_dist_to(pos, target, map)
+                            };
+                            let cost =
+                                parking_cost(spot, pos.dist_along() - front, walking_dist, map);
+                            (cost, spot, pos)
+                        })
+                        .min_by_key(|(cost, _, _)| *cost);
+                    // If parking here costs money or doesn't allow a long enough stay, maybe it's
+                    // worth driving a bit further.
+                    if let Some((cost, local_spot, _)) = best {
+                        if cost.0 || !local_spot.pricing(map).is_free() {
+                            if let Some((new_path_steps, new_spot, new_pos)) = parking
+                                .path_to_free_parking_spot(current_lane, vehicle, target, map)
+                            {
+                                let driving_dist = map.get_l(current_lane).length() - front
+                                    + new_path_steps
+                                        .iter()
+                                        .take(new_path_steps.len() - 1)
+                                        .map(|step| {
+                                            step.as_traversable().get_polyline(map).length()
+                                        })
+                                        .sum::<Distance>()
+                                    + new_pos.dist_along();
+                                let other_cost = parking_cost(
+                                    new_spot,
+                                    driving_dist,
+                                    walking_dist_to(new_pos, target, map),
+                                    map,
+                                );
+                                if other_cost < cost {
+                                    for step in new_path_steps {
+                                        self.path.add(step, map);
+                                    }
+                                    *spot = Some((new_spot, new_pos.dist_along()));
+                                    events.push(Event::PathAmended(self.path.clone()));
+                                    if let Some((t, p)) = trip_and_person {
+                                        events.push(Event::TripPhaseStarting(
+                                            t,
+                                            p,
+                                            Some(PathRequest::vehicle(
+                                                Position::new(current_lane, front),
+                                                new_pos,
+                                                PathConstraints::Car,
+                                            )),
+                                            TripPhaseType::Parking,
+                                        ));
+                                    }
+                                    return Some(ActionAtEnd::GotoLaneEnd);
+                                }
+                            }
+                        }
+                    }
+                    if let Some((_, new_spot, new_pos)) = best {
                         if let Some((t, p)) = trip_and_person {
                             events.push(Event::TripPhaseStarting(
                                 t,
@@ -273,27 +417,44 @@ impl Router {
                         assert!(new_pos.dist_along() >= front);
                         *spot = Some((new_spot, new_pos.dist_along()));
                     } else {
-                        if let Some((new_path_steps, new_spot, new_pos)) =
-                            parking.path_to_free_parking_spot(current_lane, vehicle, target, map)
-                        {
+                        *search_hops += 1;
+                        if let Some((new_path_steps, new_spot)) = search_for_parking(
+                            parking_search,
+                            *search_hops,
+                            current_lane,
+                            vehicle,
+                            target,
+                            parking,
+                            map,
+                        ) {
                             assert!(!new_path_steps.is_empty());
+                            let end_pos = match new_spot {
+                                Some((_, pos)) => pos,
+                                None => {
+                                    Position::end(new_path_steps.last().unwrap().as_lane(), map)
+                                }
+                            };
                             for step in new_path_steps {
                                 self.path.add(step, map);
                             }
-                            *spot = Some((new_spot, new_pos.dist_along()));
+                            *spot = new_spot.map(|(s, pos)| (s, pos.dist_along()));
                             events.push(Event::PathAmended(self.path.clone()));
+                            // Only the first time the driver starts circling counts as a new
+                            // phase.
                             // TODO This path might not be the same as the one found here...
-                            if let Some((t, p)) = trip_and_person {
-                                events.push(Event::TripPhaseStarting(
-                                    t,
-                                    p,
-                                    Some(PathRequest::vehicle(
-                                        Position::new(current_lane, front),
-                                        new_pos,
-                                        PathConstraints::Car,
-                                    )),
-                                    TripPhaseType::Parking,
-                                ));
+                            if *search_hops == 1 {
+                                if let Some((t, p)) = trip_and_person {
+                                    events.push(Event::TripPhaseStarting(
+                                        t,
+                                        p,
+                                        Some(PathRequest::vehicle(
+                                            Position::new(current_lane, front),
+                                            end_pos,
+                                            PathConstraints::Car,
+                                        )),
+                                        TripPhaseType::SearchingForParking,
+                                    ));
+                                }
                             }
                         } else {
                             if let Some((_, p)) = trip_and_person {
@@ -332,6 +493,56 @@ impl Router {
                     None
                 }
             }
+            Goal::StopAtCurb {
+                dwell,
+                ref mut end_dist,
+                ref mut spot,
+                ref mut started_looking,
+            } => {
+                // Use the free loading zone closest to where we'd otherwise double-park. Only
+                // spots ahead of us are candidates.
+                let current_lane = self.path.current_step().as_lane();
+                let closest_loading_zone = |want: Distance| {
+                    parking
+                        .get_free_loading_zone_spots(
+                            Position::new(current_lane, front),
+                            vehicle,
+                            map,
+                        )
+                        .into_iter()
+                        .min_by_key(|(_, pos)| (pos.dist_along() - want).abs())
+                        .map(|(s, pos)| (s, pos.dist_along()))
+                };
+
+                if !*started_looking {
+                    *started_looking = true;
+                    *spot = closest_loading_zone(*end_dist);
+                }
+
+                if let Some((s, _)) = *spot {
+                    if !parking.is_free(s) {
+                        // Somebody else took the loading zone. Try another one further along the
+                        // lane before giving up and double-parking here.
+                        *spot = closest_loading_zone(*end_dist);
+                        if spot.is_none() {
+                            *end_dist = (*end_dist).max(front);
+                        }
+                    }
+                }
+                if let Some((s, dist)) = *spot {
+                    return if dist == front {
+                        Some(ActionAtEnd::StartParking(s))
+                    } else {
+                        None
+                    };
+                }
+
+                if *end_dist == front {
+                    Some(ActionAtEnd::DoublePark(dwell))
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -497,19 +708,391 @@ impl Router {
         self.path.modify_step(1, PathStep::Turn(turn), map);
     }
 
+    /// Routing only discourages part-time turn bans, so when a vehicle reaches a turn banned right
+    /// now, switch to another turn from the current lane and route from there. Returns true if
+    /// the path changed. If there's no legal way to continue, the vehicle makes the banned turn,
+    /// and this won't search again until the ban ends.
+    pub fn detour_around_ban(&mut self, now: Time, vehicle: &Vehicle, map: &Map) -> bool {
+        let constraints = self.owner.vehicle_type.to_constraints();
+        let banned_now = |t: TurnID| {
+            map.get_i(t.parent)
+                .get_movement_ban(t.to_movement(map), constraints)
+                .map(|ban| ban.is_active(now))
+                .unwrap_or(false)
+        };
+
+        let banned_turn = match self.path.maybe_next_step() {
+            Some(PathStep::Turn(t)) => t,
+            _ => {
+                return false;
+            }
+        };
+        if !banned_now(banned_turn)
+            || self.path.currently_inside_ut().is_some()
+            || self.path.about_to_start_ut().is_some()
+        {
+            return false;
+        }
+        // Don't keep searching every time the vehicle waits at the same ban
+        if let Some((turn, retry_at)) = self.gave_up_detour {
+            if turn == banned_turn && retry_at.map(|t| now < t).unwrap_or(true) {
+                return false;
+            }
+        }
+
+        // Keep heading to the same place
+        let last_lane = self.path.last_step().as_lane();
+        let end = if last_lane == self.path.get_req().end.lane() {
+            self.path.get_req().end
+        } else {
+            Position::end(last_lane, map)
+        };
+
+        let best = map
+            .get_turns_from_lane(banned_turn.src)
+            .into_iter()
+            .filter(|t| {
+                t.id != banned_turn
+                    && !banned_now(t.id)
+                    && constraints.can_use(map.get_l(t.id.dst), map)
+            })
+            .filter_map(|t| {
+                let path = map
+                    .pathfind(PathRequest::vehicle(
+                        Position::start(t.id.dst),
+                        end,
+                        constraints,
+                    ))
+                    .ok()?;
+                if path.get_steps().iter().any(|step| match step {
+                    PathStep::Turn(t) => banned_now(*t),
+                    _ => false,
+                }) {
+                    return None;
+                }
+                Some((t.id, path))
+            })
+            .min_by_key(|(_, path)| path.estimate_duration(map, vehicle.max_speed));
+        match best {
+            Some((turn, path)) => {
+                self.path.detour(turn, path, map);
+                self.gave_up_detour = None;
+                true
+            }
+            None => {
+                let retry_at = map
+                    .get_i(banned_turn.parent)
+                    .get_movement_ban(banned_turn.to_movement(map), constraints)
+                    .and_then(|ban| ban.active_until(now));
+                self.gave_up_detour = Some((banned_turn, retry_at));
+                false
+            }
+        }
+    }
+
+    /// An electric car whose battery runs low in the middle of a trip heads for the closest free
+    /// charger, unless there's one near the destination anyway. After charging there, the trip
+    /// continues.
+    fn maybe_detour_to_charger(
+        &mut self,
+        vehicle: &Vehicle,
+        parking: &ParkingSimState,
+        map: &Map,
+        events: &mut Vec<Event>,
+    ) {
+        if !vehicle
+            .battery
+            .map(|battery| battery.needs_charging())
+            .unwrap_or(false)
+            || self.path.is_last_step()
+            || self.path.currently_inside_ut().is_some()
+        {
+            return;
+        }
+        let current_lane = match self.head() {
+            Traversable::Lane(l) => l,
+            Traversable::Turn(_) => {
+                return;
+            }
+        };
+        if let Goal::ParkNearBuilding {
+            target,
+            ref mut spot,
+            started_looking,
+            looking_for_charger,
+            ref mut charging_stop,
+            ..
+        } = self.goal
+        {
+            if started_looking || !looking_for_charger || charging_stop.is_some() {
+                return;
+            }
+            // Only consider this once per trip
+            *charging_stop = Some(false);
+            if nearest_free_charger(
+                map.get_b(target).polygon.center(),
+                MAX_CHARGER_DISTANCE,
+                parking,
+                map,
+            )
+            .is_some()
+            {
+                return;
+            }
+            let charger = match nearest_free_charger(
+                map.get_l(current_lane).lane_center_pts.last_pt(),
+                MAX_CHARGER_DETOUR,
+                parking,
+                map,
+            ) {
+                Some(x) => x,
+                None => {
+                    return;
+                }
+            };
+            let pos = parking.spot_to_driving_pos(charger, vehicle, map);
+            if pos.lane() == current_lane {
+                return;
+            }
+            if let Some(steps) = path_from_end_of_lane(current_lane, pos, map) {
+                self.path.replace_rest(steps, map);
+                *spot = Some((charger, pos.dist_along()));
+                *charging_stop = Some(true);
+                events.push(Event::PathAmended(self.path.clone()));
+            }
+        }
+    }
+
+    /// Is an electric car heading to a charger in the middle of its trip?
+    pub fn is_charging_stop(&self) -> bool {
+        matches!(
+            self.goal,
+            Goal::ParkNearBuilding {
+                charging_stop: Some(true),
+                ..
+            }
+        )
+    }
+
     pub fn is_parking(&self) -> bool {
         match self.goal {
             Goal::ParkNearBuilding {
                 started_looking, ..
             } => started_looking,
+            Goal::StopAtCurb { spot, .. } => spot.is_some(),
             _ => false,
         }
     }
 
     pub fn get_parking_spot_goal(&self) -> Option<&ParkingSpot> {
         match self.goal {
-            Goal::ParkNearBuilding { ref spot, .. } => spot.as_ref().map(|(s, _)| s),
+            Goal::ParkNearBuilding { ref spot, .. } | Goal::StopAtCurb { ref spot, .. } => {
+                spot.as_ref().map(|(s, _)| s)
+            }
             _ => None,
         }
     }
 }
+
+/// When circling around looking for parking, give up after this many blocks and head for the
+/// nearest free spot.
+const MAX_RANDOM_WALK_HOPS: usize = 20;
+/// When circling around looking for parking, try to stay this close to the destination.
+const RANDOM_WALK_RADIUS: Distance = Distance::const_meters(400.0);
+/// Only head for parking lots this close to the destination.
+const MAX_LOT_DISTANCE: Distance = Distance::const_meters(800.0);
+/// Electric cars with a low battery park at chargers this close to the destination.
+const MAX_CHARGER_DISTANCE: Distance = Distance::const_meters(800.0);
+/// If there's no charger near the destination, electric cars running low in the middle of a trip
+/// detour to a charger this close to them.
+const MAX_CHARGER_DETOUR: Distance = Distance::const_meters(3000.0);
+
+/// There's no free spot on the current lane. Returns steps to add to the path (starting with the
+/// turn after current_lane), and maybe the spot to head for. If there's no spot yet, the driver
+/// will look again at the end of the new path.
+fn search_for_parking(
+    strategy: ParkingSearchStrategy,
+    hops: usize,
+    current_lane: LaneID,
+    vehicle: &Vehicle,
+    target: BuildingID,
+    parking: &ParkingSimState,
+    map: &Map,
+) -> Option<(Vec<PathStep>, Option<(ParkingSpot, Position)>)> {
+    match strategy {
+        ParkingSearchStrategy::NearestFirst => {}
+        ParkingSearchStrategy::RandomWalk => {
+            if hops <= MAX_RANDOM_WALK_HOPS {
+                if let Some(steps) = random_walk(hops, current_lane, vehicle, target, map) {
+                    return Some((steps, None));
+                }
+            }
+        }
+        ParkingSearchStrategy::HeadToLot => {
+            if let Some((steps, spot, pos)) =
+                path_to_parking_lot(current_lane, vehicle, target, parking, map)
+            {
+                return Some((steps, Some((spot, pos))));
+            }
+        }
+    }
+
+    parking
+        .path_to_free_parking_spot(current_lane, vehicle, target, map)
+        .map(|(steps, spot, pos)| (steps, Some((spot, pos))))
+}
+
+/// Drive to one of the next lanes, picked randomly among the ones staying near the destination.
+fn random_walk(
+    hops: usize,
+    current_lane: LaneID,
+    vehicle: &Vehicle,
+    target: BuildingID,
+    map: &Map,
+) -> Option<Vec<PathStep>> {
+    let target_pt = map.get_b(target).polygon.center();
+    let current_road = map.get_l(current_lane).parent;
+    // Deterministic across runs of the same simulation, but different for every decision
+    let mut rng = XorShiftRng::seed_from_u64((vehicle.id.id + current_lane.0 + hops) as u64);
+
+    let mut nearby = Vec::new();
+    let mut closest: Option<(Distance, TurnID)> = None;
+    for turn in map.get_turns_for(current_lane, PathConstraints::Car) {
+        let lane = map.get_l(turn.id.dst);
+        // Avoid U-turns; circling around the block is more realistic
+        if lane.parent == current_road {
+            continue;
+        }
+        let dist = lane.lane_center_pts.middle().dist_to(target_pt);
+        if dist <= RANDOM_WALK_RADIUS {
+            nearby.push(turn.id);
+        }
+        if closest.map(|(d, _)| dist < d).unwrap_or(true) {
+            closest = Some((dist, turn.id));
+        }
+    }
+
+    let turn = if nearby.is_empty() {
+        closest?.1
+    } else {
+        nearby[rng.gen_range(0..nearby.len())]
+    };
+    Some(vec![PathStep::Turn(turn), PathStep::Lane(turn.dst)])
+}
+
+/// Head for a free spot in the cheapest parking lot near the destination, weighing price, the
+/// walk, and roughly how far away the lot is.
+fn path_to_parking_lot(
+    current_lane: LaneID,
+    vehicle: &Vehicle,
+    target: BuildingID,
+    parking: &ParkingSimState,
+    map: &Map,
+) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
+    let target_pt = map.get_b(target).polygon.center();
+    let current_pt = map.get_l(current_lane).last_pt();
+    let lot = map
+        .all_parking_lots()
+        .iter()
+        .filter(|pl| {
+            // Lots on the current lane would've already been found
+            pl.driving_pos.lane() != current_lane
+                && pl.polygon.center().dist_to(target_pt) <= MAX_LOT_DISTANCE
+        })
+        .filter_map(|pl| {
+            let spot = parking.get_free_lot_spots(pl.id).into_iter().next()?;
+            let cost = parking_cost(
+                spot,
+                current_pt.dist_to(pl.driving_pos.pt(map)),
+                pl.polygon.center().dist_to(target_pt),
+                map,
+            );
+            Some((cost, pl))
+        })
+        .min_by_key(|(cost, _)| *cost)?
+        .1;
+    let (spot, pos) = parking
+        .get_all_free_spots(
+            Position::start(lot.driving_pos.lane()),
+            vehicle,
+            target,
+            map,
+        )
+        .into_iter()
+        .find(|(spot, _)| matches!(spot, ParkingSpot::Lot(pl, _) if *pl == lot.id))?;
+    let steps = path_from_end_of_lane(current_lane, pos, map)?;
+    Some((steps, spot, pos))
+}
+
+/// Head for the closest free spot with a charger, if there's one nearby.
+fn path_to_charger(
+    current_lane: LaneID,
+    vehicle: &Vehicle,
+    target: BuildingID,
+    parking: &ParkingSimState,
+    map: &Map,
+) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
+    let spot = nearest_free_charger(
+        map.get_b(target).polygon.center(),
+        MAX_CHARGER_DISTANCE,
+        parking,
+        map,
+    )?;
+    let pos = parking.spot_to_driving_pos(spot, vehicle, map);
+    let steps = path_from_end_of_lane(current_lane, pos, map)?;
+    Some((steps, spot, pos))
+}
+
+/// Finds the closest free spot with a charger, as the crow flies.
+fn nearest_free_charger(
+    from: Pt2D,
+    max_dist: Distance,
+    parking: &ParkingSimState,
+    map: &Map,
+) -> Option<ParkingSpot> {
+    let mut best: Option<(Distance, ParkingSpot)> = None;
+    let mut consider = |pt: Pt2D, free_spots: Vec<ParkingSpot>| {
+        let dist = pt.dist_to(from);
+        if dist > max_dist || best.map(|(d, _)| dist >= d).unwrap_or(false) {
+            return;
+        }
+        if let Some(spot) = free_spots.into_iter().find(|spot| spot.has_charger(map)) {
+            best = Some((dist, spot));
+        }
+    };
+    for l in map.all_lanes().values() {
+        if l.is_parking() && l.charging_spots > 0 {
+            consider(
+                l.lane_center_pts.middle(),
+                parking.get_free_onstreet_spots(l.id),
+            );
+        }
+    }
+    for pl in map.all_parking_lots() {
+        if pl.charging_spots > 0 {
+            consider(pl.polygon.center(), parking.get_free_lot_spots(pl.id));
+        }
+    }
+    best.map(|(_, spot)| spot)
+}
+
+/// Returns the steps to reach some position from the end of the current lane, starting with the
+/// turn after current_lane.
+fn path_from_end_of_lane(current_lane: LaneID, pos: Position, map: &Map) -> Option<Vec<PathStep>> {
+    let path = map
+        .pathfind(PathRequest::vehicle(
+            Position::end(current_lane, map),
+            pos,
+            PathConstraints::Car,
+        ))
+        .ok()?;
+    if path.get_steps().front() != Some(&PathStep::Lane(current_lane)) {
+        return None;
+    }
+    // Don't include PathStep::Lane(current_lane)
+    let steps: Vec<PathStep> = path.get_steps().iter().skip(1).cloned().collect();
+    if steps.is_empty() {
+        return None;
+    }
+    Some(steps)
+}
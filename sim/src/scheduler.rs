@@ -27,6 +27,7 @@ pub(crate) enum Command {
     Pandemic(pandemic::Cmd),
     /// The Time is redundant, just used to dedupe commands
     StartBus(BusRouteID, Time),
+    RebalanceBikeShare,
 }
 
 impl Command {
@@ -50,6 +51,7 @@ impl Command {
             Command::Callback(_) => CommandType::Callback,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
             Command::StartBus(r, t) => CommandType::StartBus(*r, *t),
+            Command::RebalanceBikeShare => CommandType::RebalanceBikeShare,
         }
     }
 
@@ -65,6 +67,7 @@ impl Command {
             Command::Callback(_) => SimpleCommandType::Callback,
             Command::Pandemic(_) => SimpleCommandType::Pandemic,
             Command::StartBus(_, _) => SimpleCommandType::StartBus,
+            Command::RebalanceBikeShare => SimpleCommandType::RebalanceBikeShare,
         }
     }
 }
@@ -81,6 +84,7 @@ enum CommandType {
     Callback,
     Pandemic(pandemic::Cmd),
     StartBus(BusRouteID, Time),
+    RebalanceBikeShare,
 }
 
 /// A more compressed form of CommandType, just used for keeping stats on event processing.
@@ -94,6 +98,7 @@ enum SimpleCommandType {
     Callback,
     Pandemic,
     StartBus,
+    RebalanceBikeShare,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
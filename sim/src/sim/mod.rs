@@ -1,6 +1,6 @@
 // This file has a jumbled mess of queries, setup, and mutating methods.
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::panic;
 
 use anyhow::Result;
@@ -10,18 +10,22 @@ use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
-use abstutil::{prettyprint_usize, serialized_size_bytes, CmdArgs, Timer};
+use abstutil::{
+    deserialize_btreemap, prettyprint_usize, serialize_btreemap, serialized_size_bytes, CmdArgs,
+    Timer,
+};
 use geom::{Distance, Duration, Speed, Time};
 use map_model::{
-    BuildingID, BusRoute, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints,
-    PathRequest, Position, Traversable,
+    BuildingID, BusRoute, DrivingSide, IntersectionID, LaneID, Map, MovementID, ParkingLotID, Path,
+    PathConstraints, PathRequest, Position, Traversable, TurnType,
 };
 
 pub use self::queries::{AgentProperties, DelayCause};
 use crate::{
-    AgentID, AlertLocation, Analytics, CarID, Command, CreateCar, DrivingSimState, Event,
-    IntersectionSimState, OrigPersonID, PandemicModel, ParkedCar, ParkingSim, ParkingSimState,
-    ParkingSpot, Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs,
+    AgentID, AlertLocation, Analytics, BikeShareSimState, CarID, Command, CreateCar,
+    DispatchPolicy, DrivingSimState, EmissionsMethod, Event, IntersectionSimState, ModeChoiceModel,
+    OrigPersonID, PandemicModel, ParkedCar, ParkingSim, ParkingSimState, ParkingSpot, Person,
+    PersonID, RideHailSimState, Router, Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs,
     TrafficRecorder, TransitSimState, TripID, TripInfo, TripManager, TripPhaseType, Vehicle,
     VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
 };
@@ -39,7 +43,15 @@ pub struct Sim {
     walking: WalkingSimState,
     intersections: IntersectionSimState,
     transit: TransitSimState,
+    ride_hail: RideHailSimState,
+    bike_share: BikeShareSimState,
     trips: TripManager,
+    /// How many people are walking along each shared-use path, so bikes can slow down
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    peds_on_shared_use: BTreeMap<LaneID, usize>,
     #[serde(skip_serializing, skip_deserializing)]
     pandemic: Option<PandemicModel>,
     scheduler: Scheduler,
@@ -58,6 +70,13 @@ pub struct Sim {
     // This is created interactively, and there's no reason to preserve one for savestates.
     #[serde(skip_serializing, skip_deserializing)]
     recorder: Option<TrafficRecorder>,
+    // External consumers may want to see raw events as they happen. Each subscriber gets their
+    // own queue, so they don't steal events from each other. Like the recorder, this isn't
+    // preserved in savestates.
+    #[serde(skip_serializing, skip_deserializing)]
+    event_subscribers: BTreeMap<usize, Vec<(Time, Event)>>,
+    #[serde(skip_serializing, skip_deserializing)]
+    next_event_subscriber: usize,
 
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
@@ -67,7 +86,10 @@ pub(crate) struct Ctx<'a> {
     pub parking: &'a mut ParkingSimState,
     pub intersections: &'a mut IntersectionSimState,
     pub scheduler: &'a mut Scheduler,
+    pub ride_hail: &'a mut RideHailSimState,
+    pub bike_share: &'a mut BikeShareSimState,
     pub map: &'a Map,
+    pub peds_on_shared_use: &'a BTreeMap<LaneID, usize>,
     /// If present, live map edits are being processed, and the agents specified are in the process
     /// of being deleted. Some regular work should maybe be skipped.
     pub handling_live_edits: Option<BTreeSet<AgentID>>,
@@ -111,6 +133,43 @@ pub struct SimOptions {
     /// Don't collect any analytics. Only useful for benchmarking and debugging gridlock more
     /// quickly.
     pub skip_analytics: bool,
+    /// If present, variable stages of traffic signals are actuated by simulated loop detectors.
+    /// Each lane leading to a signal has a presence detector covering this distance upstream of the
+    /// stop line. A vehicle over a detector for a protected movement restarts the stage's passage
+    /// timer, the stage gaps out once that timer runs out, and pedestrians push a button to call
+    /// crosswalk stages.
+    pub loop_detectors: Option<Distance>,
+    /// If present, buses and trains approaching a traffic signal request priority for their
+    /// movement.
+    pub transit_signal_priority: Option<TransitSignalPriority>,
+    /// How drivers look for parking when there's no free spot on the lane near their destination.
+    pub parking_search: ParkingSearchStrategy,
+    /// The fraction of cars, from 0 to 1, that are electric. Electric cars use energy as they
+    /// drive and try to park at chargers when their battery is low.
+    pub ev_share: f64,
+    /// How to estimate the fuel, energy, and pollution of vehicles as they drive.
+    pub emissions: EmissionsMethod,
+    /// The number of ride-hail vehicles in service. With none, ride-hail trips are cancelled.
+    pub ride_hail_fleet: usize,
+    /// How idle ride-hail vehicles are matched to waiting riders.
+    pub dispatch: DispatchPolicy,
+    /// If present, shared bikes and scooters are moved between docks this often, so each dock
+    /// holds a share of the vehicles in proportion to its capacity.
+    pub bike_share_rebalance_every: Option<Duration>,
+    /// Let people reach or leave public transit by driving and parking near a stop, biking and
+    /// locking up there, or using bike-share, whenever that's cheaper than walking. A car left near
+    /// a stop will be driven home from there by a later transit trip.
+    pub multimodal_transit: bool,
+    /// Slow pedestrians down on crowded sidewalks and crosswalks, make them wait at the edge of
+    /// any that're packed full, and track each sidewalk's level of service.
+    pub pedestrian_crowding: bool,
+    /// If present, people choose how to travel right before each trip between two buildings
+    /// starts, instead of always using the mode from the scenario.
+    pub mode_choice: Option<ModeChoiceModel>,
+    /// If present, vehicles yielding at stop signs and traffic signals wait for a big enough gap
+    /// in approaching traffic with priority over them, not just for the intersection to clear.
+    /// Vehicles entering roundabouts always do this, using these critical gaps or the defaults.
+    pub gap_acceptance: Option<GapAcceptance>,
 }
 
 impl std::default::Default for SimOptions {
@@ -121,6 +180,12 @@ impl std::default::Default for SimOptions {
 
 impl SimOptions {
     pub fn from_args(args: &mut CmdArgs, rng_seed: u64) -> SimOptions {
+        let tsp_max_extension = args
+            .optional_parse("--tsp_max_extension", |s| s.parse::<f64>())
+            .map(Duration::seconds);
+        let tsp_max_early_green = args
+            .optional_parse("--tsp_max_early_green", |s| s.parse::<f64>())
+            .map(Duration::seconds);
         SimOptions {
             run_name: args
                 .optional("--run_name")
@@ -147,10 +212,163 @@ impl SimOptions {
             infinite_parking: args.enabled("--infinite_parking"),
             disable_turn_conflicts: args.enabled("--disable_turn_conflicts"),
             skip_analytics: args.enabled("--skip_analytics"),
+            loop_detectors: args
+                .optional_parse("--loop_detectors", |s| s.parse::<f64>())
+                .map(Distance::meters),
+            transit_signal_priority: if args.enabled("--transit_signal_priority") {
+                let default = TransitSignalPriority::default();
+                Some(TransitSignalPriority {
+                    max_extension: tsp_max_extension.unwrap_or(default.max_extension),
+                    max_early_green: tsp_max_early_green.unwrap_or(default.max_early_green),
+                })
+            } else {
+                None
+            },
+            parking_search: args
+                .optional("--parking_search")
+                .map(|x| match x.as_ref() {
+                    "nearest" => ParkingSearchStrategy::NearestFirst,
+                    "random_walk" => ParkingSearchStrategy::RandomWalk,
+                    "lot" => ParkingSearchStrategy::HeadToLot,
+                    _ => panic!(
+                        "Bad --parking_search={}. Must be nearest|random_walk|lot",
+                        x
+                    ),
+                })
+                .unwrap_or(ParkingSearchStrategy::NearestFirst),
+            ev_share: args
+                .optional_parse("--ev_share", |s| s.parse::<f64>())
+                .unwrap_or(0.0),
+            emissions: args
+                .optional("--emissions")
+                .map(|x| match x.as_ref() {
+                    "vsp" => EmissionsMethod::VehicleSpecificPower,
+                    "average_speed" => EmissionsMethod::AverageSpeed,
+                    _ => panic!("Bad --emissions={}. Must be vsp|average_speed", x),
+                })
+                .unwrap_or(EmissionsMethod::VehicleSpecificPower),
+            ride_hail_fleet: args
+                .optional_parse("--ride_hail_fleet", |s| s.parse::<usize>())
+                .unwrap_or(0),
+            dispatch: args
+                .optional("--dispatch")
+                .map(|x| match x.as_ref() {
+                    "nearest" => DispatchPolicy::NearestVehicle,
+                    "longest_idle" => DispatchPolicy::LongestIdle,
+                    _ => panic!("Bad --dispatch={}. Must be nearest|longest_idle", x),
+                })
+                .unwrap_or(DispatchPolicy::NearestVehicle),
+            bike_share_rebalance_every: args
+                .optional_parse("--bike_share_rebalance_every", |s| s.parse::<usize>())
+                .map(Duration::minutes),
+            multimodal_transit: args.enabled("--multimodal_transit"),
+            pedestrian_crowding: args.enabled("--pedestrian_crowding"),
+            mode_choice: if args.enabled("--mode_choice") {
+                Some(
+                    args.optional("--mode_choice_coefficients")
+                        .map(|path| abstio::read_json(path, &mut Timer::throwaway()))
+                        .unwrap_or_default(),
+                )
+            } else {
+                None
+            },
+            gap_acceptance: if args.enabled("--gap_acceptance") {
+                Some(
+                    args.optional("--critical_gaps")
+                        .map(|path| abstio::read_json(path, &mut Timer::throwaway()))
+                        .unwrap_or_default(),
+                )
+            } else {
+                None
+            },
         }
     }
 }
 
+/// The critical gap is the shortest time until an approaching vehicle with priority arrives that a
+/// yielding driver will accept to start their turn. Defaults roughly follow the Highway Capacity
+/// Manual. When loading these from JSON, anything missing keeps the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GapAcceptance {
+    /// Entering a roundabout, yielding to circulating traffic
+    pub roundabout: Duration,
+    /// A permissive turn at a traffic signal, yielding to protected movements
+    pub permissive_turn: Duration,
+    /// From a stop-controlled approach, yielding to roads without a stop sign. Left and right are
+    /// named for driving on the right, and swap when driving on the left.
+    pub minor_left: Duration,
+    pub minor_straight: Duration,
+    pub minor_right: Duration,
+    /// Overrides everything above for specific movements
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub per_movement: BTreeMap<MovementID, Duration>,
+}
+
+impl std::default::Default for GapAcceptance {
+    fn default() -> GapAcceptance {
+        GapAcceptance {
+            roundabout: Duration::seconds(4.0),
+            permissive_turn: Duration::seconds(4.1),
+            minor_left: Duration::seconds(7.1),
+            minor_straight: Duration::seconds(6.5),
+            minor_right: Duration::seconds(6.2),
+            per_movement: BTreeMap::new(),
+        }
+    }
+}
+
+impl GapAcceptance {
+    /// The critical gap for a vehicle on a stop-controlled approach
+    pub fn from_minor_road(&self, turn_type: TurnType, driving_side: DrivingSide) -> Duration {
+        match (turn_type, driving_side) {
+            (TurnType::Straight, _) => self.minor_straight,
+            (TurnType::Right, DrivingSide::Right) | (TurnType::Left, DrivingSide::Left) => {
+                self.minor_right
+            }
+            // Turns crossing the far side of the road, including U-turns
+            _ => self.minor_left,
+        }
+    }
+}
+
+/// How traffic signals give priority to transit vehicles. When a bus or train enters the last lane
+/// before a signal, it requests priority for its movement. If that movement is served by the
+/// current stage, the stage may be extended until the vehicle passes. Otherwise, the current stage
+/// may end early.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransitSignalPriority {
+    /// A stage can be extended for transit vehicles by at most this much.
+    pub max_extension: Duration,
+    /// A stage can be shortened for transit vehicles by at most this much. Crosswalks in the stage
+    /// still get enough time to cross.
+    pub max_early_green: Duration,
+}
+
+impl std::default::Default for TransitSignalPriority {
+    fn default() -> TransitSignalPriority {
+        TransitSignalPriority {
+            max_extension: Duration::seconds(15.0),
+            max_early_green: Duration::seconds(10.0),
+        }
+    }
+}
+
+/// How drivers look for parking when the lane near their destination has no free spots.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ParkingSearchStrategy {
+    /// Head straight for the nearest free spot, as if drivers magically know where it is.
+    NearestFirst,
+    /// Circle around the destination one block at a time, only noticing free spots on the current
+    /// lane. Gives up and heads for the nearest free spot after a while.
+    RandomWalk,
+    /// Head for the closest parking lot with room, or the nearest free spot if there isn't one.
+    HeadToLot,
+}
+
 #[derive(Clone)]
 pub enum AlertHandler {
     /// Just print the alert to STDOUT
@@ -181,6 +399,18 @@ impl SimOptions {
             infinite_parking: false,
             disable_turn_conflicts: false,
             skip_analytics: false,
+            loop_detectors: None,
+            transit_signal_priority: None,
+            parking_search: ParkingSearchStrategy::NearestFirst,
+            ev_share: 0.0,
+            emissions: EmissionsMethod::VehicleSpecificPower,
+            ride_hail_fleet: 0,
+            dispatch: DispatchPolicy::NearestVehicle,
+            bike_share_rebalance_every: None,
+            multimodal_transit: false,
+            pedestrian_crowding: false,
+            mode_choice: None,
+            gap_acceptance: None,
         }
     }
 }
@@ -204,13 +434,24 @@ impl Sim {
             opts.infinite_parking = true;
         }
 
+        let mut trips = TripManager::new(
+            opts.ev_share,
+            opts.multimodal_transit,
+            opts.mode_choice.clone(),
+        );
+        let ride_hail = RideHailSimState::new(map, &opts, &mut trips);
+        let bike_share = BikeShareSimState::new(map, &opts, &mut trips, &mut scheduler);
+
         Sim {
             driving: DrivingSimState::new(map, &opts),
             parking: ParkingSimState::new(map, opts.infinite_parking, &mut timer),
-            walking: WalkingSimState::new(),
+            walking: WalkingSimState::new(&opts),
             intersections: IntersectionSimState::new(map, &mut scheduler, &opts),
             transit: TransitSimState::new(map),
-            trips: TripManager::new(),
+            ride_hail,
+            bike_share,
+            trips,
+            peds_on_shared_use: BTreeMap::new(),
             pandemic: opts.enable_pandemic_model.map(PandemicModel::new),
             scheduler,
             time: Time::START_OF_DAY,
@@ -224,6 +465,8 @@ impl Sim {
 
             analytics: Analytics::new(!opts.skip_analytics),
             recorder: None,
+            event_subscribers: BTreeMap::new(),
+            next_event_subscriber: 0,
         }
     }
 
@@ -285,6 +528,8 @@ impl Sim {
             vehicle_type: VehicleType::Car,
             length: MIN_CAR_LENGTH,
             max_speed: None,
+            passenger_capacity: None,
+            battery: None,
         };
         let driving_lane = map.find_driving_lane_near_building(b);
 
@@ -347,6 +592,8 @@ impl Sim {
             vehicle_type,
             length,
             max_speed: None,
+            passenger_capacity: Some(route.capacity),
+            battery: None,
         }
         .make(
             CarID {
@@ -437,7 +684,10 @@ impl Sim {
             parking: &mut self.parking,
             intersections: &mut self.intersections,
             scheduler: &mut self.scheduler,
+            ride_hail: &mut self.ride_hail,
+            bike_share: &mut self.bike_share,
             map,
+            peds_on_shared_use: &self.peds_on_shared_use,
             handling_live_edits: None,
         };
 
@@ -467,11 +717,33 @@ impl Sim {
                         }
                     }
                 }
-                if !ok {
+                // A delivery van waiting in a loading zone might've been evicted by map edits
+                let evicted = create_car
+                    .maybe_parked_car
+                    .as_ref()
+                    .map(|p| ctx.parking.lookup_parked_car(p.vehicle.id).is_none())
+                    .unwrap_or(false);
+                if (!ok || evicted) && create_car.vehicle.vehicle_type == VehicleType::RideHail {
+                    if let Some(parked_car) = create_car.maybe_parked_car {
+                        if !evicted {
+                            ctx.parking.remove_parked_car(parked_car);
+                        }
+                    }
+                    self.trips.ride_hail_vehicle_removed(
+                        self.time,
+                        create_car.vehicle.id,
+                        create_car.router.get_path().get_req().start,
+                        &mut ctx,
+                    );
+                } else if !ok || evicted {
                     self.trips.cancel_trip(
                         self.time,
                         create_car.trip_and_person.unwrap().0,
-                        "path is no longer valid after map edits".to_string(),
+                        if evicted {
+                            "parked vehicle was evicted by map edits".to_string()
+                        } else {
+                            "path is no longer valid after map edits".to_string()
+                        },
                         Some(create_car.vehicle),
                         &mut ctx,
                     );
@@ -482,6 +754,7 @@ impl Sim {
                     // clone the path.
                     let id = create_car.vehicle.id;
                     let maybe_route = create_car.maybe_route;
+                    let passenger_capacity = create_car.vehicle.passenger_capacity;
                     let trip_and_person = create_car.trip_and_person;
                     let maybe_parked_car = create_car.maybe_parked_car.clone();
                     let req = create_car.router.get_path().get_req().clone();
@@ -527,10 +800,10 @@ impl Sim {
                                 trip,
                                 person,
                                 Some(req),
-                                if id.vehicle_type == VehicleType::Car {
-                                    TripPhaseType::Driving
-                                } else {
+                                if id.vehicle_type == VehicleType::Bike {
                                     TripPhaseType::Biking
+                                } else {
+                                    TripPhaseType::Driving
                                 },
                             ));
                         }
@@ -545,7 +818,8 @@ impl Sim {
                             self.parking.remove_parked_car(parked_car);
                         }
                         if let Some(route) = maybe_route {
-                            self.transit.bus_created(id, route);
+                            self.transit
+                                .bus_created(id, route, passenger_capacity.unwrap());
                         }
                         self.analytics
                             .record_demand(self.driving.get_path(id).unwrap(), map);
@@ -613,8 +887,17 @@ impl Sim {
                 );
             }
             Command::UpdateIntersection(i) => {
-                self.intersections
-                    .update_intersection(self.time, i, map, &mut self.scheduler);
+                let occupied_detectors = self.intersections.loop_detectors().map(|dist| {
+                    self.driving
+                        .occupied_loop_detectors(self.time, i, dist, map)
+                });
+                self.intersections.update_intersection(
+                    self.time,
+                    i,
+                    map,
+                    &mut self.scheduler,
+                    occupied_detectors,
+                );
             }
             Command::Callback(frequency) => {
                 self.scheduler
@@ -632,6 +915,9 @@ impl Sim {
             Command::StartBus(r, _) => {
                 self.start_bus(map.get_br(r), map);
             }
+            Command::RebalanceBikeShare => {
+                ctx.bike_share.rebalance(self.time, ctx.scheduler);
+            }
         }
 
         // Record events at precisely the time they occur.
@@ -643,8 +929,13 @@ impl Sim {
     fn dispatch_events(&mut self, mut events: Vec<Event>, map: &Map) {
         events.extend(self.trips.collect_events());
         events.extend(self.transit.collect_events());
+        events.extend(self.ride_hail.collect_events());
+        events.extend(self.bike_share.collect_events());
         events.extend(self.driving.collect_events());
-        events.extend(self.walking.collect_events());
+        events.extend(
+            self.walking
+                .collect_events(map, &mut self.peds_on_shared_use),
+        );
         events.extend(self.intersections.collect_events());
         events.extend(self.parking.collect_events());
         for ev in events {
@@ -654,6 +945,10 @@ impl Sim {
             if let Some(ref mut r) = self.recorder {
                 r.handle_event(self.time, &ev, map, &self.driving);
             }
+            self.ride_hail.handle_event(&ev, map);
+            for queue in self.event_subscribers.values_mut() {
+                queue.push((self.time, ev.clone()));
+            }
 
             self.analytics.event(ev, self.time, map);
         }
@@ -798,6 +1093,14 @@ impl Sim {
                 "- transit: {} bytes",
                 prettyprint_usize(serialized_size_bytes(&self.transit))
             );
+            println!(
+                "- ride-hail: {} bytes",
+                prettyprint_usize(serialized_size_bytes(&self.ride_hail))
+            );
+            println!(
+                "- bike-share: {} bytes",
+                prettyprint_usize(serialized_size_bytes(&self.bike_share))
+            );
             println!(
                 "- trips: {} bytes",
                 prettyprint_usize(serialized_size_bytes(&self.trips))
@@ -825,6 +1128,26 @@ impl Sim {
     pub fn load_savestate(path: String, timer: &mut Timer) -> Result<Sim> {
         abstio::maybe_read_binary(path, timer)
     }
+
+    /// Savestates don't include the map. Before using one, make sure it was created using the same
+    /// map and edits.
+    pub fn check_savestate_matches(&self, map: &Map) -> Result<()> {
+        if &self.map_name != map.get_name() {
+            bail!(
+                "savestate is for {}, but the map is {}",
+                self.map_name.describe(),
+                map.get_name().describe()
+            );
+        }
+        if self.edits_name != map.get_edits().edits_name {
+            bail!(
+                "savestate is for edits {}, but the map has edits {}",
+                self.edits_name,
+                map.get_edits().edits_name
+            );
+        }
+        Ok(())
+    }
 }
 
 // Live edits
@@ -850,7 +1173,10 @@ impl Sim {
             parking: &mut self.parking,
             intersections: &mut self.intersections,
             scheduler: &mut self.scheduler,
+            ride_hail: &mut self.ride_hail,
+            bike_share: &mut self.bike_share,
             map,
+            peds_on_shared_use: &self.peds_on_shared_use,
             handling_live_edits: Some(affected_agents),
         };
         for (agent, trip) in affected {
@@ -885,6 +1211,7 @@ impl Sim {
 
         self.driving.handle_live_edits(map);
         self.intersections.handle_live_edits(map);
+        self.bike_share.sync_docks(map, &mut self.trips);
 
         (num_trips_cancelled, num_parked_cars)
     }
@@ -965,7 +1292,10 @@ impl Sim {
                 parking: &mut self.parking,
                 intersections: &mut self.intersections,
                 scheduler: &mut self.scheduler,
+                ride_hail: &mut self.ride_hail,
+                bike_share: &mut self.bike_share,
                 map,
+                peds_on_shared_use: &self.peds_on_shared_use,
                 handling_live_edits: None,
             };
             let vehicle = self.driving.delete_car(id, self.time, &mut ctx);
@@ -1023,9 +1353,107 @@ impl Sim {
     }
 }
 
+// Buffering raw events
+impl Sim {
+    /// Start collecting every event emitted by the simulation for a new subscriber. Pass the
+    /// returned ID to `drain_buffered_events`, and to `unsubscribe_from_events` when done.
+    pub fn subscribe_to_events(&mut self) -> usize {
+        let id = self.next_event_subscriber;
+        self.next_event_subscriber += 1;
+        self.event_subscribers.insert(id, Vec::new());
+        id
+    }
+
+    /// Stop collecting events for one subscriber, discarding anything they haven't drained.
+    /// Other subscribers are unaffected.
+    pub fn unsubscribe_from_events(&mut self, subscriber: usize) {
+        self.event_subscribers.remove(&subscriber);
+    }
+
+    /// Returns all events buffered for one subscriber since their last call, in the order they
+    /// occurred. Returns nothing for an unknown subscriber.
+    pub fn drain_buffered_events(&mut self, subscriber: usize) -> Vec<(Time, Event)> {
+        self.event_subscribers
+            .get_mut(&subscriber)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
 // Managing highlighted people
 impl Sim {
     pub fn set_highlighted_people(&mut self, people: BTreeSet<PersonID>) {
         self.highlighted_people = Some(people);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use map_model::{DirectedRoadID, Direction, RoadID};
+
+    use super::*;
+
+    #[test]
+    fn critical_gap_from_minor_road() {
+        let gaps = GapAcceptance::default();
+        for side in [DrivingSide::Right, DrivingSide::Left] {
+            assert_eq!(
+                gaps.from_minor_road(TurnType::Straight, side),
+                gaps.minor_straight
+            );
+            assert_eq!(gaps.from_minor_road(TurnType::UTurn, side), gaps.minor_left);
+        }
+        assert_eq!(
+            gaps.from_minor_road(TurnType::Right, DrivingSide::Right),
+            gaps.minor_right
+        );
+        assert_eq!(
+            gaps.from_minor_road(TurnType::Left, DrivingSide::Right),
+            gaps.minor_left
+        );
+        // Left and right swap when driving on the left
+        assert_eq!(
+            gaps.from_minor_road(TurnType::Left, DrivingSide::Left),
+            gaps.minor_right
+        );
+        assert_eq!(
+            gaps.from_minor_road(TurnType::Right, DrivingSide::Left),
+            gaps.minor_left
+        );
+    }
+
+    #[test]
+    fn load_critical_gaps() {
+        // Durations are stored in ten-thousandths of a second. Anything missing keeps the default.
+        let gaps: GapAcceptance =
+            abstutil::from_json(br#"{"roundabout": 35000, "minor_left": 80000}"#).unwrap();
+        assert_eq!(gaps.roundabout, Duration::seconds(3.5));
+        assert_eq!(gaps.minor_left, Duration::seconds(8.0));
+        assert_eq!(
+            gaps.permissive_turn,
+            GapAcceptance::default().permissive_turn
+        );
+        assert!(gaps.per_movement.is_empty());
+
+        // Per-movement overrides survive a round-trip
+        let mut gaps = GapAcceptance::default();
+        gaps.per_movement.insert(
+            MovementID {
+                from: DirectedRoadID {
+                    id: RoadID(1),
+                    dir: Direction::Fwd,
+                },
+                to: DirectedRoadID {
+                    id: RoadID(2),
+                    dir: Direction::Back,
+                },
+                parent: IntersectionID(3),
+                crosswalk: false,
+            },
+            Duration::seconds(9.0),
+        );
+        let loaded: GapAcceptance =
+            abstutil::from_json(abstutil::to_json(&gaps).as_bytes()).unwrap();
+        assert_eq!(loaded, gaps);
+    }
+}
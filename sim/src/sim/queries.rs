@@ -7,16 +7,16 @@ use std::collections::{BTreeMap, BTreeSet};
 use abstutil::Counter;
 use geom::{Distance, Duration, PolyLine, Pt2D, Time};
 use map_model::{
-    BuildingID, BusRouteID, BusStopID, IntersectionID, Lane, LaneID, Map, Path, Position,
-    Traversable, TurnID,
+    BuildingID, BusRouteID, BusStopID, IntersectionID, Lane, LaneID, Map, MovementID, Path,
+    Position, RoadID, TransitCapacity, Traversable, TurnID,
 };
 
 use crate::analytics::SlidingWindow;
 use crate::{
-    AgentID, AgentType, Analytics, CarID, CommutersVehiclesCounts, DrawCarInput, DrawPedCrowdInput,
-    DrawPedestrianInput, OrigPersonID, PandemicModel, ParkedCar, ParkingSim, PedestrianID, Person,
-    PersonID, PersonState, Scenario, Sim, TripEndpoint, TripID, TripInfo, TripMode, TripResult,
-    UnzoomedAgent, VehicleType,
+    AgentID, AgentType, Analytics, Battery, CarID, CommutersVehiclesCounts, DrawCarInput,
+    DrawPedCrowdInput, DrawPedestrianInput, Emissions, LevelOfService, OrigPersonID, PandemicModel,
+    ParkedCar, ParkingSim, PedestrianID, Person, PersonID, PersonState, Scenario, Sim,
+    TripEndpoint, TripID, TripInfo, TripMode, TripResult, UnzoomedAgent, VehicleType,
 };
 
 // TODO Many of these just delegate to an inner piece. This is unorganized and hard to maintain.
@@ -38,7 +38,9 @@ impl Sim {
         self.trips.num_trips()
     }
     pub fn num_agents(&self) -> Counter<AgentType> {
-        self.trips.num_agents(&self.transit)
+        let mut cnt = self.trips.num_agents(&self.transit);
+        cnt.add(AgentType::RideHail, self.ride_hail.num_busy());
+        cnt
     }
     pub fn num_commuters_vehicles(&self) -> CommutersVehiclesCounts {
         self.trips
@@ -100,6 +102,11 @@ impl Sim {
         self.transit.get_passengers(car).len()
     }
 
+    /// (passengers on board, capacity)
+    pub fn get_transit_load(&self, car: CarID) -> (usize, TransitCapacity) {
+        self.transit.get_load(car)
+    }
+
     pub fn bus_route_id(&self, maybe_bus: CarID) -> Option<BusRouteID> {
         if maybe_bus.vehicle_type == VehicleType::Bus
             || maybe_bus.vehicle_type == VehicleType::Train
@@ -110,6 +117,33 @@ impl Sim {
         }
     }
 
+    pub fn ride_hail_fleet_size(&self) -> usize {
+        self.ride_hail.fleet_size()
+    }
+    /// How many ride-hail vehicles are heading to or carrying a passenger right now?
+    pub fn num_busy_ride_hail(&self) -> usize {
+        self.ride_hail.num_busy()
+    }
+    pub fn num_waiting_for_ride_hail(&self) -> usize {
+        self.ride_hail.num_waiting()
+    }
+    /// Who's riding in this ride-hail vehicle right now?
+    pub fn get_ride_hail_passenger(&self, car: CarID) -> Option<PersonID> {
+        self.ride_hail.get_passenger(car)
+    }
+    /// For a bike-share dock, returns (vehicles docked, capacity).
+    pub fn bike_share_dock_status(&self, b: BuildingID) -> Option<(usize, usize)> {
+        self.bike_share.dock_status(b)
+    }
+    /// How crowded is a sidewalk right now? None if pedestrian crowding isn't modeled.
+    pub fn sidewalk_level_of_service(&self, l: LaneID) -> Option<LevelOfService> {
+        self.walking.sidewalk_level_of_service(l)
+    }
+    /// How many shared bikes and scooters are checked out right now?
+    pub fn num_bike_share_in_use(&self) -> usize {
+        self.bike_share.num_in_use()
+    }
+
     pub fn active_agents(&self) -> Vec<AgentID> {
         self.trips.active_agents()
     }
@@ -164,6 +198,13 @@ impl Sim {
     pub fn lookup_parked_car(&self, id: CarID) -> Option<&ParkedCar> {
         self.parking.lookup_parked_car(id)
     }
+    /// Only electric cars have a battery. For parked cars, this doesn't include energy gained from
+    /// a charger yet; that's added when the car leaves.
+    pub fn get_battery(&self, id: CarID) -> Option<Battery> {
+        self.driving
+            .get_battery(id)
+            .or_else(|| self.parking.lookup_parked_car(id)?.vehicle.battery)
+    }
     /// For every parked car, (position of parking spot, position of owner)
     pub fn all_parked_car_positions(&self, map: &Map) -> Vec<(Position, Position)> {
         self.parking
@@ -204,6 +245,8 @@ impl Sim {
             VehicleType::Bike,
             VehicleType::Bus,
             VehicleType::Train,
+            VehicleType::Delivery,
+            VehicleType::RideHail,
         ] {
             let id = CarID {
                 id: idx,
@@ -214,13 +257,19 @@ impl Sim {
             }
         }
 
-        let id = CarID {
-            id: idx,
-            vehicle_type: VehicleType::Car,
-        };
-        // Only cars can be parked.
-        if self.parking.lookup_parked_car(id).is_some() {
-            return Some(id);
+        // Only cars, delivery vans, and ride-hail vehicles can be parked.
+        for vehicle_type in [
+            VehicleType::Car,
+            VehicleType::Delivery,
+            VehicleType::RideHail,
+        ] {
+            let id = CarID {
+                id: idx,
+                vehicle_type,
+            };
+            if self.parking.lookup_parked_car(id).is_some() {
+                return Some(id);
+            }
         }
 
         None
@@ -313,6 +362,33 @@ impl Sim {
         &self.analytics
     }
 
+    /// Energy used and pollution emitted so far by vehicles driving along a road.
+    pub fn get_road_emissions(&self, r: RoadID) -> Emissions {
+        self.analytics
+            .emissions_per_road
+            .get(&r)
+            .cloned()
+            .unwrap_or(Emissions::ZERO)
+    }
+
+    /// Energy used and pollution emitted so far by the vehicle used for a trip.
+    pub fn get_trip_emissions(&self, trip: TripID) -> Emissions {
+        self.analytics
+            .emissions_per_trip
+            .get(&trip)
+            .cloned()
+            .unwrap_or(Emissions::ZERO)
+    }
+
+    /// Energy used and pollution emitted so far by all vehicles, including transit.
+    pub fn get_total_emissions(&self) -> Emissions {
+        self.analytics
+            .emissions_per_road
+            .values()
+            .chain(self.analytics.emissions_per_intersection.values())
+            .fold(Emissions::ZERO, |sum, x| sum + *x)
+    }
+
     /// For intersections with an agent waiting beyond some threshold, return when they started
     /// waiting. Sorted by earliest waiting (likely the root cause of gridlock).
     pub fn delayed_intersections(&self, threshold: Duration) -> Vec<(IntersectionID, Time)> {
@@ -343,6 +419,37 @@ impl Sim {
             .current_stage_and_remaining_time(self.time, i)
     }
 
+    /// The index of the time-of-day plan a traffic signal is currently using.
+    pub fn current_signal_plan(&self, i: IntersectionID) -> usize {
+        self.intersections.current_signal_plan(i)
+    }
+
+    /// If loop detectors are enabled, returns each lane leading to the intersection and whether a
+    /// vehicle is currently over its detector.
+    pub fn get_loop_detector_occupancy(
+        &self,
+        i: IntersectionID,
+        map: &Map,
+    ) -> Option<BTreeMap<LaneID, bool>> {
+        let dist = self.intersections.loop_detectors()?;
+        let occupied = self
+            .driving
+            .occupied_loop_detectors(self.time, i, dist, map);
+        let mut results = BTreeMap::new();
+        for l in &map.get_i(i).incoming_lanes {
+            if map.get_l(*l).lane_type.is_for_moving_vehicles() {
+                results.insert(*l, occupied.contains(l));
+            }
+        }
+        Some(results)
+    }
+
+    /// Crosswalk movements at a traffic signal where a pedestrian has pushed the call button, but
+    /// hasn't been served yet. Only used with loop detectors.
+    pub fn get_pedestrian_calls(&self, i: IntersectionID) -> BTreeSet<MovementID> {
+        self.intersections.get_pedestrian_calls(i)
+    }
+
     // TODO This is an awkward copy of raw_throughput
     // TODO And it does NOT count buses/trains spawning
     pub fn all_arrivals_at_border(
@@ -372,7 +479,7 @@ impl Sim {
 
     /// (number of vehicles in the lane, penalty if a bike or other slow vehicle is present)
     pub fn target_lane_penalty(&self, lane: &Lane) -> (usize, usize) {
-        if lane.is_walkable() {
+        if lane.is_walkable() && !lane.is_shared_use() {
             (0, 0)
         } else {
             self.driving.target_lane_penalty(lane.id)
@@ -427,7 +534,7 @@ impl Sim {
                 let max_speed = match info.mode {
                     TripMode::Walk | TripMode::Transit => Some(person.ped_speed),
                     // TODO We should really search the vehicles and grab it from there
-                    TripMode::Drive => None,
+                    TripMode::Drive | TripMode::RideHail => None,
                     // Assume just one bike
                     TripMode::Bike => {
                         person
@@ -437,6 +544,8 @@ impl Sim {
                             .unwrap()
                             .max_speed
                     }
+                    // Shared bikes are capped like any other
+                    TripMode::BikeShare => Some(map_model::MAX_BIKE_SPEED),
                 };
                 Ok(path.estimate_duration(map, max_speed))
             }
@@ -3,18 +3,40 @@ use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::Time;
-use map_model::{BusRoute, BusRouteID, BusStopID, Map, Path, PathRequest, Position};
+use geom::{Duration, Time};
+use map_model::{
+    BusRoute, BusRouteID, BusStopID, Map, Path, PathRequest, Position, TransitCapacity,
+};
 
 use crate::sim::Ctx;
 use crate::{
-    AgentID, CarID, DrivingSimState, Event, PedestrianID, PersonID, Router, TripID, TripManager,
-    TripPhaseType, UnzoomedAgent, VehicleType, WalkingSimState,
+    AgentID, CarID, DrivingSimState, Event, PedestrianID, PersonID, Problem, Router, TripID,
+    TripManager, TripPhaseType, UnzoomedAgent, VehicleType, WalkingSimState,
 };
 
 // These index stops along a route, not stops along a single sidewalk.
 type StopIdx = usize;
 
+/// Even if nobody gets on or off, a vehicle waits this long at every stop.
+const MIN_DWELL_TIME: Duration = Duration::const_seconds(10.0);
+const TIME_PER_ALIGHTING: Duration = Duration::const_seconds(2.0);
+const TIME_PER_BOARDING: Duration = Duration::const_seconds(3.0);
+/// Squeezing past standing passengers makes each boarding slower.
+const CROWDED_BOARDING_PENALTY: Duration = Duration::const_seconds(1.5);
+
+/// How long a transit vehicle dwells at a stop where some passengers get off and others board.
+/// The last `crowded_boardings` of the boardings happen after every seat is taken.
+pub fn transit_dwell_time(
+    alightings: usize,
+    boardings: usize,
+    crowded_boardings: usize,
+) -> Duration {
+    MIN_DWELL_TIME
+        + (alightings as f64) * TIME_PER_ALIGHTING
+        + (boardings as f64) * TIME_PER_BOARDING
+        + (crowded_boardings as f64) * CROWDED_BOARDING_PENALTY
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 struct Stop {
     id: BusStopID,
@@ -36,7 +58,29 @@ struct Bus {
     route: BusRouteID,
     /// Where does each passenger want to deboard?
     passengers: Vec<(PersonID, Option<BusStopID>)>,
+    capacity: TransitCapacity,
     state: BusState,
+    /// People who board after the vehicle has started dwelling at a stop hold it there longer.
+    extra_dwell_time: Duration,
+}
+
+impl Bus {
+    fn is_full(&self) -> bool {
+        self.passengers.len() >= self.capacity.total()
+    }
+
+    fn all_seats_taken(&self) -> bool {
+        self.passengers.len() >= self.capacity.seated
+    }
+
+    /// How much longer the vehicle dwells for the next person to board
+    fn boarding_time(&self) -> Duration {
+        if self.all_seats_taken() {
+            TIME_PER_BOARDING + CROWDED_BOARDING_PENALTY
+        } else {
+            TIME_PER_BOARDING
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -153,7 +197,7 @@ impl TransitSimState {
         self.routes[&bus_route.id].start.clone()
     }
 
-    pub fn bus_created(&mut self, bus: CarID, r: BusRouteID) {
+    pub fn bus_created(&mut self, bus: CarID, r: BusRouteID, capacity: TransitCapacity) {
         let route = self.routes.get_mut(&r).unwrap();
         route.active_vehicles.insert(bus);
         self.buses.insert(
@@ -162,13 +206,15 @@ impl TransitSimState {
                 car: bus,
                 route: r,
                 passengers: Vec::new(),
+                capacity,
                 state: BusState::DrivingToStop(0),
+                extra_dwell_time: Duration::ZERO,
             },
         );
     }
 
-    /// If true, the bus is idling. If false, the bus actually arrived at a border and should now
-    /// vanish.
+    /// If this returns a duration, the bus idles at the stop for that long, depending on how many
+    /// people got on and off. If None, the bus actually arrived at a border and should now vanish.
     pub fn bus_arrived_at_stop(
         &mut self,
         now: Time,
@@ -176,7 +222,7 @@ impl TransitSimState {
         trips: &mut TripManager,
         walking: &mut WalkingSimState,
         ctx: &mut Ctx,
-    ) -> bool {
+    ) -> Option<Duration> {
         let mut bus = self.buses.get_mut(&id).unwrap();
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
@@ -187,8 +233,10 @@ impl TransitSimState {
 
                 // Deboard existing passengers.
                 let mut still_riding = Vec::new();
+                let mut alightings = 0;
                 for (person, maybe_stop2) in bus.passengers.drain(..) {
                     if Some(stop1) == maybe_stop2 {
+                        alightings += 1;
                         trips.person_left_bus(now, person, bus.car, ctx);
                         self.events.push(Event::PassengerAlightsTransit(
                             person, bus.car, bus.route, stop1,
@@ -200,17 +248,33 @@ impl TransitSimState {
                 bus.passengers = still_riding;
 
                 // Board new passengers.
+                let mut boardings = 0;
+                let mut crowded_boardings = 0;
                 let mut still_waiting = Vec::new();
                 for (ped, route, maybe_stop2, started_waiting) in
                     self.peds_waiting.remove(&stop1).unwrap()
                 {
-                    if bus.route == route {
+                    if bus.route == route && bus.is_full() {
+                        // Left behind until the next vehicle on this route arrives
+                        if let Some(trip) = trips.agent_to_trip(AgentID::Pedestrian(ped)) {
+                            self.events.push(Event::ProblemEncountered(
+                                trip,
+                                Problem::TransitBoardingDenied(stop1, route),
+                            ));
+                        }
+                        still_waiting.push((ped, route, maybe_stop2, started_waiting));
+                    } else if bus.route == route {
+                        boardings += 1;
+                        if bus.all_seats_taken() {
+                            crowded_boardings += 1;
+                        }
                         let (trip, person) = trips.ped_boarded_bus(
                             now,
                             ped,
                             bus.car,
                             now - started_waiting,
                             walking,
+                            ctx.map,
                         );
                         self.events.push(Event::PassengerBoardsTransit(
                             person,
@@ -244,7 +308,7 @@ impl TransitSimState {
                     }
                 }
                 self.peds_waiting.insert(stop1, still_waiting);
-                true
+                Some(transit_dwell_time(alightings, boardings, crowded_boardings))
             }
             BusState::DrivingOffMap => {
                 self.routes
@@ -263,12 +327,21 @@ impl TransitSimState {
                     }
                     trips.transit_rider_reached_border(now, person, id, ctx);
                 }
-                false
+                None
             }
             BusState::AtStop(_) | BusState::Done => unreachable!(),
         }
     }
 
+    /// When a vehicle is about to leave a stop, returns how much longer it should dwell for
+    /// people who boarded in the meantime. Each boarding is only counted once.
+    pub fn take_extra_dwell_time(&mut self, id: CarID) -> Duration {
+        std::mem::replace(
+            &mut self.buses.get_mut(&id).unwrap().extra_dwell_time,
+            Duration::ZERO,
+        )
+    }
+
     pub fn bus_departed_from_stop(&mut self, id: CarID, map: &Map) -> Router {
         let mut bus = self.buses.get_mut(&id).unwrap();
         let route = self.routes.get_mut(&bus.route).unwrap();
@@ -300,7 +373,8 @@ impl TransitSimState {
         }
     }
 
-    /// Returns the bus if the pedestrian boarded immediately.
+    /// Returns the bus if the pedestrian boarded immediately. If a vehicle for the route is at the
+    /// stop but already full, the pedestrian is left waiting for the next one.
     pub fn ped_waiting_for_bus(
         &mut self,
         now: Time,
@@ -317,11 +391,17 @@ impl TransitSimState {
             for bus in &route.active_vehicles {
                 if let BusState::AtStop(idx) = self.buses[bus].state {
                     if route.stops[idx].id == stop1 {
-                        self.buses
-                            .get_mut(bus)
-                            .unwrap()
-                            .passengers
-                            .push((person, maybe_stop2));
+                        if self.buses[bus].is_full() {
+                            self.events.push(Event::ProblemEncountered(
+                                trip,
+                                Problem::TransitBoardingDenied(stop1, route_id),
+                            ));
+                            continue;
+                        }
+                        let vehicle = self.buses.get_mut(bus).unwrap();
+                        let boarding_time = vehicle.boarding_time();
+                        vehicle.extra_dwell_time += boarding_time;
+                        vehicle.passengers.push((person, maybe_stop2));
                         self.events.push(Event::TripPhaseStarting(
                             trip,
                             person,
@@ -362,6 +442,12 @@ impl TransitSimState {
         &self.buses[&bus].passengers
     }
 
+    /// (passengers on board, capacity)
+    pub fn get_load(&self, bus: CarID) -> (usize, TransitCapacity) {
+        let bus = &self.buses[&bus];
+        (bus.passengers.len(), bus.capacity)
+    }
+
     pub fn bus_route(&self, bus: CarID) -> BusRouteID {
         self.buses[&bus].route
     }
@@ -1,20 +1,24 @@
 use std::collections::{BTreeMap, VecDeque};
 
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap, Counter};
 use geom::{Distance, Duration, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, BusStopID, IntersectionID, Map, PathConstraints, PathRequest, Position,
+    BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, PathConstraints, PathRequest,
+    Position,
 };
 
 use crate::sim::Ctx;
 use crate::{
-    AgentID, AgentType, AlertLocation, CarID, Command, CreateCar, CreatePedestrian, DrivingGoal,
-    Event, IndividTrip, OrigPersonID, ParkedCar, ParkingSim, ParkingSpot, PedestrianID, PersonID,
-    PersonSpec, Scenario, SidewalkPOI, SidewalkSpot, StartTripArgs, TransitSimState, TripEndpoint,
-    TripID, TripPhaseType, TripPurpose, TripSpec, Vehicle, VehicleSpec, VehicleType,
-    WalkingSimState,
+    AgentID, AgentType, AlertLocation, Battery, CarID, Command, CreateCar, CreatePedestrian,
+    CurbStop, DeliveryStop, DrivingGoal, Event, IndividTrip, ModeChoiceModel, OrigPersonID,
+    ParkedCar, ParkedCarAccess, ParkingSim, ParkingSpot, PedestrianID, PersonID, PersonSpec,
+    Problem, RideRequest, Router, Scenario, SidewalkPOI, SidewalkSpot, StartTripArgs,
+    TransitAccessOptions, TransitSimState, TripEndpoint, TripID, TripPhaseType, TripPurpose,
+    TripSpec, Vehicle, VehicleSpec, VehicleType, WalkingSimState,
 };
 
 /// Manages people, each of which executes some trips through the day. Each trip is further broken
@@ -36,19 +40,32 @@ pub(crate) struct TripManager {
     unfinished_trips: usize,
 
     car_id_counter: usize,
+    /// The fraction of new cars that're electric
+    ev_share: f64,
+    /// Can transit trips start or end by driving, biking, or using bike-share?
+    multimodal_transit: bool,
+    /// If present, choose the mode of each trip right before it starts
+    mode_choice: Option<ModeChoiceModel>,
 
     events: Vec<Event>,
 }
 
 // Initialization
 impl TripManager {
-    pub fn new() -> TripManager {
+    pub fn new(
+        ev_share: f64,
+        multimodal_transit: bool,
+        mode_choice: Option<ModeChoiceModel>,
+    ) -> TripManager {
         TripManager {
             trips: Vec::new(),
             people: Vec::new(),
             active_trip_mode: BTreeMap::new(),
             unfinished_trips: 0,
             car_id_counter: 0,
+            ev_share,
+            multimodal_transit,
+            mode_choice,
             events: Vec::new(),
         }
     }
@@ -63,11 +80,19 @@ impl TripManager {
         let id = PersonID(self.people.len());
         let vehicles = vehicle_specs
             .into_iter()
-            .map(|v| {
+            .map(|mut v| {
                 let c = CarID {
                     id: self.new_car_id(),
                     vehicle_type: v.vehicle_type,
                 };
+                if v.vehicle_type == VehicleType::Car && self.ev_share > 0.0 {
+                    // Don't disturb the scenario's RNG; decide per car, so the same cars are
+                    // electric across runs.
+                    let mut rng = XorShiftRng::seed_from_u64(c.id as u64);
+                    if rng.gen_bool(self.ev_share.min(1.0)) {
+                        v.battery = Some(Battery::typical(rng.gen_range(0.2..1.0)));
+                    }
+                }
                 v.make(c, Some(id))
             })
             .collect();
@@ -130,7 +155,7 @@ impl TripManager {
         id
     }
 
-    pub fn start_trip(&mut self, now: Time, trip: TripID, args: StartTripArgs, ctx: &mut Ctx) {
+    pub fn start_trip(&mut self, now: Time, trip: TripID, mut args: StartTripArgs, ctx: &mut Ctx) {
         assert!(self.trips[trip.0].info.cancellation_reason.is_none());
 
         let person = &mut self.people[self.trips[trip.0].person.0];
@@ -156,13 +181,53 @@ impl TripManager {
         }
         self.trips[trip.0].started = true;
 
+        if let Some(ref model) = self.mode_choice {
+            let info = &self.trips[trip.0].info;
+            if let Some((mode, use_vehicle)) =
+                model.choose_mode(trip, info, person, ctx.parking, ctx.map)
+            {
+                if mode != info.mode {
+                    self.events.push(Event::ModeChosen(trip, info.mode, mode));
+                    self.trips[trip.0].info.mode = mode;
+                }
+                args.use_vehicle = use_vehicle;
+            }
+        }
+
         let info = &self.trips[trip.0].info;
+        let transit_access = if self.multimodal_transit && info.mode == TripMode::Transit {
+            TransitAccessOptions {
+                car: person
+                    .vehicles
+                    .iter()
+                    .filter(|v| v.vehicle_type == VehicleType::Car)
+                    .find_map(|v| ctx.parking.lookup_parked_car(v.id))
+                    .map(|parked_car| ParkedCarAccess {
+                        car: parked_car.vehicle.id,
+                        sidewalk_pos: ctx.parking.spot_to_sidewalk_pos(parked_car.spot, ctx.map),
+                        driving_pos: ctx.parking.spot_to_driving_pos(
+                            parked_car.spot,
+                            &parked_car.vehicle,
+                            ctx.map,
+                        ),
+                    }),
+                bike: person
+                    .vehicles
+                    .iter()
+                    .find(|v| v.vehicle_type == VehicleType::Bike)
+                    .map(|v| v.id),
+                bike_share: true,
+            }
+        } else {
+            TransitAccessOptions::default()
+        };
         let spec = match TripSpec::maybe_new(
             info.start,
             info.end,
             info.mode,
             args.use_vehicle,
             args.retry_if_no_room,
+            &transit_access,
             ctx.map,
         ) {
             Ok(spec) => spec,
@@ -172,9 +237,15 @@ impl TripManager {
             },
         };
         // to_plan might actually change the TripSpec
-        let (spec, legs) = spec.into_plan(ctx.map);
+        let (mut spec, legs) = spec.into_plan(ctx.map);
         assert!(self.trips[trip.0].legs.is_empty());
         self.trips[trip.0].legs.extend(legs);
+        if let Some(first_stop) = self.trips[trip.0].insert_delivery_stops() {
+            // A van appearing at a border needs to head for the first stop, not the destination
+            if let TripSpec::VehicleAppearing { ref mut goal, .. } = spec {
+                *goal = first_stop;
+            }
+        }
 
         match spec {
             TripSpec::VehicleAppearing {
@@ -379,7 +450,38 @@ impl TripManager {
                     );
                 }
             }
-            TripSpec::UsingTransit { start, stop1, .. } => {
+            TripSpec::UsingBikeShare { start, .. } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+
+                // Walk to the dock
+                let start = SidewalkSpot::building(start, ctx.map);
+                let walk_to = match self.trips[trip.0].legs[0] {
+                    TripLeg::Walk(ref spot) => spot.clone(),
+                    _ => unreachable!(),
+                };
+                let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
+                match ctx.map.pathfind(req) {
+                    Ok(path) => {
+                        ctx.scheduler.push(
+                            now,
+                            Command::SpawnPed(CreatePedestrian {
+                                id: person.ped,
+                                speed: person.ped_speed,
+                                start,
+                                goal: walk_to,
+                                path,
+                                trip,
+                                person: person.id,
+                            }),
+                        );
+                    }
+                    Err(err) => {
+                        self.cancel_trip(now, trip, err.to_string(), None, ctx);
+                    }
+                }
+            }
+            TripSpec::UsingTransit { start, .. } => {
                 assert_eq!(
                     person.state,
                     match start.connection {
@@ -407,27 +509,49 @@ impl TripManager {
                 );
                 person.state = PersonState::Trip(trip);
 
-                let walk_to = SidewalkSpot::bus_stop(stop1, ctx.map);
-                let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
-                match ctx.map.pathfind(req) {
-                    Ok(path) => {
-                        ctx.scheduler.push(
-                            now,
-                            Command::SpawnPed(CreatePedestrian {
-                                id: person.ped,
-                                speed: person.ped_speed,
-                                start,
-                                goal: walk_to,
-                                path,
-                                trip,
-                                person: person.id,
-                            }),
-                        );
-                    }
-                    Err(err) => {
-                        self.cancel_trip(now, trip, err.to_string(), None, ctx);
-                    }
+                // Head to the first stop, or whatever's needed to get there
+                self.spawn_ped(now, trip, start, ctx);
+            }
+            TripSpec::UsingRideHail {
+                start,
+                goal,
+                pickup,
+                dropoff,
+            } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+                let person = person.id;
+
+                // Wait at the curb outside
+                self.events.push(Event::PersonLeavesBuilding(person, start));
+                self.events.push(Event::TripPhaseStarting(
+                    trip,
+                    person,
+                    None,
+                    TripPhaseType::WaitingForRideHail,
+                ));
+                let request = RideRequest {
+                    trip,
+                    person,
+                    from: start,
+                    to: goal,
+                    pickup,
+                    dropoff,
+                    requested_at: now,
+                };
+                if !ctx
+                    .ride_hail
+                    .request_ride(now, request, ctx.map, ctx.scheduler)
+                {
+                    self.cancel_trip(
+                        now,
+                        trip,
+                        "no ride-hail vehicles are in service".to_string(),
+                        None,
+                        ctx,
+                    );
                 }
+                self.cancel_unreachable_rides(now, ctx);
             }
         }
     }
@@ -449,6 +573,7 @@ impl TripManager {
         self.active_trip_mode.insert(agent, t);
     }
 
+    /// `charging_stop` is set when an electric car stopped to charge in the middle of its trip.
     pub fn car_reached_parking_spot(
         &mut self,
         now: Time,
@@ -456,16 +581,34 @@ impl TripManager {
         spot: ParkingSpot,
         blocked_time: Duration,
         distance_crossed: Distance,
+        charging_stop: bool,
         ctx: &mut Ctx,
     ) {
+        if car.vehicle_type == VehicleType::RideHail {
+            self.ride_hail_vehicle_parked(now, car, spot, ctx);
+            return;
+        }
+
         let trip = &mut self.trips[self.active_trip_mode.remove(&AgentID::Car(car)).unwrap().0];
         trip.total_blocked_time += blocked_time;
         trip.total_distance += distance_crossed;
 
+        if charging_stop {
+            let id = trip.id;
+            self.car_stopped_to_charge(now, car, id, spot, ctx);
+            return;
+        }
+
         match trip.legs.pop_front() {
             Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_))) => {
                 assert_eq!(car, c);
             }
+            Some(TripLeg::Drive(c, DrivingGoal::Deliver(b, dwell))) => {
+                assert_eq!(car, c);
+                let id = trip.id;
+                self.van_stopped_in_loading_zone(now, car, id, spot, b, dwell, ctx);
+                return;
+            }
             _ => unreachable!(),
         };
 
@@ -496,6 +639,339 @@ impl TripManager {
         );
     }
 
+    /// A delivery van parked in a loading zone. Once it's done there, drive to the next stop.
+    fn van_stopped_in_loading_zone(
+        &mut self,
+        now: Time,
+        car: CarID,
+        id: TripID,
+        spot: ParkingSpot,
+        bldg: BuildingID,
+        dwell: Duration,
+        ctx: &mut Ctx,
+    ) {
+        let trip = &self.trips[id.0];
+        let person = trip.person;
+        let drive_to = match trip.legs[0] {
+            TripLeg::Drive(c, ref to) => {
+                assert_eq!(c, car);
+                to.clone()
+            }
+            _ => unreachable!(),
+        };
+
+        let parked_car = ctx.parking.get_car_at_spot(spot).unwrap().clone();
+        let start = ctx
+            .parking
+            .spot_to_driving_pos(spot, &parked_car.vehicle, ctx.map);
+        self.events.push(Event::DeliveryStop(
+            car,
+            id,
+            start.lane(),
+            Some(spot),
+            dwell,
+        ));
+        self.events.push(Event::TripPhaseStarting(
+            id,
+            person,
+            None,
+            TripPhaseType::Delivering(bldg),
+        ));
+
+        let end = drive_to.goal_pos(PathConstraints::Car, ctx.map).unwrap();
+        match ctx
+            .map
+            .pathfind(PathRequest::vehicle(start, end, PathConstraints::Car))
+        {
+            Ok(path) => {
+                let router = drive_to.make_router(car, path, ctx.map);
+                ctx.scheduler.push(
+                    now + dwell,
+                    Command::SpawnCar(
+                        CreateCar::for_parked_car(parked_car, router, id, person),
+                        true,
+                    ),
+                );
+            }
+            Err(err) => {
+                ctx.parking.remove_parked_car(parked_car.clone());
+                self.cancel_trip(now, id, err.to_string(), Some(parked_car.vehicle), ctx);
+            }
+        }
+    }
+
+    /// An electric car ran low in the middle of its trip and parked at a charger. Charge enough to
+    /// finish the trip, then keep driving.
+    fn car_stopped_to_charge(
+        &mut self,
+        now: Time,
+        car: CarID,
+        id: TripID,
+        spot: ParkingSpot,
+        ctx: &mut Ctx,
+    ) {
+        let trip = &self.trips[id.0];
+        let person = trip.person;
+        let drive_to = match trip.legs[0] {
+            TripLeg::Drive(c, ref to) => {
+                assert_eq!(c, car);
+                to.clone()
+            }
+            _ => unreachable!(),
+        };
+
+        let mut parked_car = ctx.parking.get_car_at_spot(spot).unwrap().clone();
+        let start = ctx
+            .parking
+            .spot_to_driving_pos(spot, &parked_car.vehicle, ctx.map);
+        let end = drive_to.goal_pos(PathConstraints::Car, ctx.map).unwrap();
+        let req = match spot {
+            ParkingSpot::Onstreet(_, _) => PathRequest::vehicle(start, end, PathConstraints::Car),
+            ParkingSpot::Offstreet(_, _) | ParkingSpot::Lot(_, _) => {
+                PathRequest::leave_from_driveway(start, end, PathConstraints::Car, ctx.map)
+            }
+        };
+        match ctx.map.pathfind(req) {
+            Ok(path) => {
+                // The spot might've been taken by the time the car arrived, so it may have settled
+                // for one without a charger.
+                let mut duration = Duration::ZERO;
+                if spot.has_charger(ctx.map) {
+                    if let Some(ref mut battery) = parked_car.vehicle.battery {
+                        duration = battery.time_to_charge_for(path.total_length());
+                        let energy = battery.charge(duration);
+                        self.events
+                            .push(Event::VehicleCharged(car, spot, energy, duration));
+                    }
+                }
+                let router = drive_to.make_router(car, path, ctx.map);
+                ctx.scheduler.push(
+                    now + duration,
+                    Command::SpawnCar(
+                        CreateCar::for_parked_car(parked_car, router, id, person),
+                        true,
+                    ),
+                );
+            }
+            Err(err) => {
+                ctx.parking.remove_parked_car(parked_car.clone());
+                self.cancel_trip(now, id, err.to_string(), Some(parked_car.vehicle), ctx);
+            }
+        }
+    }
+
+    /// A delivery van couldn't find a free loading zone, so it stopped in the driving lane.
+    pub fn van_double_parked(&mut self, car: CarID, lane: LaneID, dwell: Duration) {
+        let trip = &self.trips[self.active_trip_mode[&AgentID::Car(car)].0];
+        let bldg = match trip.legs[0] {
+            TripLeg::Drive(c, DrivingGoal::Deliver(b, _)) => {
+                assert_eq!(c, car);
+                b
+            }
+            _ => unreachable!(),
+        };
+        self.events
+            .push(Event::DeliveryStop(car, trip.id, lane, None, dwell));
+        self.events.push(Event::TripPhaseStarting(
+            trip.id,
+            trip.person,
+            None,
+            TripPhaseType::Delivering(bldg),
+        ));
+    }
+
+    /// A double-parked delivery van is done at one stop. Returns the route to the next leg, or
+    /// None if the trip had to be cancelled.
+    pub fn van_finished_delivery(
+        &mut self,
+        now: Time,
+        car: CarID,
+        start: Position,
+        ctx: &mut Ctx,
+    ) -> Option<Router> {
+        let trip = &mut self.trips[self.active_trip_mode[&AgentID::Car(car)].0];
+        match trip.legs.pop_front() {
+            Some(TripLeg::Drive(c, DrivingGoal::Deliver(_, _))) => {
+                assert_eq!(c, car);
+            }
+            _ => unreachable!(),
+        }
+        let drive_to = match trip.legs[0] {
+            TripLeg::Drive(c, ref to) => {
+                assert_eq!(c, car);
+                to.clone()
+            }
+            _ => unreachable!(),
+        };
+        let id = trip.id;
+        let person = trip.person;
+
+        let end = drive_to.goal_pos(PathConstraints::Car, ctx.map).unwrap();
+        match ctx
+            .map
+            .pathfind(PathRequest::vehicle(start, end, PathConstraints::Car))
+        {
+            Ok(path) => {
+                self.events.push(Event::TripPhaseStarting(
+                    id,
+                    person,
+                    Some(path.get_req().clone()),
+                    TripPhaseType::Driving,
+                ));
+                Some(drive_to.make_router(car, path, ctx.map))
+            }
+            Err(err) => {
+                self.active_trip_mode.remove(&AgentID::Car(car));
+                let vehicle = self.people[person.0].get_vehicle(car);
+                self.cancel_trip(now, id, err.to_string(), Some(vehicle), ctx);
+                None
+            }
+        }
+    }
+
+    /// A ride-hail vehicle stopped at the curb to pick somebody up or drop them off. Returns how
+    /// long it stays there.
+    pub fn ride_hail_vehicle_stopped(&mut self, now: Time, car: CarID, ctx: &mut Ctx) -> Duration {
+        let (stop, dwell) = ctx.ride_hail.vehicle_stopped(now, car);
+        match stop {
+            CurbStop::Pickup(req) => {
+                self.active_trip_mode
+                    .insert(AgentID::BusPassenger(req.person, car), req.trip);
+                self.people[req.person.0].on_bus = Some(car);
+                self.events.push(Event::TripPhaseStarting(
+                    req.trip,
+                    req.person,
+                    Some(PathRequest::vehicle(
+                        req.pickup,
+                        req.dropoff,
+                        PathConstraints::Car,
+                    )),
+                    TripPhaseType::RidingRideHail(car),
+                ));
+            }
+            CurbStop::Dropoff(req) => {
+                self.active_trip_mode
+                    .remove(&AgentID::BusPassenger(req.person, car));
+                self.people[req.person.0].on_bus.take().unwrap();
+                match self.trips[req.trip.0].legs.pop_front() {
+                    Some(TripLeg::RideHail(b)) => assert_eq!(b, req.to),
+                    _ => unreachable!(),
+                }
+                self.people[req.person.0].state = PersonState::Inside(req.to);
+                self.events
+                    .push(Event::PersonEntersBuilding(req.person, req.to));
+                self.trip_finished(now, req.trip, ctx);
+            }
+            CurbStop::NoShow => {}
+        }
+        dwell
+    }
+
+    /// A ride-hail vehicle is done at the curb. Returns the route to follow next, or None if the
+    /// vehicle has no job and should vanish.
+    pub fn ride_hail_vehicle_departing(
+        &mut self,
+        now: Time,
+        car: CarID,
+        start: Position,
+        ctx: &mut Ctx,
+    ) -> Option<Router> {
+        let (router, stranded) =
+            ctx.ride_hail
+                .vehicle_departing(now, car, start, ctx.map, ctx.scheduler);
+        if let Some(req) = stranded {
+            self.active_trip_mode
+                .remove(&AgentID::BusPassenger(req.person, car));
+            self.people[req.person.0].on_bus.take().unwrap();
+            self.cancel_trip(
+                now,
+                req.trip,
+                format!("{} can't reach {}", car, req.to),
+                None,
+                ctx,
+            );
+        }
+        self.cancel_unreachable_rides(now, ctx);
+        router
+    }
+
+    /// No ride-hail vehicle could find a route to some people waiting at the curb, so give up on
+    /// them.
+    fn cancel_unreachable_rides(&mut self, now: Time, ctx: &mut Ctx) {
+        for req in ctx.ride_hail.take_unreachable() {
+            self.events.push(Event::ProblemEncountered(
+                req.trip,
+                Problem::RideHailUnreachable(req.from),
+            ));
+            self.cancel_trip(
+                now,
+                req.trip,
+                format!("no ride-hail vehicle can reach {}", req.from),
+                None,
+                ctx,
+            );
+        }
+    }
+
+    /// A ride-hail vehicle couldn't continue, probably because of live map edits. Whoever it was
+    /// serving has their trip cancelled.
+    pub fn ride_hail_vehicle_removed(
+        &mut self,
+        now: Time,
+        car: CarID,
+        pos: Position,
+        ctx: &mut Ctx,
+    ) {
+        if let Some(req) = ctx.ride_hail.vehicle_removed(now, car, pos) {
+            if self.people[req.person.0].on_bus == Some(car) {
+                self.active_trip_mode
+                    .remove(&AgentID::BusPassenger(req.person, car));
+                self.people[req.person.0].on_bus = None;
+            }
+            self.cancel_trip(
+                now,
+                req.trip,
+                format!("{} had to be removed after map edits", car),
+                None,
+                ctx,
+            );
+        }
+    }
+
+    /// A ride-hail vehicle pulled into a loading zone. Once it's done there, it leaves right away
+    /// if it has another job, or vanishes otherwise.
+    fn ride_hail_vehicle_parked(
+        &mut self,
+        now: Time,
+        car: CarID,
+        spot: ParkingSpot,
+        ctx: &mut Ctx,
+    ) {
+        let dwell = self.ride_hail_vehicle_stopped(now, car, ctx);
+        let parked_car = ctx.parking.get_car_at_spot(spot).unwrap().clone();
+        let start = ctx
+            .parking
+            .spot_to_driving_pos(spot, &parked_car.vehicle, ctx.map);
+        if let Some(router) = self.ride_hail_vehicle_departing(now, car, start, ctx) {
+            ctx.scheduler.push(
+                now + dwell,
+                Command::SpawnCar(
+                    CreateCar {
+                        vehicle: parked_car.vehicle.clone(),
+                        router,
+                        maybe_parked_car: Some(parked_car),
+                        trip_and_person: None,
+                        maybe_route: None,
+                    },
+                    true,
+                ),
+            );
+        } else {
+            // Free up the loading zone immediately
+            ctx.parking.remove_parked_car(parked_car);
+        }
+    }
+
     pub fn ped_reached_parking_spot(
         &mut self,
         now: Time,
@@ -546,6 +1022,20 @@ impl TripManager {
         let trip = trip.id;
         match ctx.map.pathfind(req) {
             Ok(path) => {
+                let mut parked_car = parked_car;
+                if parked_car.spot.has_charger(ctx.map) {
+                    if let Some(ref mut battery) = parked_car.vehicle.battery {
+                        let duration = now - parked_car.parked_since;
+                        let energy = battery.charge(duration);
+                        self.events.push(Event::VehicleCharged(
+                            parked_car.vehicle.id,
+                            parked_car.spot,
+                            energy,
+                            duration,
+                        ));
+                    }
+                }
+
                 let router = drive_to.make_router(parked_car.vehicle.id, path, ctx.map);
                 ctx.scheduler.push(
                     now,
@@ -581,8 +1071,25 @@ impl TripManager {
         trip.total_distance += distance_crossed;
 
         trip.assert_walking_leg(spot.clone());
-        let (bike, drive_to) = match trip.legs[0] {
-            TripLeg::Drive(bike, ref to) => (bike, to.clone()),
+        let (vehicle, drive_to) = match trip.legs[0].clone() {
+            TripLeg::Drive(bike, to) => (self.people[trip.person.0].get_vehicle(bike), to),
+            TripLeg::BikeShare(from_dock, to_dock) => {
+                if let Some(vehicle) = ctx.bike_share.check_out(from_dock, trip.id) {
+                    let goal = DrivingGoal::ParkNear(to_dock);
+                    trip.legs[0] = TripLeg::Drive(vehicle.id, goal.clone());
+                    (vehicle, goal)
+                } else {
+                    // Walk the rest of the way
+                    self.events.push(Event::ProblemEncountered(
+                        trip.id,
+                        Problem::DockEmpty(from_dock),
+                    ));
+                    trip.legs.pop_front();
+                    let id = trip.id;
+                    self.spawn_ped(now, id, spot, ctx);
+                    return;
+                }
+            }
             _ => unreachable!(),
         };
         let driving_pos = match spot.connection {
@@ -590,10 +1097,24 @@ impl TripManager {
             _ => unreachable!(),
         };
 
+        let (id, person) = (trip.id, trip.person);
+        self.start_biking(now, id, person, vehicle, driving_pos, drive_to, ctx);
+    }
+
+    /// Spawn a bike at a bike rack, heading for the goal.
+    fn start_biking(
+        &mut self,
+        now: Time,
+        trip: TripID,
+        person: PersonID,
+        vehicle: Vehicle,
+        driving_pos: Position,
+        drive_to: DrivingGoal,
+        ctx: &mut Ctx,
+    ) {
         let end = if let Some(end) = drive_to.goal_pos(PathConstraints::Bike, ctx.map) {
             end
         } else {
-            let trip = trip.id;
             self.cancel_trip(
                 now,
                 trip,
@@ -614,25 +1135,19 @@ impl TripManager {
         } else {
             ctx.map
                 .pathfind(req)
-                .map(|path| drive_to.make_router(bike, path, ctx.map))
+                .map(|path| drive_to.make_router(vehicle.id, path, ctx.map))
         };
         match maybe_router {
             Ok(router) => {
                 ctx.scheduler.push(
                     now,
                     Command::SpawnCar(
-                        CreateCar::for_appearing(
-                            self.people[trip.person.0].get_vehicle(bike),
-                            router,
-                            trip.id,
-                            trip.person,
-                        ),
+                        CreateCar::for_appearing(vehicle, router, trip, person),
                         true,
                     ),
                 );
             }
             Err(err) => {
-                let trip = trip.id;
                 self.cancel_trip(now, trip, err.to_string(), None, ctx);
             }
         }
@@ -655,13 +1170,34 @@ impl TripManager {
         trip.total_blocked_time += blocked_time;
         trip.total_distance += distance_crossed;
 
-        match trip.legs.pop_front() {
-            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_))) => {
+        let dock = match trip.legs.pop_front() {
+            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(b))) => {
                 assert_eq!(c, bike);
+                b
             }
             _ => unreachable!(),
         };
 
+        if ctx.bike_share.is_shared(bike) && !ctx.bike_share.return_vehicle(bike, dock) {
+            self.events
+                .push(Event::ProblemEncountered(trip.id, Problem::DockFull(dock)));
+            if let Some(next_dock) = ctx.bike_share.nearest_dock_with_space(dock, ctx.map) {
+                // Ride on to another dock, then walk from there
+                let goal = DrivingGoal::ParkNear(next_dock);
+                trip.legs.push_front(TripLeg::Drive(bike, goal.clone()));
+                let driving_pos = match bike_rack.connection {
+                    SidewalkPOI::BikeRack(p) => p,
+                    _ => unreachable!(),
+                };
+                let vehicle = ctx.bike_share.get_vehicle(bike);
+                let (id, person) = (trip.id, trip.person);
+                self.start_biking(now, id, person, vehicle, driving_pos, goal, ctx);
+                return;
+            }
+            // Every dock is full, so squeeze it in here anyway
+            ctx.bike_share.force_return(bike, dock);
+        }
+
         let id = trip.id;
         self.spawn_ped(now, id, bike_rack, ctx);
     }
@@ -755,6 +1291,7 @@ impl TripManager {
         bus: CarID,
         blocked_time: Duration,
         walking: &mut WalkingSimState,
+        map: &Map,
     ) -> (TripID, PersonID) {
         let trip = &mut self.trips[self
             .active_trip_mode
@@ -765,7 +1302,7 @@ impl TripManager {
         // No distance crossed between waiting for a bus and boarding
 
         trip.legs.pop_front();
-        walking.ped_boarded_bus(now, ped);
+        walking.ped_boarded_bus(now, ped, map);
         self.active_trip_mode
             .insert(AgentID::BusPassenger(trip.person, bus), trip.id);
         self.people[trip.person.0].on_bus = Some(bus);
@@ -928,10 +1465,29 @@ impl TripManager {
 
     fn spawn_ped(&mut self, now: Time, id: TripID, start: SidewalkSpot, ctx: &mut Ctx) {
         let trip = &self.trips[id.0];
-        let walk_to = match trip.legs[0] {
+        let mut walk_to = match trip.legs[0] {
             TripLeg::Walk(ref to) => to.clone(),
             _ => unreachable!(),
         };
+        if walk_to.connection == SidewalkPOI::DeferredParkingSpot {
+            // Walking to a car to drive the next leg, like after riding transit
+            let car = match trip.legs[1] {
+                TripLeg::Drive(car, _) => car,
+                _ => unreachable!(),
+            };
+            if let Some(parked_car) = ctx.parking.lookup_parked_car(car) {
+                walk_to = SidewalkSpot::parking_spot(parked_car.spot, ctx.map, ctx.parking);
+            } else {
+                self.cancel_trip(
+                    now,
+                    id,
+                    format!("should have {} parked somewhere, but it's unavailable", car),
+                    None,
+                    ctx,
+                );
+                return;
+            }
+        }
 
         let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
         match ctx.map.pathfind(req) {
@@ -984,6 +1540,12 @@ impl TripManager {
         self.events
             .push(Event::TripCancelled(trip.id, trip.info.mode));
         let person = trip.person;
+        if trip.info.mode == TripMode::RideHail {
+            ctx.ride_hail.trip_cancelled(id);
+        }
+        if matches!(trip.info.mode, TripMode::BikeShare | TripMode::Transit) {
+            ctx.bike_share.trip_cancelled(id);
+        }
 
         // Maintain consistentency for anyone listening to events
         if let PersonState::Inside(b) = self.people[person.0].state {
@@ -1005,7 +1567,10 @@ impl TripManager {
 
         // Don't forget the car!
         if let Some(vehicle) = abandoned_vehicle {
-            if vehicle.vehicle_type == VehicleType::Car {
+            if matches!(
+                vehicle.vehicle_type,
+                VehicleType::Car | VehicleType::Delivery
+            ) {
                 // First remove the parked car, if needed. Maybe the trip was cancelled while the
                 // car was parked in the starting building.
                 if let Some(parked_car) = ctx.parking.lookup_parked_car(vehicle.id).cloned() {
@@ -1102,6 +1667,17 @@ impl TripManager {
             TripLeg::Walk(_) => AgentID::Pedestrian(person.ped),
             TripLeg::Drive(c, _) => AgentID::Car(*c),
             TripLeg::RideBus(_, _) => AgentID::BusPassenger(person.id, person.on_bus.unwrap()),
+            TripLeg::RideHail(_) => match person.on_bus {
+                Some(car) => AgentID::BusPassenger(person.id, car),
+                // Still waiting to be picked up
+                None => {
+                    return TripResult::ModeChange;
+                }
+            },
+            // About to check out a vehicle
+            TripLeg::BikeShare(_, _) => {
+                return TripResult::ModeChange;
+            }
         };
         if self.active_trip_mode.get(&a) == Some(&id) {
             TripResult::Ok(a)
@@ -1161,18 +1737,21 @@ impl TripManager {
             trains,
             bus_riders: 0,
             train_riders: 0,
+            ride_hail_riders: 0,
         };
 
         for a in self.active_trip_mode.keys() {
             match a {
                 AgentID::Car(c) => match c.vehicle_type {
-                    VehicleType::Car => {
+                    VehicleType::Car | VehicleType::Delivery => {
                         cnt.sov_drivers += 1;
                     }
                     VehicleType::Bike => {
                         cnt.cyclists += 1;
                     }
-                    VehicleType::Bus | VehicleType::Train => unreachable!(),
+                    VehicleType::Bus | VehicleType::Train | VehicleType::RideHail => {
+                        unreachable!()
+                    }
                 },
                 AgentID::BusPassenger(_, c) => match c.vehicle_type {
                     VehicleType::Bus => {
@@ -1181,7 +1760,12 @@ impl TripManager {
                     VehicleType::Train => {
                         cnt.train_riders += 1;
                     }
-                    VehicleType::Car | VehicleType::Bike => unreachable!(),
+                    VehicleType::RideHail => {
+                        cnt.ride_hail_riders += 1;
+                    }
+                    VehicleType::Car | VehicleType::Bike | VehicleType::Delivery => {
+                        unreachable!()
+                    }
                 },
                 // These're counted separately
                 AgentID::Pedestrian(_) => {}
@@ -1264,6 +1848,8 @@ impl TripManager {
                         TripMode::Walk => AgentType::Pedestrian,
                         TripMode::Bike => AgentType::Bike,
                         TripMode::Drive => AgentType::Car,
+                        // Ride-hail and bike-share trips never start at borders
+                        TripMode::RideHail | TripMode::BikeShare => unreachable!(),
                         // TODO Not true for long. People will be able to spawn at borders already
                         // on a bus.
                         TripMode::Transit => AgentType::Pedestrian,
@@ -1288,13 +1874,15 @@ impl TripManager {
                     .iter()
                     .map(|t| {
                         let trip = &self.trips[t.0];
-                        IndividTrip::new(
+                        let mut individ = IndividTrip::new(
                             trip.info.departure,
                             trip.info.purpose,
                             trip.info.start,
                             trip.info.end,
                             trip.info.mode,
-                        )
+                        );
+                        individ.delivery_stops = trip.info.delivery_stops.clone();
+                        individ
                     })
                     .collect(),
             });
@@ -1327,9 +1915,31 @@ pub struct TripInfo {
     /// Did a ScenarioModifier apply to this?
     pub modified: bool,
     pub cancellation_reason: Option<String>,
+    /// Only delivery vans stop along the way
+    pub delivery_stops: Vec<DeliveryStop>,
 }
 
 impl Trip {
+    /// Delivery vans visit each stop before the driving leg to their destination. Returns the goal
+    /// of the first stop, if there is one.
+    fn insert_delivery_stops(&mut self) -> Option<DrivingGoal> {
+        let first = self.info.delivery_stops.first()?;
+        let idx = self.legs.iter().position(
+            |leg| matches!(leg, TripLeg::Drive(c, _) if c.vehicle_type == VehicleType::Delivery),
+        )?;
+        let van = match self.legs[idx] {
+            TripLeg::Drive(c, _) => c,
+            _ => unreachable!(),
+        };
+        for (offset, stop) in self.info.delivery_stops.iter().enumerate() {
+            self.legs.insert(
+                idx + offset,
+                TripLeg::Drive(van, DrivingGoal::Deliver(stop.bldg, stop.duration)),
+            );
+        }
+        Some(DrivingGoal::Deliver(first.bldg, first.duration))
+    }
+
     fn assert_walking_leg(&mut self, goal: SidewalkSpot) {
         match self.legs.pop_front() {
             Some(TripLeg::Walk(spot)) => {
@@ -1349,6 +1959,11 @@ pub(crate) enum TripLeg {
     Drive(CarID, DrivingGoal),
     /// Maybe get off at a stop, maybe ride off-map
     RideBus(BusRouteID, Option<BusStopID>),
+    /// Wait for a ride-hail vehicle, then ride it to this building
+    RideHail(BuildingID),
+    /// Pick up a shared bike or scooter from the dock at the first building, and return it to the
+    /// second. Once the vehicle is checked out, this becomes a `Drive` leg.
+    BikeShare(BuildingID, BuildingID),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
@@ -1357,6 +1972,8 @@ pub enum TripMode {
     Bike,
     Transit,
     Drive,
+    RideHail,
+    BikeShare,
 }
 
 impl TripMode {
@@ -1366,6 +1983,8 @@ impl TripMode {
             TripMode::Bike,
             TripMode::Transit,
             TripMode::Drive,
+            TripMode::RideHail,
+            TripMode::BikeShare,
         ]
     }
 
@@ -1375,6 +1994,8 @@ impl TripMode {
             TripMode::Bike => "bike",
             TripMode::Transit => "use transit",
             TripMode::Drive => "drive",
+            TripMode::RideHail => "take a ride-hail",
+            TripMode::BikeShare => "use bike-share",
         }
     }
 
@@ -1385,6 +2006,8 @@ impl TripMode {
             TripMode::Bike => "biking",
             TripMode::Transit => "using transit",
             TripMode::Drive => "driving",
+            TripMode::RideHail => "riding in a ride-hail",
+            TripMode::BikeShare => "riding a shared bike",
         }
     }
 
@@ -1394,16 +2017,18 @@ impl TripMode {
             TripMode::Bike => "Bike",
             TripMode::Transit => "Bus",
             TripMode::Drive => "Car",
+            TripMode::RideHail => "Ride-hail",
+            TripMode::BikeShare => "Bike-share",
         }
     }
 
     pub fn to_constraints(self) -> PathConstraints {
         match self {
             TripMode::Walk => PathConstraints::Pedestrian,
-            TripMode::Bike => PathConstraints::Bike,
+            TripMode::Bike | TripMode::BikeShare => PathConstraints::Bike,
             // TODO WRONG
             TripMode::Transit => PathConstraints::Bus,
-            TripMode::Drive => PathConstraints::Car,
+            TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
         }
     }
 
@@ -1491,4 +2116,5 @@ pub struct CommutersVehiclesCounts {
     pub trains: usize,
     pub bus_riders: usize,
     pub train_riders: usize,
+    pub ride_hail_riders: usize,
 }
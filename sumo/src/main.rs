@@ -9,7 +9,7 @@ use abstutil::{CmdArgs, Tags, Timer};
 use geom::{Distance, PolyLine};
 use map_model::{
     osm, raw, AccessRestrictions, Intersection, IntersectionID, IntersectionType, Lane, LaneID,
-    LaneType, Map, Road, RoadID, Turn, TurnID, TurnType,
+    LaneType, Map, ParkingPricing, Road, RoadID, Turn, TurnID, TurnType,
 };
 
 use sumo::{Direction, InternalLaneID, Network, NodeID, VehicleClass};
@@ -43,6 +43,7 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
             outgoing_lanes: Vec::new(),
             roads: BTreeSet::new(),
             merged: false,
+            movement_bans: BTreeMap::new(),
         });
         ids_intersections.insert(junction.id, id);
     }
@@ -89,6 +90,8 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
 
                 driving_blackhole: false,
                 biking_blackhole: false,
+                parking_pricing: ParkingPricing::free(),
+                charging_spots: 0,
             });
             // These seem to appear in the XML from right to left
             lanes_rtl.push((lane_id, direction, lane_type));
@@ -9,13 +9,18 @@ use rand::seq::SliceRandom;
 use abstio::{CityName, MapName};
 use abstutil::Timer;
 use geom::{Distance, Duration, Time};
-use map_model::{IntersectionID, Map};
-use sim::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
+use map_model::{osm, EditCmd, EditIntersection, IntersectionID, LaneType, Map, TurnPriority};
+use sim::{
+    DeliveryStop, IndividTrip, PersonSpec, Scenario, TransitPriorityType, TransitSignalPriority,
+    TripEndpoint, TripMode, TripPurpose,
+};
 
 fn main() -> Result<()> {
-    test_lane_changing(&import_map(abstio::path(
-        "../tests/input/lane_selection.osm",
-    )))?;
+    let lane_selection = import_map(abstio::path("../tests/input/lane_selection.osm"));
+    test_lane_changing(&lane_selection)?;
+    test_emissions_at_trip_end(&lane_selection)?;
+    test_transit_signal_priority(import_map(abstio::path("../tests/input/bus_priority.osm")))?;
+    test_loading_zones(&import_map(abstio::path("../tests/input/loading_zone.osm")))?;
     test_map_importer()?;
     check_proposals()?;
     smoke_test()?;
@@ -64,6 +69,8 @@ fn import_map(path: String) -> Map {
             include_railroads: true,
             extra_buildings: None,
             skip_local_roads: false,
+            gtfs: None,
+            gtfs_service_date: None,
         },
         &mut timer,
     );
@@ -237,3 +244,223 @@ fn test_lane_changing(map: &Map) -> Result<()> {
 
     Ok(())
 }
+
+/// Verify that a car's emissions on the last road of its trip are recorded, even though it never
+/// finishes crossing that road.
+fn test_emissions_at_trip_end(map: &Map) -> Result<()> {
+    // Bit brittle to hardcode IDs here, but it's fast to update
+    let north = IntersectionID(7);
+    let south = IntersectionID(0);
+
+    let mut scenario = Scenario::empty(map, "emissions_at_trip_end");
+    scenario.people.push(PersonSpec {
+        orig_id: None,
+        trips: vec![IndividTrip::new(
+            Time::START_OF_DAY,
+            TripPurpose::Shopping,
+            TripEndpoint::Border(north),
+            TripEndpoint::Border(south),
+            TripMode::Drive,
+        )],
+    });
+
+    let mut opts = sim::SimOptions::new("test_emissions_at_trip_end");
+    opts.alerts = sim::AlertHandler::Silence;
+    let mut sim = sim::Sim::new(map, opts);
+    let mut rng = sim::SimFlags::for_test("test_emissions_at_trip_end").make_rng();
+    scenario.instantiate(&mut sim, map, &mut rng, &mut Timer::throwaway());
+    while !sim.is_done() {
+        sim.tiny_step(map, &mut None);
+    }
+
+    for r in &map.get_i(south).roads {
+        let emitted = sim.get_road_emissions(*r);
+        if emitted.co2_grams <= 0.0 {
+            panic!(
+                "A car drove along {} to reach {}, but no emissions were recorded there",
+                r, south
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that transit signal priority only ends a stage early when the next stage lets the bus
+/// through.
+fn test_transit_signal_priority(mut map: Map) -> Result<()> {
+    let i = map.find_i_by_osm_id(osm::NodeID(-100))?;
+    let find_road = |way| {
+        map.all_roads()
+            .iter()
+            .find(|r| r.orig_id.osm_way_id == osm::WayID(way))
+            .unwrap()
+            .id
+    };
+    let (west, east) = (find_road(-200), find_road(-201));
+    let bus_movement = *map
+        .get_traffic_signal(i)
+        .movements
+        .keys()
+        .find(|m| !m.crosswalk && m.from.id == west && m.to.id == east)
+        .unwrap();
+
+    // Repeat every stage that doesn't serve the bus. When the bus shows up during the first copy,
+    // ending it early wouldn't help.
+    let mut signal = map.get_traffic_signal(i).clone();
+    let mut stages = Vec::new();
+    for stage in signal.stages.drain(..) {
+        if stage.get_priority_of_movement(bus_movement) == TurnPriority::Banned {
+            stages.push(stage.clone());
+        }
+        stages.push(stage);
+    }
+    signal.stages = stages;
+    let mut edits = map.get_edits().clone();
+    edits.commands.push(EditCmd::ChangeIntersection {
+        i,
+        old: map.get_i_edit(i),
+        new: EditIntersection::TrafficSignal(signal.export(&map)),
+    });
+    map.must_apply_edits(edits);
+    map.recalculate_pathfinding_after_edits(&mut Timer::throwaway());
+
+    let mut opts = sim::SimOptions::new("test_transit_signal_priority");
+    opts.alerts = sim::AlertHandler::Silence;
+    opts.transit_signal_priority = Some(TransitSignalPriority::default());
+    let mut sim = sim::Sim::new(&map, opts);
+    let mut scenario = Scenario::empty(&map, "transit_signal_priority");
+    scenario.only_seed_buses = None;
+    let mut rng = sim::SimFlags::for_test("test_transit_signal_priority").make_rng();
+    scenario.instantiate(&mut sim, &map, &mut rng, &mut Timer::throwaway());
+
+    // A few buses spawn during these hours
+    let stages = map.get_traffic_signal(i).get_plan_stages(0).clone();
+    let mut current_stage = sim.current_stage_and_remaining_time(i).0;
+    let mut num_early_greens = 0;
+    let mut truncated = false;
+    while sim.time() < Time::START_OF_DAY + Duration::hours(3) {
+        sim.tiny_step(&map, &mut None);
+
+        let early_greens = sim
+            .get_analytics()
+            .transit_signal_priority
+            .iter()
+            .filter(|(_, _, _, priority, _, _)| *priority == TransitPriorityType::EarlyGreen)
+            .count();
+        if early_greens > num_early_greens {
+            num_early_greens = early_greens;
+            truncated = true;
+        }
+
+        let stage = sim.current_stage_and_remaining_time(i).0;
+        if stage != current_stage {
+            if truncated
+                && stages[stage].get_priority_of_movement(bus_movement) == TurnPriority::Banned
+            {
+                panic!(
+                    "Stage {} at {} ended early for a bus, but stage {} doesn't serve it",
+                    current_stage, i, stage
+                );
+            }
+            truncated = false;
+            current_stage = stage;
+        }
+    }
+    println!(
+        "Transit signal priority ended {} stages early at {}",
+        num_early_greens, i
+    );
+
+    Ok(())
+}
+
+/// Verify that delivery vans stop in a loading zone while there's room, and only double-park once
+/// it's full.
+fn test_loading_zones(map: &Map) -> Result<()> {
+    let west = map.find_i_by_osm_id(osm::NodeID(-101))?;
+    let east = map.find_i_by_osm_id(osm::NodeID(-102))?;
+    let road = map
+        .all_roads()
+        .iter()
+        .find(|r| r.orig_id.osm_way_id == osm::WayID(-201))
+        .unwrap();
+    let num_spots: usize = road
+        .lanes_ltr()
+        .into_iter()
+        .filter(|(_, _, lt)| *lt == LaneType::LoadingZone)
+        .map(|(l, _, _)| map.get_l(l).number_parking_spots(map.get_config()))
+        .sum();
+    assert!(num_spots > 0, "{} has no loading zone", road.id);
+    let bldg = map.all_buildings()[0].id;
+
+    // Send more vans than there are spots, spaced out so each one finishes stopping before the
+    // next arrives. They all stay long enough that no spot frees up during the test.
+    let mut scenario = Scenario::empty(map, "loading_zones");
+    let num_vans = num_spots + 2;
+    for idx in 0..num_vans {
+        let mut trip = IndividTrip::new(
+            Time::START_OF_DAY + Duration::minutes(2) * (idx as f64),
+            TripPurpose::Delivery,
+            TripEndpoint::Border(west),
+            TripEndpoint::Border(east),
+            TripMode::Drive,
+        );
+        trip.delivery_stops.push(DeliveryStop {
+            bldg,
+            duration: Duration::hours(3),
+        });
+        scenario.people.push(PersonSpec {
+            orig_id: None,
+            trips: vec![trip],
+        });
+    }
+
+    let mut opts = sim::SimOptions::new("test_loading_zones");
+    opts.alerts = sim::AlertHandler::Silence;
+    let mut sim = sim::Sim::new(map, opts);
+    let mut rng = sim::SimFlags::for_test("test_loading_zones").make_rng();
+    scenario.instantiate(&mut sim, map, &mut rng, &mut Timer::throwaway());
+    let end = Time::START_OF_DAY + Duration::minutes(2) * (num_vans as f64) + Duration::minutes(30);
+    while sim.time() < end {
+        sim.tiny_step(map, &mut None);
+    }
+
+    let stops = sim
+        .get_analytics()
+        .delivery_stops
+        .get(&road.id)
+        .cloned()
+        .unwrap_or_else(Vec::new);
+    let in_loading_zone = stops
+        .iter()
+        .filter(|(_, _, spot, _)| spot.is_some())
+        .count();
+    if in_loading_zone == 0 {
+        panic!("No delivery van stopped in the loading zone on {}", road.id);
+    }
+    if in_loading_zone == stops.len() {
+        panic!(
+            "{} vans stopped in the loading zone on {}, which only has {} spots, but none \
+             double-parked",
+            stops.len(),
+            road.id,
+            num_spots
+        );
+    }
+    // Stops are recorded in order. Once one van double-parks, the loading zone is full and
+    // nobody leaves it, so nobody else should find a spot there.
+    if let Some(first_double_park) = stops.iter().position(|(_, _, spot, _)| spot.is_none()) {
+        if stops[first_double_park..]
+            .iter()
+            .any(|(_, _, spot, _)| spot.is_some())
+        {
+            panic!(
+                "A van double-parked on {} while the loading zone still had room: {:?}",
+                road.id, stops
+            );
+        }
+    }
+
+    Ok(())
+}